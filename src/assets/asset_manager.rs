@@ -1,28 +1,193 @@
 use std::{
-    collections::HashMap,
+    borrow::Cow,
+    collections::{HashMap, HashSet},
     hash, io,
+    panic::AssertUnwindSafe,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, Mutex, OnceLock, RwLock},
+    time::{Duration, SystemTime},
 };
 
-use glam::{Mat4, Vec2, Vec3};
+use glam::{Mat4, Vec2, Vec3, Vec4};
 use gltf::{Document, Scene};
 
 use include_dir;
 use include_dir::Dir;
 use uuid::Uuid;
 
+use crate::{
+    assets::{
+        cube_map::{self, CubeTexture},
+        hot_reload::{AssetReloaded, Watcher},
+        loading::{AssetHandle, LoadState},
+        streaming::{self, StreamingLoader, StreamingModel},
+    },
+    engine::context::ContextItem,
+};
+
 static ASSET_DIR: Dir<'_> = include_dir::include_dir!("$CARGO_MANIFEST_DIR/assets");
 
+/// how the indices of a primitive are put together
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Default)]
+pub enum PrimitiveTopology {
+    /// every 3 indices are a triangle
+    #[default]
+    Triangles,
+    /// every 2 indices are a line segment
+    Lines,
+    /// every index is a point
+    Points,
+}
+
+impl PrimitiveTopology {
+    /// turns a gltf primitive mode and its indices into a list topology, strips, loops and fans are
+    /// unrolled into plain lists
+    pub fn from_gltf(mode: gltf::mesh::Mode, indices: Vec<u32>) -> (Self, Vec<u32>) {
+        use gltf::mesh::Mode;
+        match mode {
+            Mode::Triangles => (PrimitiveTopology::Triangles, indices),
+            Mode::TriangleStrip => (
+                PrimitiveTopology::Triangles,
+                indices
+                    .windows(3)
+                    .enumerate()
+                    .flat_map(|(i, w)| {
+                        if i % 2 == 0 {
+                            [w[0], w[1], w[2]]
+                        } else {
+                            [w[1], w[0], w[2]]
+                        }
+                    })
+                    .collect(),
+            ),
+            Mode::TriangleFan => (
+                PrimitiveTopology::Triangles,
+                indices
+                    .windows(2)
+                    .skip(1)
+                    .flat_map(|w| [indices[0], w[0], w[1]])
+                    .collect(),
+            ),
+            Mode::Lines => (PrimitiveTopology::Lines, indices),
+            Mode::LineStrip => (
+                PrimitiveTopology::Lines,
+                indices.windows(2).flat_map(|w| [w[0], w[1]]).collect(),
+            ),
+            Mode::LineLoop => {
+                let mut lines: Vec<u32> = indices.windows(2).flat_map(|w| [w[0], w[1]]).collect();
+                if let (Some(first), Some(last)) = (indices.first(), indices.last()) {
+                    if indices.len() > 2 {
+                        lines.extend([*last, *first]);
+                    }
+                }
+                (PrimitiveTopology::Lines, lines)
+            }
+            Mode::Points => (PrimitiveTopology::Points, indices),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct MeshPrimitive {
     pub positions: Vec<Vec3>,
     pub normals: Vec<Vec3>,
+    /// xyz is the tangent and w the handedness of the bitangent, empty unless the primitive is
+    /// normal mapped
+    pub tangents: Vec<Vec4>,
     pub tex_coords: Vec<Vec2>,
+    /// srgb rgba per vertex, multiplied with the material's color
+    pub colors: Option<Vec<[u8; 4]>>,
     pub indices: Vec<u32>,
+    pub topology: PrimitiveTopology,
     pub material_index: Option<usize>,
 }
 
+impl MeshPrimitive {
+    /// a vertex for every corner of every triangle with the triangle's normal, for triangles
+    /// that came without normals. the other attributes are copied to the new vertices
+    pub fn with_flat_normals(self) -> Self {
+        let corners: Vec<usize> = self.indices.iter().map(|&i| i as usize).collect();
+        fn unweld<T: Copy>(values: &[T], corners: &[usize], count: usize) -> Vec<T> {
+            if values.len() != count {
+                return Vec::new();
+            }
+            corners.iter().map(|&i| values[i]).collect()
+        }
+        let count = self.positions.len();
+        let positions = unweld(&self.positions, &corners, count);
+        let normals = positions
+            .chunks_exact(3)
+            .flat_map(|t| {
+                let normal = (t[1] - t[0]).cross(t[2] - t[0]).try_normalize();
+                [normal.unwrap_or(Vec3::Y); 3]
+            })
+            .collect();
+        Self {
+            normals,
+            tangents: unweld(&self.tangents, &corners, count),
+            tex_coords: unweld(&self.tex_coords, &corners, count),
+            colors: self.colors.map(|colors| unweld(&colors, &corners, count)),
+            indices: (0..corners.len() as u32).collect(),
+            positions,
+            ..self
+        }
+    }
+
+    /// per vertex tangents from the triangles' uvs, averaged over the triangles sharing a vertex
+    /// and made orthogonal to its normal. empty when there are no normals or uvs to work from
+    pub fn compute_tangents(&self) -> Vec<Vec4> {
+        let count = self.positions.len();
+        if self.topology != PrimitiveTopology::Triangles
+            || self.normals.len() != count
+            || self.tex_coords.len() != count
+        {
+            return Vec::new();
+        }
+
+        let mut tangents = vec![Vec3::ZERO; count];
+        let mut bitangents = vec![Vec3::ZERO; count];
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+            let (e1, e2) = (
+                self.positions[b] - self.positions[a],
+                self.positions[c] - self.positions[a],
+            );
+            let (d1, d2) = (
+                self.tex_coords[b] - self.tex_coords[a],
+                self.tex_coords[c] - self.tex_coords[a],
+            );
+            let det = d1.x * d2.y - d2.x * d1.y;
+            // no uv area, nothing to say about the direction
+            if det.abs() < f32::EPSILON {
+                continue;
+            }
+            let tangent = (e1 * d2.y - e2 * d1.y) / det;
+            let bitangent = (e2 * d1.x - e1 * d2.x) / det;
+            for i in [a, b, c] {
+                tangents[i] += tangent;
+                bitangents[i] += bitangent;
+            }
+        }
+
+        (0..count)
+            .map(|i| {
+                let normal = self.normals[i];
+                let tangent = (tangents[i] - normal * normal.dot(tangents[i]))
+                    .try_normalize()
+                    .unwrap_or_else(|| normal.any_orthonormal_vector());
+                // gltf's v goes down, so an unmirrored layout has v growing against
+                // `normal × tangent`, that's the +1
+                let handedness = if normal.cross(tangent).dot(bitangents[i]) > 0.0 {
+                    -1.0
+                } else {
+                    1.0
+                };
+                tangent.extend(handedness)
+            })
+            .collect()
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Mesh {
     pub primitives: Vec<MeshPrimitive>,
@@ -33,6 +198,9 @@ pub enum TextureType {
     Albedo,
     Normal,
     Roughness,
+    MetallicRoughness,
+    Emissive,
+    Occlusion,
 }
 
 #[derive(Clone, Debug, Copy)]
@@ -41,6 +209,16 @@ pub enum ImageFormat {
     R8G8B8A8,
 }
 
+impl ImageFormat {
+    /// bytes per pixel
+    pub fn channels(&self) -> usize {
+        match self {
+            ImageFormat::R8G8B8 => 3,
+            ImageFormat::R8G8B8A8 => 4,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Texture {
     pub texture_type: TextureType,
@@ -50,10 +228,127 @@ pub struct Texture {
     pub data: Vec<u8>,
 }
 
+impl Texture {
+    /// copies a decoded gltf image with 8 bits per channel, one and two channel images are
+    /// widened to rgb and 16 bit and float ones are brought down to 8 bit rgba
+    pub fn from_gltf_image(image: &gltf::image::Data, texture_type: TextureType) -> Self {
+        use gltf::image::Format;
+        let (image_format, data) = match image.format {
+            Format::R8G8B8 => (ImageFormat::R8G8B8, image.pixels.clone()),
+            Format::R8G8B8A8 => (ImageFormat::R8G8B8A8, image.pixels.clone()),
+            Format::R8 => (
+                ImageFormat::R8G8B8,
+                image.pixels.iter().flat_map(|&r| [r, r, r]).collect(),
+            ),
+            Format::R8G8 => (
+                ImageFormat::R8G8B8,
+                image
+                    .pixels
+                    .chunks(2)
+                    .flat_map(|c| [c[0], c[1], 0])
+                    .collect(),
+            ),
+            Format::R16 => (ImageFormat::R8G8B8A8, wide_to_rgba8(&image.pixels, 1, 2)),
+            Format::R16G16 => (ImageFormat::R8G8B8A8, wide_to_rgba8(&image.pixels, 2, 2)),
+            Format::R16G16B16 => (ImageFormat::R8G8B8A8, wide_to_rgba8(&image.pixels, 3, 2)),
+            Format::R16G16B16A16 => (ImageFormat::R8G8B8A8, wide_to_rgba8(&image.pixels, 4, 2)),
+            Format::R32G32B32FLOAT => (ImageFormat::R8G8B8A8, wide_to_rgba8(&image.pixels, 3, 4)),
+            Format::R32G32B32A32FLOAT => {
+                (ImageFormat::R8G8B8A8, wide_to_rgba8(&image.pixels, 4, 4))
+            }
+        };
+        Self {
+            texture_type,
+            image_format,
+            width: image.width,
+            height: image.height,
+            data,
+        }
+    }
+
+    /// 1x1 white, for materials without a texture so only their factors and vertex colors show
+    pub fn white(texture_type: TextureType) -> Self {
+        Self {
+            texture_type,
+            image_format: ImageFormat::R8G8B8A8,
+            width: 1,
+            height: 1,
+            data: vec![255; 4],
+        }
+    }
+
+    /// decodes an encoded image (png, jpeg, hdr, ...), see `from_image`
+    pub fn from_image_bytes(bytes: &[u8], texture_type: TextureType) -> anyhow::Result<Self> {
+        Ok(Self::from_image(
+            image::load_from_memory(bytes)?,
+            texture_type,
+        ))
+    }
+
+    /// keeps opaque images rgb, anything over 8 bits per channel is brought down to 8
+    pub fn from_image(image: image::DynamicImage, texture_type: TextureType) -> Self {
+        let (width, height) = (image.width(), image.height());
+        let (image_format, data) = if image.color().has_alpha() {
+            (ImageFormat::R8G8B8A8, image.into_rgba8().into_raw())
+        } else {
+            (ImageFormat::R8G8B8, image.into_rgb8().into_raw())
+        };
+        Self {
+            texture_type,
+            image_format,
+            width,
+            height,
+            data,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Material {
     pub albedo: Texture,
     pub normals: Option<Texture>,
+    /// how strongly the normal texture bends the normals
+    pub normal_scale: f32,
+    /// roughness in green and metalness in blue, like gltf
+    pub metallic_roughness: Option<Texture>,
+    pub emissive: Option<Texture>,
+    /// ambient occlusion in red
+    pub occlusion: Option<Texture>,
+    /// rgba multiplied with the albedo texture
+    pub base_color_factor: Vec4,
+    /// multiplied with the metallic roughness texture when there is one
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    /// rgb multiplied with the emissive texture
+    pub emissive_factor: Vec3,
+    pub occlusion_strength: f32,
+    /// drawn with just its albedo, lights don't touch it
+    pub unlit: bool,
+}
+
+impl Material {
+    /// rough, non metallic material with nothing but the albedo texture
+    pub fn new(albedo: Texture) -> Self {
+        Self {
+            albedo,
+            normals: None,
+            normal_scale: 1.0,
+            metallic_roughness: None,
+            emissive: None,
+            occlusion: None,
+            base_color_factor: Vec4::ONE,
+            metallic_factor: 0.0,
+            roughness_factor: 1.0,
+            emissive_factor: Vec3::ZERO,
+            occlusion_strength: 1.0,
+            unlit: false,
+        }
+    }
+
+    pub fn unlit(mut self) -> Self {
+        self.unlit = true;
+        self
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -67,6 +362,9 @@ pub struct ModelNode {
 pub struct Model {
     pub nodes: Vec<ModelNode>,
     pub materials: Vec<Material>,
+    /// the `AssetManager` cache entry the model was loaded into, copies share it and the
+    /// renderer draws entities with the same one as instances. built and edited models have none
+    pub asset_id: Option<Uuid>,
 }
 
 impl Model {
@@ -74,6 +372,46 @@ impl Model {
         Self::get_nodes_recurse(&self.nodes, Mat4::IDENTITY)
     }
 
+    /// triangles across every node, line and point primitives don't count
+    pub fn triangle_count(&self) -> usize {
+        fn count(nodes: &[ModelNode]) -> usize {
+            nodes
+                .iter()
+                .map(|n| {
+                    n.meshes
+                        .iter()
+                        .flat_map(|m| &m.primitives)
+                        .filter(|p| p.topology == PrimitiveTopology::Triangles)
+                        .map(|p| match p.indices.len() {
+                            0 => p.positions.len() / 3,
+                            indices => indices / 3,
+                        })
+                        .sum::<usize>()
+                        + count(&n.nodes)
+                })
+                .sum()
+        }
+        count(&self.nodes)
+    }
+
+    /// min and max corner around every vertex with the node transforms baked in, `None` for a
+    /// model without vertices
+    pub fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        self.get_nodes_flattened()
+            .iter()
+            .flat_map(|node| {
+                node.meshes
+                    .iter()
+                    .flat_map(|m| &m.primitives)
+                    .flat_map(|p| &p.positions)
+                    .map(|p| node.transform.transform_point3(*p))
+            })
+            .fold(None, |bounds, p| match bounds {
+                None => Some((p, p)),
+                Some((min, max)) => Some((min.min(p), max.max(p))),
+            })
+    }
+
     fn get_nodes_recurse(nodes: &Vec<ModelNode>, upper_transform: Mat4) -> Vec<ModelNode> {
         nodes
             .iter()
@@ -92,6 +430,13 @@ impl Model {
     }
 }
 
+/// a model from a gltf, with what had to be made up or left out to get it
+#[derive(Clone, Debug)]
+pub struct ImportedModel {
+    pub model: Model,
+    pub warnings: Vec<String>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -113,6 +458,7 @@ mod tests {
         let model = Model {
             nodes: vec![root_node],
             materials: vec![],
+            asset_id: None,
         };
 
         let flattened = model.get_nodes_flattened();
@@ -135,6 +481,514 @@ mod tests {
         assert_eq!(second_node.transform, expected_transform);
         assert!(second_node.nodes.is_empty());
     }
+
+    /// two non-indexed line segments without normals
+    const LINES_GLTF: &str = r#"{
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0 }],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "mode": 1 }] }],
+        "buffers": [{
+            "byteLength": 48,
+            "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAgD8AAAAA"
+        }],
+        "bufferViews": [{ "buffer": 0, "byteLength": 48 }],
+        "accessors": [{
+            "bufferView": 0,
+            "componentType": 5126,
+            "count": 4,
+            "type": "VEC3",
+            "min": [0.0, 0.0, 0.0],
+            "max": [1.0, 1.0, 0.0]
+        }]
+    }"#;
+
+    #[test]
+    fn test_import_lines_primitive() {
+        let (gltf, buffers, images) = gltf::import_slice(LINES_GLTF.as_bytes()).unwrap();
+        let model = AssetManager::gltf_to_model(gltf, buffers, images)
+            .unwrap()
+            .model;
+
+        let prim = &model.get_nodes_flattened()[0].meshes[0].primitives[0];
+        assert_eq!(prim.topology, PrimitiveTopology::Lines);
+        assert_eq!(prim.indices, vec![0, 1, 2, 3]);
+        assert!(prim.normals.is_empty());
+    }
+
+    /// a root with a lines mesh and a child with a points mesh, and a node no scene uses
+    const TWO_NODE_GLTF: &str = r#"{
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [
+            { "mesh": 0, "translation": [0.0, 1.0, 0.0], "children": [1] },
+            { "mesh": 1, "translation": [1.0, 0.0, 0.0] },
+            { "mesh": 0 }
+        ],
+        "meshes": [
+            { "primitives": [{ "attributes": { "POSITION": 0 }, "mode": 1 }] },
+            { "primitives": [{ "attributes": { "POSITION": 0 }, "mode": 0 }] }
+        ],
+        "buffers": [{
+            "byteLength": 48,
+            "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAgD8AAAAA"
+        }],
+        "bufferViews": [{ "buffer": 0, "byteLength": 48 }],
+        "accessors": [{
+            "bufferView": 0,
+            "componentType": 5126,
+            "count": 4,
+            "type": "VEC3",
+            "min": [0.0, 0.0, 0.0],
+            "max": [1.0, 1.0, 0.0]
+        }]
+    }"#;
+
+    #[test]
+    fn nodes_only_get_their_own_mesh() {
+        let (gltf, buffers, images) = gltf::import_slice(TWO_NODE_GLTF.as_bytes()).unwrap();
+        let model = AssetManager::gltf_to_model(gltf, buffers, images)
+            .unwrap()
+            .model;
+
+        let meshes: Vec<(Mat4, PrimitiveTopology)> = model
+            .get_nodes_flattened()
+            .iter()
+            .flat_map(|node| {
+                node.meshes
+                    .iter()
+                    .map(|mesh| (node.transform, mesh.primitives[0].topology))
+            })
+            .collect();
+        assert_eq!(
+            meshes,
+            vec![
+                (
+                    Mat4::from_translation(Vec3::new(0.0, 1.0, 0.0)),
+                    PrimitiveTopology::Lines
+                ),
+                (
+                    Mat4::from_translation(Vec3::new(1.0, 1.0, 0.0)),
+                    PrimitiveTopology::Points
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn loose_images_load_as_8_bit_textures() {
+        let root = std::env::temp_dir().join(format!("textures-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        image::ImageBuffer::from_pixel(2, 2, image::Rgb([u16::MAX, 0, 0x8080]))
+            .save(root.join("deep.png"))
+            .unwrap();
+        image::RgbaImage::from_pixel(3, 1, image::Rgba([10, 20, 30, 40]))
+            .save(root.join("clear.png"))
+            .unwrap();
+        std::fs::write(root.join("broken.jpg"), b"not a jpeg").unwrap();
+
+        let assets = AssetManager::new();
+        assets.add_search_root(&root);
+        let deep = assets.get_texture(Path::new("deep.png")).unwrap();
+        assert!(matches!(deep.image_format, ImageFormat::R8G8B8));
+        assert_eq!((deep.width, deep.height), (2, 2));
+        assert_eq!(&deep.data[..3], &[255, 0, 128]);
+        let clear = assets.get_texture(Path::new("clear.png")).unwrap();
+        assert!(matches!(clear.image_format, ImageFormat::R8G8B8A8));
+        assert_eq!(clear.data, [10, 20, 30, 40].repeat(3));
+        // cached like models
+        let (_, first) = assets.get_asset_by_path(Path::new("clear.png")).unwrap();
+        let (_, second) = assets.get_asset_by_path(Path::new("clear.png")).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(assets.get_asset_by_path(Path::new("broken.jpg")).is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn gltf_flavors_load_to_the_same_model() {
+        let model = |assets: &AssetManager, path: &str| {
+            let model = assets.get_model(Path::new(path)).unwrap();
+            format!(
+                "{:?}",
+                Model {
+                    asset_id: None,
+                    ..model
+                }
+            )
+        };
+        let assets = AssetManager::new();
+        let glb = assets
+            .get_model(Path::new("fixtures/triangle.glb"))
+            .unwrap();
+        let flattened = glb.get_nodes_flattened();
+        assert_eq!(flattened.len(), 1);
+        assert_eq!(flattened[0].meshes[0].primitives[0].indices, vec![0, 1, 2]);
+        assert_eq!(glb.materials[0].albedo.width, 2);
+
+        let glb = model(&assets, "fixtures/triangle.glb");
+        assert_eq!(model(&assets, "fixtures/triangle.gltf"), glb);
+        assert_eq!(model(&assets, "fixtures/triangle_data_uri.gltf"), glb);
+
+        // the same files on disk
+        let root = std::env::temp_dir().join(format!("gltf-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("fixtures")).unwrap();
+        for name in ["triangle.gltf", "triangle.bin", "triangle texture.png"] {
+            let path = Path::new("fixtures").join(name);
+            std::fs::write(
+                root.join(&path),
+                ASSET_DIR.get_file(&path).unwrap().contents(),
+            )
+            .unwrap();
+        }
+        let disk = AssetManager::new();
+        disk.add_search_root(&root);
+        assert_eq!(model(&disk, "fixtures/triangle.gltf"), glb);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// a triangle without normals or indices, its material has a color and no texture
+    const BARE_TRIANGLE_GLTF: &str = r#"{
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0 }],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "material": 0 }] }],
+        "materials": [{ "pbrMetallicRoughness": { "baseColorFactor": [1.0, 0.5, 0.0, 1.0] } }],
+        "buffers": [{
+            "byteLength": 36,
+            "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA"
+        }],
+        "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+        "accessors": [{
+            "bufferView": 0,
+            "componentType": 5126,
+            "count": 3,
+            "type": "VEC3",
+            "min": [0.0, 0.0, 0.0],
+            "max": [1.0, 1.0, 0.0]
+        }]
+    }"#;
+
+    #[test]
+    fn bare_gltf_files_get_defaults_instead_of_panicking() {
+        let (gltf, buffers, images) = gltf::import_slice(BARE_TRIANGLE_GLTF.as_bytes()).unwrap();
+        let imported = AssetManager::gltf_to_model(gltf, buffers, images).unwrap();
+        assert_eq!(imported.warnings.len(), 1, "{:?}", imported.warnings);
+
+        let material = &imported.model.materials[0];
+        assert_eq!((material.albedo.width, material.albedo.height), (1, 1));
+        assert_eq!(material.albedo.data, vec![255; 4]);
+        assert_eq!(material.base_color_factor, Vec4::new(1.0, 0.5, 0.0, 1.0));
+        let prim = &imported.model.get_nodes_flattened()[0].meshes[0].primitives[0];
+        assert_eq!(prim.indices, vec![0, 1, 2]);
+        assert_eq!(prim.normals, vec![Vec3::Z; 3]);
+
+        // indices past the vertices leave nothing to draw
+        let broken = BARE_TRIANGLE_GLTF.replace(
+            r#""attributes": { "POSITION": 0 }, "material": 0"#,
+            r#""attributes": { "POSITION": 0 }, "indices": 1"#,
+        );
+        let broken = broken.replace(
+            r#""bufferViews": [{ "buffer": 0, "byteLength": 36 }]"#,
+            r#""bufferViews": [{ "buffer": 0, "byteLength": 36 }, { "buffer": 0, "byteOffset": 12, "byteLength": 12 }]"#,
+        );
+        let broken = broken.replace(
+            r#""max": [1.0, 1.0, 0.0]
+        }]"#,
+            r#""max": [1.0, 1.0, 0.0]
+        }, { "bufferView": 1, "componentType": 5125, "count": 3, "type": "SCALAR" }]"#,
+        );
+        let (gltf, buffers, images) = gltf::import_slice(broken.as_bytes()).unwrap();
+        assert!(AssetManager::gltf_to_model(gltf, buffers, images).is_err());
+
+        let assets = AssetManager::new();
+        assert!(assets.get_asset_by_path(Path::new("missing.glb")).is_err());
+    }
+
+    #[test]
+    fn material_factors_come_from_the_gltf() {
+        let factors = BARE_TRIANGLE_GLTF.replace(
+            r#""pbrMetallicRoughness": { "baseColorFactor": [1.0, 0.5, 0.0, 1.0] }"#,
+            r#""pbrMetallicRoughness": {
+                "baseColorFactor": [0.2, 0.4, 0.6, 0.8],
+                "metallicFactor": 0.25,
+                "roughnessFactor": 0.75
+            },
+            "emissiveFactor": [0.5, 0.25, 1.0]"#,
+        );
+        let (gltf, buffers, images) = gltf::import_slice(factors.as_bytes()).unwrap();
+        let imported = AssetManager::gltf_to_model(gltf, buffers, images).unwrap();
+
+        let material = &imported.model.materials[0];
+        assert_eq!(material.base_color_factor, Vec4::new(0.2, 0.4, 0.6, 0.8));
+        assert_eq!(material.metallic_factor, 0.25);
+        assert_eq!(material.roughness_factor, 0.75);
+        assert_eq!(material.emissive_factor, Vec3::new(0.5, 0.25, 1.0));
+        // the defaults for the textures it doesn't have
+        assert_eq!(material.normal_scale, 1.0);
+        assert_eq!(material.occlusion_strength, 1.0);
+        assert!(material.normals.is_none() && material.emissive.is_none());
+    }
+
+    #[test]
+    fn gltf_images_come_out_8_bit() {
+        use gltf::image::{Data, Format};
+        let image = |format, pixels: Vec<u8>| Data {
+            pixels,
+            format,
+            width: 1,
+            height: 1,
+        };
+        let texture = |image: &Data| {
+            let texture = Texture::from_gltf_image(image, TextureType::Albedo);
+            assert_eq!((texture.width, texture.height), (1, 1));
+            (texture.image_format, texture.data)
+        };
+
+        let (format, data) = texture(&image(Format::R8G8B8A8, vec![1, 2, 3, 4]));
+        assert!(matches!(format, ImageFormat::R8G8B8A8));
+        assert_eq!(data, [1, 2, 3, 4]);
+        let (format, data) = texture(&image(Format::R8, vec![9]));
+        assert!(matches!(format, ImageFormat::R8G8B8));
+        assert_eq!(data, [9, 9, 9]);
+        let (_, data) = texture(&image(Format::R8G8, vec![1, 2]));
+        assert_eq!(data, [1, 2, 0]);
+
+        let (format, data) = texture(&image(Format::R16, 0xFF00u16.to_ne_bytes().to_vec()));
+        assert!(matches!(format, ImageFormat::R8G8B8A8));
+        assert_eq!(data, [255, 255, 255, 255]);
+        let float = [1.0f32, 0.5, 0.0, 2.0]
+            .iter()
+            .flat_map(|c| c.to_ne_bytes())
+            .collect();
+        let (_, data) = texture(&image(Format::R32G32B32A32FLOAT, float));
+        assert_eq!(data, [255, 128, 0, 255]);
+    }
+
+    #[test]
+    fn vertex_colors_are_encoded_as_srgb() {
+        assert_eq!(srgb_vertex_color([0.0, 0.5, 1.0, 0.5]), [0, 188, 255, 128]);
+        assert_eq!(
+            srgb_vertex_color([0.002, 0.22, 2.0, -1.0]),
+            [7, 129, 255, 0]
+        );
+    }
+
+    #[test]
+    fn computed_tangents_follow_u() {
+        // quad facing +z with v going down like gltf's
+        let quad = MeshPrimitive {
+            positions: vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0), Vec3::Y],
+            normals: vec![Vec3::Z; 4],
+            tangents: Vec::new(),
+            tex_coords: vec![
+                Vec2::new(0.0, 1.0),
+                Vec2::new(1.0, 1.0),
+                Vec2::new(1.0, 0.0),
+                Vec2::new(0.0, 0.0),
+            ],
+            colors: None,
+            indices: vec![0, 1, 2, 0, 2, 3],
+            topology: PrimitiveTopology::Triangles,
+            material_index: None,
+        };
+
+        let tangents = quad.compute_tangents();
+        assert_eq!(tangents.len(), 4);
+        for tangent in tangents {
+            assert!(
+                tangent.abs_diff_eq(Vec4::new(1.0, 0.0, 0.0, 1.0), 1e-6),
+                "{tangent}"
+            );
+        }
+
+        // the same quad with u flipped is mirrored
+        let mirrored = MeshPrimitive {
+            tex_coords: quad
+                .tex_coords
+                .iter()
+                .map(|uv| Vec2::new(1.0 - uv.x, uv.y))
+                .collect(),
+            ..quad.clone()
+        };
+        for tangent in mirrored.compute_tangents() {
+            assert!(
+                tangent.abs_diff_eq(Vec4::new(-1.0, 0.0, 0.0, -1.0), 1e-6),
+                "{tangent}"
+            );
+        }
+
+        let no_uvs = MeshPrimitive {
+            tex_coords: Vec::new(),
+            ..quad
+        };
+        assert!(no_uvs.compute_tangents().is_empty());
+    }
+
+    #[test]
+    fn test_topology_unrolling() {
+        use gltf::mesh::Mode;
+        let (t, i) = PrimitiveTopology::from_gltf(Mode::TriangleStrip, vec![0, 1, 2, 3]);
+        assert_eq!(t, PrimitiveTopology::Triangles);
+        assert_eq!(i, vec![0, 1, 2, 2, 1, 3]);
+
+        let (_, i) = PrimitiveTopology::from_gltf(Mode::TriangleFan, vec![0, 1, 2, 3]);
+        assert_eq!(i, vec![0, 1, 2, 0, 2, 3]);
+
+        let (t, i) = PrimitiveTopology::from_gltf(Mode::LineLoop, vec![0, 1, 2]);
+        assert_eq!(t, PrimitiveTopology::Lines);
+        assert_eq!(i, vec![0, 1, 1, 2, 2, 0]);
+    }
+
+    #[test]
+    fn triangle_count_includes_child_nodes() {
+        let cuboid = crate::assets::basic_models::CuboidBuilder::new().build();
+        assert_eq!(cuboid.triangle_count(), 12);
+
+        let mut nested = cuboid.clone();
+        nested.nodes[0].nodes = cuboid.nodes.clone();
+        nested.nodes[0].meshes[0].primitives[0].topology = PrimitiveTopology::Lines;
+        assert_eq!(nested.triangle_count(), 12);
+    }
+
+    #[test]
+    fn assets_are_found_by_their_ids() {
+        let assets = AssetManager::new();
+        let lantern = Path::new("Lantern.glb");
+        let id = Uuid::new_v4();
+        assert_eq!(assets.id_for_path(lantern), AssetManager::asset_id(lantern));
+        assert_eq!(assets.path_for_id(id), None);
+
+        assets.assign_id(lantern, id).unwrap();
+        assert_eq!(assets.id_for_path(lantern), id);
+        assert_eq!(assets.path_for_id(id).as_deref(), Some(lantern));
+        assert!(assets.assign_id(Path::new("other.glb"), id).is_err());
+
+        let by_id = assets.get_asset_by_id(id).unwrap();
+        let (loaded_id, by_path) = assets.get_asset_by_path(lantern).unwrap();
+        assert_eq!(loaded_id, id);
+        assert!(Arc::ptr_eq(&by_id, &by_path));
+        match by_path.as_ref() {
+            Asset::Model(model) => assert_eq!(model.asset_id, Some(id)),
+            _ => panic!("not a model"),
+        }
+        // loaded assets keep their id
+        assert!(assets.assign_id(lantern, Uuid::new_v4()).is_err());
+        assert!(assets.get_asset_by_id(Uuid::new_v4()).is_none());
+    }
+
+    #[test]
+    fn threads_share_the_context_copy() {
+        let context = crate::engine::context::Context::new();
+        context.insert(AssetManager::new());
+        let threads: Vec<_> = (0..2)
+            .map(|_| {
+                let context = context.clone();
+                std::thread::spawn(move || {
+                    context
+                        .with(|assets: &AssetManager| {
+                            assets.get_asset_by_path(Path::new("Lantern.glb"))
+                        })
+                        .unwrap()
+                        .unwrap()
+                        .1
+                })
+            })
+            .collect();
+        let assets: Vec<Arc<Asset>> = threads
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .collect();
+        assert!(Arc::ptr_eq(&assets[0], &assets[1]));
+    }
+
+    #[test]
+    fn models_load_concurrently_in_the_background() {
+        let assets = AssetManager::new();
+        let paths = [Path::new("Lantern.glb"), Path::new("DamagedHelmet.glb")];
+        let handles = paths.map(|path| assets.load_async(path));
+        let missing = assets.load_async(Path::new("missing.glb"));
+        // still in flight, asking again shares the first handle
+        let again = assets.load_async(paths[1]);
+        assert_eq!(again.id(), handles[1].id());
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(60);
+        while handles
+            .iter()
+            .chain([&missing])
+            .any(AssetHandle::is_loading)
+        {
+            assert!(std::time::Instant::now() < deadline, "{handles:?}");
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+
+        for (path, handle) in paths.iter().zip(&handles) {
+            let LoadState::Ready(asset) = handle.state() else {
+                panic!("{handle:?}");
+            };
+            let (_, cached) = assets.get_asset_by_path(path).unwrap();
+            assert!(Arc::ptr_eq(&asset, &cached));
+            assert_eq!(handle.model().unwrap().asset_id, Some(handle.id()));
+            assert!(assets.load_async(path).asset().is_some());
+        }
+        assert!(matches!(missing.state(), LoadState::Failed(_)));
+    }
+
+    #[test]
+    fn changed_files_are_reloaded_and_broken_ones_keep_the_old_asset() {
+        let root = std::env::temp_dir().join(format!("assets-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        let level = Path::new("level.glb");
+        let file = root.join(level);
+        // file times can be too coarse to tell quick writes apart, so they're moved on by hand
+        let write = |bytes: &[u8], seconds: u64| {
+            std::fs::write(&file, bytes).unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&file)
+                .unwrap()
+                .set_modified(SystemTime::now() + Duration::from_secs(seconds))
+                .unwrap();
+        };
+        let embedded = |path: &str| ASSET_DIR.get_file(path).unwrap().contents();
+
+        let assets = AssetManager::new();
+        assets.add_search_root(&root);
+        std::fs::write(&file, embedded("Lantern.glb")).unwrap();
+        let (id, lantern) = assets.get_asset_by_path(level).unwrap();
+        assert_eq!(assets.reload_changed(), 0);
+
+        write(embedded("DamagedHelmet.glb"), 10);
+        assert_eq!(assets.reload_changed(), 1);
+        assert_eq!(
+            assets.drain_reloaded(),
+            vec![AssetReloaded {
+                id,
+                path: level.to_path_buf()
+            }]
+        );
+        let (_, helmet) = assets.get_asset_by_path(level).unwrap();
+        assert!(!Arc::ptr_eq(&lantern, &helmet));
+        match (lantern.as_ref(), helmet.as_ref()) {
+            (Asset::Model(lantern), Asset::Model(helmet)) => {
+                assert_eq!(helmet.asset_id, Some(id));
+                assert_ne!(lantern.triangle_count(), helmet.triangle_count());
+            }
+            _ => panic!("not models"),
+        }
+
+        write(b"not a gltf file", 20);
+        assert_eq!(assets.reload_changed(), 0);
+        assert!(!assets.has_reloaded());
+        let (_, kept) = assets.get_asset_by_path(level).unwrap();
+        assert!(Arc::ptr_eq(&helmet, &kept));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -142,115 +996,604 @@ pub enum Asset {
     Model(Model),
     Mesh(Mesh),
     Texture(Texture),
+    CubeMap(CubeTexture),
+}
+
+/// ids of the paths that were loaded or assigned one, both ways
+#[derive(Default)]
+struct AssetIds {
+    by_path: HashMap<PathBuf, Uuid>,
+    by_id: HashMap<Uuid, PathBuf>,
+}
+
+impl AssetIds {
+    fn insert(&mut self, path: &Path, id: Uuid) {
+        self.by_path.insert(path.to_path_buf(), id);
+        self.by_id.insert(id, path.to_path_buf());
+    }
 }
 
+/// the cache and ids, shared with the loader threads
+#[derive(Default)]
+struct AssetStore {
+    assets: RwLock<HashMap<PathBuf, Arc<Asset>>>,
+    ids: RwLock<AssetIds>,
+    /// handles of the `load_async` calls that haven't finished
+    loading: Mutex<HashMap<PathBuf, AssetHandle>>,
+    /// directories looked in before the embedded assets, in the order they were added
+    roots: RwLock<Vec<PathBuf>>,
+    /// when the files of the cached assets from the search roots were last changed
+    disk_times: Mutex<HashMap<PathBuf, SystemTime>>,
+    /// reloads nobody has taken yet
+    reloaded: Mutex<Vec<AssetReloaded>>,
+    /// what the last import of each model had to make up or leave out
+    warnings: RwLock<HashMap<PathBuf, Vec<String>>>,
+}
+
+impl AssetStore {
+    fn cached(&self, path: &Path) -> Option<Arc<Asset>> {
+        self.assets.read().unwrap().get(path).cloned()
+    }
+
+    /// puts a decoded asset in the cache. when another thread cached the same path while this
+    /// one was decoding, theirs is kept so everyone shares one copy
+    fn cache(&self, path: &Path, id: Uuid, asset: Asset) -> Arc<Asset> {
+        let asset = self
+            .assets
+            .write()
+            .unwrap()
+            .entry(path.to_path_buf())
+            .or_insert_with(|| Arc::new(asset))
+            .clone();
+        self.ids.write().unwrap().insert(path, id);
+        asset
+    }
+
+    /// decodes and caches the asset at `path`, remembering when its file last changed
+    fn load(&self, path: &Path, id: Uuid) -> anyhow::Result<Arc<Asset>> {
+        // taken before reading, so a change made while decoding is reloaded later
+        let modified = self.modified(path);
+        let asset = self.cache(path, id, self.decode(path, id)?);
+        if let Some(modified) = modified {
+            self.disk_times
+                .lock()
+                .unwrap()
+                .entry(path.to_path_buf())
+                .or_insert(modified);
+        }
+        Ok(asset)
+    }
+
+    /// decodes the asset at `path` without touching the cache, models get the id `id`
+    fn decode(&self, path: &Path, id: Uuid) -> anyhow::Result<Asset> {
+        if let Some(cube_map) = self.load_cube_map(path) {
+            return Ok(Asset::CubeMap(cube_map?));
+        }
+        let bytes = self
+            .read(path)
+            .ok_or(anyhow::anyhow!("{} not found", path.display()))?;
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if matches!(extension.as_str(), "png" | "jpg" | "jpeg") {
+            return Ok(Asset::Texture(Texture::from_image_bytes(
+                &bytes,
+                TextureType::Albedo,
+            )?));
+        }
+        let (gltf, buffers, images) = self.import_gltf(path, &bytes)?;
+        let imported = AssetManager::gltf_to_model(gltf, buffers, images)?;
+        for warning in &imported.warnings {
+            log::warn!("{}: {warning}", path.display());
+        }
+        self.warnings
+            .write()
+            .unwrap()
+            .insert(path.to_path_buf(), imported.warnings);
+        Ok(Asset::Model(Model {
+            asset_id: Some(id),
+            ..imported.model
+        }))
+    }
+
+    /// imports a `.glb` or `.gltf`, external buffers and images are looked up next to it,
+    /// on disk through `gltf::import` or in the embedded assets
+    fn import_gltf(
+        &self,
+        path: &Path,
+        bytes: &[u8],
+    ) -> anyhow::Result<(Document, Vec<gltf::buffer::Data>, Vec<gltf::image::Data>)> {
+        if let Some(file) = self.on_disk(path) {
+            return Ok(gltf::import(file)?);
+        }
+
+        let gltf::Gltf { document, mut blob } = gltf::Gltf::from_slice(bytes)?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        let sibling = |uri: &str| {
+            let path = base.join(uri_path(uri));
+            self.read(&path)
+                .ok_or(anyhow::anyhow!("{} not found", path.display()))
+        };
+        let buffers = document
+            .buffers()
+            .map(|buffer| match buffer.source() {
+                gltf::buffer::Source::Uri(uri) if !uri.starts_with("data:") => {
+                    let mut data = sibling(uri)?.into_owned();
+                    // padded like the importer does
+                    while data.len() % 4 != 0 {
+                        data.push(0);
+                    }
+                    Ok(gltf::buffer::Data(data))
+                }
+                source => Ok(gltf::buffer::Data::from_source_and_blob(
+                    source, None, &mut blob,
+                )?),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let images = document
+            .images()
+            .map(|gltf_image| match gltf_image.source() {
+                gltf::image::Source::Uri { uri, .. } if !uri.starts_with("data:") => {
+                    Ok(gltf_image_data(image::load_from_memory(&sibling(uri)?)?))
+                }
+                source => Ok(gltf::image::Data::from_source(source, None, &buffers)?),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok((document, buffers, images))
+    }
+
+    /// loads a cube map if `path` refers to one, either a `.hdr` equirectangular image or six
+    /// `name_px.png` ... `name_nz.png` faces where `name.png` itself doesn't exist
+    fn load_cube_map(&self, path: &Path) -> Option<anyhow::Result<CubeTexture>> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        if extension == "hdr" {
+            let bytes = self.read(path)?;
+            return Some(cube_map::load_equirectangular(path, &bytes));
+        }
+
+        let [first_face, ..] = CubeTexture::face_paths(path);
+        if self.exists(path) || !self.exists(&first_face) {
+            return None;
+        }
+        Some(cube_map::load_cube_faces(path, |p| {
+            self.read(p).map(Cow::into_owned)
+        }))
+    }
+
+    /// where `path` is on disk, in the first search root that has it
+    fn on_disk(&self, path: &Path) -> Option<PathBuf> {
+        self.roots
+            .read()
+            .unwrap()
+            .iter()
+            .map(|root| root.join(path))
+            .find(|file| file.is_file())
+    }
+
+    fn modified(&self, path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(self.on_disk(path)?).ok()?.modified().ok()
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.on_disk(path).is_some() || ASSET_DIR.get_file(path).is_some()
+    }
+
+    /// contents of `path` from the search roots, or the embedded assets when none has it
+    fn read(&self, path: &Path) -> Option<Cow<'static, [u8]>> {
+        if let Some(file) = self.on_disk(path) {
+            match std::fs::read(&file) {
+                Ok(bytes) => return Some(Cow::Owned(bytes)),
+                Err(e) => log::warn!("couldn't read {}: {e}", file.display()),
+            }
+        }
+        ASSET_DIR
+            .get_file(path)
+            .map(|f| Cow::Borrowed(f.contents()))
+    }
+
+    /// decodes the cached assets whose files changed since they were loaded again and swaps
+    /// them in, returns how many were
+    fn reload_changed(&self) -> usize {
+        let changed: Vec<(PathBuf, SystemTime)> = self
+            .disk_times
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(path, loaded)| {
+                let modified = self.modified(path)?;
+                (modified != *loaded).then(|| (path.clone(), modified))
+            })
+            .collect();
+
+        let mut reloaded = 0;
+        for (path, modified) in changed {
+            // files that fail to load aren't tried again until they change again
+            self.disk_times
+                .lock()
+                .unwrap()
+                .insert(path.clone(), modified);
+            let id = self
+                .ids
+                .read()
+                .unwrap()
+                .by_path
+                .get(&path)
+                .copied()
+                .unwrap_or_else(|| AssetManager::asset_id(&path));
+            match self.decode(&path, id) {
+                Ok(asset) => {
+                    self.assets
+                        .write()
+                        .unwrap()
+                        .insert(path.clone(), Arc::new(asset));
+                    log::info!("reloaded {}", path.display());
+                    self.reloaded
+                        .lock()
+                        .unwrap()
+                        .push(AssetReloaded { id, path });
+                    reloaded += 1;
+                }
+                Err(e) => log::error!(
+                    "kept the old {}, the changed file failed to load: {e}",
+                    path.display()
+                ),
+            }
+        }
+        reloaded
+    }
+}
+
+/// loads and caches the embedded assets, and files from the search roots, shared through the
+/// engine `Context`
+///
+/// everything takes `&self`, so the render and game threads can load through a read lock on the
+/// context item at the same time. the cache is only locked to look assets up and put them in,
+/// decoding happens without it
 pub struct AssetManager {
-    asset_cache: HashMap<PathBuf, Arc<Asset>>,
+    store: Arc<AssetStore>,
+    /// decodes both the streamed and the `load_async` assets
+    loader: OnceLock<StreamingLoader>,
+    watcher: Mutex<Option<Watcher>>,
 }
 
+impl ContextItem for AssetManager {}
+
 impl AssetManager {
     pub fn new() -> Self {
         Self {
-            asset_cache: HashMap::new(),
+            store: Arc::new(AssetStore::default()),
+            loader: OnceLock::new(),
+            watcher: Mutex::new(None),
         }
     }
 
-    pub fn get_asset_by_path(&mut self, path: &Path) -> Option<(Uuid, Arc<Asset>)> {
-        let _span = tracy_client::span!("loading asset");
-        log::debug!("assets: {:?}", ASSET_DIR.files().collect::<Vec<_>>());
-        if let Some(asset) = self.asset_cache.get(path) {
-            Some((Uuid::nil(), Arc::clone(asset)))
-        } else {
-            if let Some(file) = ASSET_DIR.get_file(path) {
-                let (gltf, buffers, images) = gltf::import_slice(file.contents()).ok()?;
-                let model = AssetManager::gltf_to_model(gltf, buffers, images);
+    /// looks for assets in the directory `root` before the embedded ones, with paths relative
+    /// to it like they are to the assets dir. roots added first are looked in first
+    pub fn add_search_root(&self, root: impl Into<PathBuf>) {
+        self.store.roots.write().unwrap().push(root.into());
+    }
+
+    /// checks the files of the cached assets from the search roots every `interval` and
+    /// reloads the ones that changed, see `reload_changed`. replaces an earlier watch
+    pub fn watch(&self, interval: Duration) {
+        let store = Arc::downgrade(&self.store);
+        let watcher = Watcher::spawn(interval, move || {
+            let Some(store) = store.upgrade() else {
+                return false;
+            };
+            store.reload_changed();
+            true
+        });
+        *self.watcher.lock().unwrap() = Some(watcher);
+    }
 
-                let model_arc = Arc::new(Asset::Model(model));
-                self.asset_cache
-                    .insert(path.to_path_buf(), model_arc.clone());
-                Some((Uuid::nil(), model_arc))
-            } else {
-                log::info!("file not found");
+    pub fn stop_watching(&self) {
+        self.watcher.lock().unwrap().take();
+    }
 
+    /// decodes the cached assets whose files in the search roots changed again and swaps the
+    /// new ones into the cache, returns how many were reloaded
+    ///
+    /// every reload is queued as an `AssetReloaded` for `drain_reloaded`. a file that fails to
+    /// load leaves the old asset in the cache and logs why
+    pub fn reload_changed(&self) -> usize {
+        self.store.reload_changed()
+    }
+
+    /// takes the reloads since the last call, the engine passes them on to the renderer
+    pub fn drain_reloaded(&self) -> Vec<AssetReloaded> {
+        std::mem::take(&mut *self.store.reloaded.lock().unwrap())
+    }
+
+    pub fn has_reloaded(&self) -> bool {
+        !self.store.reloaded.lock().unwrap().is_empty()
+    }
+
+    /// starts loading the model at `path` in the background and returns right away
+    ///
+    /// the handle shows a gray box the size of the model's bounds until the model is decoded.
+    /// models that are already cached come back ready.
+    pub fn load_streaming(&self, path: &Path) -> Option<StreamingModel> {
+        if let Some(Asset::Model(model)) = self.store.cached(path).as_deref() {
+            return Some(StreamingModel::ready(path, model.clone()));
+        }
+
+        let Some(file) = ASSET_DIR.get_file(path) else {
+            log::info!("file not found");
+            return None;
+        };
+        let bytes = file.contents();
+        let handle = StreamingModel::pending(path, streaming::placeholder_for(bytes));
+        self.loader
+            .get_or_init(StreamingLoader::new)
+            .queue(handle.clone(), bytes);
+        Some(handle)
+    }
+
+    /// starts decoding the asset at `path` on a loader thread and returns right away, poll the
+    /// handle for the result. the asset lands in the cache like `get_asset_by_path` would put it
+    /// there
+    ///
+    /// asking for a path that's still loading returns the handle of the first call, cached
+    /// assets come back ready
+    pub fn load_async(&self, path: &Path) -> AssetHandle {
+        let id = self.id_for_path(path);
+        let mut loading = self.store.loading.lock().unwrap();
+        // checked under the lock, a loader can't finish between this and registering the handle
+        if let Some(asset) = self.store.cached(path) {
+            return AssetHandle::ready(path, id, asset);
+        }
+        if let Some(handle) = loading.get(path) {
+            return handle.clone();
+        }
+
+        let handle = AssetHandle::loading(path, id);
+        loading.insert(path.to_path_buf(), handle.clone());
+        drop(loading);
+
+        let store = Arc::clone(&self.store);
+        let path = path.to_path_buf();
+        let job_handle = handle.clone();
+        self.loader
+            .get_or_init(StreamingLoader::new)
+            .spawn(move || {
+                let _span = tracy_client::span!("loading asset async");
+                let loaded = std::panic::catch_unwind(AssertUnwindSafe(|| store.load(&path, id)))
+                    .unwrap_or_else(|_| Err(anyhow::anyhow!("decoder panicked")));
+                let state = match loaded {
+                    Ok(asset) => LoadState::Ready(asset),
+                    Err(e) => {
+                        log::error!("couldn't load {}: {e}", path.display());
+                        LoadState::Failed(e.to_string())
+                    }
+                };
+                store.loading.lock().unwrap().remove(&path);
+                job_handle.finish(state);
+            });
+        handle
+    }
+
+    /// id a path gets when it wasn't assigned one, the same in every run
+    pub fn asset_id(path: &Path) -> Uuid {
+        Uuid::new_v5(&Uuid::NAMESPACE_URL, path.to_string_lossy().as_bytes())
+    }
+
+    /// gives `path` the id `id` instead of its default, for ids from a manifest or a saved scene.
+    /// has to happen before the path is loaded, and every path needs an id of its own
+    pub fn assign_id(&self, path: &Path, id: Uuid) -> anyhow::Result<()> {
+        let mut ids = self.store.ids.write().unwrap();
+        if let Some(other) = ids.by_id.get(&id).filter(|other| *other != path) {
+            return Err(anyhow::anyhow!(
+                "{id} is already the id of {}",
+                other.display()
+            ));
+        }
+        match ids.by_path.get(path).copied() {
+            Some(current) if current == id => return Ok(()),
+            Some(current) if self.store.cached(path).is_some() => {
+                return Err(anyhow::anyhow!(
+                    "{} is already loaded as {current}",
+                    path.display()
+                ));
+            }
+            Some(current) => {
+                ids.by_id.remove(&current);
+            }
+            None => (),
+        }
+        ids.insert(path, id);
+        Ok(())
+    }
+
+    /// the id `path` was assigned, or its default
+    pub fn id_for_path(&self, path: &Path) -> Uuid {
+        self.store
+            .ids
+            .read()
+            .unwrap()
+            .by_path
+            .get(path)
+            .copied()
+            .unwrap_or_else(|| AssetManager::asset_id(path))
+    }
+
+    /// path of an asset that was loaded or assigned the id `id`
+    pub fn path_for_id(&self, id: Uuid) -> Option<PathBuf> {
+        self.store.ids.read().unwrap().by_id.get(&id).cloned()
+    }
+
+    /// the asset with the id `id`, loading it if it was assigned that id and isn't cached yet
+    pub fn get_asset_by_id(&self, id: Uuid) -> Option<Arc<Asset>> {
+        let path = self.path_for_id(id)?;
+        self.loaded(&path).map(|(_, asset)| asset)
+    }
+
+    /// a copy of the model at `path`, `None` when it's missing or isn't a model
+    pub fn get_model(&self, path: &Path) -> Option<Model> {
+        match self.loaded(path)?.1.as_ref() {
+            Asset::Model(model) => Some(model.clone()),
+            _ => {
+                log::warn!("{} isn't a model", path.display());
                 None
             }
         }
     }
 
+    /// a copy of the png or jpeg at `path`, `None` when it's missing or isn't a texture
+    pub fn get_texture(&self, path: &Path) -> Option<Texture> {
+        match self.loaded(path)?.1.as_ref() {
+            Asset::Texture(texture) => Some(texture.clone()),
+            _ => {
+                log::warn!("{} isn't a texture", path.display());
+                None
+            }
+        }
+    }
+
+    /// loads the asset at `path` into the cache the first time, fails when it's missing or
+    /// can't be decoded
+    pub fn get_asset_by_path(&self, path: &Path) -> anyhow::Result<(Uuid, Arc<Asset>)> {
+        let _span = tracy_client::span!("loading asset");
+        log::debug!("assets: {:?}", ASSET_DIR.files().collect::<Vec<_>>());
+        let id = self.id_for_path(path);
+        if let Some(asset) = self.store.cached(path) {
+            return Ok((id, asset));
+        }
+        self.store
+            .load(path, id)
+            .map(|asset| (id, asset))
+            .map_err(|e| e.context(format!("couldn't load {}", path.display())))
+    }
+
+    /// what the import of the model at `path` had to make up or leave out, empty for other
+    /// assets and ones that aren't loaded
+    pub fn import_warnings(&self, path: &Path) -> Vec<String> {
+        self.store
+            .warnings
+            .read()
+            .unwrap()
+            .get(path)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// `get_asset_by_path` for the getters returning `Option`, logs why it failed
+    fn loaded(&self, path: &Path) -> Option<(Uuid, Arc<Asset>)> {
+        self.get_asset_by_path(path)
+            .inspect_err(|e| log::error!("{e:#}"))
+            .ok()
+    }
+
+    /// turns an imported gltf into a model, filling in what the engine needs and the file left
+    /// out. what couldn't be used is in the warnings, fails when none of the meshes could be
     pub fn gltf_to_model(
         gltf: Document,
         buffers: Vec<gltf::buffer::Data>,
         images: Vec<gltf::image::Data>,
-    ) -> Model {
+    ) -> anyhow::Result<ImportedModel> {
         let _span = tracy_client::span!("gltf to model");
-        let nodes = gltf
-            .nodes()
-            .map(|node| AssetManager::gltf_node_to_model_node(&node, &gltf, &buffers, &images))
+        let mut warnings = Vec::new();
+        // `gltf.nodes()` has every child a second time and nodes no scene uses, only the scene's
+        // roots go in and the children come with them
+        let roots: Vec<gltf::Node> = match gltf.default_scene().or_else(|| gltf.scenes().next()) {
+            Some(scene) => scene.nodes().collect(),
+            None => {
+                let children: HashSet<usize> = gltf
+                    .nodes()
+                    .flat_map(|node| node.children().map(|child| child.index()))
+                    .collect();
+                gltf.nodes()
+                    .filter(|node| !children.contains(&node.index()))
+                    .collect()
+            }
+        };
+        let nodes: Vec<ModelNode> = roots
+            .iter()
+            .map(|node| AssetManager::gltf_node_to_model_node(node, &buffers, &mut warnings))
             .collect();
+        fn has_meshes(nodes: &[ModelNode]) -> bool {
+            nodes
+                .iter()
+                .any(|node| !node.meshes.is_empty() || has_meshes(&node.nodes))
+        }
+        if gltf.meshes().next().is_some() && !has_meshes(&nodes) {
+            return Err(anyhow::anyhow!(
+                "none of the meshes could be imported: {}",
+                warnings.join(", ")
+            ));
+        }
 
         let materials = gltf
             .materials()
             .map(|mat| {
-                let albedo_texture_index = mat
-                    .pbr_metallic_roughness()
-                    .base_color_texture()
-                    .unwrap()
-                    .texture()
-                    .source()
-                    .index();
-                let albedo_image = images.get(albedo_texture_index).unwrap();
-                let albedo_format = match albedo_image.format {
-                    gltf::image::Format::R8G8B8 => ImageFormat::R8G8B8,
-                    gltf::image::Format::R8G8B8A8 => ImageFormat::R8G8B8A8,
-                    _ => panic!("unsupported image format"),
-                };
-                let albedo = Texture {
-                    texture_type: TextureType::Albedo,
-                    image_format: albedo_format,
-                    width: albedo_image.width,
-                    height: albedo_image.height,
-                    data: albedo_image.pixels.clone(),
-                };
-
-                let normals = {
-                    if let Some(normal_texture) = mat.normal_texture() {
-                        let index = normal_texture.texture().source().index();
-                        let image: &gltf::image::Data = images.get(index).unwrap();
-                        let normals = Texture {
-                            texture_type: TextureType::Normal,
-                            image_format: ImageFormat::R8G8B8,
-                            width: image.width,
-                            height: image.height,
-                            data: image.pixels.clone(),
-                        };
-                        Some(normals)
-                    } else {
-                        None
+                let name = mat.name().unwrap_or("unnamed");
+                let pbr = mat.pbr_metallic_roughness();
+                let mut texture = |texture: gltf::texture::Texture, texture_type| {
+                    let image = images.get(texture.source().index());
+                    if image.is_none() {
+                        warnings.push(format!(
+                            "material {name} uses image {}, which isn't there",
+                            texture.source().index()
+                        ));
                     }
+                    Some(Texture::from_gltf_image(image?, texture_type))
                 };
-
-                Material { albedo, normals }
+                let albedo = pbr
+                    .base_color_texture()
+                    .and_then(|info| texture(info.texture(), TextureType::Albedo))
+                    .unwrap_or_else(|| Texture::white(TextureType::Albedo));
+                Material {
+                    albedo,
+                    normals: mat
+                        .normal_texture()
+                        .and_then(|normal| texture(normal.texture(), TextureType::Normal)),
+                    normal_scale: mat.normal_texture().map_or(1.0, |normal| normal.scale()),
+                    metallic_roughness: pbr
+                        .metallic_roughness_texture()
+                        .and_then(|info| texture(info.texture(), TextureType::MetallicRoughness)),
+                    emissive: mat
+                        .emissive_texture()
+                        .and_then(|info| texture(info.texture(), TextureType::Emissive)),
+                    occlusion: mat
+                        .occlusion_texture()
+                        .and_then(|info| texture(info.texture(), TextureType::Occlusion)),
+                    base_color_factor: Vec4::from_array(pbr.base_color_factor()),
+                    metallic_factor: pbr.metallic_factor(),
+                    roughness_factor: pbr.roughness_factor(),
+                    emissive_factor: Vec3::from_array(mat.emissive_factor()),
+                    occlusion_strength: mat.occlusion_texture().map_or(1.0, |o| o.strength()),
+                    unlit: false,
+                }
             })
             .collect();
 
-        Model { nodes, materials }
+        Ok(ImportedModel {
+            model: Model {
+                nodes,
+                materials,
+                asset_id: None,
+            },
+            warnings,
+        })
     }
 
-    /// a recursive function that turns every gltf node into a ```ModelNode```
+    /// turns a gltf node with its own mesh, and its children, into a ```ModelNode```
     fn gltf_node_to_model_node(
         node: &gltf::Node,
-        gltf: &Document,
         buffers: &Vec<gltf::buffer::Data>,
-        images: &Vec<gltf::image::Data>,
+        warnings: &mut Vec<String>,
     ) -> ModelNode {
         let transform = Mat4::from_cols_array_2d(&node.transform().matrix());
-        // println!("name: {:?}, transform: {:?}", node.name(), transform);
 
         let meshes = match node.mesh() {
-            Some(m) => match AssetManager::gltf_mesh_to_mesh(&m, buffers) {
+            Some(m) => match AssetManager::gltf_mesh_to_mesh(&m, buffers, warnings) {
                 Ok(mesh) => vec![mesh],
                 Err(e) => {
-                    log::info!("mesh loading error: {}", e);
+                    warnings.push(format!(
+                        "left out mesh {}: {e}",
+                        m.name().unwrap_or(&m.index().to_string())
+                    ));
                     Vec::new()
                 }
             },
@@ -259,7 +1602,7 @@ impl AssetManager {
 
         let nodes = node
             .children()
-            .map(|n| AssetManager::gltf_node_to_model_node(&n, gltf, buffers, images))
+            .map(|n| AssetManager::gltf_node_to_model_node(&n, buffers, warnings))
             .collect();
 
         ModelNode {
@@ -269,9 +1612,12 @@ impl AssetManager {
         }
     }
 
+    /// fails when a primitive has no positions or indices past its vertices, made up normals go
+    /// in `warnings`
     pub fn gltf_mesh_to_mesh(
         gltf_mesh: &gltf::Mesh,
         buffers: &Vec<gltf::buffer::Data>,
+        warnings: &mut Vec<String>,
     ) -> anyhow::Result<Mesh> {
         let primitives = gltf_mesh
             .primitives()
@@ -281,32 +1627,152 @@ impl AssetManager {
                     Some(tcs) => tcs.into_f32().map(|tc| Vec2::from_array(tc)).collect(),
                     None => Vec::new(),
                 };
-                let mesh_primitive = MeshPrimitive {
-                    positions: reader
-                        .read_positions()
-                        .unwrap()
-                        .map(|p| Vec3::from_array(p))
-                        .collect(),
-                    normals: reader
-                        .read_normals()
-                        .unwrap()
-                        .map(|n| Vec3::from_array(n))
-                        .collect(),
+                let positions: Vec<Vec3> = reader
+                    .read_positions()
+                    .ok_or(anyhow::anyhow!(
+                        "primitive {} has no positions",
+                        prim.index()
+                    ))?
+                    .map(|p| Vec3::from_array(p))
+                    .collect();
+                // points and lines usually come without normals
+                let normals = match reader.read_normals() {
+                    Some(normals) => normals.map(|n| Vec3::from_array(n)).collect(),
+                    None => Vec::new(),
+                };
+                // non-indexed primitives just use their vertices in order
+                let indices = match reader.read_indices() {
+                    Some(indices) => indices.into_u32().collect(),
+                    None => (0..positions.len() as u32).collect(),
+                };
+                let tangents = match reader.read_tangents() {
+                    Some(tangents) => tangents.map(|t| Vec4::from_array(t)).collect(),
+                    None => Vec::new(),
+                };
+                // gltf's vertex colors are linear, the renderer takes them as srgb
+                let colors = reader
+                    .read_colors(0)
+                    .map(|colors| colors.into_rgba_f32().map(srgb_vertex_color).collect());
+                if let Some(index) = indices.iter().find(|&&i| i as usize >= positions.len()) {
+                    return Err(anyhow::anyhow!(
+                        "primitive {} uses vertex {index} of {}",
+                        prim.index(),
+                        positions.len()
+                    ));
+                }
+                let (topology, indices) = PrimitiveTopology::from_gltf(prim.mode(), indices);
+
+                let mut mesh_primitive = MeshPrimitive {
+                    positions,
+                    normals,
+                    tangents,
                     tex_coords,
-                    indices: reader.read_indices().unwrap().into_u32().collect(),
+                    colors,
+                    indices,
+                    topology,
                     material_index: prim.material().index(),
                 };
+                if mesh_primitive.normals.is_empty() && topology == PrimitiveTopology::Triangles {
+                    warnings.push(format!(
+                        "primitive {} of mesh {} has no normals, it's shaded flat",
+                        prim.index(),
+                        gltf_mesh.index()
+                    ));
+                    mesh_primitive = mesh_primitive.with_flat_normals();
+                }
+                // normal maps need tangents, plenty of files leave them out
+                if mesh_primitive.tangents.is_empty() && prim.material().normal_texture().is_some()
+                {
+                    mesh_primitive.tangents = mesh_primitive.compute_tangents();
+                }
 
-                mesh_primitive
+                Ok(mesh_primitive)
             })
-            .collect();
+            .collect::<anyhow::Result<Vec<_>>>()?;
 
         let mesh = Mesh { primitives };
 
         Ok(mesh)
     }
+}
 
-    pub fn get_asset_by_id(&mut self, id: Uuid) -> Asset {
-        todo!()
+/// a relative uri from a gltf as a path, with its `%20`s and such turned back into characters
+fn uri_path(uri: &str) -> PathBuf {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| uri.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
     }
+    PathBuf::from(String::from_utf8_lossy(&decoded).into_owned())
+}
+
+/// a decoded image in the formats the gltf importer gives them, 16 bit and float images are
+/// brought down to 8 bit rgba
+fn gltf_image_data(image: image::DynamicImage) -> gltf::image::Data {
+    use gltf::image::Format;
+    let (width, height) = (image.width(), image.height());
+    let (format, pixels) = match image {
+        image::DynamicImage::ImageLuma8(image) => (Format::R8, image.into_raw()),
+        image::DynamicImage::ImageLumaA8(image) => (Format::R8G8, image.into_raw()),
+        image::DynamicImage::ImageRgb8(image) => (Format::R8G8B8, image.into_raw()),
+        image => (Format::R8G8B8A8, image.into_rgba8().into_raw()),
+    };
+    gltf::image::Data {
+        pixels,
+        format,
+        width,
+        height,
+    }
+}
+
+/// 16 bit or float pixels with `channels` channels of `size` bytes as 8 bit rgba
+fn wide_to_rgba8(pixels: &[u8], channels: usize, size: usize) -> Vec<u8> {
+    let values: Vec<u8> = pixels
+        .chunks_exact(size)
+        .map(|value| match value {
+            [a, b] => (u16::from_ne_bytes([*a, *b]) >> 8) as u8,
+            [a, b, c, d] => {
+                (f32::from_ne_bytes([*a, *b, *c, *d]).clamp(0.0, 1.0) * 255.0).round() as u8
+            }
+            _ => 0,
+        })
+        .collect();
+    values
+        .chunks_exact(channels)
+        .flat_map(|p| match p {
+            [r] => [*r, *r, *r, 255],
+            [r, g] => [*r, *g, 0, 255],
+            [r, g, b] => [*r, *g, *b, 255],
+            [r, g, b, a, ..] => [*r, *g, *b, *a],
+            [] => [0, 0, 0, 255],
+        })
+        .collect()
+}
+
+/// linear rgba, 0 to 1, as 8 bit srgb with a linear alpha
+fn srgb_vertex_color(color: [f32; 4]) -> [u8; 4] {
+    let encode = |c: f32| {
+        let c = c.clamp(0.0, 1.0);
+        if c <= 0.0031308 {
+            c * 12.92
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        }
+    };
+    let [r, g, b, a] = color;
+    [encode(r), encode(g), encode(b), a.clamp(0.0, 1.0)].map(|c| (c * 255.0).round() as u8)
 }
@@ -3,17 +3,30 @@
 use std::primitive;
 
 use glam::{Vec2, Vec3};
+use rapier3d::prelude::{Collider, ColliderBuilder};
 
 use crate::{
-    assets::asset_manager::{self, Material, MeshPrimitive, Model, ModelNode},
-    utils::deg_to_rad,
+    assets::asset_manager::{self, Material, MeshPrimitive, Model, ModelNode, PrimitiveTopology},
+    rendering::sprite::UvRect,
 };
 
+/// white albedo so a builder's vertex colors show as they are
+fn vertex_color_material() -> Material {
+    Material::new(asset_manager::Texture {
+        texture_type: asset_manager::TextureType::Albedo,
+        image_format: asset_manager::ImageFormat::R8G8B8A8,
+        width: 1,
+        height: 1,
+        data: vec![255; 4],
+    })
+}
+
 pub struct CuboidBuilder {
     hx: f32,
     hy: f32,
     hz: f32,
-    color: image::Rgba<u8>,
+    /// front, back, left, right, top, bottom
+    face_colors: [image::Rgba<u8>; 6],
 }
 
 impl CuboidBuilder {
@@ -22,7 +35,7 @@ impl CuboidBuilder {
             hx: 1.0,
             hy: 1.0,
             hz: 1.0,
-            color: image::Rgba::from([255, 255, 255, 255]),
+            face_colors: [image::Rgba::from([255, 255, 255, 255]); 6],
         }
     }
 
@@ -34,7 +47,13 @@ impl CuboidBuilder {
     }
 
     pub fn color(mut self, color: image::Rgba<u8>) -> Self {
-        self.color = color;
+        self.face_colors = [color; 6];
+        self
+    }
+
+    /// a color per face, in the order front, back, left, right, top, bottom
+    pub fn face_colors(mut self, face_colors: [image::Rgba<u8>; 6]) -> Self {
+        self.face_colors = face_colors;
         self
     }
 
@@ -105,6 +124,7 @@ impl CuboidBuilder {
                     Vec3::new(0.0, -1.0, 0.0),
                     Vec3::new(0.0, -1.0, 0.0),
                 ],
+                tangents: Vec::new(),
                 tex_coords: vec![
                     // front face
                     Vec2::new(0.0, 0.0),
@@ -137,11 +157,18 @@ impl CuboidBuilder {
                     Vec2::new(1.0, 1.0),
                     Vec2::new(0.0, 1.0),
                 ],
+                colors: Some(
+                    self.face_colors
+                        .iter()
+                        .flat_map(|color| [color.0; 4])
+                        .collect(),
+                ),
                 indices: vec![
                     0, 1, 2, 2, 3, 0, 4, 5, 6, 6, 7, 4, 8, 9, 10, 10, 11, 8, 12, 13, 14, 14, 15,
                     12, 16, 17, 18, 18, 19, 16, 20, 21, 22, 22, 23, 20,
                 ],
-                material_index: None,
+                topology: PrimitiveTopology::Triangles,
+                material_index: Some(0),
             }],
         };
 
@@ -151,45 +178,455 @@ impl CuboidBuilder {
             nodes: Vec::new(),
         };
 
-        let material = Material {
-            albedo: asset_manager::Texture {
+        Model {
+            nodes: vec![model_node],
+            materials: vec![vertex_color_material()],
+            asset_id: None,
+        }
+    }
+}
+
+/// flat quad in the xy plane facing +z, centered on the origin. a `Billboard` turns it to the
+/// camera, with a texture and a sprite's `uv_rect` it shows a frame of a sprite sheet
+pub struct QuadBuilder {
+    width: f32,
+    height: f32,
+    color: image::Rgba<u8>,
+    texture: Option<asset_manager::Texture>,
+    uv_rect: UvRect,
+}
+
+impl QuadBuilder {
+    pub fn new() -> Self {
+        Self {
+            width: 1.0,
+            height: 1.0,
+            color: image::Rgba::from([255, 255, 255, 255]),
+            texture: None,
+            uv_rect: UvRect::FULL,
+        }
+    }
+
+    pub fn size(mut self, width: f32, height: f32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// multiplied with the texture when there is one
+    pub fn color(mut self, color: image::Rgba<u8>) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn texture(mut self, texture: asset_manager::Texture) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    /// the part of the texture that's shown, origin at the top left
+    pub fn uv_rect(mut self, uv_rect: UvRect) -> Self {
+        self.uv_rect = uv_rect;
+        self
+    }
+
+    pub fn build(self) -> Model {
+        let (hw, hh) = (self.width / 2.0, self.height / 2.0);
+        let UvRect {
+            x,
+            y,
+            width,
+            height,
+        } = self.uv_rect;
+        let primitive = MeshPrimitive {
+            positions: vec![
+                Vec3::new(-hw, -hh, 0.0),
+                Vec3::new(hw, -hh, 0.0),
+                Vec3::new(hw, hh, 0.0),
+                Vec3::new(-hw, hh, 0.0),
+            ],
+            normals: vec![Vec3::Z; 4],
+            tangents: Vec::new(),
+            tex_coords: vec![
+                Vec2::new(x, y + height),
+                Vec2::new(x + width, y + height),
+                Vec2::new(x + width, y),
+                Vec2::new(x, y),
+            ],
+            colors: Some(vec![self.color.0; 4]),
+            indices: vec![0, 1, 2, 2, 3, 0],
+            topology: PrimitiveTopology::Triangles,
+            material_index: Some(0),
+        };
+        let material = match self.texture {
+            Some(texture) => Material::new(asset_manager::Texture {
                 texture_type: asset_manager::TextureType::Albedo,
-                image_format: asset_manager::ImageFormat::R8G8B8A8,
-                width: 2,
-                height: 2,
-                data: vec![
-                    self.color[0],
-                    self.color[1],
-                    self.color[2],
-                    self.color[3],
-                    //
-                    self.color[0],
-                    self.color[1],
-                    self.color[2],
-                    self.color[3],
-                    //
-                    self.color[0],
-                    self.color[1],
-                    self.color[2],
-                    self.color[3],
-                    //
-                    self.color[0],
-                    self.color[1],
-                    self.color[2],
-                    self.color[3],
-                ],
-            },
-            normals: None,
+                ..texture
+            }),
+            None => vertex_color_material(),
         };
 
         Model {
-            nodes: vec![model_node],
+            nodes: vec![ModelNode {
+                transform: glam::Mat4::IDENTITY,
+                meshes: vec![asset_manager::Mesh {
+                    primitives: vec![primitive],
+                }],
+                nodes: Vec::new(),
+            }],
             materials: vec![material],
+            asset_id: None,
+        }
+    }
+}
+
+/// where a terrain's heights come from, heights are expected in 0..1
+#[derive(Clone, Debug)]
+pub enum HeightSource {
+    /// grayscale heightmap, the first channel of every pixel is the height
+    Texture(asset_manager::Texture),
+    /// `function(column, row, seed)` sampled once per grid point
+    Noise {
+        function: fn(f32, f32, u32) -> f32,
+        seed: u32,
+    },
+    /// `heights[row][column]`, has to be rectangular
+    Grid(Vec<Vec<f32>>),
+}
+
+/// a terrain mesh and the height grid it was built from
+///
+/// `heights` is row major, rows go along z and columns along x. the grid is centered on the
+/// origin like a rapier heightfield so `collider()` lines up with the mesh vertex for vertex.
+#[derive(Clone, Debug)]
+pub struct Terrain {
+    pub model: Model,
+    pub heights: Vec<f32>,
+    pub rows: usize,
+    pub columns: usize,
+    pub cell_size: f32,
+    pub height_scale: f32,
+}
+
+impl Terrain {
+    pub fn height(&self, row: usize, column: usize) -> f32 {
+        self.heights[row * self.columns + column]
+    }
+
+    /// position of a grid point in model space
+    pub fn vertex(&self, row: usize, column: usize) -> Vec3 {
+        Vec3::new(
+            (column as f32 - (self.columns - 1) as f32 / 2.0) * self.cell_size,
+            self.height(row, column) * self.height_scale,
+            (row as f32 - (self.rows - 1) as f32 / 2.0) * self.cell_size,
+        )
+    }
+
+    pub fn collider(&self) -> Collider {
+        heightfield(
+            self.rows,
+            self.columns,
+            self.cell_size,
+            self.height_scale,
+            |r, c| self.height(r, c),
+        )
+    }
+}
+
+/// heightfield collider centered on the origin over a `rows` x `columns` grid, the same surface
+/// `TerrainBuilder` meshes
+fn heightfield(
+    rows: usize,
+    columns: usize,
+    cell_size: f32,
+    height_scale: f32,
+    height: impl Fn(usize, usize) -> f32,
+) -> Collider {
+    let heights = rapier3d::na::DMatrix::from_fn(rows, columns, height);
+    ColliderBuilder::heightfield(
+        heights,
+        rapier3d::na::Vector3::new(
+            cell_size * (columns - 1) as f32,
+            height_scale,
+            cell_size * (rows - 1) as f32,
+        ),
+    )
+    .build()
+}
+
+/// rows and columns of a `heights[row][column]` grid, it has to be rectangular and at least 2x2
+fn grid_size(heights: &[Vec<f32>]) -> anyhow::Result<(usize, usize)> {
+    let rows = heights.len();
+    let columns = heights.first().map_or(0, Vec::len);
+    if rows < 2 || columns < 2 {
+        return Err(anyhow::anyhow!(
+            "height grid needs at least 2x2 samples, got {columns}x{rows}"
+        ));
+    }
+    if let Some(row) = heights.iter().position(|r| r.len() != columns) {
+        return Err(anyhow::anyhow!(
+            "height grid row {row} has {} samples, expected {columns}",
+            heights[row].len()
+        ));
+    }
+    Ok((rows, columns))
+}
+
+/// builds a grid terrain mesh and the matching heightfield data
+pub struct TerrainBuilder {
+    source: HeightSource,
+    rows: usize,
+    columns: usize,
+    cell_size: f32,
+    height_scale: f32,
+    uv_tiling: f32,
+    chunk_cells: usize,
+    color: image::Rgba<u8>,
+}
+
+impl TerrainBuilder {
+    /// cells per chunk side, keeps every primitive well under u32 index limits
+    const DEFAULT_CHUNK_CELLS: usize = 128;
+
+    pub fn new(source: HeightSource) -> Self {
+        let (rows, columns) = match &source {
+            HeightSource::Texture(t) => (t.height as usize, t.width as usize),
+            HeightSource::Noise { .. } => (129, 129),
+            HeightSource::Grid(heights) => (heights.len(), heights.first().map_or(0, Vec::len)),
+        };
+        Self {
+            source,
+            rows,
+            columns,
+            cell_size: 1.0,
+            height_scale: 10.0,
+            uv_tiling: 1.0,
+            chunk_cells: Self::DEFAULT_CHUNK_CELLS,
+            color: image::Rgba::from([255, 255, 255, 255]),
+        }
+    }
+
+    /// grid points along z and x, only used by noise sources
+    pub fn samples(mut self, rows: usize, columns: usize) -> Self {
+        if let HeightSource::Noise { .. } = self.source {
+            self.rows = rows;
+            self.columns = columns;
+        }
+        self
+    }
+
+    pub fn cell_size(mut self, cell_size: f32) -> Self {
+        self.cell_size = cell_size;
+        self
+    }
+
+    pub fn height_scale(mut self, height_scale: f32) -> Self {
+        self.height_scale = height_scale;
+        self
+    }
+
+    /// how many times the texture repeats over one cell
+    pub fn uv_tiling(mut self, uv_tiling: f32) -> Self {
+        self.uv_tiling = uv_tiling;
+        self
+    }
+
+    pub fn chunk_cells(mut self, chunk_cells: usize) -> Self {
+        self.chunk_cells = chunk_cells.max(1);
+        self
+    }
+
+    pub fn color(mut self, color: image::Rgba<u8>) -> Self {
+        self.color = color;
+        self
+    }
+
+    fn sample_heights(&self) -> Vec<f32> {
+        match &self.source {
+            HeightSource::Texture(texture) => {
+                let channels = texture.image_format.channels();
+                (0..self.rows * self.columns)
+                    .map(|i| texture.data.get(i * channels).copied().unwrap_or(0) as f32 / 255.0)
+                    .collect()
+            }
+            HeightSource::Noise { function, seed } => (0..self.rows)
+                .flat_map(|r| (0..self.columns).map(move |c| (r, c)))
+                .map(|(r, c)| function(c as f32, r as f32, *seed))
+                .collect(),
+            HeightSource::Grid(heights) => heights.iter().flatten().copied().collect(),
+        }
+    }
+
+    pub fn build(self) -> anyhow::Result<Terrain> {
+        if let HeightSource::Grid(heights) = &self.source {
+            grid_size(heights)?;
+        }
+        if self.rows < 2 || self.columns < 2 {
+            return Err(anyhow::anyhow!(
+                "terrain needs at least 2x2 samples, got {}x{}",
+                self.columns,
+                self.rows
+            ));
         }
+
+        let mut terrain = Terrain {
+            model: Model {
+                nodes: Vec::new(),
+                materials: Vec::new(),
+                asset_id: None,
+            },
+            heights: self.sample_heights(),
+            rows: self.rows,
+            columns: self.columns,
+            cell_size: self.cell_size,
+            height_scale: self.height_scale,
+        };
+
+        let nodes = (0..self.rows - 1)
+            .step_by(self.chunk_cells)
+            .flat_map(|r| {
+                (0..self.columns - 1)
+                    .step_by(self.chunk_cells)
+                    .map(move |c| (r, c))
+            })
+            .map(|(r, c)| ModelNode {
+                transform: glam::Mat4::IDENTITY,
+                meshes: vec![asset_manager::Mesh {
+                    primitives: vec![self.chunk(&terrain, r, c)],
+                }],
+                nodes: Vec::new(),
+            })
+            .collect();
+
+        terrain.model = Model {
+            nodes,
+            materials: vec![vertex_color_material()],
+            asset_id: None,
+        };
+        Ok(terrain)
+    }
+
+    /// normal from the height gradient over the whole grid so chunk edges match
+    fn normal(&self, terrain: &Terrain, row: usize, column: usize) -> Vec3 {
+        let (c0, c1) = (column.saturating_sub(1), (column + 1).min(self.columns - 1));
+        let (r0, r1) = (row.saturating_sub(1), (row + 1).min(self.rows - 1));
+        let dx = (terrain.height(row, c1) - terrain.height(row, c0)) * self.height_scale
+            / ((c1 - c0) as f32 * self.cell_size);
+        let dz = (terrain.height(r1, column) - terrain.height(r0, column)) * self.height_scale
+            / ((r1 - r0) as f32 * self.cell_size);
+        Vec3::new(-dx, 1.0, -dz).normalize()
+    }
+
+    fn chunk(&self, terrain: &Terrain, row: usize, column: usize) -> MeshPrimitive {
+        let last_row = (row + self.chunk_cells).min(self.rows - 1);
+        let last_column = (column + self.chunk_cells).min(self.columns - 1);
+        let width = (last_column - column + 1) as u32;
+
+        let points: Vec<_> = (row..=last_row)
+            .flat_map(|r| (column..=last_column).map(move |c| (r, c)))
+            .collect();
+
+        let indices = (0..(last_row - row) as u32)
+            .flat_map(|r| (0..width - 1).map(move |c| (r, c)))
+            .flat_map(|(r, c)| {
+                // same diagonal rapier's heightfield uses so the surfaces match between vertices
+                let i = r * width + c;
+                [i, i + width, i + width + 1, i, i + width + 1, i + 1]
+            })
+            .collect();
+
+        MeshPrimitive {
+            positions: points.iter().map(|&(r, c)| terrain.vertex(r, c)).collect(),
+            normals: points
+                .iter()
+                .map(|&(r, c)| self.normal(terrain, r, c))
+                .collect(),
+            tangents: Vec::new(),
+            tex_coords: points
+                .iter()
+                .map(|&(r, c)| Vec2::new(c as f32, r as f32) * self.uv_tiling)
+                .collect(),
+            colors: Some(vec![self.color.0; points.len()]),
+            indices,
+            topology: PrimitiveTopology::Triangles,
+            material_index: Some(0),
+        }
+    }
+}
+
+/// mesh for a grid of heights used as they are, `collider` makes the heightfield that matches it
+pub struct HeightfieldBuilder {
+    heights: Vec<Vec<f32>>,
+    cell_size: f32,
+    color: image::Rgba<u8>,
+}
+
+impl HeightfieldBuilder {
+    /// `heights[row][column]`, rows go along z and columns along x
+    pub fn new(heights: Vec<Vec<f32>>, cell_size: f32) -> Self {
+        Self {
+            heights,
+            cell_size,
+            color: image::Rgba::from([255, 255, 255, 255]),
+        }
+    }
+
+    pub fn color(mut self, color: image::Rgba<u8>) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn collider(&self) -> anyhow::Result<Collider> {
+        let (rows, columns) = grid_size(&self.heights)?;
+        Ok(heightfield(rows, columns, self.cell_size, 1.0, |r, c| {
+            self.heights[r][c]
+        }))
+    }
+
+    pub fn build(self) -> anyhow::Result<Model> {
+        let terrain = TerrainBuilder::new(HeightSource::Grid(self.heights))
+            .cell_size(self.cell_size)
+            .height_scale(1.0)
+            .color(self.color)
+            .build()?;
+        Ok(terrain.model)
+    }
+}
+
+/// smooth value noise in 0..1, a few octaves with a base period of 32 samples
+pub fn value_noise(x: f32, z: f32, seed: u32) -> f32 {
+    fn lattice(x: i32, z: i32, seed: u32) -> f32 {
+        let mut h = (x as u32)
+            .wrapping_mul(0x8da6_b343)
+            .wrapping_add((z as u32).wrapping_mul(0xd816_3841))
+            .wrapping_add(seed.wrapping_mul(0xcb1a_b31f));
+        h = (h ^ (h >> 13)).wrapping_mul(0x5bd1_e995);
+        (h ^ (h >> 15)) as f32 / u32::MAX as f32
     }
+
+    fn octave(x: f32, z: f32, seed: u32) -> f32 {
+        let (x0, z0) = (x.floor(), z.floor());
+        let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+        let (tx, tz) = (smooth(x - x0), smooth(z - z0));
+        let (xi, zi) = (x0 as i32, z0 as i32);
+        let top = lattice(xi, zi, seed) + (lattice(xi + 1, zi, seed) - lattice(xi, zi, seed)) * tx;
+        let bottom = lattice(xi, zi + 1, seed)
+            + (lattice(xi + 1, zi + 1, seed) - lattice(xi, zi + 1, seed)) * tx;
+        top + (bottom - top) * tz
+    }
+
+    let (mut total, mut amplitude, mut frequency, mut weight) = (0.0, 1.0, 1.0 / 32.0, 0.0);
+    for i in 0..4 {
+        total += octave(x * frequency, z * frequency, seed.wrapping_add(i)) * amplitude;
+        weight += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    total / weight
 }
 
-struct SphereBuilder {
+/// uv sphere centered on the origin, the texture wraps around once with the seam at +z
+pub struct SphereBuilder {
     radius: f32,
     color: image::Rgba<u8>,
     radial_segments: u32,
@@ -216,6 +653,7 @@ impl SphereBuilder {
         self
     }
 
+    /// `rings` go from pole to pole, at least 3 radial segments and 2 rings are used
     pub fn segments(mut self, radial_segments: u32, rings: u32) -> Self {
         self.radial_segments = radial_segments;
         self.rings = rings;
@@ -223,55 +661,665 @@ impl SphereBuilder {
     }
 
     pub fn build(self) -> Model {
-        todo!()
+        let rings = self.rings.max(2);
+        let profile: Vec<_> = (0..=rings)
+            .map(|r| {
+                let v = r as f32 / rings as f32;
+                ProfilePoint::arc(self.radius, v * std::f32::consts::PI, 0.0, v)
+            })
+            .collect();
+
+        let mut geometry = Geometry::default();
+        geometry.lathe(&profile, self.radial_segments);
+        geometry.into_model(self.color)
     }
+}
 
-    fn uv_sphere(&self) -> MeshPrimitive {
-        let north_pole = Vec3::new(0.0, self.radius, 0.0);
-        let south_pole = Vec3::new(0.0, -self.radius, 0.0);
+/// capsule along y centered on the origin, `height` is the length of the straight part
+/// between the two half spheres like in `ColliderBuilder::capsule_y`
+pub struct CapsuleBuilder {
+    radius: f32,
+    height: f32,
+    color: image::Rgba<u8>,
+    radial_segments: u32,
+    rings: u32,
+}
 
-        let rings: Vec<Vec<_>> = (0..self.rings)
-            .map(|r| {
-                let ring_y =
-                    f32::cos((deg_to_rad(180.0) as f32 / (self.rings - 1) as f32) * (r + 1) as f32);
-                let ring: Vec<_> = (0..self.radial_segments)
-                    .map(|s| {
-                        let rotation =
-                            (deg_to_rad(360.0) as f32 / self.radial_segments as f32) * (s as f32);
-                        Vec3::new(f32::cos(rotation), ring_y, f32::sin(rotation))
-                    })
-                    .collect();
-
-                ring
-            })
+impl CapsuleBuilder {
+    pub fn new() -> Self {
+        Self {
+            radius: 0.5,
+            height: 1.0,
+            color: image::Rgba::from([255, 255, 255, 255]),
+            radial_segments: 32,
+            rings: 8,
+        }
+    }
+
+    pub fn size(mut self, radius: f32, height: f32) -> Self {
+        self.radius = radius;
+        self.height = height;
+        self
+    }
+
+    pub fn color(mut self, color: image::Rgba<u8>) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// `rings` per half sphere, at least 3 radial segments and 1 ring are used
+    pub fn segments(mut self, radial_segments: u32, rings: u32) -> Self {
+        self.radial_segments = radial_segments;
+        self.rings = rings;
+        self
+    }
+
+    pub fn build(self) -> Model {
+        use std::f32::consts::FRAC_PI_2;
+
+        let rings = self.rings.max(1);
+        let (radius, half_height) = (self.radius, self.height.max(0.0) / 2.0);
+        // v follows the length of the outline so the texture isn't stretched on the caps
+        let length = 2.0 * FRAC_PI_2 * radius + 2.0 * half_height;
+        let v = |distance: f32| if length > 0.0 { distance / length } else { 0.0 };
+
+        let top = (0..=rings).map(|r| {
+            let angle = r as f32 / rings as f32 * FRAC_PI_2;
+            ProfilePoint::arc(radius, angle, half_height, v(angle * radius))
+        });
+        // without a straight part the equator rings are the same, one of them is enough
+        let first_bottom = if half_height > 0.0 { 0 } else { 1 };
+        let bottom = (first_bottom..=rings).map(|r| {
+            let angle = FRAC_PI_2 + r as f32 / rings as f32 * FRAC_PI_2;
+            ProfilePoint::arc(
+                radius,
+                angle,
+                -half_height,
+                v(angle * radius + 2.0 * half_height),
+            )
+        });
+        let profile: Vec<_> = top.chain(bottom).collect();
+
+        let mut geometry = Geometry::default();
+        geometry.lathe(&profile, self.radial_segments);
+        geometry.into_model(self.color)
+    }
+}
+
+/// closed cylinder along y centered on the origin
+pub struct CylinderBuilder {
+    radius: f32,
+    height: f32,
+    color: image::Rgba<u8>,
+    radial_segments: u32,
+}
+
+impl CylinderBuilder {
+    pub fn new() -> Self {
+        Self {
+            radius: 0.5,
+            height: 1.0,
+            color: image::Rgba::from([255, 255, 255, 255]),
+            radial_segments: 32,
+        }
+    }
+
+    pub fn size(mut self, radius: f32, height: f32) -> Self {
+        self.radius = radius;
+        self.height = height;
+        self
+    }
+
+    pub fn color(mut self, color: image::Rgba<u8>) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// at least 3 are used
+    pub fn segments(mut self, radial_segments: u32) -> Self {
+        self.radial_segments = radial_segments;
+        self
+    }
+
+    pub fn build(self) -> Model {
+        let half_height = self.height / 2.0;
+        let side = [
+            ProfilePoint {
+                radius: self.radius,
+                y: half_height,
+                normal: Vec2::X,
+                v: 0.0,
+            },
+            ProfilePoint {
+                radius: self.radius,
+                y: -half_height,
+                normal: Vec2::X,
+                v: 1.0,
+            },
+        ];
+
+        let mut geometry = Geometry::default();
+        geometry.lathe(&side, self.radial_segments);
+        geometry.disc(self.radius, half_height, true, self.radial_segments);
+        geometry.disc(self.radius, -half_height, false, self.radial_segments);
+        geometry.into_model(self.color)
+    }
+}
+
+/// cone along y centered on the origin, pointing up, with a closed base
+pub struct ConeBuilder {
+    radius: f32,
+    height: f32,
+    color: image::Rgba<u8>,
+    radial_segments: u32,
+}
+
+impl ConeBuilder {
+    pub fn new() -> Self {
+        Self {
+            radius: 0.5,
+            height: 1.0,
+            color: image::Rgba::from([255, 255, 255, 255]),
+            radial_segments: 32,
+        }
+    }
+
+    /// `radius` of the base
+    pub fn size(mut self, radius: f32, height: f32) -> Self {
+        self.radius = radius;
+        self.height = height;
+        self
+    }
+
+    pub fn color(mut self, color: image::Rgba<u8>) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// at least 3 are used
+    pub fn segments(mut self, radial_segments: u32) -> Self {
+        self.radial_segments = radial_segments;
+        self
+    }
+
+    pub fn build(self) -> Model {
+        let half_height = self.height / 2.0;
+        // perpendicular to the slope, the same all the way up
+        let normal = Vec2::new(self.height, self.radius).normalize_or(Vec2::Y);
+        let side = [
+            ProfilePoint {
+                radius: 0.0,
+                y: half_height,
+                normal,
+                v: 0.0,
+            },
+            ProfilePoint {
+                radius: self.radius,
+                y: -half_height,
+                normal,
+                v: 1.0,
+            },
+        ];
+
+        let mut geometry = Geometry::default();
+        geometry.lathe(&side, self.radial_segments);
+        geometry.disc(self.radius, -half_height, false, self.radial_segments);
+        geometry.into_model(self.color)
+    }
+}
+
+/// flat grid in the xz plane facing +y, centered on the origin
+pub struct PlaneBuilder {
+    width: f32,
+    depth: f32,
+    color: image::Rgba<u8>,
+    segments_x: u32,
+    segments_z: u32,
+    uv_tiling: Vec2,
+}
+
+impl PlaneBuilder {
+    pub fn new() -> Self {
+        Self {
+            width: 1.0,
+            depth: 1.0,
+            color: image::Rgba::from([255, 255, 255, 255]),
+            segments_x: 1,
+            segments_z: 1,
+            uv_tiling: Vec2::ONE,
+        }
+    }
+
+    /// `width` along x, `depth` along z
+    pub fn size(mut self, width: f32, depth: f32) -> Self {
+        self.width = width;
+        self.depth = depth;
+        self
+    }
+
+    pub fn color(mut self, color: image::Rgba<u8>) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// quads along x and z, at least 1 each
+    pub fn segments(mut self, segments_x: u32, segments_z: u32) -> Self {
+        self.segments_x = segments_x;
+        self.segments_z = segments_z;
+        self
+    }
+
+    /// how many times the texture repeats along x and z, needs a repeating sampler
+    pub fn uv_tiling(mut self, x: f32, z: f32) -> Self {
+        self.uv_tiling = Vec2::new(x, z);
+        self
+    }
+
+    pub fn build(self) -> Model {
+        let (columns, rows) = (self.segments_x.max(1), self.segments_z.max(1));
+        let mut geometry = Geometry::default();
+        for row in 0..=rows {
+            for column in 0..=columns {
+                let uv = Vec2::new(column as f32 / columns as f32, row as f32 / rows as f32);
+                geometry.positions.push(Vec3::new(
+                    (uv.x - 0.5) * self.width,
+                    0.0,
+                    (uv.y - 0.5) * self.depth,
+                ));
+                geometry.normals.push(Vec3::Y);
+                geometry.tex_coords.push(uv * self.uv_tiling);
+            }
+        }
+
+        let width = columns + 1;
+        for row in 0..rows {
+            for column in 0..columns {
+                let i = row * width + column;
+                geometry
+                    .indices
+                    .extend([i, i + width, i + width + 1, i, i + width + 1, i + 1]);
+            }
+        }
+        geometry.into_model(self.color)
+    }
+}
+
+/// point on the outline a `Geometry::lathe` turns around the y axis, `normal` is in the
+/// (radius, y) plane
+struct ProfilePoint {
+    radius: f32,
+    y: f32,
+    normal: Vec2,
+    v: f32,
+}
+
+impl ProfilePoint {
+    /// point `angle` radians down from the top of a circle centered at `center_y`
+    fn arc(radius: f32, angle: f32, center_y: f32, v: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        // sin isn't exactly 0 at pi, the poles have to be for their triangles to be found
+        let sin = if sin.abs() < 1e-6 { 0.0 } else { sin };
+        Self {
+            radius: radius * sin,
+            y: center_y + radius * cos,
+            normal: Vec2::new(sin, cos),
+            v,
+        }
+    }
+}
+
+/// vertices and triangles of a shape being built, triangles are counter clockwise from outside
+#[derive(Default)]
+struct Geometry {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    tex_coords: Vec<Vec2>,
+    indices: Vec<u32>,
+}
+
+impl Geometry {
+    /// turns `profile` around the y axis, from top to bottom
+    ///
+    /// every ring gets an extra vertex at the seam so u goes all the way to 1, points with a
+    /// radius of 0 are poles and get one triangle per segment instead of a quad
+    fn lathe(&mut self, profile: &[ProfilePoint], segments: u32) {
+        let segments = segments.max(3);
+        let stride = segments + 1;
+        let first = self.positions.len() as u32;
+
+        for point in profile {
+            for s in 0..=segments {
+                let (sin, cos) = (s as f32 / segments as f32 * std::f32::consts::TAU).sin_cos();
+                self.positions
+                    .push(Vec3::new(point.radius * sin, point.y, point.radius * cos));
+                self.normals.push(
+                    Vec3::new(point.normal.x * sin, point.normal.y, point.normal.x * cos)
+                        .normalize_or(Vec3::Y),
+                );
+                // a pole vertex is the tip of one triangle, its u is that triangle's middle
+                let u = if point.radius == 0.0 {
+                    (s as f32 + 0.5) / segments as f32
+                } else {
+                    s as f32 / segments as f32
+                };
+                self.tex_coords.push(Vec2::new(u, point.v));
+            }
+        }
+
+        for (ring, pair) in profile.windows(2).enumerate() {
+            let top = first + ring as u32 * stride;
+            let bottom = top + stride;
+            for s in 0..segments {
+                let (a0, a1, b0, b1) = (top + s, top + s + 1, bottom + s, bottom + s + 1);
+                match (pair[0].radius == 0.0, pair[1].radius == 0.0) {
+                    (true, true) => {}
+                    (true, false) => self.indices.extend([b0, b1, a0]),
+                    (false, true) => self.indices.extend([b0, a1, a0]),
+                    (false, false) => self.indices.extend([b0, b1, a1, b0, a1, a0]),
+                }
+            }
+        }
+    }
+
+    /// flat cap at `y` facing +y when `up`, -y otherwise
+    fn disc(&mut self, radius: f32, y: f32, up: bool, segments: u32) {
+        let segments = segments.max(3);
+        let normal = if up { Vec3::Y } else { Vec3::NEG_Y };
+        let center = self.positions.len() as u32;
+
+        self.positions.push(Vec3::new(0.0, y, 0.0));
+        self.normals.push(normal);
+        self.tex_coords.push(Vec2::splat(0.5));
+        for s in 0..=segments {
+            let (sin, cos) = (s as f32 / segments as f32 * std::f32::consts::TAU).sin_cos();
+            self.positions
+                .push(Vec3::new(radius * sin, y, radius * cos));
+            self.normals.push(normal);
+            self.tex_coords
+                .push(Vec2::new(0.5 + 0.5 * sin, 0.5 - 0.5 * cos));
+        }
+
+        for s in 0..segments {
+            let (r0, r1) = (center + 1 + s, center + 2 + s);
+            if up {
+                self.indices.extend([center, r0, r1]);
+            } else {
+                self.indices.extend([center, r1, r0]);
+            }
+        }
+    }
+
+    fn into_model(self, color: image::Rgba<u8>) -> Model {
+        let primitive = MeshPrimitive {
+            colors: Some(vec![color.0; self.positions.len()]),
+            positions: self.positions,
+            normals: self.normals,
+            tangents: Vec::new(),
+            tex_coords: self.tex_coords,
+            indices: self.indices,
+            topology: PrimitiveTopology::Triangles,
+            material_index: Some(0),
+        };
+
+        Model {
+            nodes: vec![ModelNode {
+                transform: glam::Mat4::IDENTITY,
+                meshes: vec![asset_manager::Mesh {
+                    primitives: vec![primitive],
+                }],
+                nodes: Vec::new(),
+            }],
+            materials: vec![vertex_color_material()],
+            asset_id: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rapier3d::{
+        na::{Point3, Vector3},
+        parry::query::{Ray, RayCast},
+    };
+
+    use super::*;
+
+    fn noise_terrain(chunk_cells: usize) -> Terrain {
+        TerrainBuilder::new(HeightSource::Noise {
+            function: value_noise,
+            seed: 7,
+        })
+        .samples(17, 21)
+        .cell_size(2.0)
+        .height_scale(8.0)
+        .chunk_cells(chunk_cells)
+        .build()
+        .unwrap()
+    }
+
+    #[test]
+    fn chunks_cover_the_grid() {
+        let terrain = noise_terrain(8);
+        // 16x20 cells in 8 cell chunks
+        assert_eq!(terrain.model.nodes.len(), 2 * 3);
+
+        let triangles: usize = terrain
+            .model
+            .nodes
+            .iter()
+            .flat_map(|n| n.meshes.iter())
+            .flat_map(|m| m.primitives.iter())
+            .map(|p| p.indices.len() / 3)
+            .sum();
+        assert_eq!(triangles, 16 * 20 * 2);
+    }
+
+    #[test]
+    fn collider_matches_mesh_vertices() {
+        let terrain = noise_terrain(128);
+        let collider = terrain.collider();
+        let primitive = &terrain.model.nodes[0].meshes[0].primitives[0];
+
+        for r in 1..terrain.rows - 1 {
+            for c in 1..terrain.columns - 1 {
+                let vertex = primitive.positions[r * terrain.columns + c];
+                assert_eq!(vertex, terrain.vertex(r, c));
+
+                let ray = Ray::new(
+                    Point3::new(vertex.x, 100.0, vertex.z),
+                    Vector3::new(0.0, -1.0, 0.0),
+                );
+                let toi = collider
+                    .shape()
+                    .cast_ray(collider.position(), &ray, 200.0, true)
+                    .unwrap();
+                assert!((100.0 - toi - vertex.y).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn heightfield_model_matches_collider() {
+        let heights: Vec<Vec<f32>> = (0..5)
+            .map(|r| (0..4).map(|c| (r * c) as f32 * 0.5).collect())
             .collect();
+        let builder = HeightfieldBuilder::new(heights.clone(), 1.5);
+        let collider = builder.collider().unwrap();
+        let model = builder.build().unwrap();
+        let primitive = &model.nodes[0].meshes[0].primitives[0];
+
+        for (r, c) in [(0, 0), (2, 1), (4, 3)] {
+            let vertex = primitive.positions[r * 4 + c];
+            assert_eq!(vertex.y, heights[r][c]);
+
+            let ray = Ray::new(
+                Point3::new(vertex.x, 100.0, vertex.z),
+                Vector3::new(0.0, -1.0, 0.0),
+            );
+            let toi = collider
+                .shape()
+                .cast_ray(collider.position(), &ray, 200.0, true)
+                .unwrap();
+            assert!((100.0 - toi - vertex.y).abs() < 1e-3, "({r}, {c})");
+        }
+        // slopes up along both axes
+        let normal = primitive.normals[2 * 4 + 1];
+        assert!(
+            normal.x < 0.0 && normal.z < 0.0 && normal.y > 0.0,
+            "{normal}"
+        );
+
+        assert!(
+            HeightfieldBuilder::new(vec![vec![0.0; 3], vec![0.0; 2]], 1.0)
+                .build()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn cuboid_faces_carry_their_colors() {
+        let red = image::Rgba([255, 0, 0, 255]);
+        let blue = image::Rgba([0, 0, 255, 255]);
+        let model = CuboidBuilder::new()
+            .face_colors([red, red, red, red, blue, red])
+            .build();
+        let primitive = &model.nodes[0].meshes[0].primitives[0];
+        let colors = primitive.colors.as_ref().unwrap();
+        assert_eq!(colors.len(), primitive.positions.len());
+
+        for (i, (color, position)) in colors.iter().zip(&primitive.positions).enumerate() {
+            // the top face is the fifth
+            if (16..20).contains(&i) {
+                assert_eq!(*color, blue.0);
+                assert_eq!(position.y, 0.5);
+            } else {
+                assert_eq!(*color, red.0);
+            }
+        }
+        assert_eq!(model.materials[0].albedo.data, vec![255; 4]);
+    }
 
-        let indices = {
-            let north_pole_indices: Vec<u32> = (1..self.radial_segments)
-                .map(|i| vec![0, i, i + 1])
-                .flatten()
-                .collect();
-
-            let middle_indices: Vec<u32> = (2..self.rings)
-                .map(|r| {
-                    ((r * self.radial_segments)..=(r * self.radial_segments + self.radial_segments))
-                        .map(|i| {
-                            vec![
-                                i,
-                                i - self.radial_segments,
-                                i + 1,
-                                i + 1,
-                                i - self.radial_segments,
-                                i + 1 - self.radial_segments,
-                            ]
-                        })
-                        .flatten()
-                        .collect::<Vec<_>>()
-                })
-                .flatten()
-                .collect();
+    #[test]
+    fn quad_shows_the_sprite_frame() {
+        let frame = UvRect {
+            x: 0.25,
+            y: 0.5,
+            width: 0.25,
+            height: 0.5,
         };
+        let model = QuadBuilder::new().size(2.0, 1.0).uv_rect(frame).build();
+        let primitive = &model.nodes[0].meshes[0].primitives[0];
+        assert_eq!(
+            model.bounds(),
+            Some((Vec3::new(-1.0, -0.5, 0.0), Vec3::new(1.0, 0.5, 0.0)))
+        );
+        // top left corner of the quad shows the top left of the frame
+        assert_eq!(primitive.positions[3], Vec3::new(-1.0, 0.5, 0.0));
+        assert_eq!(primitive.tex_coords[3], Vec2::new(0.25, 0.5));
+        assert_eq!(primitive.tex_coords[1], Vec2::new(0.5, 1.0));
+    }
+
+    #[test]
+    fn flat_terrain_normals_point_up() {
+        let terrain = TerrainBuilder::new(HeightSource::Noise {
+            function: |_, _, _| 0.5,
+            seed: 0,
+        })
+        .samples(4, 4)
+        .build()
+        .unwrap();
+        let primitive = &terrain.model.nodes[0].meshes[0].primitives[0];
+        assert!(primitive.normals.iter().all(|n| *n == Vec3::Y));
+    }
+
+    /// indices in range, unit normals and every triangle facing the way its normals do
+    fn assert_well_formed(model: &Model) -> &MeshPrimitive {
+        let primitive = &model.nodes[0].meshes[0].primitives[0];
+        let vertices = primitive.positions.len();
+        assert_eq!(primitive.normals.len(), vertices);
+        assert_eq!(primitive.tex_coords.len(), vertices);
+        assert_eq!(primitive.colors.as_ref().unwrap().len(), vertices);
+        assert_eq!(model.materials.len(), 1);
+        assert_eq!(primitive.indices.len() % 3, 0);
+        assert!(primitive.indices.iter().all(|i| (*i as usize) < vertices));
+        assert!(
+            primitive
+                .normals
+                .iter()
+                .all(|n| (n.length() - 1.0).abs() < 1e-4)
+        );
+
+        for triangle in primitive.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+            let p = &primitive.positions;
+            let face = (p[b] - p[a]).cross(p[c] - p[a]);
+            assert!(face.length() > 0.0, "degenerate triangle {triangle:?}");
+            let normal = primitive.normals[a] + primitive.normals[b] + primitive.normals[c];
+            assert!(
+                face.dot(normal) > 0.0,
+                "triangle {triangle:?} is wound backwards"
+            );
+        }
+        primitive
+    }
+
+    #[test]
+    fn sphere_vertices_sit_on_the_radius() {
+        let model = SphereBuilder::new().radius(2.0).segments(12, 6).build();
+        let primitive = assert_well_formed(&model);
+        // two pole rings of one triangle per segment, quads in between
+        assert_eq!(primitive.indices.len() / 3, 12 * 2 + 12 * 4 * 2);
+        for (position, normal) in primitive.positions.iter().zip(&primitive.normals) {
+            assert!((position.length() - 2.0).abs() < 1e-4);
+            assert!((*position / 2.0 - *normal).length() < 1e-4);
+        }
+        let (min, max) = model.bounds().unwrap();
+        assert!((max.y - 2.0).abs() < 1e-6 && (min.y + 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn capsule_reaches_past_its_straight_part() {
+        let model = CapsuleBuilder::new().size(0.5, 2.0).segments(16, 4).build();
+        assert_well_formed(&model);
+        let (min, max) = model.bounds().unwrap();
+        assert!((max.y - 1.5).abs() < 1e-6 && (min.y + 1.5).abs() < 1e-6);
+        assert!((max.x - 0.5).abs() < 1e-4);
+
+        // a capsule without a straight part is a sphere
+        assert_well_formed(&CapsuleBuilder::new().size(0.5, 0.0).build());
+    }
+
+    #[test]
+    fn cylinder_and_cone_are_closed() {
+        let cylinder = CylinderBuilder::new().size(1.0, 3.0).segments(8).build();
+        let primitive = assert_well_formed(&cylinder);
+        // side quads and two caps
+        assert_eq!(primitive.indices.len() / 3, 8 * 2 + 8 * 2);
+        assert_eq!(
+            cylinder.bounds().map(|(min, max)| (min.y, max.y)),
+            Some((-1.5, 1.5))
+        );
+
+        let cone = ConeBuilder::new().size(1.0, 2.0).segments(8).build();
+        let primitive = assert_well_formed(&cone);
+        assert_eq!(primitive.indices.len() / 3, 8 + 8);
+        assert_eq!(primitive.positions[0], Vec3::new(0.0, 1.0, 0.0));
+    }
 
-        todo!()
+    #[test]
+    fn plane_tiles_its_texture() {
+        let model = PlaneBuilder::new()
+            .size(4.0, 2.0)
+            .segments(4, 2)
+            .uv_tiling(3.0, 1.5)
+            .build();
+        let primitive = assert_well_formed(&model);
+        assert_eq!(primitive.positions.len(), 5 * 3);
+        assert!(primitive.normals.iter().all(|n| *n == Vec3::Y));
+        assert_eq!(
+            model.bounds(),
+            Some((Vec3::new(-2.0, 0.0, -1.0), Vec3::new(2.0, 0.0, 1.0)))
+        );
+        assert_eq!(primitive.tex_coords.last(), Some(&Vec2::new(3.0, 1.5)));
     }
 }
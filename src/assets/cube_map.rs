@@ -0,0 +1,242 @@
+use std::path::{Path, PathBuf};
+
+use glam::Vec3;
+
+use crate::assets::asset_manager::{ImageFormat, Texture, TextureType};
+
+/// face order used everywhere cube maps are stored, matches the gl cube map targets
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl CubeFace {
+    pub const ALL: [CubeFace; 6] = [
+        CubeFace::PositiveX,
+        CubeFace::NegativeX,
+        CubeFace::PositiveY,
+        CubeFace::NegativeY,
+        CubeFace::PositiveZ,
+        CubeFace::NegativeZ,
+    ];
+
+    /// suffix in the `name_px.png` ... `name_nz.png` naming convention
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            CubeFace::PositiveX => "px",
+            CubeFace::NegativeX => "nx",
+            CubeFace::PositiveY => "py",
+            CubeFace::NegativeY => "ny",
+            CubeFace::PositiveZ => "pz",
+            CubeFace::NegativeZ => "nz",
+        }
+    }
+
+    /// world direction through the face at face coordinates `u`, `v` in -1..=1
+    fn direction(&self, u: f32, v: f32) -> Vec3 {
+        match self {
+            CubeFace::PositiveX => Vec3::new(1.0, -v, -u),
+            CubeFace::NegativeX => Vec3::new(-1.0, -v, u),
+            CubeFace::PositiveY => Vec3::new(u, 1.0, v),
+            CubeFace::NegativeY => Vec3::new(u, -1.0, -v),
+            CubeFace::PositiveZ => Vec3::new(u, -v, 1.0),
+            CubeFace::NegativeZ => Vec3::new(-u, -v, -1.0),
+        }
+        .normalize()
+    }
+}
+
+/// six face cube texture for skyboxes and reflections, faces are in `CubeFace::ALL` order
+#[derive(Clone, Debug)]
+pub struct CubeTexture {
+    pub faces: [Texture; 6],
+}
+
+impl CubeTexture {
+    /// makes a cube texture from six faces, they all need the same square size and format
+    ///
+    /// `names` are only used for error messages
+    pub fn new(faces: [Texture; 6], names: &[String; 6]) -> anyhow::Result<Self> {
+        let (width, height) = (faces[0].width, faces[0].height);
+        if width != height {
+            return Err(anyhow::anyhow!(
+                "cube map face {} isn't square ({}x{})",
+                names[0],
+                width,
+                height
+            ));
+        }
+        for (face, name) in faces.iter().zip(names.iter()) {
+            if face.width != width || face.height != height {
+                return Err(anyhow::anyhow!(
+                    "cube map face {} is {}x{} but {} is {}x{}",
+                    name,
+                    face.width,
+                    face.height,
+                    names[0],
+                    width,
+                    height
+                ));
+            }
+        }
+        Ok(Self { faces })
+    }
+
+    pub fn face(&self, face: CubeFace) -> &Texture {
+        &self.faces[face as usize]
+    }
+
+    pub fn size(&self) -> u32 {
+        self.faces[0].width
+    }
+
+    /// file names for the faces of a cube map following the naming convention, `sky.png` gives
+    /// `sky_px.png`, `sky_nx.png` and so on
+    pub fn face_paths(path: &Path) -> [PathBuf; 6] {
+        let stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "png".into());
+        CubeFace::ALL
+            .map(|face| path.with_file_name(format!("{}_{}.{}", stem, face.suffix(), extension)))
+    }
+
+    /// converts an equirectangular (latitude/longitude) image into a cube texture on the cpu
+    pub fn from_equirectangular(source: &Texture, face_size: u32) -> Self {
+        let channels = source.image_format.channels();
+        let faces = CubeFace::ALL.map(|face| {
+            let mut data = Vec::with_capacity((face_size * face_size * 4) as usize);
+            for y in 0..face_size {
+                for x in 0..face_size {
+                    let u = (x as f32 + 0.5) / face_size as f32 * 2.0 - 1.0;
+                    let v = (y as f32 + 0.5) / face_size as f32 * 2.0 - 1.0;
+                    let dir = face.direction(u, v);
+
+                    let longitude = dir.z.atan2(dir.x);
+                    let latitude = dir.y.clamp(-1.0, 1.0).asin();
+                    let sx = (longitude / std::f32::consts::TAU + 0.5) * source.width as f32;
+                    let sy = (0.5 - latitude / std::f32::consts::PI) * source.height as f32;
+                    let sx = (sx as u32).min(source.width - 1);
+                    let sy = (sy as u32).min(source.height - 1);
+
+                    let i = ((sy * source.width + sx) as usize) * channels;
+                    let pixel = &source.data[i..i + channels];
+                    data.extend_from_slice(&[
+                        pixel[0],
+                        pixel[1],
+                        pixel[2],
+                        if channels == 4 { pixel[3] } else { 255 },
+                    ]);
+                }
+            }
+            Texture {
+                texture_type: source.texture_type,
+                image_format: ImageFormat::R8G8B8A8,
+                width: face_size,
+                height: face_size,
+                data,
+            }
+        });
+        Self { faces }
+    }
+}
+
+/// loads six faces next to each other, `read` gets the contents of a path
+pub fn load_cube_faces(
+    path: &Path,
+    read: impl Fn(&Path) -> Option<Vec<u8>>,
+) -> anyhow::Result<CubeTexture> {
+    let paths = CubeTexture::face_paths(path);
+    let names = paths.clone().map(|p| p.display().to_string());
+
+    let mut faces = Vec::with_capacity(6);
+    for (face_path, name) in paths.iter().zip(names.iter()) {
+        let bytes = read(face_path).ok_or(anyhow::anyhow!("cube map face {} not found", name))?;
+        let texture = Texture::from_image_bytes(&bytes, TextureType::Albedo)
+            .map_err(|e| anyhow::anyhow!("cube map face {} failed to decode: {}", name, e))?;
+        faces.push(texture);
+    }
+
+    let faces: [Texture; 6] = faces
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("expected six cube map faces"))?;
+    CubeTexture::new(faces, &names)
+}
+
+/// loads a single equirectangular image (`.hdr` or any other image) as a cube texture
+pub fn load_equirectangular(path: &Path, bytes: &[u8]) -> anyhow::Result<CubeTexture> {
+    let source = Texture::from_image_bytes(bytes, TextureType::Albedo).map_err(|e| {
+        anyhow::anyhow!(
+            "equirectangular image {} failed to decode: {}",
+            path.display(),
+            e
+        )
+    })?;
+    let face_size = (source.width / 4).max(1);
+    Ok(CubeTexture::from_equirectangular(&source, face_size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(size: u32) -> Texture {
+        Texture {
+            texture_type: TextureType::Albedo,
+            image_format: ImageFormat::R8G8B8A8,
+            width: size,
+            height: size,
+            data: vec![255; (size * size * 4) as usize],
+        }
+    }
+
+    #[test]
+    fn mismatched_face_names_the_file() {
+        let names = CubeTexture::face_paths(Path::new("sky.png")).map(|p| p.display().to_string());
+        let mut faces = [0; 6].map(|_| solid(4));
+        faces[3] = solid(8);
+        let err = CubeTexture::new(faces, &names).unwrap_err().to_string();
+        assert!(err.contains("sky_ny.png"), "{err}");
+    }
+
+    #[test]
+    fn missing_face_names_the_file() {
+        let err = load_cube_faces(Path::new("sky.png"), |p| {
+            (!p.ends_with("sky_px.png")).then(|| Vec::new())
+        })
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("sky_px.png not found"), "{err}");
+    }
+
+    #[test]
+    fn equirectangular_conversion_sizes() {
+        let source = Texture {
+            width: 16,
+            height: 8,
+            ..solid(1)
+        };
+        let source = Texture {
+            data: vec![128; 16 * 8 * 4],
+            ..source
+        };
+        let cube = CubeTexture::from_equirectangular(&source, 4);
+        assert_eq!(cube.size(), 4);
+        assert!(cube.faces.iter().all(|f| f.data.len() == 4 * 4 * 4));
+        assert!(
+            cube.face(CubeFace::PositiveY)
+                .data
+                .iter()
+                .all(|b| *b == 128)
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// an asset under a search root changed on disk and was swapped in the `AssetManager` cache
+///
+/// the engine hands these to the renderer as `RendererCommand::AssetReloaded`, which rebuilds
+/// the meshes of entities using the asset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetReloaded {
+    pub id: Uuid,
+    pub path: PathBuf,
+}
+
+/// thread calling `poll` every `interval` until it returns false or the watcher is dropped
+///
+/// polls file times instead of asking the os for change events, so it works the same
+/// everywhere, just with a delay of up to `interval`
+pub(crate) struct Watcher {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watcher {
+    pub fn spawn(interval: Duration, mut poll: impl FnMut() -> bool + Send + 'static) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = Arc::clone(&stop);
+            std::thread::Builder::new()
+                .name("asset watcher".to_string())
+                .spawn(move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        std::thread::park_timeout(interval);
+                        if stop.load(Ordering::Relaxed) || !poll() {
+                            break;
+                        }
+                    }
+                })
+                .expect("failed to spawn the asset watcher thread")
+        };
+        Self {
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
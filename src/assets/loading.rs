@@ -0,0 +1,105 @@
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use uuid::Uuid;
+
+use crate::{
+    assets::asset_manager::{Asset, Model},
+    engine::component::Component,
+};
+
+/// threads the `StreamingLoader` decodes `load_async` and `load_streaming` assets on
+pub const LOADER_THREADS: usize = 2;
+
+/// where an `AssetHandle`'s asset is at
+#[derive(Debug, Clone)]
+pub enum LoadState {
+    Loading,
+    Ready(Arc<Asset>),
+    /// what went wrong, the asset won't load on another try either
+    Failed(String),
+}
+
+/// asset that's decoded in the background by `AssetManager::load_async`, clones share the state
+///
+/// added to an entity's components it takes the place of `Entity::model` once it's ready, the
+/// renderer skips the entity until then
+#[derive(Clone, Component)]
+pub struct AssetHandle {
+    id: Uuid,
+    path: PathBuf,
+    state: Arc<RwLock<LoadState>>,
+}
+
+impl AssetHandle {
+    pub(crate) fn loading(path: &Path, id: Uuid) -> Self {
+        Self::new(path, id, LoadState::Loading)
+    }
+
+    /// handle for an asset that's already loaded
+    pub fn ready(path: &Path, id: Uuid, asset: Arc<Asset>) -> Self {
+        Self::new(path, id, LoadState::Ready(asset))
+    }
+
+    fn new(path: &Path, id: Uuid, state: LoadState) -> Self {
+        Self {
+            id,
+            path: path.to_path_buf(),
+            state: Arc::new(RwLock::new(state)),
+        }
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn state(&self) -> LoadState {
+        self.state.read().unwrap().clone()
+    }
+
+    pub fn is_loading(&self) -> bool {
+        matches!(*self.state.read().unwrap(), LoadState::Loading)
+    }
+
+    /// the asset once it's ready
+    pub fn asset(&self) -> Option<Arc<Asset>> {
+        match &*self.state.read().unwrap() {
+            LoadState::Ready(asset) => Some(Arc::clone(asset)),
+            _ => None,
+        }
+    }
+
+    /// a copy of the model once it's ready, `None` for other assets
+    pub fn model(&self) -> Option<Model> {
+        match self.asset()?.as_ref() {
+            Asset::Model(model) => Some(model.clone()),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn finish(&self, state: LoadState) {
+        *self.state.write().unwrap() = state;
+    }
+}
+
+impl Debug for AssetHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = match &*self.state.read().unwrap() {
+            LoadState::Loading => "loading".to_string(),
+            LoadState::Ready(_) => "ready".to_string(),
+            LoadState::Failed(e) => format!("failed: {e}"),
+        };
+        f.debug_struct("AssetHandle")
+            .field("id", &self.id)
+            .field("path", &self.path)
+            .field("state", &state)
+            .finish()
+    }
+}
@@ -1,2 +1,7 @@
 pub mod asset_manager;
 pub mod basic_models;
+pub mod cube_map;
+pub mod hot_reload;
+pub mod loading;
+pub mod simplify;
+pub mod streaming;
@@ -0,0 +1,485 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use glam::{DVec3, Vec3};
+
+use crate::assets::asset_manager::{Mesh, MeshPrimitive, Model, ModelNode, PrimitiveTopology};
+
+/// fewest triangles `MeshPrimitive::simplify` goes down to
+pub const MIN_TRIANGLES: usize = 4;
+
+/// vertices closer than this are treated as the same position
+const WELD_EPSILON: f32 = 1e-5;
+
+/// extra cost for moving a vertex that sits on a uv/normal seam, scaled by the edge length
+const SEAM_PENALTY: f64 = 0.1;
+
+type Quadric = [[f64; 4]; 4];
+
+fn plane_quadric(normal: DVec3, point: DVec3) -> Quadric {
+    let plane = [normal.x, normal.y, normal.z, -normal.dot(point)];
+    let mut q = [[0.0; 4]; 4];
+    for i in 0..4 {
+        for j in 0..4 {
+            q[i][j] = plane[i] * plane[j];
+        }
+    }
+    q
+}
+
+fn quadric_add(a: &mut Quadric, b: &Quadric) {
+    for i in 0..4 {
+        for j in 0..4 {
+            a[i][j] += b[i][j];
+        }
+    }
+}
+
+fn quadric_error(q: &Quadric, point: DVec3) -> f64 {
+    let v = [point.x, point.y, point.z, 1.0];
+    (0..4)
+        .flat_map(|i| (0..4).map(move |j| (i, j)))
+        .map(|(i, j)| v[i] * q[i][j] * v[j])
+        .sum()
+}
+
+fn triangle_normal(a: Vec3, b: Vec3, c: Vec3) -> Vec3 {
+    (b - a).cross(c - a)
+}
+
+/// candidate edge collapse, `from` gets moved onto `to`
+struct Collapse {
+    cost: f64,
+    from: usize,
+    to: usize,
+    stamps: (u32, u32),
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost.total_cmp(&other.cost) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    // reversed so the heap pops the cheapest collapse first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+/// quadric error edge collapse over a primitive's welded positions
+///
+/// collapses are half edge collapses, vertices only ever move onto existing vertices, so
+/// normals and uvs are kept as they are. a corner that moves picks the attribute vertex at the
+/// destination that matches it best, which keeps uv seams apart.
+struct Simplifier<'a> {
+    primitive: &'a MeshPrimitive,
+    /// attribute vertex -> welded vertex
+    weld: Vec<usize>,
+    positions: Vec<Vec3>,
+    /// welded vertex -> attribute vertices
+    attributes: Vec<Vec<usize>>,
+    quadrics: Vec<Quadric>,
+    /// triangles of attribute vertex indices
+    triangles: Vec<[usize; 3]>,
+    alive: Vec<bool>,
+    adjacency: Vec<Vec<usize>>,
+    stamps: Vec<u32>,
+    heap: BinaryHeap<Collapse>,
+    live_triangles: usize,
+}
+
+impl<'a> Simplifier<'a> {
+    fn new(primitive: &'a MeshPrimitive) -> Self {
+        let mut welded: HashMap<[i64; 3], usize> = HashMap::new();
+        let mut positions = Vec::new();
+        let mut attributes: Vec<Vec<usize>> = Vec::new();
+        let weld: Vec<usize> = primitive
+            .positions
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let key = (*p / WELD_EPSILON).round().as_i64vec3().to_array();
+                let index = *welded.entry(key).or_insert_with(|| {
+                    positions.push(*p);
+                    attributes.push(Vec::new());
+                    positions.len() - 1
+                });
+                attributes[index].push(i);
+                index
+            })
+            .collect();
+
+        let triangles: Vec<[usize; 3]> = primitive
+            .indices
+            .chunks_exact(3)
+            .map(|t| [t[0] as usize, t[1] as usize, t[2] as usize])
+            .filter(|t| {
+                let [a, b, c] = t.map(|i| weld[i]);
+                a != b && b != c && a != c
+            })
+            .collect();
+
+        let mut quadrics = vec![[[0.0; 4]; 4]; positions.len()];
+        let mut adjacency = vec![Vec::new(); positions.len()];
+        for (t, triangle) in triangles.iter().enumerate() {
+            let corners = triangle.map(|i| weld[i]);
+            let [a, b, c] = corners.map(|v| positions[v]);
+            let normal = triangle_normal(a, b, c);
+            if normal.length_squared() > 0.0 {
+                let q = plane_quadric(normal.normalize().as_dvec3(), a.as_dvec3());
+                corners
+                    .iter()
+                    .for_each(|&v| quadric_add(&mut quadrics[v], &q));
+            }
+            corners.iter().for_each(|&v| adjacency[v].push(t));
+        }
+
+        Self {
+            primitive,
+            weld,
+            stamps: vec![0; positions.len()],
+            positions,
+            attributes,
+            quadrics,
+            alive: vec![true; triangles.len()],
+            live_triangles: triangles.len(),
+            triangles,
+            adjacency,
+            heap: BinaryHeap::new(),
+        }
+    }
+
+    fn welded(&self, t: usize) -> [usize; 3] {
+        self.triangles[t].map(|i| self.weld[i])
+    }
+
+    fn live(&self, v: usize) -> impl Iterator<Item = usize> + '_ {
+        self.adjacency[v].iter().copied().filter(|&t| self.alive[t])
+    }
+
+    fn neighbors(&self, v: usize) -> HashSet<usize> {
+        self.live(v)
+            .flat_map(|t| self.welded(t))
+            .filter(|&w| w != v)
+            .collect()
+    }
+
+    fn cost(&self, from: usize, to: usize) -> f64 {
+        let mut q = self.quadrics[from];
+        quadric_add(&mut q, &self.quadrics[to]);
+        let mut cost = quadric_error(&q, self.positions[to].as_dvec3());
+        if self.attributes[from].len() > 1 {
+            cost += SEAM_PENALTY * self.positions[from].distance_squared(self.positions[to]) as f64;
+        }
+        cost
+    }
+
+    fn push_edges(&mut self, v: usize) {
+        for w in self.neighbors(v) {
+            for (from, to) in [(v, w), (w, v)] {
+                self.heap.push(Collapse {
+                    cost: self.cost(from, to),
+                    from,
+                    to,
+                    stamps: (self.stamps[from], self.stamps[to]),
+                });
+            }
+        }
+    }
+
+    fn can_collapse(&self, from: usize, to: usize) -> bool {
+        // link condition, keeps the surface manifold (and closed meshes closed)
+        let shared: HashSet<usize> = self
+            .neighbors(from)
+            .intersection(&self.neighbors(to))
+            .copied()
+            .collect();
+        let opposite: HashSet<usize> = self
+            .live(from)
+            .map(|t| self.welded(t))
+            .filter(|w| w.contains(&to))
+            .flat_map(|w| w.into_iter().filter(|&v| v != from && v != to))
+            .collect();
+        if shared != opposite {
+            return false;
+        }
+
+        // no triangle may flip or collapse to nothing
+        self.live(from)
+            .map(|t| self.welded(t))
+            .filter(|w| !w.contains(&to))
+            .all(|w| {
+                let before = w.map(|v| self.positions[v]);
+                let after = w.map(|v| self.positions[if v == from { to } else { v }]);
+                let old = triangle_normal(before[0], before[1], before[2]);
+                let new = triangle_normal(after[0], after[1], after[2]);
+                new.length_squared() > f32::EPSILON * old.length_squared() && old.dot(new) > 0.0
+            })
+    }
+
+    /// attribute vertex at `to` closest to attribute vertex `a`
+    fn matching_attribute(&self, a: usize, to: usize) -> usize {
+        let prim = self.primitive;
+        let difference = |b: usize| {
+            let normal = match (prim.normals.get(a), prim.normals.get(b)) {
+                (Some(na), Some(nb)) => 1.0 - na.dot(*nb),
+                _ => 0.0,
+            };
+            let uv = match (prim.tex_coords.get(a), prim.tex_coords.get(b)) {
+                (Some(ua), Some(ub)) => ua.distance(*ub),
+                _ => 0.0,
+            };
+            normal + uv
+        };
+        *self.attributes[to]
+            .iter()
+            .min_by(|x, y| difference(**x).total_cmp(&difference(**y)))
+            .unwrap()
+    }
+
+    fn collapse(&mut self, from: usize, to: usize) {
+        for t in self.adjacency[from].clone() {
+            if !self.alive[t] {
+                continue;
+            }
+            if self.welded(t).contains(&to) {
+                self.alive[t] = false;
+                self.live_triangles -= 1;
+                continue;
+            }
+            for k in 0..3 {
+                if self.weld[self.triangles[t][k]] == from {
+                    self.triangles[t][k] = self.matching_attribute(self.triangles[t][k], to);
+                }
+            }
+            self.adjacency[to].push(t);
+        }
+
+        let q = self.quadrics[from];
+        quadric_add(&mut self.quadrics[to], &q);
+        self.stamps[from] += 1;
+        self.stamps[to] += 1;
+        self.push_edges(to);
+    }
+
+    fn run(mut self, target: usize) -> MeshPrimitive {
+        (0..self.positions.len()).for_each(|v| self.push_edges(v));
+
+        while self.live_triangles > target {
+            let Some(c) = self.heap.pop() else {
+                break;
+            };
+            if c.stamps != (self.stamps[c.from], self.stamps[c.to]) {
+                continue;
+            }
+            if self.can_collapse(c.from, c.to) {
+                self.collapse(c.from, c.to);
+            }
+        }
+
+        self.output()
+    }
+
+    fn output(&self) -> MeshPrimitive {
+        let prim = self.primitive;
+        let mut remap: HashMap<usize, u32> = HashMap::new();
+        let mut used = Vec::new();
+        let indices = (0..self.triangles.len())
+            .filter(|&t| self.alive[t])
+            .flat_map(|t| self.triangles[t])
+            .map(|i| {
+                *remap.entry(i).or_insert_with(|| {
+                    used.push(i);
+                    (used.len() - 1) as u32
+                })
+            })
+            .collect();
+
+        fn pick<T: Copy>(attribute: &[T], used: &[usize]) -> Vec<T> {
+            if attribute.is_empty() {
+                Vec::new()
+            } else {
+                used.iter().map(|&i| attribute[i]).collect()
+            }
+        }
+
+        MeshPrimitive {
+            positions: pick(&prim.positions, &used),
+            normals: pick(&prim.normals, &used),
+            tangents: pick(&prim.tangents, &used),
+            tex_coords: pick(&prim.tex_coords, &used),
+            colors: prim.colors.as_ref().map(|colors| pick(colors, &used)),
+            indices,
+            topology: prim.topology,
+            material_index: prim.material_index,
+        }
+    }
+}
+
+impl MeshPrimitive {
+    /// reduces the triangle count to about `target_ratio` of what it is now
+    ///
+    /// never goes below `MIN_TRIANGLES`, and stops early when no collapse is left that keeps
+    /// the surface intact. lines and points are returned as they are.
+    pub fn simplify(&self, target_ratio: f32) -> MeshPrimitive {
+        let triangles = self.indices.len() / 3;
+        let target =
+            ((triangles as f32 * target_ratio.clamp(0.0, 1.0)).round() as usize).max(MIN_TRIANGLES);
+        if self.topology != PrimitiveTopology::Triangles || triangles <= target {
+            return self.clone();
+        }
+
+        Simplifier::new(self).run(target)
+    }
+}
+
+impl Model {
+    /// one simplified copy of the model per ratio, in the same order
+    pub fn generate_lods(&self, ratios: &[f32]) -> Vec<Model> {
+        fn simplify_nodes(nodes: &[ModelNode], ratio: f32) -> Vec<ModelNode> {
+            nodes
+                .iter()
+                .map(|n| ModelNode {
+                    transform: n.transform,
+                    meshes: n
+                        .meshes
+                        .iter()
+                        .map(|m| Mesh {
+                            primitives: m.primitives.iter().map(|p| p.simplify(ratio)).collect(),
+                        })
+                        .collect(),
+                    nodes: simplify_nodes(&n.nodes, ratio),
+                })
+                .collect()
+        }
+
+        ratios
+            .iter()
+            .map(|&ratio| Model {
+                nodes: simplify_nodes(&self.nodes, ratio),
+                materials: self.materials.clone(),
+                // simplified meshes aren't the loaded asset anymore
+                asset_id: None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec2;
+
+    use super::*;
+    use crate::assets::basic_models::CuboidBuilder;
+
+    /// every edge between welded positions is used by exactly two triangles
+    fn is_watertight(prim: &MeshPrimitive) -> bool {
+        let key = |i: u32| {
+            (prim.positions[i as usize] / WELD_EPSILON)
+                .round()
+                .as_i64vec3()
+                .to_array()
+        };
+        let mut edges: HashMap<([i64; 3], [i64; 3]), usize> = HashMap::new();
+        for t in prim.indices.chunks_exact(3) {
+            for (a, b) in [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+                let (a, b) = (key(a), key(b));
+                *edges
+                    .entry(if a < b { (a, b) } else { (b, a) })
+                    .or_default() += 1;
+            }
+        }
+        edges.values().all(|&count| count == 2)
+    }
+
+    fn uv_sphere(rings: u32, segments: u32) -> MeshPrimitive {
+        let mut prim = MeshPrimitive {
+            positions: Vec::new(),
+            normals: Vec::new(),
+            tangents: Vec::new(),
+            tex_coords: Vec::new(),
+            colors: None,
+            indices: Vec::new(),
+            topology: PrimitiveTopology::Triangles,
+            material_index: None,
+        };
+        for r in 0..=rings {
+            for s in 0..=segments {
+                let theta = std::f32::consts::PI * r as f32 / rings as f32;
+                let phi = std::f32::consts::TAU * s as f32 / segments as f32;
+                let p = Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+                prim.positions.push(p);
+                prim.normals.push(p);
+                prim.tex_coords.push(Vec2::new(
+                    s as f32 / segments as f32,
+                    r as f32 / rings as f32,
+                ));
+            }
+        }
+        let row = segments + 1;
+        for r in 0..rings {
+            for s in 0..segments {
+                let i = r * row + s;
+                if r != 0 {
+                    prim.indices.extend([i, i + 1, i + row]);
+                }
+                if r != rings - 1 {
+                    prim.indices.extend([i + 1, i + row + 1, i + row]);
+                }
+            }
+        }
+        prim
+    }
+
+    #[test]
+    fn simplified_cuboid_stays_watertight() {
+        let cuboid = CuboidBuilder::new().build();
+        let prim = &cuboid.nodes[0].meshes[0].primitives[0];
+        assert!(is_watertight(prim));
+
+        let simplified = prim.simplify(0.5);
+        assert_eq!(simplified.indices.len() / 3, 6);
+        assert!(is_watertight(&simplified));
+        assert_eq!(simplified.normals.len(), simplified.positions.len());
+    }
+
+    #[test]
+    fn sphere_hits_ratio() {
+        let sphere = uv_sphere(16, 32);
+        assert!(is_watertight(&sphere));
+        let triangles = sphere.indices.len() / 3;
+
+        for ratio in [0.5, 0.25, 0.1] {
+            let simplified = sphere.simplify(ratio);
+            let expected = triangles as f32 * ratio;
+            let got = (simplified.indices.len() / 3) as f32;
+            assert!(
+                (got - expected).abs() <= expected * 0.1,
+                "{got} vs {expected}"
+            );
+            assert!(is_watertight(&simplified));
+        }
+    }
+
+    #[test]
+    fn aggressive_ratio_clamps() {
+        let simplified = uv_sphere(8, 16).simplify(0.0);
+        assert!(simplified.indices.len() / 3 >= MIN_TRIANGLES);
+    }
+}
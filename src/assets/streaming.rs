@@ -0,0 +1,339 @@
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Condvar, Mutex, RwLock,
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+    },
+    thread::JoinHandle,
+};
+
+use glam::{Mat4, Vec3};
+
+use crate::{
+    assets::{
+        asset_manager::{AssetManager, Model},
+        basic_models::CuboidBuilder,
+        loading::LOADER_THREADS,
+    },
+    engine::component::Component,
+};
+
+struct StreamingModelInner {
+    path: PathBuf,
+    placeholder: Model,
+    model: RwLock<Option<Model>>,
+    version: AtomicU64,
+    /// f32 bits, lower loads first
+    priority: AtomicU32,
+}
+
+/// handle to a model that's loaded in the background
+///
+/// shows `placeholder` until the real model is decoded, `version` goes up when it's swapped in
+/// so the renderer knows to rebuild anything it made from the placeholder. added to an entity's
+/// components it takes the place of `Entity::model`.
+#[derive(Clone, Component)]
+pub struct StreamingModel {
+    inner: Arc<StreamingModelInner>,
+}
+
+impl StreamingModel {
+    fn new(path: &Path, placeholder: Model, model: Option<Model>) -> Self {
+        Self {
+            inner: Arc::new(StreamingModelInner {
+                path: path.to_path_buf(),
+                placeholder,
+                version: AtomicU64::new(model.is_some() as u64),
+                model: RwLock::new(model),
+                priority: AtomicU32::new(0.0f32.to_bits()),
+            }),
+        }
+    }
+
+    pub(crate) fn pending(path: &Path, placeholder: Model) -> Self {
+        Self::new(path, placeholder, None)
+    }
+
+    /// handle for a model that's already loaded
+    pub fn ready(path: &Path, model: Model) -> Self {
+        Self::new(path, model.clone(), Some(model))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.inner.path
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.inner.model.read().unwrap().is_some()
+    }
+
+    /// the loaded model, or the placeholder while it's still loading
+    pub fn current(&self) -> Model {
+        self.inner
+            .model
+            .read()
+            .unwrap()
+            .clone()
+            .unwrap_or_else(|| self.inner.placeholder.clone())
+    }
+
+    pub fn version(&self) -> u64 {
+        self.inner.version.load(Ordering::Acquire)
+    }
+
+    pub fn priority(&self) -> f32 {
+        f32::from_bits(self.inner.priority.load(Ordering::Relaxed))
+    }
+
+    /// lower loads first, the renderer sets it to the distance from the camera
+    pub fn set_priority(&self, priority: f32) {
+        self.inner
+            .priority
+            .store(priority.to_bits(), Ordering::Relaxed);
+    }
+
+    fn finish(&self, model: Model) {
+        *self.inner.model.write().unwrap() = Some(model);
+        self.inner.version.fetch_add(1, Ordering::Release);
+    }
+}
+
+impl Debug for StreamingModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamingModel")
+            .field("path", &self.inner.path)
+            .field("ready", &self.is_ready())
+            .field("version", &self.version())
+            .finish()
+    }
+}
+
+/// gray box covering the bounds in the gltf header, a unit cube if there aren't any
+pub fn placeholder_for(bytes: &[u8]) -> Model {
+    let bounds = gltf::Gltf::from_slice(bytes).ok().and_then(|gltf| {
+        let scene = gltf.default_scene().or_else(|| gltf.scenes().next())?;
+        scene
+            .nodes()
+            .filter_map(|n| node_bounds(&n, Mat4::IDENTITY))
+            .reduce(|(min_a, max_a), (min_b, max_b)| (min_a.min(min_b), max_a.max(max_b)))
+    });
+    let (min, max) = bounds.unwrap_or((Vec3::splat(-0.5), Vec3::splat(0.5)));
+
+    let size = (max - min).max(Vec3::splat(0.01));
+    let mut model = CuboidBuilder::new()
+        .size(size.x, size.y, size.z)
+        .color(image::Rgba([128, 128, 128, 255]))
+        .build();
+    model.nodes[0].transform = Mat4::from_translation((min + max) / 2.0);
+    model
+}
+
+fn node_bounds(node: &gltf::Node, parent: Mat4) -> Option<(Vec3, Vec3)> {
+    let transform = parent * Mat4::from_cols_array_2d(&node.transform().matrix());
+
+    let own = node.mesh().into_iter().flat_map(|m| {
+        m.primitives()
+            .map(|p| p.bounding_box())
+            .flat_map(|b| {
+                let (min, max) = (Vec3::from(b.min), Vec3::from(b.max));
+                (0..8).map(move |i| {
+                    Vec3::new(
+                        if i & 1 == 0 { min.x } else { max.x },
+                        if i & 2 == 0 { min.y } else { max.y },
+                        if i & 4 == 0 { min.z } else { max.z },
+                    )
+                })
+            })
+            .map(|corner| transform.transform_point3(corner))
+            .map(|p| (p, p))
+            .collect::<Vec<_>>()
+    });
+
+    own.chain(node.children().filter_map(|c| node_bounds(&c, transform)))
+        .reduce(|(min_a, max_a), (min_b, max_b)| (min_a.min(min_b), max_a.max(max_b)))
+}
+
+struct StreamingQueue {
+    jobs: Mutex<Vec<Job>>,
+    available: Condvar,
+    running: AtomicBool,
+}
+
+/// what the loader threads decode
+enum Job {
+    Stream(StreamingModel, &'static [u8]),
+    /// a `load_async` load
+    Load(Box<dyn FnOnce() + Send>),
+}
+
+impl Job {
+    fn priority(&self) -> f32 {
+        match self {
+            Job::Stream(handle, _) => handle.priority(),
+            // something is polling for these, streamed models already have a placeholder
+            Job::Load(_) => f32::NEG_INFINITY,
+        }
+    }
+}
+
+/// worker threads decoding the queued assets, lowest priority first
+pub struct StreamingLoader {
+    queue: Arc<StreamingQueue>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl StreamingLoader {
+    pub fn new() -> Self {
+        let queue = Arc::new(StreamingQueue {
+            jobs: Mutex::new(Vec::new()),
+            available: Condvar::new(),
+            running: AtomicBool::new(true),
+        });
+
+        let workers = (0..LOADER_THREADS)
+            .map(|i| {
+                let worker_queue = Arc::clone(&queue);
+                std::thread::Builder::new()
+                    .name(format!("asset loader {i}"))
+                    .spawn(move || Self::run(worker_queue))
+                    .expect("failed to spawn an asset loader thread")
+            })
+            .collect();
+
+        Self { queue, workers }
+    }
+
+    pub fn queue(&self, handle: StreamingModel, bytes: &'static [u8]) {
+        self.push(Job::Stream(handle, bytes));
+    }
+
+    /// runs `load` on a loader thread, before any streamed model
+    pub fn spawn(&self, load: impl FnOnce() + Send + 'static) {
+        self.push(Job::Load(Box::new(load)));
+    }
+
+    fn push(&self, job: Job) {
+        self.queue.jobs.lock().unwrap().push(job);
+        self.queue.available.notify_one();
+    }
+
+    pub fn pending(&self) -> usize {
+        self.queue.jobs.lock().unwrap().len()
+    }
+
+    fn run(queue: Arc<StreamingQueue>) {
+        loop {
+            let job = {
+                let mut jobs = queue
+                    .available
+                    .wait_while(queue.jobs.lock().unwrap(), |jobs| {
+                        jobs.is_empty() && queue.running.load(Ordering::Acquire)
+                    })
+                    .unwrap();
+                if !queue.running.load(Ordering::Acquire) {
+                    return;
+                }
+                // priorities can change while waiting so pick the next job every time, the
+                // first of equal ones goes first
+                let next = jobs
+                    .iter()
+                    .enumerate()
+                    .min_by(|(_, a), (_, b)| a.priority().total_cmp(&b.priority()))
+                    .map(|(i, _)| i)
+                    .unwrap();
+                jobs.remove(next)
+            };
+
+            match job {
+                Job::Stream(handle, bytes) => Self::stream(&handle, bytes),
+                Job::Load(load) => load(),
+            }
+        }
+    }
+
+    fn stream(handle: &StreamingModel, bytes: &[u8]) {
+        let _span = tracy_client::span!("streaming model");
+        let imported = gltf::import_slice(bytes)
+            .map_err(anyhow::Error::from)
+            .and_then(|(gltf, buffers, images)| AssetManager::gltf_to_model(gltf, buffers, images));
+        match imported {
+            Ok(imported) => {
+                for warning in &imported.warnings {
+                    log::warn!("{}: {warning}", handle.path().display());
+                }
+                handle.finish(imported.model)
+            }
+            Err(e) => log::error!(
+                "failed to stream {}, keeping the placeholder: {e}",
+                handle.path().display()
+            ),
+        }
+    }
+}
+
+impl Drop for StreamingLoader {
+    fn drop(&mut self) {
+        self.queue.running.store(false, Ordering::Release);
+        self.queue.available.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    const LINES_GLTF: &str = r#"{
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0, "translation": [0.0, 2.0, 0.0] }],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "mode": 1 }] }],
+        "buffers": [{
+            "byteLength": 48,
+            "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAgD8AAAAA"
+        }],
+        "bufferViews": [{ "buffer": 0, "byteLength": 48 }],
+        "accessors": [{
+            "bufferView": 0,
+            "componentType": 5126,
+            "count": 4,
+            "type": "VEC3",
+            "min": [0.0, 0.0, 0.0],
+            "max": [1.0, 1.0, 0.0]
+        }]
+    }"#;
+
+    #[test]
+    fn placeholder_covers_header_bounds() {
+        let placeholder = placeholder_for(LINES_GLTF.as_bytes());
+        assert_eq!(
+            placeholder.nodes[0].transform,
+            Mat4::from_translation(Vec3::new(0.5, 2.5, 0.0))
+        );
+    }
+
+    #[test]
+    fn streamed_model_swaps_in() {
+        let loader = StreamingLoader::new();
+        let handle = StreamingModel::pending(
+            Path::new("lines.gltf"),
+            placeholder_for(LINES_GLTF.as_bytes()),
+        );
+        assert_eq!(handle.version(), 0);
+        loader.queue(handle.clone(), LINES_GLTF.as_bytes());
+
+        let started = Instant::now();
+        while !handle.is_ready() {
+            assert!(started.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(handle.version(), 1);
+        assert_eq!(handle.current().nodes[0].meshes.len(), 1);
+    }
+}
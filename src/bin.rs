@@ -17,19 +17,33 @@ use glam::{Mat4, Quat, Vec3};
 use game_engine_lib::{
     self,
     assets::{
-        asset_manager::{Asset, AssetManager, Model},
+        asset_manager::{AssetManager, ImageFormat, Model, Texture, TextureType},
         basic_models,
+        streaming::StreamingModel,
     },
     engine::{
         Engine,
-        component::{ComponentSet, Transform3D},
+        camera_controller::FpsCameraController,
+        component::{Component, ComponentSet, Transform3D},
         entity::{DefaultCamera, Entity, EntityContainer, EntityRegistry},
-        event::EventHandler,
-        messages::Message,
+        event::{EventHandler, PlayerControlled},
+        frame_timings::FrameTimings,
+        id::{IdGenerator, IdMode, named_id, new_id},
+        messages::{Message, MessageCommand, MessageContext, Systems},
     },
-    physics::{PhysicsBody, commands::PhysicsCommand},
-    rendering::{EngineRenderer, RendererType},
-    utils::{Shared, SharedBox, deg_to_rad, new_shared, new_shared_box},
+    physics::{
+        PhysicsBody,
+        commands::{PhysicsCommand, PhysicsEvent},
+        model_collider::ColliderKind,
+    },
+    rendering::{
+        DirtyModel, EngineRenderer, RendererCommand, RendererType, ViewMode,
+        billboard::Billboard,
+        light::DirectionalLight,
+        sprite::{Sprite, SpriteAnimation, SpriteClip, SpriteSheet},
+        ui::UiLayer,
+    },
+    utils::{Shared, SharedBox, deg_to_rad, deg_to_rad_f32, new_shared, new_shared_box},
     windowing::windower::Windower,
 };
 use rapier3d::prelude::{ColliderBuilder, RigidBodyBuilder};
@@ -44,12 +58,46 @@ use winit::{
 
 use silly_game_engine_macros;
 
+/// moves an entity up and down on a sine wave
+#[derive(Debug, Clone, Component)]
+pub struct Oscillate {
+    base: Vec3,
+    amplitude: f32,
+    speed: f32,
+    elapsed: f32,
+}
+
+impl Oscillate {
+    fn position(&self) -> Vec3 {
+        self.base + Vec3::Y * (self.elapsed * self.speed).sin() * self.amplitude
+    }
+}
+
+/// model the entity switches to the first time it lands on something
+#[derive(Debug, Clone, Component)]
+pub struct LandedModel(Model);
+
+/// gives the entity a fixed trimesh body once its `StreamingModel` has loaded
+#[derive(Debug, Clone, Component)]
+pub struct TrimeshWhenLoaded;
+
+/// swaps the entity's model for this one every `interval` seconds
+#[derive(Debug, Clone, Component)]
+pub struct SwapModel {
+    other: Model,
+    interval: f32,
+    elapsed: f32,
+}
+
 #[derive(Debug, Clone)]
 pub struct TestObj {
     model: Option<Model>,
     components: ComponentSet,
     messages: VecDeque<Message>,
     id: Uuid,
+    show_diagnostics: bool,
+    show_physics_debug: bool,
+    view_mode: ViewMode,
 }
 
 impl TestObj {
@@ -58,14 +106,18 @@ impl TestObj {
         components.add(transform);
         Self {
             model,
-            id: Uuid::new_v4(),
+            id: new_id(),
             messages: VecDeque::new(),
             components,
+            show_diagnostics: false,
+            show_physics_debug: false,
+            view_mode: ViewMode::Shaded,
         }
     }
 
-    fn set_id(&mut self, id: Uuid) {
-        self.id = id
+    fn with_id(mut self, id: Uuid) -> Self {
+        self.id = id;
+        self
     }
 }
 
@@ -84,14 +136,53 @@ impl Entity for TestObj {
         &self.model
     }
 
-    fn transform(&self) -> Transform3D {
-        *self.components.get().unwrap()
-    }
-    fn transform_mut(&mut self) -> &mut Transform3D {
-        self.components.get_mut().unwrap()
-    }
-
     fn update(&mut self, delta: f64) {
+        if let Some(oscillate) = self.components.get_mut::<Oscillate>() {
+            oscillate.elapsed += delta as f32 / 1000.0;
+            let position = oscillate.position();
+            if let Some(transform) = self.components.get_mut::<Transform3D>() {
+                transform.position = position;
+            }
+        }
+
+        let loaded = self
+            .components
+            .get::<StreamingModel>()
+            .filter(|m| m.is_ready() && self.components.get::<TrimeshWhenLoaded>().is_some())
+            .map(StreamingModel::current);
+        if let Some(model) = loaded {
+            self.components.take::<TrimeshWhenLoaded>();
+            match PhysicsBody::from_model(
+                &model,
+                ColliderKind::TriMesh,
+                RigidBodyBuilder::fixed().build(),
+            ) {
+                Ok(body) => self.components.add(body),
+                Err(e) => log::warn!("no collider for {}: {e}", self.id),
+            }
+        }
+
+        if let Some(animation) = self.components.get_mut::<SpriteAnimation>() {
+            for event in animation.drain_events() {
+                log::debug!("{}: {event:?}", self.id);
+            }
+        }
+
+        let mut swapped = false;
+        if let Some(swap) = self.components.get_mut::<SwapModel>() {
+            swap.elapsed += delta as f32 / 1000.0;
+            if swap.elapsed >= swap.interval {
+                swap.elapsed -= swap.interval;
+                if let Some(model) = self.model.as_mut() {
+                    std::mem::swap(model, &mut swap.other);
+                    swapped = true;
+                }
+            }
+        }
+        if swapped {
+            self.components.add(DirtyModel);
+        }
+
         // self.transform.position.x += 1.0 * delta as f32;
         // self.transform.rotation =
         //     self.transform.rotation * Quat::from_rotation_y(deg_to_rad(200.0 * delta) as f32);
@@ -110,8 +201,18 @@ impl Entity for TestObj {
         });
     }
 
-    fn physics_update(&mut self, delta: f64) {
-        ()
+    fn physics_update(&mut self, delta: f64, events: &[PhysicsEvent]) {
+        let landed = events
+            .iter()
+            .any(|e| matches!(e, PhysicsEvent::CollisionStarted { .. }));
+        if !landed {
+            return;
+        }
+        if let Some(LandedModel(model)) = self.components.take::<LandedModel>() {
+            log::info!("{} landed", self.id);
+            self.model = Some(model);
+            self.components.add(DirtyModel);
+        }
     }
 
     fn input(&mut self, event: &winit::event::WindowEvent) {
@@ -127,32 +228,46 @@ impl Entity for TestObj {
                         state: ElementState::Pressed,
                         ..
                     } => match keycode {
-                        KeyCode::KeyW => {
-                            self.transform().position.z += 1.0;
-                        }
-                        KeyCode::KeyS => {
-                            self.transform().position.z -= 1.0;
+                        KeyCode::F3 => {
+                            self.show_diagnostics = !self.show_diagnostics;
+                            self.messages.push_back(Message {
+                                from: Systems::Engine,
+                                to: Systems::Renderer,
+                                context: MessageContext {
+                                    command: MessageCommand::RendererCommand(
+                                        RendererCommand::ShowDiagnostics(self.show_diagnostics),
+                                    ),
+                                },
+                            });
                         }
-                        KeyCode::KeyA => {
-                            self.transform().position.x += 1.0;
+                        KeyCode::F4 => {
+                            self.show_physics_debug = !self.show_physics_debug;
+                            self.messages.push_back(Message {
+                                from: Systems::Engine,
+                                to: Systems::Renderer,
+                                context: MessageContext {
+                                    command: MessageCommand::RendererCommand(
+                                        RendererCommand::SetPhysicsDebug(self.show_physics_debug),
+                                    ),
+                                },
+                            });
                         }
-                        KeyCode::KeyD => {
-                            self.transform().position.x -= 1.0;
-                        }
-                        KeyCode::Space => {
-                            self.transform().position.y += 1.0;
-                        }
-                        KeyCode::ShiftLeft => {
-                            self.transform().position.y -= 1.0;
-                        }
-                        KeyCode::ArrowLeft => {
-                            self.transform().rotation = self.transform().rotation
-                                * Quat::from_euler(
-                                    glam::EulerRot::XYZ,
-                                    0.0,
-                                    deg_to_rad(10.0) as f32,
-                                    0.0,
-                                )
+                        KeyCode::F5 => {
+                            self.view_mode = match self.view_mode {
+                                ViewMode::Shaded => ViewMode::Wireframe,
+                                ViewMode::Wireframe => ViewMode::Normals,
+                                ViewMode::Normals => ViewMode::Unlit,
+                                ViewMode::Unlit => ViewMode::Shaded,
+                            };
+                            self.messages.push_back(Message {
+                                from: Systems::Engine,
+                                to: Systems::Renderer,
+                                context: MessageContext {
+                                    command: MessageCommand::RendererCommand(
+                                        RendererCommand::SetViewMode(self.view_mode),
+                                    ),
+                                },
+                            });
                         }
                         _ => (),
                     },
@@ -199,51 +314,52 @@ impl Entity for TestObj {
     }
 }
 
+/// 8 frame walk cycle drawn in code, a body with two legs swinging back and forth
+fn walk_sheet() -> Texture {
+    const FRAME: u32 = 16;
+    let (width, height) = (FRAME * 8, FRAME);
+    let mut data = vec![0; (width * height * 4) as usize];
+    for frame in 0..8 {
+        let swing = [0, 1, 2, 3, 0, -1, -2, -3][frame as usize];
+        for y in 0..FRAME {
+            for x in 0..FRAME {
+                let body = (6..10).contains(&x) && (2..10).contains(&y);
+                let legs = y >= 10 && (x as i32 == 7 + swing || x as i32 == 8 - swing);
+                if body || legs {
+                    let i = ((y * width + frame * FRAME + x) * 4) as usize;
+                    data[i..i + 4].copy_from_slice(&[240, 200, 80, 255]);
+                }
+            }
+        }
+    }
+    Texture {
+        texture_type: TextureType::Albedo,
+        image_format: ImageFormat::R8G8B8A8,
+        width,
+        height,
+        data,
+    }
+}
+
 fn main() {
     env_logger::init();
     log::info!("logger init");
     tracy_client::Client::start();
 
-    let mut entities = EntityRegistry::new();
-
-    let mut asset_manager = AssetManager::new();
-
-    let transform = Transform3D {
-        position: Vec3::new(0.0, 300.0, 0.0),
-        rotation: Quat::from_axis_angle(
-            Vec3::new(1.0, 0.0, 0.0).normalize(),
-            deg_to_rad(0.0) as f32,
-        ),
-        scale: Vec3::new(1.0, 1.0, 1.0),
-    };
-
-    let lantern_model = {
-        let (uuid, maybe_model) = asset_manager
-            .get_asset_by_path(Path::new("Lantern.glb"))
-            .expect("model not found");
-        match maybe_model.as_ref() {
-            Asset::Model(m) => m.clone(),
-            _ => panic!("model isn't model"),
-        }
-    };
+    // the demo scene's entities get the same ids every run, so recordings of it replay
+    IdGenerator::global().set_mode(IdMode::Deterministic {
+        namespace: Uuid::new_v5(&Uuid::NAMESPACE_OID, b"silly-game-engine demo"),
+    });
 
-    let avocado_model = {
-        let (uuid, maybe_model) = asset_manager
-            .get_asset_by_path(Path::new("DamagedHelmet.glb"))
-            .expect("model not found");
-        match maybe_model.as_ref() {
-            Asset::Model(m) => m.clone(),
-            _ => panic!("model isn't model"),
-        }
-    };
+    let mut entities = EntityRegistry::new();
 
-    let camera = DefaultCamera::new(
+    let mut camera = DefaultCamera::new(
         Transform3D {
             position: Vec3::new(50.0, 75.0, -50.0),
             rotation: Quat::from_euler(
                 glam::EulerRot::XYZ,
-                deg_to_rad(210.0) as f32,
-                deg_to_rad(30.0) as f32,
+                deg_to_rad_f32(210.0),
+                deg_to_rad_f32(30.0),
                 0.0,
             ),
             scale: Vec3::new(1.0, 1.0, 1.0),
@@ -252,21 +368,60 @@ fn main() {
         1080.0,
         Vec3::new(0.0, 1.0, 0.0),
         Vec3::new(0.0, 0.0, -1.0),
-        deg_to_rad(120.0) as f32,
+        deg_to_rad_f32(120.0),
         0.1,
         500.0,
     );
 
+    let camera_rotation = camera
+        .components()
+        .get::<Transform3D>()
+        .map(|t| t.rotation)
+        .unwrap_or_default();
+    camera
+        .components_mut()
+        .add(FpsCameraController::from_rotation(camera_rotation).speed(50.0, 4.0));
+    camera.components_mut().add(PlayerControlled(0));
+    camera.id = named_id("camera");
+
     let camera_id = camera.id();
 
+    let mut engine = Engine::new(RendererType::ThreeD, entities.clone(), camera_id);
+    // entities can still be added, the engine shares the registry
+    let assets = engine
+        .context
+        .get::<AssetManager>()
+        .expect("the engine adds the asset manager");
+    let assets = assets.read().unwrap();
+    // re-exported files in the assets dir show up without restarting
+    #[cfg(debug_assertions)]
+    {
+        assets.add_search_root(concat!(env!("CARGO_MANIFEST_DIR"), "/assets"));
+        assets.watch(Duration::from_millis(500));
+    }
+
+    let transform = Transform3D {
+        position: Vec3::new(0.0, 19.5, 0.0),
+        rotation: Quat::from_axis_angle(Vec3::new(1.0, 0.0, 0.0).normalize(), deg_to_rad_f32(0.0)),
+        scale: Vec3::new(1.0, 1.0, 1.0),
+    };
+
+    // streamed in the background, shows up as a gray box until it's loaded
+    let lantern_model = assets
+        .load_streaming(Path::new("Lantern.glb"))
+        .expect("model not found");
+
+    // decoded on a loader thread, the entity isn't drawn until it's ready
+    let avocado_model = assets.load_async(Path::new("DamagedHelmet.glb"));
+    drop(assets);
+
     let mut components = ComponentSet::new();
-    let pb = PhysicsBody::new(
-        ColliderBuilder::cuboid(5.0, 20.0, 5.0).build(),
-        RigidBodyBuilder::dynamic().build(),
-    );
-    components.add(pb);
+    // the trimesh collider waits for the real triangles too
+    components.add(TrimeshWhenLoaded);
+    components.add(PlayerControlled(0));
+    components.add(lantern_model);
 
-    let test_obj = TestObj::new(transform, Some(lantern_model), components);
+    let test_obj = TestObj::new(transform, None, components).with_id(named_id("lantern"));
 
     let plane = TestObj::new(
         Transform3D {
@@ -288,31 +443,213 @@ fn main() {
 
             creg
         },
-    );
+    )
+    .with_id(named_id("plane"));
 
+    // falls onto the lantern's trimesh
     let avocado = TestObj::new(
         Transform3D {
             position: Vec3::new(0.0, 100.0, 1.0),
             rotation: Quat::from_euler(glam::EulerRot::XYZ, 0.0, 0.0, 0.0),
             scale: Vec3::new(10.0, 10.0, 10.0),
         },
-        Some(avocado_model),
+        None,
         {
             let mut creg = ComponentSet::new();
+            creg.add(avocado_model);
             creg.add(PhysicsBody::new(
                 ColliderBuilder::ball(1.0).build(),
                 RigidBodyBuilder::dynamic().build(),
             ));
             creg
         },
-    );
+    )
+    .with_id(named_id("avocado"));
+
+    // kinematic platform moved through its transform, carries the crate riding on it
+    let platform_base = Vec3::new(60.0, 20.0, 0.0);
+    let platform = TestObj::new(
+        Transform3D::new(platform_base, Quat::IDENTITY, Vec3::ONE),
+        Some(
+            basic_models::CuboidBuilder::new()
+                .size(20.0, 2.0, 20.0)
+                .color(image::Rgba([200, 120, 60, 255]))
+                .build(),
+        ),
+        {
+            let mut creg = ComponentSet::new();
+            creg.add(
+                PhysicsBody::new(
+                    ColliderBuilder::cuboid(10.0, 1.0, 10.0).build(),
+                    RigidBodyBuilder::kinematic_position_based().build(),
+                )
+                .as_carrier(),
+            );
+            creg.add(Oscillate {
+                base: platform_base,
+                amplitude: 10.0,
+                speed: 1.0,
+                elapsed: 0.0,
+            });
+            creg
+        },
+    )
+    .with_id(named_id("platform"));
+
+    let rider = TestObj::new(
+        Transform3D::new(platform_base + Vec3::Y * 5.0, Quat::IDENTITY, Vec3::ONE),
+        Some(
+            basic_models::CuboidBuilder::new()
+                .size(4.0, 4.0, 4.0)
+                .build(),
+        ),
+        {
+            let mut creg = ComponentSet::new();
+            creg.add(PhysicsBody::new(
+                ColliderBuilder::cuboid(2.0, 2.0, 2.0).build(),
+                RigidBodyBuilder::dynamic().build(),
+            ));
+            creg.add(LandedModel(
+                basic_models::CuboidBuilder::new()
+                    .size(4.0, 4.0, 4.0)
+                    .color(image::Rgba([60, 200, 90, 255]))
+                    .build(),
+            ));
+            creg
+        },
+    )
+    .with_id(named_id("rider"));
+
+    // flips between red and blue every second
+    let swapper = TestObj::new(
+        Transform3D::new(Vec3::new(-40.0, 15.0, 0.0), Quat::IDENTITY, Vec3::ONE),
+        Some(
+            basic_models::CuboidBuilder::new()
+                .size(6.0, 6.0, 6.0)
+                .color(image::Rgba([220, 40, 40, 255]))
+                .build(),
+        ),
+        {
+            let mut creg = ComponentSet::new();
+            creg.add(SwapModel {
+                other: basic_models::CuboidBuilder::new()
+                    .size(6.0, 6.0, 6.0)
+                    .color(image::Rgba([40, 60, 220, 255]))
+                    .build(),
+                interval: 1.0,
+                elapsed: 0.0,
+            });
+            creg
+        },
+    )
+    .with_id(named_id("swapper"));
+
+    // billboard cycling through the walk clip
+    let walker = TestObj::new(
+        Transform3D::new(Vec3::new(20.0, 4.0, 20.0), Quat::IDENTITY, Vec3::ONE),
+        None,
+        {
+            let mut creg = ComponentSet::new();
+            creg.add(Sprite::new(Some(walk_sheet()), glam::Vec2::splat(8.0)));
+            let mut animation = SpriteAnimation::new(
+                SpriteSheet::new(8, 1).clip("walk", SpriteClip::new(0, 7, 8.0, true)),
+            );
+            animation.play("walk").expect("walk clip");
+            creg.add(animation);
+            creg.add(Billboard::Cylindrical);
+            creg
+        },
+    )
+    .with_id(named_id("walker"));
+
+    // the ball rolls over the same hills that are drawn
+    let terrain_position = Vec3::new(0.0, 20.0, -170.0);
+    let terrain = basic_models::TerrainBuilder::new(basic_models::HeightSource::Noise {
+        function: basic_models::value_noise,
+        seed: 3,
+    })
+    .samples(65, 65)
+    .cell_size(2.0)
+    .height_scale(12.0)
+    .color(image::Rgba([90, 160, 70, 255]))
+    .build()
+    .expect("terrain");
+    let hills = TestObj::new(
+        Transform3D::new(terrain_position, Quat::IDENTITY, Vec3::ONE),
+        Some(terrain.model.clone()),
+        {
+            let mut creg = ComponentSet::new();
+            creg.add(PhysicsBody::new(
+                terrain.collider(),
+                RigidBodyBuilder::fixed().build(),
+            ));
+            creg
+        },
+    )
+    .with_id(named_id("hills"));
+
+    let ball = TestObj::new(
+        Transform3D::new(
+            terrain_position + Vec3::new(-50.0, 30.0, 0.0),
+            Quat::IDENTITY,
+            Vec3::ONE,
+        ),
+        Some(
+            basic_models::SphereBuilder::new()
+                .radius(2.0)
+                .color(image::Rgba([230, 200, 40, 255]))
+                .build(),
+        ),
+        {
+            let mut creg = ComponentSet::new();
+            creg.add(PhysicsBody::new(
+                ColliderBuilder::ball(2.0).build(),
+                RigidBodyBuilder::dynamic()
+                    .linvel(rapier3d::na::Vector3::new(12.0, 0.0, 0.0))
+                    .build(),
+            ));
+            creg
+        },
+    )
+    .with_id(named_id("ball"));
+
+    let sun = TestObj::new(
+        Transform3D::new(
+            Vec3::ZERO,
+            DirectionalLight::rotation_towards(Vec3::new(0.0, -0.5, -0.5)),
+            Vec3::ONE,
+        ),
+        None,
+        {
+            let mut creg = ComponentSet::new();
+            creg.add(DirectionalLight::default());
+            creg
+        },
+    )
+    .with_id(named_id("sun"));
 
     entities.add(camera.into_container());
+    entities.add(sun.into_container());
+    entities.add(swapper.into_container());
+    entities.add(walker.into_container());
+    entities.add(platform.into_container());
+    entities.add(rider.into_container());
     entities.add(plane.into_container());
     entities.add(test_obj.into_container());
     entities.add(avocado.into_container());
-
-    let mut engine = Engine::new(RendererType::ThreeD, entities.clone(), camera_id);
+    entities.add(hills.into_container());
+    entities.add(ball.into_container());
+
+    let frame_timings = engine
+        .context
+        .get::<FrameTimings>()
+        .expect("the engine adds frame timings");
+    engine.context.with_mut(|ui: &mut UiLayer| {
+        ui.add(move |ctx| {
+            let fps = frame_timings.read().unwrap().fps();
+            three_d::egui::Window::new("stats").show(ctx, |ui| ui.label(format!("{fps:.0} fps")));
+        })
+    });
 
     let mut windower = Windower::new(
         engine,
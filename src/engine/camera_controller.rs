@@ -0,0 +1,161 @@
+use std::collections::HashSet;
+
+use glam::{Quat, Vec2, Vec3};
+use winit::{
+    event::{DeviceEvent, ElementState, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+use crate::{
+    engine::component::{Component, Transform3D},
+    utils::{clamp_pitch, yaw_pitch_rotation},
+};
+
+/// yaw/pitch mouse look with wasd fly movement
+///
+/// add it to a `DefaultCamera`'s components and the camera feeds it input and writes the result
+/// into its transform every update. mouse movement comes from raw device motion so it keeps
+/// working with a grabbed cursor.
+#[derive(Debug, Clone, Component)]
+pub struct FpsCameraController {
+    pub yaw: f32,
+    pub pitch: f32,
+    /// radians per unit of mouse motion
+    pub sensitivity: f32,
+    /// max pitch either way in radians, `clamp_pitch` still keeps it off straight up/down
+    pub pitch_limit: f32,
+    /// units per second
+    pub speed: f32,
+    /// speed multiplier while `fast_key` is held
+    pub fast_multiplier: f32,
+    pub fast_key: KeyCode,
+    pub enabled: bool,
+
+    pressed: HashSet<KeyCode>,
+    mouse_delta: Vec2,
+}
+
+impl FpsCameraController {
+    pub fn new() -> Self {
+        Self {
+            yaw: 0.0,
+            pitch: 0.0,
+            sensitivity: 0.002,
+            pitch_limit: std::f32::consts::FRAC_PI_2,
+            speed: 10.0,
+            fast_multiplier: 4.0,
+            fast_key: KeyCode::ShiftLeft,
+            enabled: true,
+            pressed: HashSet::new(),
+            mouse_delta: Vec2::ZERO,
+        }
+    }
+
+    /// starts looking in the same direction as `rotation`
+    pub fn from_rotation(rotation: Quat) -> Self {
+        let forward = rotation * Vec3::NEG_Z;
+        let mut controller = Self::new();
+        controller.yaw = (-forward.x).atan2(-forward.z);
+        controller.pitch = forward.y.clamp(-1.0, 1.0).asin();
+        controller
+    }
+
+    pub fn sensitivity(mut self, sensitivity: f32) -> Self {
+        self.sensitivity = sensitivity;
+        self
+    }
+
+    pub fn pitch_limit(mut self, pitch_limit: f32) -> Self {
+        self.pitch_limit = pitch_limit;
+        self
+    }
+
+    pub fn speed(mut self, speed: f32, fast_multiplier: f32) -> Self {
+        self.speed = speed;
+        self.fast_multiplier = fast_multiplier;
+        self
+    }
+
+    pub fn handle_window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(keycode),
+                        state,
+                        ..
+                    },
+                ..
+            } => match state {
+                ElementState::Pressed => {
+                    self.pressed.insert(*keycode);
+                }
+                ElementState::Released => {
+                    self.pressed.remove(keycode);
+                }
+            },
+            WindowEvent::Focused(false) => self.pressed.clear(),
+            _ => (),
+        }
+    }
+
+    pub fn handle_device_event(&mut self, event: &DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta: (x, y) } = event {
+            self.mouse_delta += Vec2::new(*x as f32, *y as f32);
+        }
+    }
+
+    fn axis(&self, positive: KeyCode, negative: KeyCode) -> f32 {
+        self.pressed.contains(&positive) as i32 as f32
+            - self.pressed.contains(&negative) as i32 as f32
+    }
+
+    /// applies the input gathered since the last call to `transform`
+    pub fn apply(&mut self, transform: &mut Transform3D, delta_seconds: f32) {
+        let mouse_delta = std::mem::take(&mut self.mouse_delta);
+        if !self.enabled {
+            return;
+        }
+
+        self.yaw -= mouse_delta.x * self.sensitivity;
+        self.pitch = clamp_pitch(
+            (self.pitch - mouse_delta.y * self.sensitivity)
+                .clamp(-self.pitch_limit, self.pitch_limit),
+        );
+        transform.rotation = yaw_pitch_rotation(self.yaw, self.pitch);
+
+        let forward = transform.rotation * Vec3::NEG_Z;
+        let right = transform.rotation * Vec3::X;
+        let movement = forward * self.axis(KeyCode::KeyW, KeyCode::KeyS)
+            + right * self.axis(KeyCode::KeyD, KeyCode::KeyA)
+            + Vec3::Y * self.axis(KeyCode::Space, KeyCode::ControlLeft);
+
+        let speed = if self.pressed.contains(&self.fast_key) {
+            self.speed * self.fast_multiplier
+        } else {
+            self.speed
+        };
+        transform.position += movement.normalize_or_zero() * speed * delta_seconds;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pitch_is_clamped_and_rotation_matches() {
+        let mut controller = FpsCameraController::new().pitch_limit(1.0);
+        let mut transform = Transform3D::new(Vec3::ZERO, Quat::IDENTITY, Vec3::ONE);
+
+        controller.handle_device_event(&DeviceEvent::MouseMotion {
+            delta: (0.0, -100_000.0),
+        });
+        controller.apply(&mut transform, 0.016);
+        assert_eq!(controller.pitch, 1.0);
+
+        let start = FpsCameraController::from_rotation(transform.rotation);
+        assert!((start.pitch - 1.0).abs() < 1e-4);
+        assert!(start.yaw.abs() < 1e-4);
+    }
+}
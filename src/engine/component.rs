@@ -5,6 +5,7 @@ use std::{
 };
 
 use glam::{Mat4, Quat, Vec3};
+use serde::{Deserialize, Serialize};
 
 pub use silly_game_engine_macros::Component;
 
@@ -13,6 +14,7 @@ pub trait Component: Debug + Send + Sync {
     fn label(&self) -> &str;
     fn as_any(&self) -> &dyn Any;
     fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
     fn clone_box(&self) -> Box<dyn Component>;
 }
 
@@ -58,11 +60,37 @@ impl ComponentSet {
     pub fn has<C: 'static + Component>(&mut self) -> bool {
         self.components.contains_key(&TypeId::of::<C>())
     }
+
+    /// gets the component, adding the one made by `f` first if it's missing
+    pub fn get_or_insert_with<C: 'static + Component>(&mut self, f: impl FnOnce() -> C) -> &mut C {
+        self.components
+            .entry(TypeId::of::<C>())
+            .or_insert_with(|| Box::new(f()))
+            .as_any_mut()
+            .downcast_mut::<C>()
+            .expect("component stored under the wrong type id")
+    }
+
+    /// adds the component and returns the one it replaced
+    pub fn replace<C: 'static + Component>(&mut self, component: C) -> Option<C> {
+        self.components
+            .insert(TypeId::of::<C>(), Box::new(component))
+            .and_then(|old| old.into_any().downcast::<C>().ok())
+            .map(|old| *old)
+    }
+
+    /// removes the component and returns it as its concrete type
+    pub fn take<C: 'static + Component>(&mut self) -> Option<C> {
+        self.components
+            .remove(&TypeId::of::<C>())
+            .and_then(|old| old.into_any().downcast::<C>().ok())
+            .map(|old| *old)
+    }
 }
 
 #[cfg(test)]
 mod component_registry_test {
-    use super::{ComponentSet, Transform3D};
+    use super::{ComponentSet, Layer, LayerMask, Transform3D};
 
     #[test]
     fn add_get_eq() {
@@ -76,10 +104,51 @@ mod component_registry_test {
         let transform_c_2 = cr.get::<Transform3D>().unwrap();
         assert_eq!(&transform_c, transform_c_2);
     }
+
+    fn transform(x: f32) -> Transform3D {
+        Transform3D::new(
+            glam::Vec3::new(x, 0.0, 0.0),
+            glam::Quat::IDENTITY,
+            glam::Vec3::ONE,
+        )
+    }
+
+    #[test]
+    fn get_or_insert_with_inserts_on_miss() {
+        let mut cr = ComponentSet::new();
+        cr.get_or_insert_with(|| transform(1.0)).position.y = 2.0;
+        let existing = cr.get_or_insert_with(|| transform(5.0));
+        assert_eq!(existing.position, glam::Vec3::new(1.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn replace_returns_old() {
+        let mut cr = ComponentSet::new();
+        assert_eq!(cr.replace(transform(1.0)), None);
+        assert_eq!(cr.replace(transform(2.0)), Some(transform(1.0)));
+        assert_eq!(cr.get::<Transform3D>(), Some(&transform(2.0)));
+    }
+
+    #[test]
+    fn layer_visible_to_overlapping_mask() {
+        let debris = Layer::new(0b100);
+        assert!(debris.visible_to(&LayerMask::default()));
+        assert!(debris.visible_to(&LayerMask(0b110)));
+        assert!(!debris.visible_to(&LayerMask(0b011)));
+    }
+
+    #[test]
+    fn take_missing_and_present() {
+        let mut cr = ComponentSet::new();
+        assert_eq!(cr.take::<Transform3D>(), None);
+        cr.add(transform(3.0));
+        assert_eq!(cr.take::<Transform3D>(), Some(transform(3.0)));
+        assert!(!cr.has::<Transform3D>());
+    }
 }
 
 /// 3 dimensional transform component
-#[derive(Debug, Clone, Copy, PartialEq, Component)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Component)]
 pub struct Transform3D {
     pub position: Vec3,
     pub rotation: Quat,
@@ -107,3 +176,94 @@ impl Transform3D {
         self.position_matrix() * self.rotation_matrix() * self.scale_matrix()
     }
 }
+
+impl Default for Transform3D {
+    fn default() -> Self {
+        Self::new(Vec3::ZERO, Quat::IDENTITY, Vec3::ONE)
+    }
+}
+
+/// layers an entity is on as a bitmask, physics turns it into collision groups and cameras skip
+/// entities none of whose layers are in their `LayerMask`
+///
+/// entities without one are treated like `Layer::default()`, on layer 1 and colliding with every
+/// layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Component)]
+pub struct Layer {
+    pub layers: u32,
+    /// layers the entity's colliders collide with, both sides have to agree
+    pub collides_with: u32,
+}
+
+impl Layer {
+    pub const ALL: u32 = u32::MAX;
+
+    /// on `layers` and colliding with every layer
+    pub fn new(layers: u32) -> Self {
+        Self {
+            layers,
+            collides_with: Self::ALL,
+        }
+    }
+
+    pub fn with_collides_with(mut self, layers: u32) -> Self {
+        self.collides_with = layers;
+        self
+    }
+
+    pub fn visible_to(&self, mask: &LayerMask) -> bool {
+        self.layers & mask.0 != 0
+    }
+}
+
+impl Default for Layer {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+/// hides an entity from every camera without taking it out of the registry, it keeps updating
+/// and simulating. entities without one are visible
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Component)]
+pub struct Visibility {
+    pub visible: bool,
+    /// while hidden, entities under this one through `Parent` are hidden too
+    pub hide_children: bool,
+}
+
+impl Visibility {
+    pub fn visible() -> Self {
+        Self {
+            visible: true,
+            hide_children: true,
+        }
+    }
+
+    pub fn hidden() -> Self {
+        Self {
+            visible: false,
+            hide_children: true,
+        }
+    }
+
+    pub fn with_hide_children(mut self, hide_children: bool) -> Self {
+        self.hide_children = hide_children;
+        self
+    }
+}
+
+impl Default for Visibility {
+    fn default() -> Self {
+        Self::visible()
+    }
+}
+
+/// layers a camera draws, cameras without one draw every layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+pub struct LayerMask(pub u32);
+
+impl Default for LayerMask {
+    fn default() -> Self {
+        Self(Layer::ALL)
+    }
+}
@@ -0,0 +1,90 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, RwLock},
+};
+
+/// trait for things that live in the engine `Context`
+pub trait ContextItem: Any + Send + Sync {}
+
+type ContextMap = Arc<RwLock<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>>;
+
+/// shared type map for engine wide state (settings, stats, shared services)
+///
+/// cloning a context gives another handle to the same items, every item sits behind its own
+/// `RwLock` so grabbing one doesn't block the others.
+#[derive(Clone)]
+pub struct Context {
+    items: ContextMap,
+}
+
+impl Context {
+    pub fn new() -> Self {
+        Self {
+            items: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// inserts an item, replacing any previous item of the same type
+    pub fn insert<T: ContextItem>(&self, item: T) -> Arc<RwLock<T>> {
+        let item = Arc::new(RwLock::new(item));
+        self.items
+            .write()
+            .unwrap()
+            .insert(TypeId::of::<T>(), item.clone());
+        item
+    }
+
+    /// gets the shared item of type `T`
+    pub fn get<T: ContextItem>(&self) -> Option<Arc<RwLock<T>>> {
+        self.items
+            .read()
+            .unwrap()
+            .get(&TypeId::of::<T>())
+            .cloned()
+            .and_then(|item| item.downcast::<RwLock<T>>().ok())
+    }
+
+    /// gets the item of type `T`, inserting the one made by `f` if there isn't one yet
+    pub fn get_or_insert_with<T: ContextItem>(&self, f: impl FnOnce() -> T) -> Arc<RwLock<T>> {
+        match self.get::<T>() {
+            Some(item) => item,
+            None => self.insert(f()),
+        }
+    }
+
+    /// runs `f` with a read lock on the item of type `T`
+    pub fn with<T: ContextItem, R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        let item = self.get::<T>()?;
+        let guard = item.read().unwrap();
+        Some(f(&guard))
+    }
+
+    /// runs `f` with a write lock on the item of type `T`
+    pub fn with_mut<T: ContextItem, R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        let item = self.get::<T>()?;
+        let mut guard = item.write().unwrap();
+        Some(f(&mut guard))
+    }
+
+    pub fn has<T: ContextItem>(&self) -> bool {
+        self.items.read().unwrap().contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn remove<T: ContextItem>(&self) -> Option<Arc<RwLock<T>>> {
+        self.items
+            .write()
+            .unwrap()
+            .remove(&TypeId::of::<T>())
+            .and_then(|item| item.downcast::<RwLock<T>>().ok())
+    }
+}
+
+impl Debug for Context {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Context")
+            .field("items", &self.items.read().unwrap().len())
+            .finish()
+    }
+}
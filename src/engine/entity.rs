@@ -1,41 +1,83 @@
 use std::{
     any::{Any, TypeId},
-    collections::{HashMap, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     fmt::{Debug, Display},
-    ops::{Deref, DerefMut},
     sync::{Arc, Mutex, RwLock},
 };
 
 use glam::{Mat4, Vec3};
 use three_d::{ColorMaterial, Gm, Mesh};
 use uuid::Uuid;
-use winit::event::WindowEvent;
+use winit::event::{DeviceEvent, WindowEvent};
 
 use crate::{
     assets::asset_manager::Model,
     engine::{component::ComponentSet, messages::Message},
-    utils::{Shared, SharedBox},
+    physics::commands::PhysicsEvent,
+    utils::{Shared, SharedBox, SharedRwBox},
 };
 
-use super::component::{Component, Transform3D};
+use super::{
+    camera_controller::FpsCameraController,
+    component::{Component, Transform3D, Visibility},
+    id::new_id,
+};
 
+/// shared handle to an entity
+///
+/// entities are behind a `RwLock` so the read-heavy paths (rendering, physics write-back reads,
+/// message collection) don't block each other. prefer `read_with`/`write_with` over holding the
+/// guards around so locks get released as soon as possible.
+///
+/// lock ordering: the `EntityRegistry` map lock is always taken before any entity lock, and
+/// nothing should ever hold two entity locks at the same time. the registry's iterator clones the
+/// containers out and drops the map lock before handing them out, so iterating and then locking
+/// entities one by one is always fine.
 #[derive(Clone, Debug)]
-pub struct EntityContainer(SharedBox<dyn Entity>);
+pub struct EntityContainer(SharedRwBox<dyn Entity>);
 
 impl EntityContainer {
     pub fn new(entity: Box<dyn Entity>) -> Self {
-        Self(Arc::new(Mutex::new(entity)))
+        Self(Arc::new(RwLock::new(entity)))
     }
 
     pub fn id(&self) -> Uuid {
-        self.0.lock().as_ref().unwrap().id()
+        self.read_with(|e| e.id())
+    }
+
+    /// takes a read lock, runs `f` and releases the lock
+    pub fn read_with<R>(&self, f: impl FnOnce(&dyn Entity) -> R) -> R {
+        let entity = self.0.read().expect("entity lock poisoned");
+        f(entity.as_ref())
+    }
+
+    /// takes a write lock, runs `f` and releases the lock
+    pub fn write_with<R>(&self, f: impl FnOnce(&mut dyn Entity) -> R) -> R {
+        let mut entity = self.0.write().expect("entity lock poisoned");
+        f(entity.as_mut())
+    }
+
+    /// same as `read_with`, for when no downcasting is needed
+    pub fn with<R>(&self, f: impl FnOnce(&dyn Entity) -> R) -> R {
+        self.read_with(f)
+    }
+
+    /// checks if the entity is of type `T`
+    pub fn is<T: 'static + Entity>(&self) -> bool {
+        self.read_with(|e| e.entity_type()) == TypeId::of::<T>()
     }
-}
 
-impl Deref for EntityContainer {
-    type Target = Mutex<Box<dyn Entity>>;
-    fn deref(&self) -> &Self::Target {
-        &self.0.as_ref()
+    /// downcasts the entity to `T` and runs `f` on it, returns `None` if the entity isn't a `T`
+    pub fn with_downcast<T: 'static + Entity, R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        self.read_with(|e| e.as_any().downcast_ref::<T>().map(f))
+    }
+
+    /// mutable version of `with_downcast`
+    pub fn with_downcast_mut<T: 'static + Entity, R>(
+        &self,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> Option<R> {
+        self.write_with(|e| e.as_any_mut().downcast_mut::<T>().map(f))
     }
 }
 
@@ -57,6 +99,7 @@ impl EntityRegistry {
         self.entities.write().unwrap().insert(entity.id(), entity);
     }
 
+    /// doesn't touch physics, use `Engine::remove_entity` for entities with a physics body
     pub fn remove(&mut self, id: &Uuid) {
         self.entities.write().unwrap().remove(id);
     }
@@ -68,6 +111,78 @@ impl EntityRegistry {
     pub fn len(&self) -> usize {
         self.entities.read().as_ref().unwrap().len()
     }
+
+    /// if the entity's `Visibility` is hidden, or the one of any parent that hides its children
+    pub fn is_hidden(&self, entity: &EntityContainer) -> bool {
+        let (visibility, mut parent) = entity.read_with(visibility_and_parent);
+        if !visibility.visible {
+            return true;
+        }
+        // a parent loop would otherwise never end
+        let mut seen = HashSet::new();
+        while let Some(id) = parent.filter(|id| seen.insert(*id)) {
+            let Some(ancestor) = self.get(&id) else {
+                break;
+            };
+            let (visibility, next) = ancestor.read_with(visibility_and_parent);
+            if !visibility.visible && visibility.hide_children {
+                return true;
+            }
+            parent = next;
+        }
+        false
+    }
+
+    /// the ids of every entity `is_hidden` is true for, each parent is only looked at once.
+    /// for when all of them are asked about, like once a frame
+    pub fn hidden(&self) -> HashSet<Uuid> {
+        let entities: HashMap<Uuid, (Visibility, Option<Uuid>)> = self
+            .clone()
+            .into_iter()
+            .map(|e| (e.id(), e.read_with(visibility_and_parent)))
+            .collect();
+
+        /// whether the entity hides its children, itself or through one of its parents
+        fn hides_children(
+            id: Uuid,
+            entities: &HashMap<Uuid, (Visibility, Option<Uuid>)>,
+            known: &mut HashMap<Uuid, bool>,
+            seen: &mut HashSet<Uuid>,
+        ) -> bool {
+            if let Some(hides) = known.get(&id) {
+                return *hides;
+            }
+            // seen but not known yet means a parent loop
+            let Some((visibility, parent)) = entities.get(&id).filter(|_| seen.insert(id)) else {
+                return false;
+            };
+            let hides = (!visibility.visible && visibility.hide_children)
+                || parent.is_some_and(|parent| hides_children(parent, entities, known, seen));
+            known.insert(id, hides);
+            hides
+        }
+
+        let mut known = HashMap::new();
+        let mut seen = HashSet::new();
+        entities
+            .iter()
+            .filter(|(_, (visibility, parent))| {
+                !visibility.visible
+                    || parent.is_some_and(|parent| {
+                        hides_children(parent, &entities, &mut known, &mut seen)
+                    })
+            })
+            .map(|(id, _)| *id)
+            .collect()
+    }
+}
+
+fn visibility_and_parent(e: &dyn Entity) -> (Visibility, Option<Uuid>) {
+    let components = e.components();
+    (
+        components.get::<Visibility>().copied().unwrap_or_default(),
+        components.get::<Parent>().map(|p| p.get_id()),
+    )
 }
 
 impl IntoIterator for EntityRegistry {
@@ -88,12 +203,18 @@ impl IntoIterator for EntityRegistry {
 pub trait Entity: Debug + Send + Sync {
     fn id(&self) -> Uuid;
     fn model(&self) -> &Option<crate::assets::asset_manager::Model>;
-    fn transform(&self) -> Transform3D;
-    fn transform_mut(&mut self) -> &mut Transform3D;
-
+    /// called once a frame by `Engine::update` with the milliseconds since the last frame,
+    /// before the frame is drawn
+    ///
+    /// note: this used to run inside the renderer with its frame delta, so it was skipped
+    /// whenever a frame failed. it now runs for every frame the windower asks for, drawn or not
     fn update(&mut self, delta: f64);
-    fn physics_update(&mut self, delta: f64);
+    /// called on the physics thread after every step with the fixed dt in milliseconds and the events
+    /// from that step this entity is part of
+    fn physics_update(&mut self, delta: f64, events: &[PhysicsEvent]);
     fn input(&mut self, event: &WindowEvent);
+    /// raw device input like mouse motion, ignored unless overridden
+    fn device_input(&mut self, event: &DeviceEvent) {}
 
     fn components(&self) -> &ComponentSet;
     fn components_mut(&mut self) -> &mut ComponentSet;
@@ -129,10 +250,7 @@ impl Children {
             .iter()
             .map(|c| {
                 let c_id = c.id();
-                c.lock()
-                    .unwrap()
-                    .components_mut()
-                    .add(Parent::new(parent, c_id));
+                c.write_with(|c| c.components_mut().add(Parent::new(parent, c_id)));
                 entities.add(c.clone());
                 c_id
             })
@@ -158,15 +276,7 @@ impl Children {
     pub fn get_by_type<E: 'static + Entity>(&self) -> Vec<EntityContainer> {
         self.children
             .iter()
-            .filter_map(|c_id| {
-                self.entities.get(c_id).and_then(|e| {
-                    if e.lock().unwrap().entity_type() == TypeId::of::<E>() {
-                        Some(e)
-                    } else {
-                        None
-                    }
-                })
-            })
+            .filter_map(|c_id| self.entities.get(c_id).filter(|e| e.is::<E>()))
             .collect()
     }
 }
@@ -232,7 +342,7 @@ impl DefaultCamera {
         components.add(transform);
         Self {
             components,
-            id: Uuid::new_v4(),
+            id: new_id(),
             messages: VecDeque::new(),
             width,
             height,
@@ -243,6 +353,21 @@ impl DefaultCamera {
             far,
         }
     }
+
+    /// position, forward and up in world space, `forward` and `up` turned by the camera's
+    /// rotation
+    pub fn view_vectors(&self) -> (Vec3, Vec3, Vec3) {
+        let transform = self
+            .components
+            .get::<Transform3D>()
+            .copied()
+            .unwrap_or_default();
+        (
+            transform.position,
+            transform.rotation * self.forward,
+            transform.rotation * self.up,
+        )
+    }
 }
 
 impl Entity for DefaultCamera {
@@ -252,9 +377,29 @@ impl Entity for DefaultCamera {
     fn model(&self) -> &Option<crate::assets::asset_manager::Model> {
         &None
     }
-    fn input(&mut self, event: &WindowEvent) {}
-    fn update(&mut self, delta: f64) {}
-    fn physics_update(&mut self, delta: f64) {}
+    fn input(&mut self, event: &WindowEvent) {
+        if let Some(controller) = self.components.get_mut::<FpsCameraController>() {
+            controller.handle_window_event(event);
+        }
+    }
+    fn device_input(&mut self, event: &DeviceEvent) {
+        if let Some(controller) = self.components.get_mut::<FpsCameraController>() {
+            controller.handle_device_event(event);
+        }
+    }
+    fn update(&mut self, delta: f64) {
+        let mut transform = self
+            .components
+            .get::<Transform3D>()
+            .copied()
+            .unwrap_or_default();
+        if let Some(controller) = self.components.get_mut::<FpsCameraController>() {
+            // delta is in milliseconds
+            controller.apply(&mut transform, (delta / 1000.0) as f32);
+            self.components.add(transform);
+        }
+    }
+    fn physics_update(&mut self, delta: f64, events: &[PhysicsEvent]) {}
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
@@ -264,12 +409,6 @@ impl Entity for DefaultCamera {
     fn entity_type(&self) -> TypeId {
         TypeId::of::<DefaultCamera>()
     }
-    fn transform(&self) -> Transform3D {
-        *self.components.get().unwrap()
-    }
-    fn transform_mut(&mut self) -> &mut Transform3D {
-        self.components.get_mut().unwrap()
-    }
     fn components(&self) -> &ComponentSet {
         &self.components
     }
@@ -292,17 +431,8 @@ impl Entity for DefaultCamera {
 
 impl Camera for DefaultCamera {
     fn view_matrix(&self) -> Mat4 {
-        (self
-            .components
-            .get::<Transform3D>()
-            .unwrap()
-            .position_matrix()
-            * self
-                .components
-                .get::<Transform3D>()
-                .unwrap()
-                .rotation_matrix())
-        .inverse()
+        let (position, forward, up) = self.view_vectors();
+        Mat4::look_to_rh(position, forward, up)
     }
 
     fn projection_matrix_lh(&self) -> Mat4 {
@@ -331,3 +461,227 @@ impl Camera for DefaultCamera {
         ])
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// bare entity for tests, also used by other modules' tests
+    #[derive(Debug, Clone)]
+    pub(crate) struct Dummy {
+        id: Uuid,
+        components: ComponentSet,
+        messages: VecDeque<Message>,
+        model: Option<Model>,
+        /// delta of every `update` call
+        pub(crate) updates: Vec<f64>,
+        /// delta and events of every `physics_update` call
+        pub(crate) physics_updates: Vec<(f64, Vec<PhysicsEvent>)>,
+        /// how many times `input` was called
+        pub(crate) inputs: usize,
+        echoes: usize,
+    }
+
+    impl Dummy {
+        /// adds an identity transform if `components` doesn't have one
+        pub(crate) fn new(mut components: ComponentSet) -> Self {
+            components.get_or_insert_with(|| {
+                Transform3D::new(Vec3::ZERO, glam::Quat::IDENTITY, Vec3::ONE)
+            });
+            Self {
+                id: Uuid::new_v4(),
+                components,
+                messages: VecDeque::new(),
+                model: None,
+                updates: Vec::new(),
+                physics_updates: Vec::new(),
+                inputs: 0,
+                echoes: 0,
+            }
+        }
+
+        pub(crate) fn with_id(mut self, id: Uuid) -> Self {
+            self.id = id;
+            self
+        }
+
+        pub(crate) fn with_model(mut self, model: Model) -> Self {
+            self.model = Some(model);
+            self
+        }
+
+        /// messages the engine collects from it on the next `handle_messages`
+        pub(crate) fn with_messages(mut self, messages: impl IntoIterator<Item = Message>) -> Self {
+            self.messages.extend(messages);
+            self
+        }
+
+        /// every time its messages are collected the first one is sent again, `echoes` times
+        pub(crate) fn with_echoes(mut self, echoes: usize) -> Self {
+            self.echoes = echoes;
+            self
+        }
+    }
+
+    impl Entity for Dummy {
+        fn id(&self) -> Uuid {
+            self.id
+        }
+        fn model(&self) -> &Option<Model> {
+            &self.model
+        }
+        fn update(&mut self, delta: f64) {
+            self.updates.push(delta);
+        }
+        fn physics_update(&mut self, delta: f64, events: &[PhysicsEvent]) {
+            self.physics_updates.push((delta, events.to_vec()));
+        }
+        fn input(&mut self, event: &WindowEvent) {
+            self.inputs += 1;
+        }
+        fn components(&self) -> &ComponentSet {
+            &self.components
+        }
+        fn components_mut(&mut self) -> &mut ComponentSet {
+            &mut self.components
+        }
+        fn get_messages(&self) -> &VecDeque<Message> {
+            &self.messages
+        }
+        fn clear_messages(&mut self) {
+            let first = self.messages.drain(..).next();
+            if let Some(first) = first.filter(|_| self.echoes > 0) {
+                self.echoes -= 1;
+                self.messages.push_back(first);
+            }
+        }
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+        fn entity_type(&self) -> TypeId {
+            TypeId::of::<Dummy>()
+        }
+        fn clone_box(&self) -> Box<dyn Entity> {
+            Box::new(self.clone())
+        }
+        fn into_container(self) -> EntityContainer {
+            EntityContainer::new(Box::new(self))
+        }
+    }
+
+    fn camera() -> DefaultCamera {
+        DefaultCamera::new(
+            Transform3D::new(Vec3::ZERO, glam::Quat::IDENTITY, Vec3::ONE),
+            16.0,
+            9.0,
+            Vec3::Y,
+            Vec3::NEG_Z,
+            1.0,
+            0.1,
+            100.0,
+        )
+    }
+
+    #[test]
+    fn downcast_right_type() {
+        let container = camera().into_container();
+        assert!(container.is::<DefaultCamera>());
+        assert_eq!(
+            container.with_downcast(|c: &DefaultCamera| c.far),
+            Some(100.0)
+        );
+
+        container.with_downcast_mut(|c: &mut DefaultCamera| c.far = 50.0);
+        assert_eq!(
+            container.with_downcast(|c: &DefaultCamera| c.far),
+            Some(50.0)
+        );
+    }
+
+    #[test]
+    fn downcast_wrong_type_is_none() {
+        let container = camera().into_container();
+        assert!(!container.is::<Dummy>());
+        assert!(container.with_downcast(|d: &Dummy| d.id).is_none());
+        assert!(container.with_downcast_mut(|d: &mut Dummy| d.id).is_none());
+        // lock must have been released
+        assert_eq!(container.with(|e| e.id()), container.id());
+    }
+
+    #[test]
+    fn children_get_by_type() {
+        let registry = EntityRegistry::new();
+        let parent = Uuid::new_v4();
+        let dummy = Dummy::new(ComponentSet::new());
+        let children = Children::new(
+            parent,
+            vec![camera().into_container(), dummy.into_container()],
+            registry,
+        );
+        assert_eq!(children.get_by_type::<DefaultCamera>().len(), 1);
+        assert_eq!(children.get_by_type::<Dummy>().len(), 1);
+    }
+
+    #[test]
+    fn hidden_parents_hide_their_children() {
+        let mut registry = EntityRegistry::new();
+        let parent = Dummy::new(ComponentSet::new()).into_container();
+        let parent_id = parent.id();
+        registry.add(parent.clone());
+        let child = Dummy::new(ComponentSet::new()).into_container();
+        let grandchild = Dummy::new(ComponentSet::new()).into_container();
+        Children::new(parent_id, vec![child.clone()], registry.clone());
+        Children::new(child.id(), vec![grandchild.clone()], registry.clone());
+
+        assert!(!registry.is_hidden(&grandchild));
+
+        parent.write_with(|p| p.components_mut().add(Visibility::hidden()));
+        assert!(registry.is_hidden(&parent));
+        assert!(registry.is_hidden(&child));
+        assert!(registry.is_hidden(&grandchild));
+
+        parent.write_with(|p| {
+            p.components_mut()
+                .add(Visibility::hidden().with_hide_children(false))
+        });
+        assert!(registry.is_hidden(&parent));
+        assert!(!registry.is_hidden(&grandchild));
+    }
+
+    #[test]
+    fn hidden_agrees_with_is_hidden() {
+        let mut registry = EntityRegistry::new();
+        let parent = Dummy::new(ComponentSet::new()).into_container();
+        registry.add(parent.clone());
+        let child = Dummy::new(ComponentSet::new()).into_container();
+        let grandchild = Dummy::new(ComponentSet::new()).into_container();
+        let other = Dummy::new(ComponentSet::new()).into_container();
+        registry.add(other.clone());
+        Children::new(parent.id(), vec![child.clone()], registry.clone());
+        Children::new(child.id(), vec![grandchild.clone()], registry.clone());
+        assert!(registry.hidden().is_empty());
+
+        parent.write_with(|p| p.components_mut().add(Visibility::hidden()));
+        let hidden = registry.hidden();
+        assert_eq!(
+            hidden,
+            HashSet::from([parent.id(), child.id(), grandchild.id()])
+        );
+        for entity in registry.clone() {
+            assert_eq!(hidden.contains(&entity.id()), registry.is_hidden(&entity));
+        }
+
+        // a parent loop ends instead of going around forever
+        let looped = Parent::new(grandchild.id(), parent.id());
+        parent.write_with(|p| p.components_mut().add(looped));
+        child.write_with(|c| {
+            c.components_mut()
+                .add(Visibility::hidden().with_hide_children(false))
+        });
+        parent.write_with(|p| p.components_mut().add(Visibility::default()));
+        assert_eq!(registry.hidden(), HashSet::from([child.id()]));
+    }
+}
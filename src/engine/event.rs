@@ -1,19 +1,46 @@
-use std::{collections::VecDeque, sync::Weak};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, RwLock, Weak},
+};
 
-use winit::{event::WindowEvent, window::WindowId};
+use serde::{Deserialize, Serialize};
+use winit::{
+    event::{DeviceEvent, DeviceId, WindowEvent},
+    window::WindowId,
+};
 
-use super::{Engine, entity::EntityRegistry};
+use super::{
+    Engine,
+    component::{Component, ComponentSet},
+    entity::EntityRegistry,
+};
 
-use crate::engine::messages::Message;
+use crate::{engine::messages::Message, rendering::ui::UiInputCapture};
 
-#[derive(Debug, Clone)]
+/// winit events can't be serialized, so these are left out of recordings
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum EventHandlerCommand {
+    #[serde(skip)]
     WindowEvent((WindowId, WindowEvent)),
 }
 
+/// marks an entity as driven by the player with this index
+///
+/// only these entities get keyboard, mouse and device input. with devices assigned through
+/// `EventHandler::assign_device` each player only gets input from their own devices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component)]
+pub struct PlayerControlled(pub u8);
+
+/// opts an entity without `PlayerControlled` into window level events (resize, focus, ...)
+#[derive(Debug, Clone, Copy, Component)]
+pub struct WindowEventInterest;
+
 pub struct EventHandler {
     pub messages: VecDeque<Message>,
     entities: EntityRegistry,
+    device_players: RwLock<HashMap<DeviceId, u8>>,
+    /// events the ui takes don't go on to the entities
+    ui_capture: Option<Arc<RwLock<UiInputCapture>>>,
 }
 
 impl EventHandler {
@@ -21,15 +48,77 @@ impl EventHandler {
         Self {
             messages: VecDeque::new(),
             entities,
+            device_players: RwLock::new(HashMap::new()),
+            ui_capture: None,
         }
     }
 
+    pub fn with_ui_capture(mut self, ui_capture: Arc<RwLock<UiInputCapture>>) -> Self {
+        self.ui_capture = Some(ui_capture);
+        self
+    }
+
+    /// routes input from `device_id` only to entities controlled by `player`
+    pub fn assign_device(&self, device_id: DeviceId, player: u8) {
+        self.device_players
+            .write()
+            .unwrap()
+            .insert(device_id, player);
+    }
+
+    /// input from `device_id` goes back to every player
+    pub fn unassign_device(&self, device_id: DeviceId) -> Option<u8> {
+        self.device_players.write().unwrap().remove(&device_id)
+    }
+
+    pub fn device_player(&self, device_id: DeviceId) -> Option<u8> {
+        self.device_players.read().unwrap().get(&device_id).copied()
+    }
+
+    /// whether an entity with these components should get input from `device_id`
+    fn wants_player_input(&self, components: &ComponentSet, device_id: Option<DeviceId>) -> bool {
+        let Some(PlayerControlled(player)) = components.get::<PlayerControlled>() else {
+            return false;
+        };
+        device_id
+            .and_then(|id| self.device_player(id))
+            .is_none_or(|assigned| assigned == *player)
+    }
+
     pub fn send_event(&self, window_id: WindowId, event: WindowEvent) -> () {
         // log::debug!("input event: {:?}", event);
-        self.entities
-            .clone()
-            .into_iter()
-            .for_each(|e| e.lock().unwrap().input(&event));
+        if self
+            .ui_capture
+            .as_ref()
+            .is_some_and(|capture| capture.read().unwrap().captures(&event))
+        {
+            return;
+        }
+        let player_input = player_input_device(&event);
+        self.entities.clone().into_iter().for_each(|e| {
+            e.write_with(|e| {
+                let wants = match player_input {
+                    Some(device_id) => self.wants_player_input(e.components(), device_id),
+                    None => {
+                        e.components().get::<PlayerControlled>().is_some()
+                            || e.components().get::<WindowEventInterest>().is_some()
+                    }
+                };
+                if wants {
+                    e.input(&event)
+                }
+            })
+        });
+    }
+
+    pub fn send_device_event(&self, device_id: DeviceId, event: &DeviceEvent) {
+        self.entities.clone().into_iter().for_each(|e| {
+            e.write_with(|e| {
+                if self.wants_player_input(e.components(), Some(device_id)) {
+                    e.device_input(event)
+                }
+            })
+        });
     }
 
     pub fn get_messages(&self) -> &VecDeque<Message> {
@@ -44,3 +133,119 @@ impl EventHandler {
         self.messages.clear();
     }
 }
+
+/// `Some` for events that are player input, holding the device it came from if known
+fn player_input_device(event: &WindowEvent) -> Option<Option<DeviceId>> {
+    match event {
+        WindowEvent::KeyboardInput { device_id, .. }
+        | WindowEvent::CursorMoved { device_id, .. }
+        | WindowEvent::CursorEntered { device_id }
+        | WindowEvent::CursorLeft { device_id }
+        | WindowEvent::MouseWheel { device_id, .. }
+        | WindowEvent::MouseInput { device_id, .. }
+        | WindowEvent::AxisMotion { device_id, .. }
+        | WindowEvent::Touch(winit::event::Touch { device_id, .. }) => Some(Some(*device_id)),
+        WindowEvent::ModifiersChanged(_) | WindowEvent::Ime(_) => Some(None),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+    use winit::{
+        event::{ElementState, MouseButton},
+        keyboard::ModifiersState,
+    };
+
+    use super::*;
+    use crate::engine::entity::{Entity, tests::Dummy};
+
+    fn listener(components: &[fn(&mut ComponentSet)]) -> Dummy {
+        let mut set = ComponentSet::new();
+        components.iter().for_each(|add| add(&mut set));
+        Dummy::new(set)
+    }
+
+    fn inputs(entities: &EntityRegistry, id: Uuid) -> usize {
+        entities
+            .get(&id)
+            .unwrap()
+            .with_downcast(|d: &Dummy| d.inputs)
+            .unwrap()
+    }
+
+    #[test]
+    fn keyboard_input_only_reaches_player_controlled() {
+        let mut entities = EntityRegistry::new();
+        let npc = listener(&[]);
+        let watcher = listener(&[|c| c.add(WindowEventInterest)]);
+        let player = listener(&[|c| c.add(PlayerControlled(0))]);
+        let ids = (npc.id(), watcher.id(), player.id());
+        entities.add(npc.into_container());
+        entities.add(watcher.into_container());
+        entities.add(player.into_container());
+
+        let handler = EventHandler::new(entities.clone());
+        let window_id = unsafe { WindowId::dummy() };
+        handler.send_event(
+            window_id,
+            WindowEvent::ModifiersChanged(ModifiersState::SHIFT.into()),
+        );
+        handler.send_event(window_id, WindowEvent::Focused(true));
+
+        assert_eq!(inputs(&entities, ids.0), 0);
+        assert_eq!(inputs(&entities, ids.1), 1);
+        assert_eq!(inputs(&entities, ids.2), 2);
+    }
+
+    #[test]
+    fn assigned_devices_go_to_their_player() {
+        let mut entities = EntityRegistry::new();
+        let one = listener(&[|c| c.add(PlayerControlled(0))]);
+        let two = listener(&[|c| c.add(PlayerControlled(1))]);
+        let ids = (one.id(), two.id());
+        entities.add(one.into_container());
+        entities.add(two.into_container());
+
+        let handler = EventHandler::new(entities.clone());
+        let device_id = unsafe { DeviceId::dummy() };
+        handler.assign_device(device_id, 1);
+        handler.send_event(
+            unsafe { WindowId::dummy() },
+            WindowEvent::MouseInput {
+                device_id,
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+            },
+        );
+
+        assert_eq!(inputs(&entities, ids.0), 0);
+        assert_eq!(inputs(&entities, ids.1), 1);
+    }
+
+    #[test]
+    fn ui_keeps_the_events_it_takes() {
+        let mut entities = EntityRegistry::new();
+        let player = listener(&[|c| c.add(PlayerControlled(0))]);
+        let id = player.id();
+        entities.add(player.into_container());
+
+        let capture = Arc::new(RwLock::new(UiInputCapture {
+            pointer: true,
+            keyboard: false,
+        }));
+        let handler = EventHandler::new(entities.clone()).with_ui_capture(capture.clone());
+        let click = WindowEvent::MouseInput {
+            device_id: unsafe { DeviceId::dummy() },
+            state: ElementState::Pressed,
+            button: MouseButton::Left,
+        };
+        handler.send_event(unsafe { WindowId::dummy() }, click.clone());
+        assert_eq!(inputs(&entities, id), 0);
+
+        capture.write().unwrap().pointer = false;
+        handler.send_event(unsafe { WindowId::dummy() }, click);
+        assert_eq!(inputs(&entities, id), 1);
+    }
+}
@@ -0,0 +1,50 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// how often the window draws, owned by the `Engine` and followed by the `Windower`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FramePacing {
+    /// a new frame as soon as the last one is done, only vsync holds it back
+    #[default]
+    Continuous,
+    /// at most this many frames a second, 0 is the same as `Continuous`
+    TargetFps(u32),
+    /// only when something changed, see `Engine::request_frame`
+    OnDemand,
+}
+
+impl FramePacing {
+    /// when the frame after the one started at `last_frame` is due, `None` when it waits for the
+    /// scene to change. `dirty` is whether it has since that frame
+    pub fn next_frame(&self, last_frame: Instant, dirty: bool) -> Option<Instant> {
+        match *self {
+            FramePacing::Continuous | FramePacing::TargetFps(0) => Some(last_frame),
+            FramePacing::TargetFps(fps) => {
+                Some(last_frame + Duration::from_secs_f64(1.0 / fps as f64))
+            }
+            FramePacing::OnDemand => dirty.then_some(last_frame),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_are_due_by_policy() {
+        let last = Instant::now();
+        assert_eq!(FramePacing::Continuous.next_frame(last, false), Some(last));
+        assert_eq!(
+            FramePacing::TargetFps(0).next_frame(last, false),
+            Some(last)
+        );
+        assert_eq!(
+            FramePacing::TargetFps(50).next_frame(last, true),
+            Some(last + Duration::from_millis(20))
+        );
+        assert_eq!(FramePacing::OnDemand.next_frame(last, false), None);
+        assert_eq!(FramePacing::OnDemand.next_frame(last, true), Some(last));
+    }
+}
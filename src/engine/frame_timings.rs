@@ -0,0 +1,207 @@
+use std::{sync::Mutex, time::Duration};
+
+use crate::engine::context::ContextItem;
+
+/// narrowest `FrameTimings::histogram` bucket
+pub const MIN_BUCKET_MS: f64 = 0.01;
+/// most buckets a `FrameTimings::histogram` has
+pub const MAX_BUCKETS: usize = 256;
+
+/// ring buffer of recent frame times, kept in the engine `Context`
+///
+/// nothing allocates after `new`, percentiles are worked out in a preallocated scratch buffer.
+#[derive(Debug)]
+pub struct FrameTimings {
+    samples: Vec<f64>,
+    next: usize,
+    len: usize,
+    scratch: Mutex<Vec<f64>>,
+}
+
+impl ContextItem for FrameTimings {}
+
+impl FrameTimings {
+    /// keeps the last `capacity` frames
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            samples: vec![0.0; capacity],
+            next: 0,
+            len: 0,
+            scratch: Mutex::new(Vec::with_capacity(capacity)),
+        }
+    }
+
+    pub fn push(&mut self, frame_time: Duration) {
+        self.samples[self.next] = frame_time.as_secs_f64() * 1000.0;
+        self.next = (self.next + 1) % self.samples.len();
+        self.len = (self.len + 1).min(self.samples.len());
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.next = 0;
+        self.len = 0;
+    }
+
+    /// frame times in milliseconds, oldest first
+    pub fn iter_ms(&self) -> impl Iterator<Item = f64> + '_ {
+        let start = (self.next + self.samples.len() - self.len) % self.samples.len();
+        (0..self.len).map(move |i| self.samples[(start + i) % self.samples.len()])
+    }
+
+    /// the most recent frame time
+    pub fn last(&self) -> Option<Duration> {
+        if self.len == 0 {
+            return None;
+        }
+        let index = (self.next + self.samples.len() - 1) % self.samples.len();
+        Some(ms_to_duration(self.samples[index]))
+    }
+
+    pub fn average(&self) -> Option<Duration> {
+        if self.len == 0 {
+            return None;
+        }
+        Some(ms_to_duration(
+            self.iter_ms().sum::<f64>() / self.len as f64,
+        ))
+    }
+
+    pub fn worst(&self) -> Option<Duration> {
+        self.iter_ms().reduce(f64::max).map(ms_to_duration)
+    }
+
+    /// average frames per second over the buffer
+    pub fn fps(&self) -> f64 {
+        match self.average() {
+            Some(avg) if !avg.is_zero() => 1.0 / avg.as_secs_f64(),
+            _ => 0.0,
+        }
+    }
+
+    /// nearest-rank percentile, `p` is in 0..=100
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        if self.len == 0 {
+            return None;
+        }
+        let mut scratch = self.scratch.lock().unwrap();
+        scratch.clear();
+        scratch.extend(self.iter_ms());
+        scratch.sort_unstable_by(|a, b| a.total_cmp(b));
+
+        let rank = ((p.clamp(0.0, 100.0) / 100.0) * self.len as f64).ceil() as usize;
+        Some(ms_to_duration(scratch[rank.saturating_sub(1)]))
+    }
+
+    /// (bucket start in ms, frame count) for every `bucket_ms` wide bucket up to the worst frame
+    ///
+    /// widths under `MIN_BUCKET_MS` or not finite use `MIN_BUCKET_MS`. there are at most
+    /// `MAX_BUCKETS`, frames past the last one are counted in it
+    pub fn histogram(&self, bucket_ms: f64) -> impl Iterator<Item = (f64, usize)> + '_ {
+        let bucket_ms = match bucket_ms.is_finite() {
+            true => bucket_ms.max(MIN_BUCKET_MS),
+            false => MIN_BUCKET_MS,
+        };
+        let bucket = |ms: f64| ((ms / bucket_ms) as usize).min(MAX_BUCKETS - 1);
+        let buckets = self
+            .worst()
+            .map_or(0, |worst| bucket(worst.as_secs_f64() * 1000.0) + 1);
+
+        let mut counts = [0; MAX_BUCKETS];
+        self.iter_ms().for_each(|ms| counts[bucket(ms)] += 1);
+        counts
+            .into_iter()
+            .take(buckets)
+            .enumerate()
+            .map(move |(b, count)| (b as f64 * bucket_ms, count))
+    }
+}
+
+fn ms_to_duration(ms: f64) -> Duration {
+    Duration::from_secs_f64(ms / 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx(a: Duration, ms: f64) -> bool {
+        (a.as_secs_f64() * 1000.0 - ms).abs() < 1e-6
+    }
+
+    #[test]
+    fn percentile_matches_sorted_reference() {
+        let mut timings = FrameTimings::new(100);
+        let mut reference = Vec::new();
+        for i in 0..250 {
+            let ms = ((i * 37) % 50) as f64 + 1.0;
+            timings.push(Duration::from_secs_f64(ms / 1000.0));
+            reference.push(ms);
+        }
+        // only the last 100 frames are kept
+        let mut reference = reference.split_off(150);
+        reference.sort_by(|a, b| a.total_cmp(b));
+
+        assert_eq!(timings.len(), 100);
+        for p in [1.0, 50.0, 90.0, 99.0, 100.0] {
+            let rank = ((p / 100.0) * 100.0_f64).ceil() as usize;
+            assert!(approx(timings.percentile(p).unwrap(), reference[rank - 1]));
+        }
+        assert!(approx(timings.worst().unwrap(), *reference.last().unwrap()));
+    }
+
+    #[test]
+    fn average_fps_and_histogram() {
+        let mut timings = FrameTimings::new(4);
+        assert!(timings.average().is_none());
+        assert_eq!(timings.fps(), 0.0);
+
+        for ms in [10.0, 10.0, 20.0, 40.0] {
+            timings.push(Duration::from_secs_f64(ms / 1000.0));
+        }
+        assert!(approx(timings.average().unwrap(), 20.0));
+        assert!((timings.fps() - 50.0).abs() < 1e-6);
+
+        let histogram: Vec<_> = timings.histogram(10.0).collect();
+        assert_eq!(histogram.len(), 5);
+        assert_eq!(histogram[1], (10.0, 2));
+        assert_eq!(histogram[2], (20.0, 1));
+        assert_eq!(histogram[4], (40.0, 1));
+    }
+
+    #[test]
+    fn histogram_buckets_stay_bounded() {
+        let mut timings = FrameTimings::new(4);
+        for ms in [0.005, 3.0, 16.0] {
+            timings.push(Duration::from_secs_f64(ms / 1000.0));
+        }
+
+        for width in [0.0, -1.0, f64::EPSILON, f64::NAN, f64::INFINITY] {
+            let histogram: Vec<_> = timings.histogram(width).collect();
+            assert_eq!(histogram.len(), MAX_BUCKETS);
+            assert_eq!(histogram[1].0, MIN_BUCKET_MS);
+            assert_eq!(histogram[0].1, 1);
+            // 3 and 16 ms are both past the last 0.01 ms bucket
+            assert_eq!(histogram[MAX_BUCKETS - 1].1, 2);
+        }
+        assert_eq!(
+            timings
+                .histogram(0.1)
+                .map(|(_, count)| count)
+                .sum::<usize>(),
+            3
+        );
+    }
+}
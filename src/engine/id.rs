@@ -0,0 +1,137 @@
+use std::sync::{Arc, Mutex, OnceLock};
+
+use uuid::Uuid;
+
+use crate::engine::context::ContextItem;
+
+/// how an `IdGenerator` makes ids
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdMode {
+    /// random v4 ids, the default
+    Random,
+    /// v5 ids made from a namespace and a counter or a name, same sequence every run
+    Deterministic { namespace: Uuid },
+}
+
+#[derive(Debug)]
+struct IdGeneratorState {
+    mode: IdMode,
+    counter: u64,
+}
+
+/// hands out entity ids
+///
+/// this is a cheap handle, clones share the same counter. the engine puts the global generator
+/// in its `Context`, so switching it to deterministic mode affects everything made with `new_id`.
+#[derive(Debug, Clone)]
+pub struct IdGenerator {
+    state: Arc<Mutex<IdGeneratorState>>,
+}
+
+impl ContextItem for IdGenerator {}
+
+static GLOBAL_ID_GENERATOR: OnceLock<IdGenerator> = OnceLock::new();
+
+impl IdGenerator {
+    pub fn new(mode: IdMode) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(IdGeneratorState { mode, counter: 0 })),
+        }
+    }
+
+    pub fn random() -> Self {
+        Self::new(IdMode::Random)
+    }
+
+    pub fn deterministic(namespace: Uuid) -> Self {
+        Self::new(IdMode::Deterministic { namespace })
+    }
+
+    /// the process wide generator used by `new_id`
+    pub fn global() -> Self {
+        GLOBAL_ID_GENERATOR.get_or_init(IdGenerator::random).clone()
+    }
+
+    pub fn mode(&self) -> IdMode {
+        self.state.lock().unwrap().mode
+    }
+
+    /// switches mode and restarts the counter
+    pub fn set_mode(&self, mode: IdMode) {
+        let mut state = self.state.lock().unwrap();
+        state.mode = mode;
+        state.counter = 0;
+    }
+
+    /// restarts the counter so a deterministic generator hands out the same sequence again
+    pub fn reset(&self) {
+        self.state.lock().unwrap().counter = 0;
+    }
+
+    pub fn next_id(&self) -> Uuid {
+        let mut state = self.state.lock().unwrap();
+        match state.mode {
+            IdMode::Random => Uuid::new_v4(),
+            IdMode::Deterministic { namespace } => {
+                let id = Uuid::new_v5(&namespace, &state.counter.to_le_bytes());
+                state.counter += 1;
+                id
+            }
+        }
+    }
+
+    /// id derived from `name` alone, so it doesn't depend on the order things are made in. for
+    /// the entities of a scene, see `named_id`
+    ///
+    /// in random mode this is just a random id
+    pub fn id_for_name(&self, name: &str) -> Uuid {
+        match self.mode() {
+            IdMode::Random => Uuid::new_v4(),
+            IdMode::Deterministic { namespace } => Uuid::new_v5(&namespace, name.as_bytes()),
+        }
+    }
+}
+
+/// new id from the global generator, use this for entity ids
+pub fn new_id() -> Uuid {
+    IdGenerator::global().next_id()
+}
+
+/// id for the entity called `name` from the global generator, in deterministic mode a scene
+/// built from the same names gets the same ids every run
+pub fn named_id(name: &str) -> Uuid {
+    IdGenerator::global().id_for_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn make_ids(generator: &IdGenerator) -> HashSet<Uuid> {
+        ["camera", "plane", "lantern", "helmet"]
+            .iter()
+            .map(|name| generator.id_for_name(name))
+            .chain((0..4).map(|_| generator.next_id()))
+            .collect()
+    }
+
+    #[test]
+    fn deterministic_ids_match_between_runs() {
+        let namespace = Uuid::new_v5(&Uuid::NAMESPACE_OID, b"test ids");
+        let first = make_ids(&IdGenerator::deterministic(namespace));
+        let second = make_ids(&IdGenerator::deterministic(namespace));
+        assert_eq!(first.len(), 8);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn reset_restarts_sequence() {
+        let generator = IdGenerator::deterministic(Uuid::NAMESPACE_OID);
+        let a = generator.next_id();
+        generator.reset();
+        assert_eq!(a, generator.next_id());
+        assert_ne!(a, generator.next_id());
+    }
+}
@@ -0,0 +1,173 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use crate::engine::{
+    context::ContextItem,
+    messages::{Message, MessageCommand, Systems},
+};
+
+/// default for `MessageStats::threshold`
+pub const DEFAULT_MESSAGE_THRESHOLD: usize = 1000;
+
+/// frames in a row the physics command queue has to grow before it's reported
+const PHYSICS_GROWTH_FRAMES: usize = 30;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackpressureEvent {
+    /// a single `handle_messages` call went over the threshold
+    TooManyMessages { processed: usize, threshold: usize },
+    /// the physics thread hasn't kept up with commands for a while
+    PhysicsQueueGrowing { pending: usize },
+}
+
+/// message counts from `Engine::handle_messages`, kept in the engine context
+///
+/// counts are per call, which is once per frame in practice. going over `threshold` or a
+/// growing physics command queue logs a warning and publishes a `BackpressureEvent`, at most
+/// once every `warning_interval`.
+#[derive(Debug)]
+pub struct MessageStats {
+    pub threshold: usize,
+    pub warning_interval: Duration,
+
+    processed: usize,
+    by_command: HashMap<&'static str, usize>,
+    by_source: HashMap<Systems, usize>,
+    peak_queue_depth: usize,
+    warned_this_frame: bool,
+
+    physics_pending: usize,
+    physics_growth: usize,
+
+    warnings: usize,
+    last_warning: Option<Instant>,
+    events: VecDeque<BackpressureEvent>,
+}
+
+impl ContextItem for MessageStats {}
+
+impl MessageStats {
+    pub fn new(threshold: usize) -> Self {
+        Self {
+            threshold,
+            warning_interval: Duration::from_secs(1),
+            processed: 0,
+            by_command: HashMap::new(),
+            by_source: HashMap::new(),
+            peak_queue_depth: 0,
+            warned_this_frame: false,
+            physics_pending: 0,
+            physics_growth: 0,
+            warnings: 0,
+            last_warning: None,
+            events: VecDeque::new(),
+        }
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.processed = 0;
+        self.by_command.clear();
+        self.by_source.clear();
+        self.warned_this_frame = false;
+    }
+
+    pub fn record_queue_depth(&mut self, depth: usize) {
+        self.peak_queue_depth = self.peak_queue_depth.max(depth);
+    }
+
+    pub fn record(&mut self, msg: &Message) {
+        self.processed += 1;
+        *self
+            .by_command
+            .entry(command_name(&msg.context.command))
+            .or_default() += 1;
+        *self.by_source.entry(msg.from).or_default() += 1;
+
+        if self.processed > self.threshold && !self.warned_this_frame {
+            self.warned_this_frame = true;
+            self.warn(BackpressureEvent::TooManyMessages {
+                processed: self.processed,
+                threshold: self.threshold,
+            });
+        }
+    }
+
+    /// `physics_pending` is how many commands the physics thread hasn't picked up yet
+    pub fn end_frame(&mut self, physics_pending: usize) {
+        if physics_pending > self.physics_pending {
+            self.physics_growth += 1;
+        } else {
+            self.physics_growth = 0;
+        }
+        self.physics_pending = physics_pending;
+
+        if self.physics_growth >= PHYSICS_GROWTH_FRAMES {
+            self.physics_growth = 0;
+            self.warn(BackpressureEvent::PhysicsQueueGrowing {
+                pending: physics_pending,
+            });
+        }
+    }
+
+    fn warn(&mut self, event: BackpressureEvent) {
+        if self
+            .last_warning
+            .is_some_and(|last| last.elapsed() < self.warning_interval)
+        {
+            return;
+        }
+        self.last_warning = Some(Instant::now());
+        self.warnings += 1;
+        log::warn!("message backpressure: {:?}", event);
+        self.events.push_back(event);
+    }
+
+    /// messages handled in the last frame
+    pub fn processed(&self) -> usize {
+        self.processed
+    }
+
+    pub fn by_command(&self) -> &HashMap<&'static str, usize> {
+        &self.by_command
+    }
+
+    pub fn by_source(&self) -> &HashMap<Systems, usize> {
+        &self.by_source
+    }
+
+    pub fn peak_queue_depth(&self) -> usize {
+        self.peak_queue_depth
+    }
+
+    pub fn physics_pending(&self) -> usize {
+        self.physics_pending
+    }
+
+    /// number of warnings logged so far
+    pub fn warnings(&self) -> usize {
+        self.warnings
+    }
+
+    /// takes the events published since the last call
+    pub fn drain_events(&mut self) -> impl Iterator<Item = BackpressureEvent> + '_ {
+        self.events.drain(..)
+    }
+}
+
+impl Default for MessageStats {
+    fn default() -> Self {
+        Self::new(DEFAULT_MESSAGE_THRESHOLD)
+    }
+}
+
+fn command_name(command: &MessageCommand) -> &'static str {
+    match command {
+        MessageCommand::EngineCommand(_) => "EngineCommand",
+        MessageCommand::RendererCommand(_) => "RendererCommand",
+        MessageCommand::WindowerCommand(_) => "WindowerCommand",
+        MessageCommand::EventHandlerCommand(_) => "EventHandlerCommand",
+        MessageCommand::PhysicsCommand(_) => "PhysicsCommand",
+    }
+}
@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{
     physics::commands::PhysicsCommand, rendering::RendererCommand,
     windowing::windower::WindowerCommand,
@@ -5,7 +7,7 @@ use crate::{
 
 use super::{EngineCommand, event::EventHandlerCommand};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Systems {
     Engine,
     EventHandler,
@@ -14,7 +16,7 @@ pub enum Systems {
     Physics,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum MessageCommand {
     EngineCommand(EngineCommand),
     RendererCommand(RendererCommand),
@@ -23,12 +25,12 @@ pub enum MessageCommand {
     PhysicsCommand(PhysicsCommand),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MessageContext {
     pub command: MessageCommand,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub from: Systems,
     pub to: Systems,
@@ -1,28 +1,61 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     sync::{Arc, Mutex, RwLock, atomic::AtomicU64},
     time::{Duration, Instant},
 };
 
-use entity::{Entity, EntityRegistry};
+use component::{Layer, LayerMask, Transform3D};
+use context::Context;
+use entity::{DefaultCamera, Entity, EntityRegistry};
 use event::{EventHandler, EventHandlerCommand};
-use messages::{Message, MessageCommand};
+use frame_pacing::FramePacing;
+use frame_timings::FrameTimings;
+use id::IdGenerator;
+use message_stats::MessageStats;
+use messages::{Message, MessageCommand, MessageContext, Systems};
+use recorder::{MessageRecorder, RecordedMessage};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use winit::window::{Window, WindowId};
 
 use crate::{
-    physics::{PhysicsEngine, rapier_engine::RapierEngine},
-    rendering::{EngineRenderer, Renderer, RendererCommand, RendererType},
+    assets::asset_manager::AssetManager,
+    physics::{
+        DEFAULT_GRAVITY, PhysicsBackend, PhysicsBody, PhysicsEngine, StaticCollider,
+        commands::PhysicsCommand, debug_render::PhysicsDebugLines,
+        interpolation::InterpolationSettings, rapier_engine::RapierEngine,
+        settings::PhysicsSettings, stats::PhysicsStats,
+    },
+    rendering::{
+        EngineRenderer, RendererCommand, RendererType, picking, screenshot::Screenshot,
+        settings::RendererSettings, sprite::SpriteAnimation, ui::UiInputCapture,
+        viewport::ViewportMapper,
+    },
 };
 
+pub mod camera_controller;
 pub mod component;
+pub mod context;
 pub mod entity;
 pub mod event;
+pub mod frame_pacing;
+pub mod frame_timings;
+pub mod id;
+pub mod message_stats;
 pub mod messages;
+pub mod recorder;
 
-#[derive(Debug, Clone)]
+/// max passes of one `handle_messages` call when `RedrawComplete`s keep asking for another
+const MAX_MESSAGE_PASSES: usize = 8;
+
+/// about 10 seconds worth of frames at 60 fps
+const FRAME_TIMINGS_CAPACITY: usize = 600;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum EngineCommand {
+    #[serde(skip)]
     RedrawComplete(WindowId),
+    SetFramePacing(FramePacing),
 }
 
 pub struct Engine {
@@ -30,11 +63,24 @@ pub struct Engine {
     pub event_handler: EventHandler,
     pub physics_engine: PhysicsEngine,
 
+    pub context: Context,
+
     windows: Arc<RwLock<HashMap<WindowId, Arc<Window>>>>,
     pub default_camera_id: Uuid,
     pub objects: EntityRegistry,
 
     last_frame_render: Instant,
+    frame_index: u64,
+    frame_pacing: FramePacing,
+    /// something changed since the last frame started, only `FramePacing::OnDemand` looks at it
+    scene_dirty: bool,
+    /// entity transforms from the start of the last frame, kept for `FramePacing::OnDemand`
+    frame_transforms: HashMap<Uuid, Transform3D>,
+    recorder: Option<MessageRecorder>,
+
+    draining_messages: bool,
+    rerun_messages: bool,
+    messages_processed: usize,
 }
 
 impl Engine {
@@ -43,22 +89,177 @@ impl Engine {
         entities: EntityRegistry,
         default_camera_id: Uuid,
     ) -> Self {
+        let context = Context::new();
+        context.insert(IdGenerator::global());
+        context.insert(FrameTimings::new(FRAME_TIMINGS_CAPACITY));
+        context.insert(MessageStats::default());
+        context.insert(InterpolationSettings::default());
+        context.insert(AssetManager::new());
+        let debug_lines = context.insert(PhysicsDebugLines::default());
+        let physics_stats = context.insert(PhysicsStats::default());
+        let ui_capture = context.insert(UiInputCapture::default());
+
         Self {
-            renderer: EngineRenderer::new(renderer_type, entities.clone()),
-            event_handler: EventHandler::new(entities.clone()),
+            renderer: EngineRenderer::new(renderer_type, entities.clone(), context.clone()),
+            event_handler: EventHandler::new(entities.clone()).with_ui_capture(ui_capture),
             physics_engine: PhysicsEngine::new(
-                glam::Vec3 {
-                    x: 0.0,
-                    y: -9.81,
-                    z: 0.0,
-                },
+                DEFAULT_GRAVITY,
+                PhysicsSettings::default(),
                 entities.clone(),
-            ),
+            )
+            .with_debug_lines(debug_lines)
+            .with_stats(physics_stats),
+            context,
             windows: Arc::new(RwLock::new(HashMap::new())),
             default_camera_id,
             objects: entities,
             last_frame_render: Instant::now(),
+            frame_index: 0,
+            frame_pacing: FramePacing::default(),
+            scene_dirty: true,
+            frame_transforms: HashMap::new(),
+            recorder: None,
+            draining_messages: false,
+            rerun_messages: false,
+            messages_processed: 0,
+        }
+    }
+
+    /// runs physics on `backend` instead of 3d rapier, gravity and settings carry over
+    pub fn with_physics_backend(mut self, backend: PhysicsBackend) -> Self {
+        let debug_lines = self.context.get_or_insert_with(PhysicsDebugLines::default);
+        let physics_stats = self.context.get_or_insert_with(PhysicsStats::default);
+        let gravity = self.physics_engine.gravity().unwrap_or(DEFAULT_GRAVITY);
+        let settings = self.physics_engine.settings().unwrap_or_default();
+        self.physics_engine =
+            PhysicsEngine::with_backend(backend, gravity, settings, self.objects.clone())
+                .with_debug_lines(debug_lines)
+                .with_stats(physics_stats);
+        self
+    }
+
+    /// replaces the default gravity of `DEFAULT_GRAVITY`, use `PhysicsCommand::SetGravity` once
+    /// the engine is running
+    pub fn with_gravity(mut self, gravity: glam::Vec3) -> Self {
+        if let Err(e) = self.physics_engine.set_gravity(gravity) {
+            log::error!("failed to set gravity: {e}");
+        }
+        self
+    }
+
+    pub fn with_physics_settings(mut self, settings: PhysicsSettings) -> Self {
+        if let Err(e) = self.physics_engine.set_settings(settings) {
+            log::error!("failed to set physics settings: {e}");
         }
+        self
+    }
+
+    /// surface settings for the renderer, they're used when the window opens
+    pub fn with_renderer_settings(mut self, settings: RendererSettings) -> Self {
+        self.renderer.set_settings(settings);
+        self
+    }
+
+    pub fn with_frame_pacing(mut self, pacing: FramePacing) -> Self {
+        self.set_frame_pacing(pacing);
+        self
+    }
+
+    pub fn set_frame_pacing(&mut self, pacing: FramePacing) {
+        self.frame_pacing = pacing;
+        self.frame_transforms.clear();
+        self.scene_dirty = true;
+    }
+
+    pub fn frame_pacing(&self) -> FramePacing {
+        self.frame_pacing
+    }
+
+    /// marks the scene as changed, so `FramePacing::OnDemand` draws another frame. handled
+    /// messages, window input and moved entities already do this
+    pub fn request_frame(&mut self) {
+        self.scene_dirty = true;
+    }
+
+    /// when the next frame is due under the frame pacing, `None` while an on demand scene hasn't
+    /// changed
+    pub fn next_frame(&mut self) -> Option<Instant> {
+        if self.frame_pacing == FramePacing::OnDemand && !self.scene_dirty {
+            self.scene_dirty = self.entities_moved()
+                || self
+                    .context
+                    .with(|assets: &AssetManager| assets.has_reloaded())
+                    .unwrap_or(false);
+        }
+        self.frame_pacing
+            .next_frame(self.last_frame_render, self.scene_dirty)
+    }
+
+    /// whether any entity's transform differs from the start of the last frame
+    fn entities_moved(&self) -> bool {
+        let mut transforms = 0;
+        let moved = self.objects.clone().into_iter().any(|o| {
+            let (id, transform) =
+                o.read_with(|o| (o.id(), o.components().get::<Transform3D>().copied()));
+            transforms += transform.is_some() as usize;
+            transform != self.frame_transforms.get(&id).copied()
+        });
+        moved || transforms != self.frame_transforms.len()
+    }
+
+    /// renders the entities from the active camera, or the default one, into an image without
+    /// a window. needs a renderer that supports it, like `RendererType::Offscreen`
+    pub fn render_to_image(&mut self, width: u32, height: u32) -> anyhow::Result<Screenshot> {
+        let camera_id = self
+            .renderer
+            .active_camera()
+            .unwrap_or(self.default_camera_id);
+        self.renderer.render_to_image(&camera_id, width, height)
+    }
+
+    /// entity under `screen_pos` as seen from the active camera, in physical window pixels like
+    /// winit cursor positions. goes through the physics raycast while physics runs and through
+    /// the models' bounding boxes otherwise
+    pub fn pick(&mut self, screen_pos: glam::Vec2) -> Option<Uuid> {
+        let camera_id = self
+            .renderer
+            .active_camera()
+            .unwrap_or(self.default_camera_id);
+        let camera = self.objects.get(&camera_id)?;
+        let layer_mask = camera.read_with(|c| {
+            c.components()
+                .get::<LayerMask>()
+                .copied()
+                .unwrap_or_default()
+        });
+        let camera = camera.with_downcast(|c: &DefaultCamera| c.clone())?;
+        // renderers without a window leave the camera's size as the whole screen
+        let mapper = self
+            .context
+            .with(|mapper: &ViewportMapper| *mapper)
+            .unwrap_or_else(|| {
+                ViewportMapper::new(glam::Vec2::new(camera.width, camera.height), 1.0)
+            });
+        let ray = picking::screen_ray(&camera, &mapper, screen_pos)?;
+
+        if self.physics_engine.is_running() {
+            match self.physics_engine.raycast(ray.origin, ray.dir, camera.far) {
+                // the first collider can be on an entity the camera doesn't show, or there's
+                // none at all, the bounding boxes are tried then
+                Ok(hit) => {
+                    let picked = hit.and_then(|hit| hit.entity).filter(|id| {
+                        self.objects
+                            .get(id)
+                            .is_some_and(|o| picking::pickable(&self.objects, &o, &layer_mask))
+                    });
+                    if picked.is_some() {
+                        return picked;
+                    }
+                }
+                Err(e) => log::warn!("picking by bounding boxes, the raycast failed: {e}"),
+            }
+        }
+        picking::pick_bounds(&self.objects, &ray, &layer_mask)
     }
 
     pub fn init(
@@ -80,72 +281,164 @@ impl Engine {
         self.physics_engine.start_physics()
     }
 
-    /// handles the rendering of a frame
-    pub fn handle_render(&mut self, window: Arc<Window>) {
-        let delta = Instant::now()
-            .duration_since(self.last_frame_render)
-            .as_millis_f64();
+    /// stops the systems running on their own threads, called when the window closes
+    pub fn shutdown(&mut self) -> anyhow::Result<()> {
+        if self.physics_engine.is_running() {
+            self.physics_engine.stop()?;
+        }
+        Ok(())
+    }
+
+    /// starts a frame, runs every entity's `update` with the milliseconds since the last frame
+    ///
+    /// called for every `RendererCommand::Render` before the frame is drawn, so messages the
+    /// entities queue here are handled by the `RedrawComplete` that follows in the same frame
+    pub fn update(&mut self) {
+        let _span = tracy_client::span!("Entity Update");
+        let frame_time = Instant::now().duration_since(self.last_frame_render);
+        let delta = frame_time.as_millis_f64();
         self.last_frame_render = Instant::now();
+        self.context
+            .with_mut(|timings: &mut FrameTimings| timings.push(frame_time));
+        self.frame_index += 1;
 
-        // self.rapier_engine.step(delta).unwrap();
+        // taken before the update, so whatever it moves shows up in the next frame too
+        self.scene_dirty = false;
+        if self.frame_pacing == FramePacing::OnDemand {
+            self.frame_transforms = self
+                .objects
+                .clone()
+                .into_iter()
+                .filter_map(|o| {
+                    o.read_with(|o| Some((o.id(), o.components().get::<Transform3D>().copied()?)))
+                })
+                .collect();
+        }
 
-        self.renderer.render(window).unwrap();
+        self.objects.clone().into_iter().for_each(|o| {
+            o.write_with(|o| {
+                o.update(delta);
+                SpriteAnimation::update_components(o.components_mut(), delta);
+            });
+        });
     }
 
+    /// handles the rendering of a frame, the renderer only reads the entities
+    pub fn handle_render(&mut self, window: Arc<Window>) -> anyhow::Result<()> {
+        self.renderer.render(window)
+    }
+
+    /// drains every system's message queue and handles the messages
+    ///
+    /// messages produced while handling wait for the next call, unless a `RedrawComplete` was
+    /// handled, then the queues are drained again. at most `MAX_MESSAGE_PASSES` times per call so
+    /// a feedback loop can't stall a frame
     pub fn handle_messages(&mut self) {
-        let mut msg_queues = [
-            self.event_handler.get_messages().clone(),
-            self.renderer.get_messages().clone(),
-            self.objects
-                .clone()
-                .into_iter()
-                .map(|e| {
-                    let msgs = e.lock().unwrap().get_messages().clone();
-                    e.lock().unwrap().clear_messages();
-                    msgs
-                })
-                .flatten()
-                .collect(),
-        ];
+        let mut passes = 0;
+        self.messages_processed = 0;
+        self.draining_messages = true;
+        let stats = self.context.get_or_insert_with(MessageStats::default);
+        stats.write().unwrap().begin_frame();
 
-        self.event_handler.clear_messages();
-        self.renderer.clear_messages();
+        loop {
+            self.rerun_messages = false;
+            let mut queue = self.collect_messages();
+            if queue.is_empty() {
+                break;
+            }
+            stats.write().unwrap().record_queue_depth(queue.len());
 
-        log::info!("messages: {:?}", msg_queues);
+            log::info!("messages: {:?}", queue);
 
-        for queue in msg_queues.iter_mut() {
-            while !queue.is_empty() {
-                let msg = match queue.pop_front() {
-                    Some(m) => m,
-                    None => {
-                        log::error!("message deque failed");
-                        continue;
-                    }
-                };
+            while let Some(msg) = queue.pop_front() {
                 log::info!("message: {:?}", msg);
-                match self.handle_message(msg) {
-                    Ok(()) => (),
-                    Err(e) => {
-                        log::error!("error: {:?}", e);
-                        continue;
-                    }
-                };
+                if let Some(recorder) = self.recorder.as_mut() {
+                    recorder.record(self.frame_index, &msg);
+                }
+                self.messages_processed += 1;
+                stats.write().unwrap().record(&msg);
+                if let Err(e) = self.handle_message(msg) {
+                    log::error!("error: {:?}", e);
+                }
             }
+
+            passes += 1;
+            if !self.rerun_messages {
+                break;
+            }
+            if passes >= MAX_MESSAGE_PASSES {
+                log::warn!(
+                    "stopped handling messages after {} passes, the rest are left for next frame",
+                    passes
+                );
+                break;
+            }
+        }
+
+        stats
+            .write()
+            .unwrap()
+            .end_frame(self.physics_engine.pending_commands());
+        self.draining_messages = false;
+        // any message could have changed the scene
+        if self.messages_processed > 0 {
+            self.scene_dirty = true;
         }
     }
 
+    /// takes the pending messages out of every system into one queue
+    fn collect_messages(&mut self) -> VecDeque<Message> {
+        let mut queue: VecDeque<Message> = VecDeque::new();
+        queue.extend(self.event_handler.get_messages().iter().cloned());
+        queue.extend(self.renderer.get_messages().iter().cloned());
+        self.event_handler.clear_messages();
+        self.renderer.clear_messages();
+
+        // assets the watcher reloaded since the last pass
+        let reloaded = self
+            .context
+            .with(|assets: &AssetManager| assets.drain_reloaded())
+            .unwrap_or_default();
+        queue.extend(reloaded.into_iter().map(|reloaded| Message {
+            from: Systems::Engine,
+            to: Systems::Renderer,
+            context: MessageContext {
+                command: MessageCommand::RendererCommand(RendererCommand::AssetReloaded(reloaded)),
+            },
+        }));
+
+        self.objects.clone().into_iter().for_each(|e| {
+            e.write_with(|e| {
+                queue.extend(e.get_messages().iter().cloned());
+                e.clear_messages();
+            })
+        });
+
+        queue
+    }
+
+    /// number of messages handled by the last `handle_messages` call
+    pub fn messages_processed(&self) -> usize {
+        self.messages_processed
+    }
+
     pub fn handle_message(&mut self, msg: Message) -> anyhow::Result<()> {
         match msg.context.command {
             MessageCommand::RendererCommand(rc) => match rc {
-                RendererCommand::Render(wid) => self.renderer.render(Arc::clone(
-                    self.windows
-                        .read()
-                        .unwrap()
-                        .get(&wid)
-                        .ok_or(anyhow::anyhow!("window not found"))?,
-                )),
+                RendererCommand::Render(wid) => {
+                    // game logic keeps going even when the frame can't be drawn
+                    self.update();
+                    self.handle_render(Arc::clone(
+                        self.windows
+                            .read()
+                            .unwrap()
+                            .get(&wid)
+                            .ok_or(anyhow::anyhow!("window not found"))?,
+                    ))
+                }
                 RendererCommand::HandleResize((wid, wevent)) => {
-                    self.renderer.renderer.handle_resize(
+                    self.scene_dirty = true;
+                    self.renderer.handle_resize(
                         Arc::clone(
                             self.windows
                                 .read()
@@ -157,7 +450,8 @@ impl Engine {
                     )
                 }
                 RendererCommand::HandleScaleChange((wid, wevent)) => {
-                    self.renderer.renderer.handle_scale_factor_change(
+                    self.scene_dirty = true;
+                    self.renderer.handle_scale_factor_change(
                         Arc::clone(
                             self.windows
                                 .read()
@@ -168,7 +462,7 @@ impl Engine {
                         &wevent,
                     )
                 }
-                RendererCommand::HandleClose((wid, wevent)) => self.renderer.renderer.handle_close(
+                RendererCommand::HandleClose((wid, wevent)) => self.renderer.handle_close(
                     Arc::clone(
                         self.windows
                             .read()
@@ -178,6 +472,10 @@ impl Engine {
                     ),
                     &wevent,
                 ),
+                command => {
+                    self.scene_dirty = true;
+                    self.renderer.handle_command(command)
+                }
             },
             MessageCommand::EventHandlerCommand(ehc) => match ehc {
                 EventHandlerCommand::WindowEvent((wid, wevent)) => {
@@ -186,22 +484,615 @@ impl Engine {
             },
             MessageCommand::EngineCommand(ec) => match ec {
                 EngineCommand::RedrawComplete(wid) => {
-                    self.handle_messages();
-                    Ok(self
+                    // never re-enter the drain, just ask it for another pass
+                    if self.draining_messages {
+                        self.rerun_messages = true;
+                    } else {
+                        self.handle_messages();
+                    }
+                    let window = self
                         .windows
                         .read()
                         .unwrap()
                         .get(&wid)
-                        .ok_or(anyhow::anyhow!("window not found"))?
-                        .request_redraw())
+                        .cloned()
+                        .ok_or(anyhow::anyhow!("window not found"))?;
+                    // frames that aren't due yet are requested by the windower later on
+                    if self.next_frame().is_some_and(|at| at <= Instant::now()) {
+                        window.request_redraw();
+                    }
+                    Ok(())
+                }
+                EngineCommand::SetFramePacing(pacing) => {
+                    self.set_frame_pacing(pacing);
+                    Ok(())
                 }
             },
-            MessageCommand::PhysicsCommand(phc) => self.physics_engine.send_command(phc),
+            MessageCommand::PhysicsCommand(phc) => {
+                self.scene_dirty = true;
+                self.physics_engine.send_command(phc)
+            }
             _ => Ok(()),
         }
     }
 
+    /// starts recording every message processed by `handle_messages`
+    pub fn start_recording(&mut self, recorder: MessageRecorder) {
+        self.recorder = Some(recorder);
+    }
+
+    /// stops recording and hands back the recorder
+    pub fn stop_recording(&mut self) -> Option<MessageRecorder> {
+        self.recorder.take()
+    }
+
+    /// feeds a recorded message stream back through `handle_message`
+    ///
+    /// commands that weren't recorded, the ones tied to a window or a channel, are skipped
+    pub fn replay(&mut self, records: &[RecordedMessage]) -> anyhow::Result<()> {
+        for msg in records.iter().filter_map(|r| r.to_message()) {
+            self.handle_message(msg)?;
+        }
+        Ok(())
+    }
+
+    pub fn frame_index(&self) -> u64 {
+        self.frame_index
+    }
+
+    /// despawns an entity, taking its physics body out of the simulation too
+    pub fn remove_entity(&mut self, id: &Uuid) -> anyhow::Result<()> {
+        let has_body = self
+            .objects
+            .get(id)
+            .is_some_and(|e| e.read_with(in_physics));
+        self.objects.remove(id);
+        if has_body {
+            self.physics_engine
+                .send_command(PhysicsCommand::RemoveBody { id: *id })?;
+        }
+        Ok(())
+    }
+
+    /// puts the entity on `layer`, its colliders follow on the next physics step
+    pub fn set_layer(&mut self, id: &Uuid, layer: Layer) -> anyhow::Result<()> {
+        let entity = self
+            .objects
+            .get(id)
+            .ok_or(anyhow::anyhow!("no entity with id {id}"))?;
+        // a body that's still pending picks the component up when it's inserted
+        let has_body = entity.write_with(|e| {
+            e.components_mut().add(layer);
+            in_physics(e)
+        });
+        if has_body {
+            self.physics_engine
+                .send_command(PhysicsCommand::SetLayer { id: *id, layer })?;
+        }
+        Ok(())
+    }
+
     pub fn set_objects(&mut self, objects: EntityRegistry) {
         self.objects = objects;
     }
 }
+
+/// whether the entity has anything in the physics simulation for commands to go to
+fn in_physics(entity: &dyn Entity) -> bool {
+    let components = entity.components();
+    components.get::<PhysicsBody>().is_some() || components.get::<StaticCollider>().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        engine::{
+            component::ComponentSet,
+            entity::{DefaultCamera, tests::Dummy},
+            messages::{MessageContext, Systems},
+        },
+        rendering::{
+            NullRenderer,
+            environment::{EnvironmentSettings, Fog},
+            gizmo::{Gizmo, GizmoQueue},
+            sprite::{Sprite, SpriteClip, SpriteSheet},
+        },
+    };
+
+    fn message(from: Systems, to: Systems, command: MessageCommand) -> Message {
+        Message {
+            from,
+            to,
+            context: MessageContext { command },
+        }
+    }
+
+    fn redraw_complete() -> Message {
+        let wid = unsafe { WindowId::dummy() };
+        message(
+            Systems::Engine,
+            Systems::Engine,
+            MessageCommand::EngineCommand(EngineCommand::RedrawComplete(wid)),
+        )
+    }
+
+    fn render() -> Message {
+        message(
+            Systems::Windower,
+            Systems::Renderer,
+            MessageCommand::RendererCommand(RendererCommand::Render(unsafe { WindowId::dummy() })),
+        )
+    }
+
+    fn camera_at(position: glam::Vec3) -> DefaultCamera {
+        DefaultCamera::new(
+            component::Transform3D::new(position, glam::Quat::IDENTITY, glam::Vec3::ONE),
+            1280.0,
+            720.0,
+            glam::Vec3::Y,
+            glam::Vec3::NEG_Z,
+            1.0,
+            0.1,
+            100.0,
+        )
+    }
+
+    #[test]
+    fn message_flood_warns_once() {
+        let mut entities = EntityRegistry::new();
+        let flood = message(
+            Systems::Renderer,
+            Systems::Renderer,
+            MessageCommand::RendererCommand(RendererCommand::ShowDiagnostics(false)),
+        );
+        let sender =
+            Dummy::new(ComponentSet::new()).with_messages(std::iter::repeat_n(flood, 10_000));
+        entities.add(sender.into_container());
+
+        let mut engine = Engine::new(RendererType::ThreeD, entities, Uuid::nil());
+        engine.handle_messages();
+
+        assert_eq!(engine.messages_processed(), 10_000);
+        engine
+            .context
+            .with(|stats: &MessageStats| {
+                assert_eq!(stats.warnings(), 1);
+                assert_eq!(stats.processed(), 10_000);
+                assert_eq!(stats.by_command()["RendererCommand"], 10_000);
+                assert_eq!(stats.by_source()[&Systems::Renderer], 10_000);
+                assert_eq!(stats.peak_queue_depth(), 10_000);
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn redraw_complete_chain_is_bounded() {
+        let mut entities = EntityRegistry::new();
+        let echo = Dummy::new(ComponentSet::new())
+            .with_messages([redraw_complete()])
+            .with_echoes(100);
+        entities.add(echo.into_container());
+
+        let mut engine = Engine::new(RendererType::ThreeD, entities, Uuid::nil());
+        engine.handle_messages();
+
+        // one message per pass, every one handled once, and the rest wait for the next call
+        assert_eq!(engine.messages_processed(), MAX_MESSAGE_PASSES);
+        engine.handle_messages();
+        assert_eq!(engine.messages_processed(), MAX_MESSAGE_PASSES);
+    }
+
+    #[test]
+    fn update_advances_sprite_animations() {
+        let mut entities = EntityRegistry::new();
+        let mut components = ComponentSet::new();
+        components.add(Sprite::new(None, glam::Vec2::ONE));
+        let mut animation = SpriteAnimation::new(
+            SpriteSheet::new(8, 1).clip("walk", SpriteClip::new(0, 7, 1000.0, false)),
+        );
+        animation.play("walk").unwrap();
+        components.add(animation);
+        let walker = Dummy::new(components);
+        let id = walker.id();
+        entities.add(walker.into_container());
+
+        let mut engine = Engine::new(RendererType::ThreeD, entities.clone(), Uuid::nil());
+        // a frame a millisecond, so any real frame time moves it on
+        std::thread::sleep(Duration::from_millis(2));
+        engine.update();
+
+        let (frame, uv_x) = entities.get(&id).unwrap().read_with(|e| {
+            let components = e.components();
+            (
+                components.get::<SpriteAnimation>().unwrap().frame(),
+                components.get::<Sprite>().unwrap().uv_rect.x,
+            )
+        });
+        assert!(frame > 0);
+        assert_eq!(uv_x, frame as f32 / 8.0);
+    }
+
+    #[test]
+    fn replay_repeats_the_recorded_commands() {
+        let clear_color = glam::Vec4::new(0.0, 0.0, 0.1, 1.0);
+        let sender = Dummy::new(ComponentSet::new()).with_messages([
+            message(
+                Systems::Engine,
+                Systems::Physics,
+                MessageCommand::PhysicsCommand(PhysicsCommand::ApplyForce {
+                    id: Uuid::nil(),
+                    force: glam::Vec3::Z,
+                }),
+            ),
+            message(
+                Systems::Engine,
+                Systems::Renderer,
+                MessageCommand::RendererCommand(RendererCommand::SetClearColor(clear_color)),
+            ),
+        ]);
+        let mut entities = EntityRegistry::new();
+        entities.add(sender.into_container());
+        let mut engine = Engine::new(RendererType::ThreeD, entities, Uuid::nil());
+        engine.start_recording(MessageRecorder::in_memory(16));
+        engine.handle_messages();
+        let records: Vec<_> = engine
+            .stop_recording()
+            .unwrap()
+            .records()
+            .iter()
+            .cloned()
+            .collect();
+
+        let mut replayed = Engine::new(RendererType::ThreeD, EntityRegistry::new(), Uuid::nil());
+        replayed.replay(&records).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(replayed.physics_engine.pending_commands(), 1);
+        let settings = replayed
+            .context
+            .with(|settings: &EnvironmentSettings| *settings)
+            .unwrap();
+        assert_eq!(settings.clear_color, clear_color);
+    }
+
+    #[test]
+    fn environment_commands_update_the_context() {
+        let mut engine = Engine::new(RendererType::ThreeD, EntityRegistry::new(), Uuid::nil());
+        let command = |command| {
+            message(
+                Systems::Engine,
+                Systems::Renderer,
+                MessageCommand::RendererCommand(command),
+            )
+        };
+        engine
+            .handle_message(command(RendererCommand::SetClearColor(glam::Vec4::new(
+                0.0, 0.0, 0.1, 1.0,
+            ))))
+            .unwrap();
+        engine
+            .handle_message(command(RendererCommand::SetAmbientLight {
+                color: glam::Vec3::new(1.0, 0.5, 0.0),
+                intensity: 0.05,
+            }))
+            .unwrap();
+        let fog = Fog::Exponential {
+            color: glam::Vec3::new(0.0, 0.0, 0.1),
+            density: 0.02,
+        };
+        engine
+            .handle_message(command(RendererCommand::SetFog(fog)))
+            .unwrap();
+
+        let settings = engine
+            .context
+            .with(|settings: &EnvironmentSettings| *settings)
+            .unwrap();
+        assert_eq!(settings.clear_color, glam::Vec4::new(0.0, 0.0, 0.1, 1.0));
+        assert_eq!(settings.ambient_color, glam::Vec3::new(1.0, 0.5, 0.0));
+        assert_eq!(settings.ambient_intensity, 0.05);
+        assert_eq!(settings.fog, fog);
+    }
+
+    #[test]
+    fn gizmo_commands_fill_the_queue() {
+        let mut engine = Engine::new(RendererType::ThreeD, EntityRegistry::new(), Uuid::nil());
+        let gizmo = Gizmo::Sphere {
+            center: glam::Vec3::ZERO,
+            radius: 1.0,
+            color: [1.0, 0.0, 0.0, 1.0],
+        };
+        engine
+            .handle_message(message(
+                Systems::Engine,
+                Systems::Renderer,
+                MessageCommand::RendererCommand(RendererCommand::Gizmo(gizmo)),
+            ))
+            .unwrap();
+
+        let queued = engine
+            .context
+            .with(|queue: &GizmoQueue| queue.gizmos.clone())
+            .unwrap();
+        assert_eq!(queued, vec![gizmo]);
+    }
+
+    #[test]
+    fn active_camera_only_switches_to_cameras() {
+        let mut entities = EntityRegistry::new();
+        let camera = camera_at(glam::Vec3::ZERO);
+        let camera_id = camera.id();
+        entities.add(camera.into_container());
+        let not_a_camera = Dummy::new(ComponentSet::new());
+        let not_a_camera_id = not_a_camera.id();
+        entities.add(not_a_camera.into_container());
+
+        let mut engine = Engine::new(RendererType::ThreeD, entities, camera_id);
+        let mut switch = |id| {
+            engine
+                .handle_message(message(
+                    Systems::Engine,
+                    Systems::Renderer,
+                    MessageCommand::RendererCommand(RendererCommand::SetActiveCamera(id)),
+                ))
+                .unwrap();
+            engine.renderer.active_camera()
+        };
+
+        assert_eq!(switch(camera_id), Some(camera_id));
+        assert_eq!(switch(Uuid::new_v4()), Some(camera_id));
+        assert_eq!(switch(not_a_camera_id), Some(camera_id));
+    }
+
+    #[test]
+    fn runs_without_a_gpu_on_the_null_renderer() {
+        let mut entities = EntityRegistry::new();
+        let echo = Dummy::new(ComponentSet::new())
+            .with_messages([redraw_complete()])
+            .with_echoes(3);
+        entities.add(echo.into_container());
+
+        let mut engine = Engine::new(
+            RendererType::Custom(Box::new(NullRenderer::new())),
+            entities,
+            Uuid::nil(),
+        );
+        engine.handle_messages();
+        assert!(engine.messages_processed() > 0);
+
+        let camera_id = Uuid::new_v4();
+        engine
+            .handle_message(message(
+                Systems::Engine,
+                Systems::Renderer,
+                MessageCommand::RendererCommand(RendererCommand::SetActiveCamera(camera_id)),
+            ))
+            .unwrap();
+        assert_eq!(engine.renderer.active_camera(), Some(camera_id));
+    }
+
+    #[test]
+    fn entities_update_when_the_frame_fails() {
+        let mut entities = EntityRegistry::new();
+        let dummy = Dummy::new(ComponentSet::new());
+        let id = dummy.id();
+        entities.add(dummy.into_container());
+
+        let mut engine = Engine::new(
+            RendererType::Custom(Box::new(NullRenderer::new())),
+            entities.clone(),
+            Uuid::nil(),
+        );
+        // there's no window, so drawing the frame errors out
+        assert!(engine.handle_message(render()).is_err());
+        assert!(engine.handle_message(render()).is_err());
+
+        let updates = entities
+            .get(&id)
+            .unwrap()
+            .with_downcast(|d: &Dummy| d.updates.clone())
+            .unwrap();
+        assert_eq!(updates.len(), 2);
+        assert!(updates.iter().all(|delta| *delta >= 0.0));
+        assert_eq!(engine.frame_index(), 2);
+    }
+
+    #[test]
+    fn picks_the_cuboid_in_the_middle_of_the_screen() {
+        let mut entities = EntityRegistry::new();
+        let camera = camera_at(glam::Vec3::new(0.0, 1.0, 8.0));
+        let camera_id = camera.id();
+        entities.add(camera.into_container());
+        let mut components = ComponentSet::new();
+        components.add(component::Transform3D::new(
+            glam::Vec3::new(0.0, 1.0, 0.0),
+            glam::Quat::IDENTITY,
+            glam::Vec3::ONE,
+        ));
+        let cuboid = Dummy::new(components)
+            .with_model(crate::assets::basic_models::CuboidBuilder::new().build());
+        let cuboid_id = cuboid.id();
+        entities.add(cuboid.into_container());
+
+        let mut engine = Engine::new(
+            RendererType::Custom(Box::new(NullRenderer::new())),
+            entities,
+            camera_id,
+        );
+        assert_eq!(engine.pick(glam::Vec2::new(640.0, 360.0)), Some(cuboid_id));
+        assert_eq!(engine.pick(glam::Vec2::new(40.0, 360.0)), None);
+        assert_eq!(engine.pick(glam::Vec2::new(-10.0, 360.0)), None);
+    }
+
+    #[test]
+    fn physics_picks_skip_what_the_camera_doesnt_show() {
+        use rapier3d::prelude::{ColliderBuilder, RigidBodyBuilder};
+
+        use crate::physics::{PhysicsBody, RigidBodyState};
+
+        let mut entities = EntityRegistry::new();
+        let mut camera = camera_at(glam::Vec3::new(0.0, 0.0, 8.0));
+        camera.components_mut().add(LayerMask(1));
+        let camera_id = camera.id();
+        entities.add(camera.into_container());
+        // a collider between the camera and the cuboid, on a layer the camera doesn't show
+        let mut components = ComponentSet::new();
+        components.add(component::Transform3D::new(
+            glam::Vec3::new(0.0, 0.0, 3.0),
+            glam::Quat::IDENTITY,
+            glam::Vec3::ONE,
+        ));
+        components.add(Layer::new(2));
+        components.add(PhysicsBody::new(
+            ColliderBuilder::cuboid(1.0, 1.0, 0.1).build(),
+            RigidBodyBuilder::fixed().build(),
+        ));
+        let blocker = Dummy::new(components);
+        let blocker_id = blocker.id();
+        entities.add(blocker.into_container());
+        // no collider, only the bounding boxes find it
+        let cuboid = Dummy::new(ComponentSet::new())
+            .with_model(crate::assets::basic_models::CuboidBuilder::new().build());
+        let cuboid_id = cuboid.id();
+        entities.add(cuboid.into_container());
+
+        let mut engine = Engine::new(
+            RendererType::Custom(Box::new(NullRenderer::new())),
+            entities.clone(),
+            camera_id,
+        );
+        engine.start_physics().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !entities.get(&blocker_id).unwrap().read_with(|b| {
+            matches!(
+                b.components().get::<PhysicsBody>().unwrap().rigid_body,
+                RigidBodyState::Active(_)
+            )
+        }) {
+            assert!(Instant::now() < deadline, "the blocker never got a body");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(engine.pick(glam::Vec2::new(640.0, 360.0)), Some(cuboid_id));
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn on_demand_pacing_skips_frames_of_a_static_scene() {
+        let mut entities = EntityRegistry::new();
+        let dummy = Dummy::new(ComponentSet::new());
+        let id = dummy.id();
+        entities.add(dummy.into_container());
+        let mut engine = Engine::new(
+            RendererType::Custom(Box::new(NullRenderer::new())),
+            entities.clone(),
+            Uuid::nil(),
+        )
+        .with_frame_pacing(FramePacing::OnDemand);
+
+        // the windower's loop, without a window the frames fail after the update
+        let run = |engine: &mut Engine| {
+            (0..100)
+                .filter(|_| {
+                    let due = engine.next_frame().is_some_and(|at| at <= Instant::now());
+                    if due {
+                        let _ = engine.handle_message(render());
+                        engine.handle_messages();
+                    }
+                    due
+                })
+                .count()
+        };
+        assert_eq!(run(&mut engine), 1);
+
+        entities.get(&id).unwrap().write_with(|o| {
+            o.components_mut().add(component::Transform3D::new(
+                glam::Vec3::X,
+                glam::Quat::IDENTITY,
+                glam::Vec3::ONE,
+            ))
+        });
+        assert_eq!(run(&mut engine), 1);
+        engine.request_frame();
+        assert_eq!(run(&mut engine), 1);
+
+        engine.set_frame_pacing(FramePacing::Continuous);
+        assert_eq!(run(&mut engine), 100);
+    }
+
+    #[test]
+    fn switching_physics_backend_keeps_gravity_and_settings() {
+        let gravity = glam::Vec3::new(0.0, -1.6, 0.0);
+        let settings = PhysicsSettings {
+            solver_iterations: 8,
+            ..Default::default()
+        };
+        let engine = Engine::new(
+            RendererType::Custom(Box::new(NullRenderer::new())),
+            EntityRegistry::new(),
+            Uuid::nil(),
+        )
+        .with_gravity(gravity)
+        .with_physics_settings(settings)
+        .with_physics_backend(PhysicsBackend::Rapier2D);
+
+        assert_eq!(engine.physics_engine.gravity(), Some(gravity));
+        assert_eq!(engine.physics_engine.settings(), Some(settings));
+    }
+
+    #[test]
+    fn scene_ids_match_between_builds() {
+        use crate::engine::id::{IdGenerator, IdMode};
+
+        let namespace = Uuid::new_v5(&Uuid::NAMESPACE_OID, b"test scene");
+        let build = |ids: &IdGenerator| {
+            let mut entities = EntityRegistry::new();
+            let mut camera = camera_at(glam::Vec3::new(0.0, 1.0, 8.0));
+            camera.id = ids.id_for_name("camera");
+            let camera_id = camera.id;
+            entities.add(camera.into_container());
+            for name in ["plane", "lantern", "helmet"] {
+                let entity = Dummy::new(ComponentSet::new()).with_id(ids.id_for_name(name));
+                entities.add(entity.into_container());
+            }
+            let engine = Engine::new(
+                RendererType::Custom(Box::new(NullRenderer::new())),
+                entities.clone(),
+                camera_id,
+            );
+            engine
+                .objects
+                .clone()
+                .into_iter()
+                .map(|e| e.id())
+                .collect::<std::collections::HashSet<_>>()
+        };
+
+        let first = build(&IdGenerator::deterministic(namespace));
+        assert_eq!(first.len(), 4);
+        assert_eq!(first, build(&IdGenerator::deterministic(namespace)));
+        assert_ne!(first, build(&IdGenerator::new(IdMode::Random)));
+    }
+
+    #[test]
+    fn zero_physics_timestep_is_clamped() {
+        use crate::physics::timestep::MIN_TIMESTEP;
+
+        let engine = Engine::new(
+            RendererType::Custom(Box::new(NullRenderer::new())),
+            EntityRegistry::new(),
+            Uuid::nil(),
+        )
+        .with_physics_settings(PhysicsSettings {
+            timestep: Duration::ZERO,
+            ..Default::default()
+        });
+
+        assert_eq!(engine.physics_engine.timestep(), MIN_TIMESTEP);
+        assert_eq!(
+            engine.physics_engine.settings().unwrap().timestep,
+            MIN_TIMESTEP
+        );
+    }
+}
@@ -0,0 +1,258 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    time::Instant,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    engine::{
+        EngineCommand,
+        messages::{Message, MessageCommand, MessageContext, Systems},
+    },
+    rendering::RendererCommand,
+};
+
+/// whether the command can be recorded, the ones tied to a window, a winit event or a channel
+/// only mean something in the run they came from
+fn recordable(command: &MessageCommand) -> bool {
+    !matches!(
+        command,
+        MessageCommand::EngineCommand(EngineCommand::RedrawComplete(_))
+            | MessageCommand::RendererCommand(
+                RendererCommand::Render(_)
+                    | RendererCommand::HandleResize(_)
+                    | RendererCommand::HandleScaleChange(_)
+                    | RendererCommand::HandleClose(_)
+                    | RendererCommand::CaptureScreenshotBytes(_)
+            )
+            | MessageCommand::EventHandlerCommand(_)
+    )
+}
+
+/// a single processed message along with when it was processed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedMessage {
+    pub frame: u64,
+    pub timestamp_ms: f64,
+    pub from: Systems,
+    pub to: Systems,
+    /// `None` for commands tied to a window, a winit event or a channel, they're skipped on
+    /// replay
+    pub command: Option<MessageCommand>,
+}
+
+impl RecordedMessage {
+    /// turns the record back into a message, returns `None` for unrecorded commands
+    pub fn to_message(&self) -> Option<Message> {
+        Some(Message {
+            from: self.from,
+            to: self.to,
+            context: MessageContext {
+                command: self.command.clone()?,
+            },
+        })
+    }
+}
+
+/// records every message the engine processes, either into an in-memory ring or a file
+pub struct MessageRecorder {
+    started: Instant,
+    capacity: Option<usize>,
+    records: VecDeque<RecordedMessage>,
+    writer: Option<BufWriter<File>>,
+}
+
+impl MessageRecorder {
+    /// keeps the last `capacity` messages in memory
+    pub fn in_memory(capacity: usize) -> Self {
+        Self {
+            started: Instant::now(),
+            capacity: Some(capacity),
+            records: VecDeque::with_capacity(capacity),
+            writer: None,
+        }
+    }
+
+    /// appends every message to the file at `path` as json lines
+    pub fn to_file(path: &Path) -> anyhow::Result<Self> {
+        Ok(Self {
+            started: Instant::now(),
+            capacity: None,
+            records: VecDeque::new(),
+            writer: Some(BufWriter::new(File::create(path)?)),
+        })
+    }
+
+    pub fn record(&mut self, frame: u64, msg: &Message) {
+        let record = RecordedMessage {
+            frame,
+            timestamp_ms: Instant::now().duration_since(self.started).as_millis_f64(),
+            from: msg.from,
+            to: msg.to,
+            command: recordable(&msg.context.command).then(|| msg.context.command.clone()),
+        };
+
+        if let Some(writer) = self.writer.as_mut() {
+            let written = serde_json::to_writer(&mut *writer, &record)
+                .map_err(anyhow::Error::from)
+                .and_then(|()| Ok(writer.write_all(b"\n")?));
+            if let Err(e) = written {
+                log::error!("failed to write recorded message: {e}");
+            }
+            return;
+        }
+
+        if let Some(capacity) = self.capacity {
+            if self.records.len() >= capacity {
+                self.records.pop_front();
+            }
+        }
+        self.records.push_back(record);
+    }
+
+    /// in-memory records, empty when recording to a file
+    pub fn records(&self) -> &VecDeque<RecordedMessage> {
+        &self.records
+    }
+
+    pub fn flush(&mut self) -> anyhow::Result<()> {
+        if let Some(writer) = self.writer.as_mut() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// writes the in-memory records to `path` in the same format `to_file` uses
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        for record in self.records.iter() {
+            serde_json::to_writer(&mut writer, record)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// loads a recording made with `save` or `to_file`
+    pub fn load(path: &Path) -> anyhow::Result<Vec<RecordedMessage>> {
+        BufReader::new(File::open(path)?)
+            .lines()
+            .filter(|line| !line.as_ref().is_ok_and(|l| l.trim().is_empty()))
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect()
+    }
+}
+
+impl Drop for MessageRecorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            log::error!("failed to flush message recording: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Vec3, Vec4};
+    use uuid::Uuid;
+    use winit::window::WindowId;
+
+    use super::*;
+    use crate::physics::commands::PhysicsCommand;
+
+    fn physics_commands(records: &[RecordedMessage]) -> Vec<String> {
+        records
+            .iter()
+            .filter_map(|r| r.to_message())
+            .map(|m| format!("{:?}", m.context.command))
+            .collect()
+    }
+
+    #[test]
+    fn record_and_replay_ten_frames() {
+        let ids = [Uuid::new_v4(), Uuid::new_v4()];
+        let mut recorder = MessageRecorder::in_memory(1024);
+
+        for frame in 0..10 {
+            for id in ids {
+                recorder.record(
+                    frame,
+                    &Message {
+                        from: Systems::Engine,
+                        to: Systems::Physics,
+                        context: MessageContext {
+                            command: MessageCommand::PhysicsCommand(PhysicsCommand::ApplyForce {
+                                id,
+                                force: Vec3::new(0.0, 0.0, 1.0) * frame as f32,
+                            }),
+                        },
+                    },
+                );
+            }
+        }
+
+        let path = std::env::temp_dir().join(format!("recording-{}.jsonl", Uuid::new_v4()));
+        recorder.save(&path).unwrap();
+        let loaded = MessageRecorder::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let recorded: Vec<_> = recorder.records().iter().cloned().collect();
+        assert_eq!(loaded.len(), 20);
+        assert_eq!(loaded.last().unwrap().frame, 9);
+        assert_eq!(physics_commands(&recorded), physics_commands(&loaded));
+    }
+
+    #[test]
+    fn ring_drops_oldest() {
+        let mut recorder = MessageRecorder::in_memory(2);
+        for frame in 0..3 {
+            recorder.record(
+                frame,
+                &Message {
+                    from: Systems::Engine,
+                    to: Systems::Physics,
+                    context: MessageContext {
+                        command: MessageCommand::PhysicsCommand(PhysicsCommand::Enable {
+                            id: Uuid::nil(),
+                        }),
+                    },
+                },
+            );
+        }
+        assert_eq!(recorder.records().len(), 2);
+        assert_eq!(recorder.records().front().unwrap().frame, 1);
+    }
+
+    #[test]
+    fn renderer_commands_are_kept_and_window_ones_skipped() {
+        let mut recorder = MessageRecorder::in_memory(16);
+        let renderer = |command| Message {
+            from: Systems::Engine,
+            to: Systems::Renderer,
+            context: MessageContext {
+                command: MessageCommand::RendererCommand(command),
+            },
+        };
+        recorder.record(0, &renderer(RendererCommand::SetClearColor(Vec4::ONE)));
+        recorder.record(
+            0,
+            &renderer(RendererCommand::Render(unsafe { WindowId::dummy() })),
+        );
+
+        let path = std::env::temp_dir().join(format!("recording-{}.jsonl", Uuid::new_v4()));
+        recorder.save(&path).unwrap();
+        let loaded = MessageRecorder::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            loaded[0].to_message().unwrap().context.command,
+            MessageCommand::RendererCommand(RendererCommand::SetClearColor(color)) if color == Vec4::ONE
+        ));
+        assert!(loaded[1].command.is_none());
+        assert!(loaded[1].to_message().is_none());
+    }
+}
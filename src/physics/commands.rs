@@ -1,7 +1,14 @@
 use glam::{Quat, Vec3};
+use rapier3d::prelude::Collider;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Debug, Clone)]
+use crate::{
+    engine::component::{Layer, Transform3D},
+    physics::{AdditionalMass, settings::PhysicsSettings},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PhysicsCommand {
     Enable {
         id: Uuid,
@@ -46,6 +53,296 @@ pub enum PhysicsCommand {
         id: Uuid,
         rotation: Quat,
     },
+    /// moves the body and its entity's transform together, writing the transform directly gets
+    /// undone by the next step. the scale is left alone, `reset_velocity` stops the body too
+    Teleport {
+        id: Uuid,
+        transform: Transform3D,
+        reset_velocity: bool,
+    },
+    /// applies to every collider of the entity's body
+    SetFriction {
+        id: Uuid,
+        value: f32,
+    },
+    SetRestitution {
+        id: Uuid,
+        value: f32,
+    },
+    /// swaps the collision groups of every collider of the entity's body, `Engine::set_layer`
+    /// sends it along with updating the component
+    SetLayer {
+        id: Uuid,
+        layer: Layer,
+    },
+    /// takes the entity's body out of the simulation, also sent by `Engine::remove_entity`
+    RemoveBody {
+        id: Uuid,
+    },
+    /// answered with a `RaycastHit` or `RaycastMiss` carrying the same `request_id`
+    Raycast {
+        origin: Vec3,
+        dir: Vec3,
+        max_toi: f32,
+        request_id: Uuid,
+    },
+    /// sweeps `shape` from `origin` along `dir` for up to `max_toi` units, answered with a
+    /// `ShapeCastHit` or `ShapeCastMiss` carrying the same `request_id`. the collider's own
+    /// position is ignored
+    ShapeCast {
+        shape: Collider,
+        origin: Vec3,
+        rotation: Quat,
+        dir: Vec3,
+        max_toi: f32,
+        request_id: Uuid,
+    },
+    /// answered with an `Overlaps` listing every entity `shape` overlaps at `origin`
+    OverlapShape {
+        shape: Collider,
+        origin: Vec3,
+        rotation: Quat,
+        request_id: Uuid,
+    },
+    /// the joint commands answer with a `JointCreated` carrying the new joint's id, anchors are
+    /// in each body's local space
+    CreateFixedJoint {
+        a: Uuid,
+        b: Uuid,
+        anchor_a: Vec3,
+        anchor_b: Vec3,
+    },
+    /// `limits` is the allowed angle range around `axis` in radians
+    CreateRevoluteJoint {
+        a: Uuid,
+        b: Uuid,
+        anchor_a: Vec3,
+        anchor_b: Vec3,
+        axis: Vec3,
+        limits: Option<[f32; 2]>,
+    },
+    CreateSphericalJoint {
+        a: Uuid,
+        b: Uuid,
+        anchor_a: Vec3,
+        anchor_b: Vec3,
+    },
+    /// `limits` is the allowed distance range along `axis`
+    CreatePrismaticJoint {
+        a: Uuid,
+        b: Uuid,
+        anchor_a: Vec3,
+        anchor_b: Vec3,
+        axis: Vec3,
+        limits: Option<[f32; 2]>,
+    },
+    RemoveJoint {
+        joint_id: Uuid,
+    },
+    /// drives a revolute joint around its axis or a prismatic one along it
+    SetJointMotor {
+        joint_id: Uuid,
+        target_velocity: f32,
+        factor: f32,
+    },
+    /// moves an entity with a `CharacterController` as far as it can towards
+    /// `desired_translation`, answered with a `CharacterMoved`
+    MoveCharacter {
+        id: Uuid,
+        desired_translation: Vec3,
+    },
+    /// wakes every body so sleeping ones react to the change
+    SetGravity {
+        gravity: Vec3,
+    },
+    SetGravityScale {
+        id: Uuid,
+        scale: f32,
+    },
+    SetLinearDamping {
+        id: Uuid,
+        damping: f32,
+    },
+    SetAngularDamping {
+        id: Uuid,
+        damping: f32,
+    },
+    /// replaces the mass added on top of the body's colliders, `None` takes it away
+    SetAdditionalMass {
+        id: Uuid,
+        mass: Option<AdditionalMass>,
+    },
+    /// answered with a `BodyState` carrying the same `request_id`
+    QueryBody {
+        id: Uuid,
+        request_id: Uuid,
+    },
+    /// `true` locks the axis, `false` frees it again
+    LockRotations {
+        id: Uuid,
+        x: bool,
+        y: bool,
+        z: bool,
+    },
+    LockTranslations {
+        id: Uuid,
+        x: bool,
+        y: bool,
+        z: bool,
+    },
+    /// like `SetLinearVelocity` and `SetAngularVelocity` but also works on a body that's still
+    /// pending, it's kept until the body is inserted so spawn and shoot can happen in one frame
+    SetInitialVelocity {
+        id: Uuid,
+        linvel: Vec3,
+        angvel: Vec3,
+    },
+    /// bodies in a higher group push lower ones without being pushed back, -127..=127
+    SetDominance {
+        id: Uuid,
+        group: i8,
+    },
+    /// puts the body to sleep until something touches it or it's woken up
+    Sleep {
+        id: Uuid,
+    },
+    WakeUp {
+        id: Uuid,
+    },
+    /// stops the simulation, commands are still handled while paused
+    Pause,
+    Resume,
+    /// advances a paused simulation by one timestep
+    StepOnce,
+    /// multiplies the simulated time per step, clamped to `0.0..=4.0`, 0 is like pausing
+    SetTimeScale(f32),
+    /// solver iterations, timestep and the rest, used from the next step on
+    SetIntegrationParameters(PhysicsSettings),
+}
+
+/// result of `PhysicsEngine::raycast`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaycastHit {
+    pub entity: Option<Uuid>,
+    pub point: Vec3,
+    pub normal: Vec3,
+}
+
+/// result of `PhysicsEngine::shape_cast`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShapeHit {
+    pub entity: Option<Uuid>,
+    pub toi: f32,
+    pub witness_hit: Vec3,
+    pub witness_shape: Vec3,
+    pub normal: Vec3,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PhysicsEvent {
+    /// two entities' colliders started touching or overlapping
+    CollisionStarted {
+        a: Uuid,
+        b: Uuid,
+    },
+    /// two entities' colliders stopped touching, also sent when one of them is removed
+    CollisionEnded {
+        a: Uuid,
+        b: Uuid,
+    },
+    RaycastHit {
+        request_id: Uuid,
+        entity: Option<Uuid>,
+        point: Vec3,
+        normal: Vec3,
+    },
+    RaycastMiss {
+        request_id: Uuid,
+    },
+    /// witness points are where the two shapes touch at `toi` in world space, one on the hit
+    /// collider and one on the cast shape, `normal` points out of the hit collider
+    ShapeCastHit {
+        request_id: Uuid,
+        entity: Option<Uuid>,
+        toi: f32,
+        witness_hit: Vec3,
+        witness_shape: Vec3,
+        normal: Vec3,
+    },
+    ShapeCastMiss {
+        request_id: Uuid,
+    },
+    Overlaps {
+        request_id: Uuid,
+        entities: Vec<Uuid>,
+    },
+    /// a joint between `a` and `b` was made, `joint_id` is what `RemoveJoint` and
+    /// `SetJointMotor` take
+    JointCreated {
+        joint_id: Uuid,
+        a: Uuid,
+        b: Uuid,
+    },
+    /// two entities pushed on each other harder than one of their `contact_force_threshold`s
+    ContactForce {
+        a: Uuid,
+        b: Uuid,
+        force_magnitude: f32,
+        largest_contact_point: Vec3,
+    },
+    /// rapier put the entity's dynamic body to sleep, it stays put until something wakes it
+    BodySlept {
+        id: Uuid,
+    },
+    /// the entity's body was woken up by a collision, a command or a joint
+    BodyWoke {
+        id: Uuid,
+    },
+    BodyState {
+        request_id: Uuid,
+        linvel: Vec3,
+        angvel: Vec3,
+        mass: f32,
+        is_sleeping: bool,
+    },
+    /// where a `MoveCharacter` actually took the character and whether it ended on the ground
+    CharacterMoved {
+        id: Uuid,
+        translation: Vec3,
+        grounded: bool,
+    },
 }
 
-pub enum PhysicsEvent {}
+impl PhysicsEvent {
+    /// entities the event is about, `None` for answers to queries, which only go back to the
+    /// caller waiting on their `request_id`
+    pub fn entities(&self) -> Option<Vec<Uuid>> {
+        match self {
+            PhysicsEvent::CollisionStarted { a, b }
+            | PhysicsEvent::CollisionEnded { a, b }
+            | PhysicsEvent::JointCreated { a, b, .. }
+            | PhysicsEvent::ContactForce { a, b, .. } => Some(vec![*a, *b]),
+            PhysicsEvent::CharacterMoved { id, .. }
+            | PhysicsEvent::BodySlept { id }
+            | PhysicsEvent::BodyWoke { id } => Some(vec![*id]),
+            PhysicsEvent::RaycastHit { .. }
+            | PhysicsEvent::RaycastMiss { .. }
+            | PhysicsEvent::ShapeCastHit { .. }
+            | PhysicsEvent::ShapeCastMiss { .. }
+            | PhysicsEvent::Overlaps { .. }
+            | PhysicsEvent::BodyState { .. } => None,
+        }
+    }
+
+    pub fn request_id(&self) -> Option<Uuid> {
+        match self {
+            PhysicsEvent::RaycastHit { request_id, .. }
+            | PhysicsEvent::RaycastMiss { request_id }
+            | PhysicsEvent::ShapeCastHit { request_id, .. }
+            | PhysicsEvent::ShapeCastMiss { request_id }
+            | PhysicsEvent::Overlaps { request_id, .. }
+            | PhysicsEvent::BodyState { request_id, .. } => Some(*request_id),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use glam::Vec3;
+use rapier3d::prelude::*;
+
+use crate::engine::context::ContextItem;
+
+/// a colored line segment from the physics debug renderer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DebugLine {
+    pub a: Vec3,
+    pub b: Vec3,
+    /// rgba, 0 to 1
+    pub color: [f32; 4],
+}
+
+/// collider, body and joint outlines from the last physics step, filled by the physics thread
+/// while `enabled` and drawn by the renderer
+#[derive(Debug, Clone, Default)]
+pub struct PhysicsDebugLines {
+    pub enabled: bool,
+    pub lines: Vec<DebugLine>,
+    /// goes up every time `lines` changes, the renderer only builds a new mesh then
+    pub revision: u64,
+}
+
+impl ContextItem for PhysicsDebugLines {}
+
+/// collects what rapier's debug render pipeline draws
+#[derive(Default)]
+pub(crate) struct LineCollector {
+    pub lines: Vec<DebugLine>,
+}
+
+impl DebugRenderBackend for LineCollector {
+    fn draw_line(
+        &mut self,
+        _object: DebugRenderObject,
+        a: Point<Real>,
+        b: Point<Real>,
+        color: DebugColor,
+    ) {
+        self.lines.push(DebugLine {
+            a: Vec3::from(a),
+            b: Vec3::from(b),
+            color: hsla_to_rgba(color),
+        });
+    }
+}
+
+/// rapier's debug colors are hsla with the hue in degrees
+pub fn hsla_to_rgba([h, s, l, a]: [f32; 4]) -> [f32; 4] {
+    let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let h = h.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = l - chroma / 2.0;
+    [r + m, g + m, b + m, a]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hsla_conversion() {
+        assert_eq!(hsla_to_rgba([0.0, 1.0, 0.5, 1.0]), [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(hsla_to_rgba([120.0, 1.0, 0.5, 0.5]), [0.0, 1.0, 0.0, 0.5]);
+        assert_eq!(hsla_to_rgba([240.0, 1.0, 0.5, 1.0]), [0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(
+            hsla_to_rgba([42.0, 0.0, 0.25, 1.0]),
+            [0.25, 0.25, 0.25, 1.0]
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use std::time::{Duration, Instant};
+
+use crate::engine::{
+    component::{Component, Transform3D},
+    context::ContextItem,
+};
+
+/// the last two poses the physics thread gave a dynamic body
+///
+/// the renderer draws a blend of the two so bodies move smoothly when physics and rendering
+/// run at different rates, at the cost of showing physics one step late
+#[derive(Debug, Clone, Component)]
+pub struct InterpolatedTransform {
+    pub previous: Transform3D,
+    pub current: Transform3D,
+    stepped_at: Instant,
+    timestep: Duration,
+}
+
+impl InterpolatedTransform {
+    pub fn new(transform: Transform3D) -> Self {
+        Self {
+            previous: transform,
+            current: transform,
+            stepped_at: Instant::now(),
+            timestep: Duration::ZERO,
+        }
+    }
+
+    /// records the pose after a physics step of length `timestep`
+    pub fn push(&mut self, transform: Transform3D, timestep: Duration) {
+        self.previous = self.current;
+        self.current = transform;
+        self.stepped_at = Instant::now();
+        self.timestep = timestep;
+    }
+
+    /// how far `now` is into the step after the current pose, 0 is the previous pose and 1 the
+    /// current one
+    pub fn alpha(&self, now: Instant) -> f32 {
+        if self.timestep.is_zero() {
+            return 1.0;
+        }
+        let since_step = now.saturating_duration_since(self.stepped_at);
+        (since_step.as_secs_f32() / self.timestep.as_secs_f32()).min(1.0)
+    }
+
+    pub fn blend(&self, alpha: f32) -> Transform3D {
+        Transform3D::new(
+            self.previous.position.lerp(self.current.position, alpha),
+            self.previous.rotation.slerp(self.current.rotation, alpha),
+            self.current.scale,
+        )
+    }
+
+    /// pose to draw at `now`
+    pub fn at(&self, now: Instant) -> Transform3D {
+        self.blend(self.alpha(now))
+    }
+}
+
+/// whether the renderer draws `InterpolatedTransform`s, turn it off to see the raw physics poses
+#[derive(Debug, Clone, Copy)]
+pub struct InterpolationSettings {
+    pub enabled: bool,
+}
+
+impl Default for InterpolationSettings {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+impl ContextItem for InterpolationSettings {}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Quat, Vec3};
+
+    use super::*;
+
+    #[test]
+    fn blends_between_steps() {
+        let mut interpolated =
+            InterpolatedTransform::new(Transform3D::new(Vec3::ZERO, Quat::IDENTITY, Vec3::ONE));
+        assert_eq!(interpolated.at(Instant::now()).position, Vec3::ZERO);
+
+        interpolated.push(
+            Transform3D::new(
+                Vec3::new(2.0, 0.0, 0.0),
+                Quat::from_rotation_y(1.0),
+                Vec3::ONE,
+            ),
+            Duration::from_millis(10),
+        );
+        let halfway = interpolated.blend(0.5);
+        assert!(halfway.position.abs_diff_eq(Vec3::new(1.0, 0.0, 0.0), 1e-6));
+        assert!(
+            halfway
+                .rotation
+                .abs_diff_eq(Quat::from_rotation_y(0.5), 1e-6)
+        );
+
+        let late = interpolated.stepped_at + Duration::from_millis(50);
+        assert_eq!(interpolated.alpha(late), 1.0);
+        assert_eq!(interpolated.at(late).position, Vec3::new(2.0, 0.0, 0.0));
+    }
+}
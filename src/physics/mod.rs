@@ -1,95 +1,856 @@
 pub mod commands;
+pub mod debug_render;
+pub mod interpolation;
+pub mod model_collider;
+pub mod rapier2d_engine;
 pub mod rapier_engine;
+pub mod recording;
+pub mod settings;
+pub mod simulator;
+pub mod stats;
+pub mod timestep;
 use std::{
-    sync::{Arc, Mutex, mpsc},
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread::JoinHandle,
     time::{Duration, Instant},
 };
 
 use crate::{
+    assets::asset_manager::Model,
     engine::{component::Component, entity::EntityRegistry},
     physics::{
-        commands::{PhysicsCommand, PhysicsEvent},
+        commands::{PhysicsCommand, PhysicsEvent, RaycastHit, ShapeHit},
+        debug_render::PhysicsDebugLines,
+        model_collider::{ColliderKind, collider_from_model},
         rapier_engine::RapierEngine,
+        rapier2d_engine::Rapier2dEngine,
+        recording::{PhysicsRecorder, PhysicsRecording},
+        settings::PhysicsSettings,
+        stats::PhysicsStats,
+        timestep::{FixedTimestep, clamp_timestep},
     },
 };
 use glam::{Quat, Vec3};
-use rapier3d::prelude::*;
+use rapier3d::{
+    control::{CharacterAutostep, CharacterLength, KinematicCharacterController},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const DEFAULT_GRAVITY: Vec3 = Vec3::new(0.0, -9.81, 0.0);
+
+/// how long `PhysicsEngine::raycast` and `query_body` wait for an answer
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(1);
 
 #[derive(Clone, Debug)]
 pub enum RigidBodyState {
     Pending(RigidBody),
     Active(RigidBodyHandle),
+    /// inserted into the 2d backend
+    Active2D(rapier2d::prelude::RigidBodyHandle),
     Removed,
 }
 
+/// which rapier the physics thread runs, picked when the `PhysicsEngine` is made
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PhysicsBackend {
+    #[default]
+    Rapier3D,
+    /// works in the xy plane, z in commands is dropped and transforms keep their own z
+    Rapier2D,
+}
+
+/// what the physics thread drives, one for each `PhysicsBackend`
+pub trait Simulation: Send {
+    /// advances the simulation by one timestep, or only handles commands while paused
+    fn step(&mut self) -> anyhow::Result<()>;
+    fn set_timestep(&mut self, timestep: Duration);
+    fn gravity(&self) -> Vec3;
+    fn set_gravity(&mut self, gravity: Vec3);
+    fn settings(&self) -> PhysicsSettings;
+    fn set_settings(&mut self, settings: PhysicsSettings);
+    fn set_debug_lines(&mut self, lines: Arc<RwLock<PhysicsDebugLines>>);
+    /// filled in after every step
+    fn set_stats(&mut self, stats: Arc<RwLock<PhysicsStats>>);
+    /// commands go into `recorder` while it's recording
+    fn set_recorder(&mut self, recorder: PhysicsRecorder);
+    /// runs the recording's steps with its commands instead of the channel's
+    fn replay(&mut self, recording: &PhysicsRecording) -> anyhow::Result<()>;
+}
+
+/// surface and mass properties applied to a body's collider when it's inserted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicsMaterial {
+    pub friction: f32,
+    pub restitution: f32,
+    pub density: f32,
+}
+
+impl PhysicsMaterial {
+    pub fn new(friction: f32, restitution: f32, density: f32) -> Self {
+        Self {
+            friction,
+            restitution,
+            density,
+        }
+    }
+
+    pub fn apply(&self, collider: &mut Collider) {
+        collider.set_friction(self.friction);
+        collider.set_restitution(self.restitution);
+        collider.set_density(self.density);
+    }
+
+    pub fn apply_2d(&self, collider: &mut rapier2d::prelude::Collider) {
+        collider.set_friction(self.friction);
+        collider.set_restitution(self.restitution);
+        collider.set_density(self.density);
+    }
+}
+
+impl Default for PhysicsMaterial {
+    /// same as rapier's collider defaults
+    fn default() -> Self {
+        Self::new(0.5, 0.0, 1.0)
+    }
+}
+
+/// mass added to a body on top of what its colliders' densities give it
+///
+/// with only `mass` set it's spread like the colliders' own mass, otherwise it's a mass at
+/// `local_center_of_mass` with `principal_inertia`, defaulting to the body's origin and none
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct AdditionalMass {
+    pub mass: f32,
+    /// relative to the body, unrotated
+    pub local_center_of_mass: Option<Vec3>,
+    /// around the body's local axes
+    pub principal_inertia: Option<Vec3>,
+}
+
+impl AdditionalMass {
+    pub fn new(mass: f32) -> Self {
+        Self {
+            mass,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_center_of_mass(mut self, center: Vec3) -> Self {
+        self.local_center_of_mass = Some(center);
+        self
+    }
+
+    pub fn with_principal_inertia(mut self, inertia: Vec3) -> Self {
+        self.principal_inertia = Some(inertia);
+        self
+    }
+
+    pub fn apply(&self, rigid_body: &mut RigidBody, wake_up: bool) {
+        if self.local_center_of_mass.is_none() && self.principal_inertia.is_none() {
+            rigid_body.set_additional_mass(self.mass, wake_up);
+            return;
+        }
+        let center = self.local_center_of_mass.unwrap_or(Vec3::ZERO);
+        let inertia = self.principal_inertia.unwrap_or(Vec3::ZERO);
+        rigid_body.set_additional_mass_properties(
+            MassProperties::new(center.into(), self.mass, inertia.into()),
+            wake_up,
+        );
+    }
+
+    /// keeps the center's xy and the inertia around z
+    pub fn apply_2d(&self, rigid_body: &mut rapier2d::prelude::RigidBody, wake_up: bool) {
+        if self.local_center_of_mass.is_none() && self.principal_inertia.is_none() {
+            rigid_body.set_additional_mass(self.mass, wake_up);
+            return;
+        }
+        let center = self.local_center_of_mass.unwrap_or(Vec3::ZERO);
+        let inertia = self.principal_inertia.unwrap_or(Vec3::ZERO);
+        rigid_body.set_additional_mass_properties(
+            rapier2d::prelude::MassProperties::new(
+                rapier2d::prelude::Point::new(center.x, center.y),
+                self.mass,
+                inertia.z,
+            ),
+            wake_up,
+        );
+    }
+}
+
 #[derive(Debug, Clone, Component)]
 pub struct PhysicsBody {
-    pub collider: Collider,
+    /// every collider with its offset from the body, on top of the collider's own position
+    pub colliders: Vec<(Collider, Isometry<Real>)>,
     pub rigid_body: RigidBodyState,
+    /// overrides whatever the collider was built with, `None` keeps the collider as it is
+    pub material: Option<PhysicsMaterial>,
+    /// override the rigid body's damping when it's inserted, `None` keeps what it was built with
+    pub linear_damping: Option<f32>,
+    pub angular_damping: Option<f32>,
+    /// axes the body can't rotate around or move along, applied when it's inserted
+    pub locked_rotations: Option<[bool; 3]>,
+    pub locked_translations: Option<[bool; 3]>,
+    /// sends `PhysicsEvent::ContactForce` when the total contact force on one of the body's
+    /// colliders goes over this, `None` sends none
+    pub contact_force_threshold: Option<f32>,
+    /// applied when it's inserted, `SetAdditionalMass` changes it afterwards
+    pub additional_mass: Option<AdditionalMass>,
+    /// position based kinematic bodies only, dynamic bodies resting on it move along with it
+    pub carrier: bool,
+    /// used by the 2d backend instead of flattening `colliders`
+    pub collider_2d: Option<rapier2d::prelude::Collider>,
 }
 
 impl PhysicsBody {
     pub fn new(collider: Collider, rigid_body: RigidBody) -> Self {
+        Self::compound(vec![(collider, Isometry::identity())], rigid_body)
+    }
+
+    /// body made of several colliders, like a table out of a top and four legs
+    pub fn compound(colliders: Vec<(Collider, Isometry<Real>)>, rigid_body: RigidBody) -> Self {
         Self {
-            collider,
+            colliders,
             rigid_body: RigidBodyState::Pending(rigid_body),
+            material: None,
+            linear_damping: None,
+            angular_damping: None,
+            locked_rotations: None,
+            locked_translations: None,
+            contact_force_threshold: None,
+            additional_mass: None,
+            carrier: false,
+            collider_2d: None,
         }
     }
+
+    /// adds another collider at `offset` from the body
+    pub fn with_collider(mut self, collider: Collider, offset: Isometry<Real>) -> Self {
+        self.colliders.push((collider, offset));
+        self
+    }
+
+    /// body with a collider made from the model's triangles, see `ColliderKind`
+    pub fn from_model(
+        model: &Model,
+        kind: ColliderKind,
+        rigid_body: RigidBody,
+    ) -> anyhow::Result<Self> {
+        Ok(Self::new(collider_from_model(model, kind)?, rigid_body))
+    }
+
+    pub fn with_material(mut self, material: PhysicsMaterial) -> Self {
+        self.material = Some(material);
+        self
+    }
+
+    pub fn with_damping(mut self, linear: f32, angular: f32) -> Self {
+        self.linear_damping = Some(linear);
+        self.angular_damping = Some(angular);
+        self
+    }
+
+    pub fn with_contact_force_threshold(mut self, threshold: f32) -> Self {
+        self.contact_force_threshold = Some(threshold);
+        self
+    }
+
+    /// a heavy mass low down keeps tall bodies from tipping over, see `AdditionalMass`
+    pub fn with_additional_mass(mut self, mass: AdditionalMass) -> Self {
+        self.additional_mass = Some(mass);
+        self
+    }
+
+    /// shape for the 2d backend, otherwise it flattens the 3d colliders onto the xy plane
+    pub fn with_collider_2d(mut self, collider: rapier2d::prelude::Collider) -> Self {
+        self.collider_2d = Some(collider);
+        self
+    }
+
+    /// takes whatever rests on top along when it moves, like a lift or a moving platform
+    pub fn as_carrier(mut self) -> Self {
+        self.carrier = true;
+        self
+    }
+
+    pub fn with_locked_rotations(mut self, x: bool, y: bool, z: bool) -> Self {
+        self.locked_rotations = Some([x, y, z]);
+        self
+    }
+
+    pub fn with_locked_translations(mut self, x: bool, y: bool, z: bool) -> Self {
+        self.locked_translations = Some([x, y, z]);
+        self
+    }
+}
+
+/// level geometry that never moves, its colliders go straight into the simulation at the
+/// entity's transform without a rigid body
+///
+/// the transform is only read when it's inserted, moving it afterwards does nothing
+#[derive(Debug, Clone, Component)]
+pub struct StaticCollider {
+    /// every collider with its offset from the entity, on top of the collider's own position
+    pub colliders: Vec<(Collider, Isometry<Real>)>,
+    pub material: Option<PhysicsMaterial>,
+}
+
+impl StaticCollider {
+    pub fn new(collider: Collider) -> Self {
+        Self::compound(vec![(collider, Isometry::identity())])
+    }
+
+    pub fn compound(colliders: Vec<(Collider, Isometry<Real>)>) -> Self {
+        Self {
+            colliders,
+            material: None,
+        }
+    }
+
+    /// static collider made from the model's triangles, see `ColliderKind`
+    pub fn from_model(model: &Model, kind: ColliderKind) -> anyhow::Result<Self> {
+        Ok(Self::new(collider_from_model(model, kind)?))
+    }
+
+    pub fn with_material(mut self, material: PhysicsMaterial) -> Self {
+        self.material = Some(material);
+        self
+    }
+}
+
+/// velocity of the entity's body as of the last physics step, kept up to date by the physics
+/// thread for every entity with a `PhysicsBody`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Component)]
+pub struct PhysicsVelocity {
+    pub linear: Vec3,
+    pub angular: Vec3,
+}
+
+/// marker the physics thread keeps on entities whose dynamic body is asleep, so `update` can
+/// skip work for things that aren't moving
+#[derive(Debug, Clone, Copy, Default, PartialEq, Component)]
+pub struct IsSleeping;
+
+/// answer to `PhysicsEngine::query_body`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BodyState {
+    pub linvel: Vec3,
+    pub angvel: Vec3,
+    pub mass: f32,
+    pub is_sleeping: bool,
+}
+
+/// moves a kinematic position based body with `PhysicsCommand::MoveCharacter`, sliding along
+/// walls, climbing slopes up to `max_slope` radians and stepping up ledges up to `step_height`
+#[derive(Debug, Clone, Component)]
+pub struct CharacterController {
+    pub max_slope: f32,
+    /// 0 turns stepping off
+    pub step_height: f32,
+    /// gap kept between the character and whatever it walks on or into
+    pub offset: f32,
+}
+
+impl CharacterController {
+    pub fn new(max_slope: f32, step_height: f32, offset: f32) -> Self {
+        Self {
+            max_slope,
+            step_height,
+            offset,
+        }
+    }
+
+    pub(crate) fn kinematic_controller(&self) -> KinematicCharacterController {
+        KinematicCharacterController {
+            offset: CharacterLength::Absolute(self.offset),
+            autostep: (self.step_height > 0.0).then(|| CharacterAutostep {
+                max_height: CharacterLength::Absolute(self.step_height),
+                ..Default::default()
+            }),
+            max_slope_climb_angle: self.max_slope,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for CharacterController {
+    fn default() -> Self {
+        Self::new(std::f32::consts::FRAC_PI_4, 0.3, 0.01)
+    }
 }
 
 pub struct PhysicsEngine {
-    physics_engine: Option<RapierEngine>,
+    physics_engine: Option<Box<dyn Simulation>>,
     command_sender: mpsc::Sender<PhysicsCommand>,
+    /// answers to queries, entity events go to `Entity::physics_update` instead
     event_receiver: mpsc::Receiver<PhysicsEvent>,
+    /// commands sent but not yet picked up by the physics thread
+    pending_commands: Arc<AtomicUsize>,
 
-    last_physics_step: Arc<Mutex<Instant>>,
+    /// nanoseconds per physics step, read by the physics thread every loop
+    timestep: Arc<AtomicU64>,
+    /// cleared to make the physics thread finish its loop
+    running: Arc<AtomicBool>,
+    /// gives the simulation back when the physics thread stops
+    physics_thread: Option<JoinHandle<Box<dyn Simulation>>>,
+    recorder: PhysicsRecorder,
 }
 
 impl PhysicsEngine {
-    pub fn new(gravity: Vec3, entities: EntityRegistry) -> Self {
+    pub fn new(gravity: Vec3, settings: PhysicsSettings, entities: EntityRegistry) -> Self {
+        Self::with_backend(PhysicsBackend::Rapier3D, gravity, settings, entities)
+    }
+
+    pub fn with_backend(
+        backend: PhysicsBackend,
+        gravity: Vec3,
+        settings: PhysicsSettings,
+        entities: EntityRegistry,
+    ) -> Self {
+        let settings = PhysicsSettings {
+            timestep: clamp_timestep(settings.timestep),
+            ..settings
+        };
         let (command_tx, command_rx) = mpsc::channel();
         let (event_tx, event_rx) = mpsc::channel();
-        let rapier_engine = RapierEngine::new(gravity, entities, command_rx, event_tx);
+        let pending_commands = Arc::new(AtomicUsize::new(0));
+        let mut simulation: Box<dyn Simulation> = match backend {
+            PhysicsBackend::Rapier3D => Box::new(RapierEngine::new(
+                gravity,
+                entities,
+                command_rx,
+                event_tx,
+                pending_commands.clone(),
+                settings,
+            )),
+            PhysicsBackend::Rapier2D => Box::new(Rapier2dEngine::new(
+                gravity,
+                entities,
+                command_rx,
+                event_tx,
+                pending_commands.clone(),
+                settings,
+            )),
+        };
+        let recorder = PhysicsRecorder::default();
+        simulation.set_recorder(recorder.clone());
 
         Self {
             command_sender: command_tx,
             event_receiver: event_rx,
-            physics_engine: Some(rapier_engine),
-            last_physics_step: Arc::new(Mutex::new(Instant::now())),
+            pending_commands,
+            physics_engine: Some(simulation),
+            timestep: Arc::new(AtomicU64::new(settings.timestep.as_nanos() as u64)),
+            running: Arc::new(AtomicBool::new(false)),
+            physics_thread: None,
+            recorder,
         }
     }
 
     pub fn start_physics(&mut self) -> anyhow::Result<()> {
         log::debug!("physics started");
-        let last_physics_step_mutex = self.last_physics_step.clone();
-        let mut rapier_engine = match self.physics_engine.take() {
+        let timestep_nanos = self.timestep.clone();
+        let running = self.running.clone();
+        let mut simulation = match self.physics_engine.take() {
             Some(pe) => pe,
             None => return Err(anyhow::anyhow!("no physics engine")),
         };
-        std::thread::spawn(move || {
+        running.store(true, Ordering::Relaxed);
+        self.physics_thread = Some(std::thread::spawn(move || {
             tracy_client::set_thread_name!("Physics Thread");
-            loop {
+            let mut timestep = FixedTimestep::default();
+            let mut last_loop = Instant::now();
+            while running.load(Ordering::Relaxed) {
                 let _span = tracy_client::span!("physics step");
-                let before_step = Instant::now();
-                let delta = Instant::now()
-                    .duration_since(last_physics_step_mutex.get_cloned().unwrap())
-                    .as_millis_f64();
-                rapier_engine.step(delta).unwrap();
-                let step_time = Instant::now().duration_since(before_step).as_millis_f64();
-
-                std::thread::sleep(Duration::from_millis(
-                    10_u64.checked_sub(step_time as u64).unwrap_or(0),
-                ));
+                let now = Instant::now();
+                let elapsed = now.duration_since(last_loop);
+                last_loop = now;
+
+                let step_size = Duration::from_nanos(timestep_nanos.load(Ordering::Relaxed));
+                if step_size != timestep.timestep() {
+                    timestep.set_timestep(step_size);
+                    simulation.set_timestep(timestep.timestep());
+                }
+
+                for _ in 0..timestep.advance(elapsed) {
+                    simulation.step().unwrap();
+                }
+
+                std::thread::sleep(timestep.until_next_step());
             }
-        });
+            simulation
+        }));
+
+        Ok(())
+    }
 
+    /// stops the physics thread and waits for it to finish its step, `start_physics` picks up
+    /// where it left off
+    pub fn stop(&mut self) -> anyhow::Result<()> {
+        let thread = self
+            .physics_thread
+            .take()
+            .ok_or(anyhow::anyhow!("physics isn't running"))?;
+        self.running.store(false, Ordering::Relaxed);
+        let simulation = thread
+            .join()
+            .map_err(|_| anyhow::anyhow!("physics thread panicked"))?;
+        self.physics_engine = Some(simulation);
+        log::debug!("physics stopped");
         Ok(())
     }
 
+    pub fn is_running(&self) -> bool {
+        self.physics_thread.is_some()
+    }
+
+    /// logs every command the physics thread handles along with the step it was handled on,
+    /// start it before `start_physics` to be able to replay it from the initial scene
+    pub fn start_recording(&self) {
+        self.recorder.start();
+    }
+
+    pub fn stop_recording(&self) -> Option<PhysicsRecording> {
+        self.recorder.stop()
+    }
+
+    /// runs a recording on the stopped simulation, which has to hold the same scene the
+    /// recording started on for the results to match
+    pub fn replay(&mut self, recording: &PhysicsRecording) -> anyhow::Result<()> {
+        match self.physics_engine.as_mut() {
+            Some(simulation) => simulation.replay(recording),
+            None => Err(anyhow::anyhow!("can't replay while physics is running")),
+        }
+    }
+
+    /// shares the debug lines the physics thread fills, has to happen before physics starts
+    pub fn with_debug_lines(mut self, lines: Arc<RwLock<PhysicsDebugLines>>) -> Self {
+        match self.physics_engine.as_mut() {
+            Some(simulation) => simulation.set_debug_lines(lines),
+            None => log::error!("physics debug lines set after physics started"),
+        }
+        self
+    }
+
+    /// shares the stats the physics thread fills, has to happen before physics starts
+    pub fn with_stats(mut self, stats: Arc<RwLock<PhysicsStats>>) -> Self {
+        match self.physics_engine.as_mut() {
+            Some(simulation) => simulation.set_stats(stats),
+            None => log::error!("physics stats set after physics started"),
+        }
+        self
+    }
+
+    /// freezes the simulation, the renderer keeps drawing the last step
+    pub fn pause(&mut self) -> anyhow::Result<()> {
+        self.send_command(PhysicsCommand::Pause)
+    }
+
+    pub fn resume(&mut self) -> anyhow::Result<()> {
+        self.send_command(PhysicsCommand::Resume)
+    }
+
+    /// advances a paused simulation by one timestep
+    pub fn step_once(&mut self) -> anyhow::Result<()> {
+        self.send_command(PhysicsCommand::StepOnce)
+    }
+
+    /// length of a physics step, `PhysicsSettings::timestep` unless set
+    pub fn timestep(&self) -> Duration {
+        Duration::from_nanos(self.timestep.load(Ordering::Relaxed))
+    }
+
+    pub fn set_timestep(&mut self, timestep: Duration) {
+        let timestep = clamp_timestep(timestep);
+        self.timestep
+            .store(timestep.as_nanos() as u64, Ordering::Relaxed);
+        if let Some(simulation) = self.physics_engine.as_mut() {
+            simulation.set_timestep(timestep);
+        }
+    }
+
+    /// gravity of the stopped simulation, `None` while the physics thread has it
+    pub fn gravity(&self) -> Option<Vec3> {
+        self.physics_engine
+            .as_ref()
+            .map(|simulation| simulation.gravity())
+    }
+
+    /// settings of the stopped simulation, `None` while the physics thread has it
+    pub fn settings(&self) -> Option<PhysicsSettings> {
+        self.physics_engine
+            .as_ref()
+            .map(|simulation| simulation.settings())
+    }
+
+    /// sets it straight away before the physics thread starts, sends `SetGravity` after
+    pub fn set_gravity(&mut self, gravity: Vec3) -> anyhow::Result<()> {
+        match self.physics_engine.as_mut() {
+            Some(simulation) => {
+                simulation.set_gravity(gravity);
+                Ok(())
+            }
+            None => self.send_command(PhysicsCommand::SetGravity { gravity }),
+        }
+    }
+
+    /// sets them straight away before the physics thread starts, sends
+    /// `SetIntegrationParameters` after
+    pub fn set_settings(&mut self, settings: PhysicsSettings) -> anyhow::Result<()> {
+        let settings = PhysicsSettings {
+            timestep: clamp_timestep(settings.timestep),
+            ..settings
+        };
+        match self.physics_engine.as_mut() {
+            Some(simulation) => {
+                self.timestep
+                    .store(settings.timestep.as_nanos() as u64, Ordering::Relaxed);
+                simulation.set_settings(settings);
+                Ok(())
+            }
+            None => self.send_command(PhysicsCommand::SetIntegrationParameters(settings)),
+        }
+    }
+
     pub fn send_command(&mut self, command: PhysicsCommand) -> anyhow::Result<()> {
-        self.command_sender.send(command)?;
+        // the physics thread's clock has to step at the new rate too
+        let command = match command {
+            PhysicsCommand::SetIntegrationParameters(settings) => {
+                let settings = PhysicsSettings {
+                    timestep: clamp_timestep(settings.timestep),
+                    ..settings
+                };
+                self.timestep
+                    .store(settings.timestep.as_nanos() as u64, Ordering::Relaxed);
+                PhysicsCommand::SetIntegrationParameters(settings)
+            }
+            command => command,
+        };
+        // counted before sending so the physics thread never takes it below zero
+        self.pending_commands.fetch_add(1, Ordering::Relaxed);
+        if let Err(e) = self.command_sender.send(command) {
+            self.pending_commands.fetch_sub(1, Ordering::Relaxed);
+            return Err(e.into());
+        }
         Ok(())
     }
+
+    /// casts a ray and waits for the physics thread to answer
+    pub fn raycast(
+        &mut self,
+        origin: Vec3,
+        dir: Vec3,
+        max_toi: f32,
+    ) -> anyhow::Result<Option<RaycastHit>> {
+        let request_id = Uuid::new_v4();
+        self.send_command(PhysicsCommand::Raycast {
+            origin,
+            dir,
+            max_toi,
+            request_id,
+        })?;
+
+        match self.wait_for_response(request_id)? {
+            PhysicsEvent::RaycastHit {
+                entity,
+                point,
+                normal,
+                ..
+            } => Ok(Some(RaycastHit {
+                entity,
+                point,
+                normal,
+            })),
+            _ => Ok(None),
+        }
+    }
+
+    /// sweeps `shape` along `dir` and waits for the first thing it would hit
+    pub fn shape_cast(
+        &mut self,
+        shape: Collider,
+        origin: Vec3,
+        rotation: Quat,
+        dir: Vec3,
+        max_toi: f32,
+    ) -> anyhow::Result<Option<ShapeHit>> {
+        let request_id = Uuid::new_v4();
+        self.send_command(PhysicsCommand::ShapeCast {
+            shape,
+            origin,
+            rotation,
+            dir,
+            max_toi,
+            request_id,
+        })?;
+
+        match self.wait_for_response(request_id)? {
+            PhysicsEvent::ShapeCastHit {
+                entity,
+                toi,
+                witness_hit,
+                witness_shape,
+                normal,
+                ..
+            } => Ok(Some(ShapeHit {
+                entity,
+                toi,
+                witness_hit,
+                witness_shape,
+                normal,
+            })),
+            _ => Ok(None),
+        }
+    }
+
+    /// entities overlapping `shape` at `origin`, for things like area of effect damage
+    pub fn overlapping(
+        &mut self,
+        shape: Collider,
+        origin: Vec3,
+        rotation: Quat,
+    ) -> anyhow::Result<Vec<Uuid>> {
+        let request_id = Uuid::new_v4();
+        self.send_command(PhysicsCommand::OverlapShape {
+            shape,
+            origin,
+            rotation,
+            request_id,
+        })?;
+
+        match self.wait_for_response(request_id)? {
+            PhysicsEvent::Overlaps { entities, .. } => Ok(entities),
+            other => Err(anyhow::anyhow!(
+                "unexpected answer to an overlap query: {other:?}"
+            )),
+        }
+    }
+
+    /// asks for the velocity, mass and sleep state of the entity's body and waits for it
+    pub fn query_body(&mut self, id: Uuid) -> anyhow::Result<BodyState> {
+        let request_id = Uuid::new_v4();
+        self.send_command(PhysicsCommand::QueryBody { id, request_id })?;
+
+        match self.wait_for_response(request_id)? {
+            PhysicsEvent::BodyState {
+                linvel,
+                angvel,
+                mass,
+                is_sleeping,
+                ..
+            } => Ok(BodyState {
+                linvel,
+                angvel,
+                mass,
+                is_sleeping,
+            }),
+            other => Err(anyhow::anyhow!(
+                "unexpected answer to a body query: {other:?}"
+            )),
+        }
+    }
+
+    /// waits for the event answering `request_id`, late answers to earlier requests that gave up
+    /// waiting are dropped. fails if the physics thread doesn't answer within
+    /// `RESPONSE_TIMEOUT`, like when it isn't running or the command failed.
+    fn wait_for_response(&mut self, request_id: Uuid) -> anyhow::Result<PhysicsEvent> {
+        let deadline = Instant::now() + RESPONSE_TIMEOUT;
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let event = self
+                .event_receiver
+                .recv_timeout(timeout)
+                .map_err(|e| anyhow::anyhow!("no answer from the physics thread: {e}"))?;
+            if event.request_id() == Some(request_id) {
+                return Ok(event);
+            }
+            log::debug!("dropped a late physics answer: {event:?}");
+        }
+    }
+
+    pub fn pending_commands(&self) -> usize {
+        self.pending_commands.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for PhysicsEngine {
+    fn drop(&mut self) {
+        if self.is_running() {
+            if let Err(e) = self.stop() {
+                log::error!("failed to stop physics: {e}");
+            }
+        }
+    }
+}
+
+#[test]
+fn restart_physics() {
+    let mut physics = PhysicsEngine::new(
+        DEFAULT_GRAVITY,
+        PhysicsSettings::default(),
+        EntityRegistry::new(),
+    );
+    for _ in 0..2 {
+        physics.start_physics().unwrap();
+        assert!(physics.start_physics().is_err());
+        physics.stop().unwrap();
+        assert!(!physics.is_running());
+    }
+    assert!(physics.stop().is_err());
+}
+
+#[test]
+fn raycast_waits_for_its_answer() {
+    use crate::engine::{
+        component::{ComponentSet, Transform3D},
+        entity::{Entity, tests::Dummy},
+    };
+
+    let mut components = ComponentSet::new();
+    components.add(Transform3D::default());
+    components.add(StaticCollider::new(
+        ColliderBuilder::cuboid(10.0, 0.5, 10.0).build(),
+    ));
+    let ground = Dummy::new(components);
+    let ground_id = ground.id();
+    let mut entities = EntityRegistry::new();
+    entities.add(ground.into_container());
+
+    let mut physics = PhysicsEngine::new(DEFAULT_GRAVITY, PhysicsSettings::default(), entities);
+    physics.start_physics().unwrap();
+    // nobody waits on this one, its answer is dropped on the way to the next
+    physics
+        .send_command(PhysicsCommand::Raycast {
+            origin: Vec3::new(-1.0, 5.0, 0.0),
+            dir: Vec3::NEG_Y,
+            max_toi: 10.0,
+            request_id: Uuid::new_v4(),
+        })
+        .unwrap();
+
+    let hit = physics
+        .raycast(Vec3::new(1.0, 5.0, 0.0), Vec3::NEG_Y, 10.0)
+        .unwrap()
+        .expect("the ray missed the ground");
+    assert_eq!(hit.entity, Some(ground_id));
+    assert!(
+        hit.point.distance(Vec3::new(1.0, 0.5, 0.0)) < 1e-4,
+        "{}",
+        hit.point
+    );
+    assert!(hit.normal.distance(Vec3::Y) < 1e-4, "{}", hit.normal);
+    assert_eq!(
+        physics
+            .raycast(Vec3::new(1.0, 5.0, 0.0), Vec3::Y, 10.0)
+            .unwrap(),
+        None
+    );
+    physics.stop().unwrap();
 }
 
 #[test]
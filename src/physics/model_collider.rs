@@ -0,0 +1,190 @@
+use glam::Vec3;
+use rapier3d::prelude::*;
+
+use crate::assets::{
+    asset_manager::{Model, PrimitiveTopology},
+    basic_models::HeightfieldBuilder,
+};
+
+/// shape built from a model's triangles by `PhysicsBody::from_model`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColliderKind {
+    /// the exact triangles, best for fixed bodies since rapier can't give a triangle soup a
+    /// proper mass
+    TriMesh,
+    /// the smallest convex shape around every vertex, works well on dynamic bodies
+    ConvexHull,
+}
+
+/// triangles of every triangle primitive in the model with the node transforms baked in
+///
+/// degenerate triangles are left out, lines and points are skipped
+fn model_triangles(model: &Model) -> anyhow::Result<(Vec<Vec3>, Vec<[u32; 3]>)> {
+    let mut vertices: Vec<Vec3> = Vec::new();
+    let mut triangles: Vec<[u32; 3]> = Vec::new();
+    let mut degenerate = 0;
+
+    for node in model.get_nodes_flattened() {
+        for primitive in node.meshes.iter().flat_map(|m| m.primitives.iter()) {
+            if primitive.topology != PrimitiveTopology::Triangles {
+                continue;
+            }
+
+            let offset = vertices.len() as u32;
+            vertices.extend(
+                primitive
+                    .positions
+                    .iter()
+                    .map(|p| node.transform.transform_point3(*p)),
+            );
+
+            for triangle in primitive.indices.chunks_exact(3) {
+                if let Some(index) = triangle
+                    .iter()
+                    .find(|i| **i as usize >= primitive.positions.len())
+                {
+                    return Err(anyhow::anyhow!(
+                        "model index {index} is out of bounds for a primitive with {} vertices",
+                        primitive.positions.len()
+                    ));
+                }
+
+                let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|i| i + offset);
+                let [pa, pb, pc] = [a, b, c].map(|i| vertices[i as usize]);
+                if (pb - pa).cross(pc - pa).length_squared() <= f32::EPSILON * f32::EPSILON {
+                    degenerate += 1;
+                    continue;
+                }
+                triangles.push([a, b, c]);
+            }
+        }
+    }
+
+    if degenerate > 0 {
+        log::debug!("left {degenerate} degenerate triangles out of a model collider");
+    }
+    if triangles.is_empty() {
+        return Err(anyhow::anyhow!(
+            "model has no triangles to build a collider from ({degenerate} degenerate)"
+        ));
+    }
+    Ok((vertices, triangles))
+}
+
+/// builds a collider in the model's own space from its triangles
+pub fn collider_from_model(model: &Model, kind: ColliderKind) -> anyhow::Result<Collider> {
+    let (vertices, triangles) = model_triangles(model)?;
+
+    let builder = match kind {
+        ColliderKind::TriMesh => {
+            let points = vertices.into_iter().map(Point::from).collect();
+            ColliderBuilder::trimesh(points, triangles)
+                .map_err(|e| anyhow::anyhow!("couldn't build a trimesh collider: {e:?}"))?
+        }
+        ColliderKind::ConvexHull => {
+            // only the vertices triangles use, so stray ones don't bloat the hull
+            let mut used: Vec<u32> = triangles.into_iter().flatten().collect();
+            used.sort_unstable();
+            used.dedup();
+            let points: Vec<Point<Real>> = used
+                .into_iter()
+                .map(|i| Point::from(vertices[i as usize]))
+                .collect();
+            ColliderBuilder::convex_hull(&points).ok_or(anyhow::anyhow!(
+                "model vertices don't make up a convex hull"
+            ))?
+        }
+    };
+    Ok(builder.build())
+}
+
+/// heightfield centered on the origin, rows go along z and columns along x with `cell_size`
+/// between samples. same as `basic_models::HeightfieldBuilder::collider` for the same grid
+pub fn heightfield_collider(heights: &[Vec<f32>], cell_size: f32) -> anyhow::Result<Collider> {
+    HeightfieldBuilder::new(heights.to_vec(), cell_size).collider()
+}
+
+#[cfg(test)]
+mod tests {
+    use rapier3d::parry::query::{Ray, RayCast};
+
+    use super::*;
+    use crate::assets::{
+        asset_manager::{Mesh, MeshPrimitive, ModelNode},
+        basic_models::CuboidBuilder,
+    };
+
+    #[test]
+    fn cuboid_model_colliders() {
+        let model = CuboidBuilder::new().size(2.0, 4.0, 2.0).build();
+        for kind in [ColliderKind::TriMesh, ColliderKind::ConvexHull] {
+            let aabb = collider_from_model(&model, kind).unwrap().compute_aabb();
+            assert_eq!(aabb.mins, point![-1.0, -2.0, -1.0], "{kind:?}");
+            assert_eq!(aabb.maxs, point![1.0, 2.0, 1.0], "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn degenerate_model_is_an_error() {
+        let flat = MeshPrimitive {
+            positions: vec![Vec3::ZERO, Vec3::X, Vec3::X * 2.0],
+            normals: vec![Vec3::Y; 3],
+            tangents: Vec::new(),
+            tex_coords: vec![glam::Vec2::ZERO; 3],
+            colors: None,
+            indices: vec![0, 1, 2],
+            topology: PrimitiveTopology::Triangles,
+            material_index: None,
+        };
+        let model = Model {
+            nodes: vec![ModelNode {
+                transform: glam::Mat4::IDENTITY,
+                meshes: vec![Mesh {
+                    primitives: vec![flat],
+                }],
+                nodes: vec![],
+            }],
+            materials: vec![],
+            asset_id: None,
+        };
+        let err = collider_from_model(&model, ColliderKind::TriMesh).unwrap_err();
+        assert!(err.to_string().contains("1 degenerate"), "{err}");
+
+        let empty = Model {
+            nodes: vec![],
+            materials: vec![],
+            asset_id: None,
+        };
+        assert!(collider_from_model(&empty, ColliderKind::ConvexHull).is_err());
+    }
+
+    #[test]
+    fn heightfield_samples_the_grid() {
+        let heights = vec![
+            vec![0.0, 1.0, 2.0],
+            vec![3.0, 4.0, 5.0],
+            vec![6.0, 7.0, 8.0],
+            vec![9.0, 10.0, 11.0],
+        ];
+        let collider = heightfield_collider(&heights, 2.0).unwrap();
+        let aabb = collider.compute_aabb();
+        assert_eq!(aabb.mins, point![-2.0, 0.0, -3.0]);
+        assert_eq!(aabb.maxs, point![2.0, 11.0, 3.0]);
+
+        // corner, middle and far corner
+        for (r, c) in [(0, 0), (1, 1), (3, 2)] {
+            let ray = Ray::new(
+                point![c as f32 * 2.0 - 2.0, 50.0, r as f32 * 2.0 - 3.0],
+                vector![0.0, -1.0, 0.0],
+            );
+            let toi = collider
+                .shape()
+                .cast_ray(collider.position(), &ray, 100.0, true)
+                .unwrap();
+            assert!((50.0 - toi - heights[r][c]).abs() < 1e-4, "({r}, {c})");
+        }
+
+        assert!(heightfield_collider(&[vec![0.0, 1.0], vec![0.0]], 1.0).is_err());
+        assert!(heightfield_collider(&[vec![0.0, 1.0]], 1.0).is_err());
+    }
+}
@@ -0,0 +1,916 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use glam::{EulerRot, Quat, Vec2, Vec3};
+use rapier2d::prelude::*;
+use uuid::Uuid;
+
+use crate::{
+    engine::{
+        component::{Layer, Transform3D},
+        entity::EntityRegistry,
+    },
+    physics::{
+        AdditionalMass, PhysicsBody, PhysicsMaterial, RigidBodyState, StaticCollider,
+        commands::{PhysicsCommand, PhysicsEvent},
+        debug_render::PhysicsDebugLines,
+        rapier_engine::SCALE_SUBDIVISIONS,
+        settings::PhysicsSettings,
+        simulator::{Simulator, World, WorldBody, WorldCollider},
+    },
+};
+
+/// `RapierEngine` in the xy plane, takes the same commands with z dropped
+///
+/// bodies are flattened from the 3d `PhysicsBody` when they're inserted, unless they have a
+/// `collider_2d`. transforms keep their z and only turn around z. joints, character controllers,
+/// carriers and debug lines aren't supported
+pub type Rapier2dEngine = Simulator<Rapier2dWorld>;
+
+/// a contact force over a collider's threshold, the contact point is in the xy plane
+struct ContactForce {
+    collider1: ColliderHandle,
+    collider2: ColliderHandle,
+    magnitude: f32,
+    largest_contact_point: Vec2,
+}
+
+/// collects collision events during a step, rapier calls it from the solver threads
+#[derive(Default)]
+struct CollisionCollector {
+    events: Mutex<Vec<CollisionEvent>>,
+    forces: Mutex<Vec<ContactForce>>,
+}
+
+impl EventHandler for CollisionCollector {
+    fn handle_collision_event(
+        &self,
+        _bodies: &RigidBodySet,
+        _colliders: &ColliderSet,
+        event: CollisionEvent,
+        _contact_pair: Option<&ContactPair>,
+    ) {
+        self.events.lock().unwrap().push(event);
+    }
+
+    fn handle_contact_force_event(
+        &self,
+        _dt: Real,
+        _bodies: &RigidBodySet,
+        colliders: &ColliderSet,
+        contact_pair: &ContactPair,
+        total_force_magnitude: Real,
+    ) {
+        let largest = contact_pair
+            .manifolds
+            .iter()
+            .flat_map(|m| m.points.iter())
+            .max_by(|a, b| a.data.impulse.total_cmp(&b.data.impulse));
+        let largest_contact_point = match (largest, colliders.get(contact_pair.collider1)) {
+            (Some(point), Some(collider)) => {
+                let p = collider.position() * point.local_p1;
+                Vec2::new(p.x, p.y)
+            }
+            _ => Vec2::ZERO,
+        };
+
+        self.forces.lock().unwrap().push(ContactForce {
+            collider1: contact_pair.collider1,
+            collider2: contact_pair.collider2,
+            magnitude: total_force_magnitude,
+            largest_contact_point,
+        });
+    }
+}
+
+/// rapier's 2d sets and pipeline
+pub struct Rapier2dWorld {
+    pub rigid_body_set: RigidBodySet,
+    pub collider_set: ColliderSet,
+    /// entity each collider belongs to, kept after a collider is removed until its last
+    /// collision event has gone out
+    collider_entities: HashMap<ColliderHandle, Uuid>,
+    /// rigid body of every entity in the simulation, works after the entity is despawned
+    entity_bodies: HashMap<Uuid, RigidBodyHandle>,
+    /// parentless colliders of every entity with a `StaticCollider`
+    static_colliders: HashMap<Uuid, Vec<ColliderHandle>>,
+    collisions: CollisionCollector,
+
+    integration_parameters: IntegrationParameters,
+    physics_pipeline: PhysicsPipeline,
+    island_manager: IslandManager,
+    broad_phase: DefaultBroadPhase,
+    narrow_phase: NarrowPhase,
+    impulse_joint_set: ImpulseJointSet,
+    multibody_joint_set: MultibodyJointSet,
+    ccd_solver: CCDSolver,
+}
+
+impl Default for Rapier2dWorld {
+    fn default() -> Self {
+        Self {
+            rigid_body_set: RigidBodySet::new(),
+            collider_set: ColliderSet::new(),
+            collider_entities: HashMap::new(),
+            entity_bodies: HashMap::new(),
+            static_colliders: HashMap::new(),
+            collisions: CollisionCollector::default(),
+            integration_parameters: IntegrationParameters::default(),
+            physics_pipeline: PhysicsPipeline::new(),
+            island_manager: IslandManager::new(),
+            broad_phase: DefaultBroadPhase::new(),
+            narrow_phase: NarrowPhase::new(),
+            impulse_joint_set: ImpulseJointSet::new(),
+            multibody_joint_set: MultibodyJointSet::new(),
+            ccd_solver: CCDSolver::new(),
+        }
+    }
+}
+
+impl Rapier2dWorld {
+    /// moves position based kinematic bodies to where their entity's transform is
+    fn drive_kinematic_bodies(&mut self, entities: &EntityRegistry) {
+        for e in entities.clone().into_iter() {
+            let target = e.read_with(|e| {
+                let pb = e.components().get::<PhysicsBody>()?;
+                let RigidBodyState::Active2D(handle) = pb.rigid_body else {
+                    return None;
+                };
+                let transform = e.components().get::<Transform3D>()?;
+                Some((handle, isometry(transform.position, transform.rotation)))
+            });
+            let Some((handle, target)) = target else {
+                continue;
+            };
+            let Some(rb) = self.rigid_body_set.get_mut(handle) else {
+                continue;
+            };
+            if rb.body_type() == RigidBodyType::KinematicPositionBased {
+                rb.set_next_kinematic_position(target);
+            }
+        }
+    }
+
+    /// like `RapierWorld`'s, contact points get the z of the first entity
+    fn collision_events(&mut self, entities: &EntityRegistry) -> Vec<PhysicsEvent> {
+        let mut physics_events = Vec::new();
+
+        let events = std::mem::take(&mut *self.collisions.events.lock().unwrap());
+        for event in events {
+            let (h1, h2) = (event.collider1(), event.collider2());
+            let (Some(a), Some(b)) = (
+                self.collider_entities.get(&h1).copied(),
+                self.collider_entities.get(&h2).copied(),
+            ) else {
+                log::debug!("collision event for a collider without an entity");
+                continue;
+            };
+
+            physics_events.push(match event {
+                CollisionEvent::Started(..) => PhysicsEvent::CollisionStarted { a, b },
+                CollisionEvent::Stopped(..) => PhysicsEvent::CollisionEnded { a, b },
+            });
+        }
+
+        let forces = std::mem::take(&mut *self.collisions.forces.lock().unwrap());
+        for force in forces {
+            let (Some(a), Some(b)) = (
+                self.collider_entities.get(&force.collider1).copied(),
+                self.collider_entities.get(&force.collider2).copied(),
+            ) else {
+                log::debug!("contact force event for a collider without an entity");
+                continue;
+            };
+
+            physics_events.push(PhysicsEvent::ContactForce {
+                a,
+                b,
+                force_magnitude: force.magnitude,
+                largest_contact_point: force.largest_contact_point.extend(depth(entities, a)),
+            });
+        }
+
+        let colliders = &self.collider_set;
+        self.collider_entities
+            .retain(|handle, _| colliders.get(*handle).is_some());
+
+        physics_events
+    }
+}
+
+impl World for Rapier2dWorld {
+    type Body = RigidBody;
+    type Collider = Collider;
+
+    fn configure(&mut self, dt: Option<f32>, settings: &PhysicsSettings) {
+        if let Some(dt) = dt {
+            self.integration_parameters.dt = dt;
+        }
+        settings.apply_2d(&mut self.integration_parameters);
+    }
+
+    fn dt(&self) -> f32 {
+        self.integration_parameters.dt
+    }
+
+    /// flattens the body, and its colliders unless it has a `collider_2d`
+    fn build_body(
+        &self,
+        rigid_body: &rapier3d::prelude::RigidBody,
+        body: &PhysicsBody,
+    ) -> anyhow::Result<(RigidBody, Vec<Collider>)> {
+        let colliders = match &body.collider_2d {
+            Some(collider) => vec![collider.clone()],
+            None => body
+                .colliders
+                .iter()
+                .map(|(collider, offset)| {
+                    let mut collider = flatten_collider(collider)?;
+                    collider.set_position(flatten_isometry(offset) * collider.position());
+                    Ok(collider)
+                })
+                .collect::<anyhow::Result<Vec<Collider>>>()?,
+        };
+        Ok((flatten_rigid_body(rigid_body), colliders))
+    }
+
+    fn build_static_colliders(&self, id: Uuid, statics: &StaticCollider) -> Vec<Collider> {
+        statics
+            .colliders
+            .iter()
+            .filter_map(|(collider, offset)| match flatten_collider(collider) {
+                Ok(mut collider) => {
+                    collider.set_position(flatten_isometry(offset) * collider.position());
+                    Some(collider)
+                }
+                Err(e) => {
+                    log::warn!("entity {id}'s static collider left out of the 2d simulation: {e}");
+                    None
+                }
+            })
+            .collect()
+    }
+
+    fn insert_body(
+        &mut self,
+        id: Uuid,
+        body: RigidBody,
+        colliders: Vec<Collider>,
+    ) -> RigidBodyState {
+        let rb_handle = self.rigid_body_set.insert(body);
+        for collider in colliders {
+            let collider_handle =
+                self.collider_set
+                    .insert_with_parent(collider, rb_handle, &mut self.rigid_body_set);
+            self.collider_entities.insert(collider_handle, id);
+        }
+        self.entity_bodies.insert(id, rb_handle);
+        RigidBodyState::Active2D(rb_handle)
+    }
+
+    fn insert_static_colliders(&mut self, id: Uuid, colliders: Vec<Collider>) {
+        let handles: Vec<ColliderHandle> = colliders
+            .into_iter()
+            .map(|collider| {
+                let handle = self.collider_set.insert(collider);
+                self.collider_entities.insert(handle, id);
+                handle
+            })
+            .collect();
+        self.static_colliders.entry(id).or_default().extend(handles);
+    }
+
+    fn remove_body(&mut self, id: Uuid) -> anyhow::Result<bool> {
+        let statics = self.static_colliders.remove(&id);
+        for handle in statics.iter().flatten() {
+            self.collider_set.remove(
+                *handle,
+                &mut self.island_manager,
+                &mut self.rigid_body_set,
+                true,
+            );
+        }
+        let handle = match self.entity_bodies.remove(&id) {
+            Some(handle) => handle,
+            None if statics.is_some() => return Ok(false),
+            None => return Err(anyhow::anyhow!("no rigid body for entity {id}")),
+        };
+        self.rigid_body_set.remove(
+            handle,
+            &mut self.island_manager,
+            &mut self.collider_set,
+            &mut self.impulse_joint_set,
+            &mut self.multibody_joint_set,
+            true,
+        );
+        Ok(true)
+    }
+
+    fn body<'a>(&'a self, state: &'a RigidBodyState) -> Option<&'a RigidBody> {
+        match state {
+            RigidBodyState::Active2D(handle) => self.rigid_body_set.get(*handle),
+            _ => None,
+        }
+    }
+
+    fn body_mut(&mut self, state: &RigidBodyState) -> anyhow::Result<&mut RigidBody> {
+        let handle = match state {
+            RigidBodyState::Active2D(handle) => *handle,
+            RigidBodyState::Removed => return Err(anyhow::anyhow!("rigid body has been removed")),
+            RigidBodyState::Pending(_rb) => {
+                return Err(anyhow::anyhow!("cannot mutate pending body"));
+            }
+            RigidBodyState::Active(_) => {
+                return Err(anyhow::anyhow!("rigid body belongs to a 3d simulation"));
+            }
+        };
+        self.rigid_body_set
+            .get_mut(handle)
+            .ok_or(anyhow::anyhow!("rigid body handle leads to no rigid body"))
+    }
+
+    fn static_colliders_mut(&mut self, id: Uuid, op: &mut dyn FnMut(&mut Collider)) -> bool {
+        let Some(handles) = self.static_colliders.get(&id) else {
+            return false;
+        };
+        handles
+            .iter()
+            .filter_map(|h| self.collider_set.get_mut(*h))
+            .for_each(op);
+        true
+    }
+
+    fn body_colliders_mut(
+        &mut self,
+        state: &RigidBodyState,
+        op: &mut dyn FnMut(&mut Collider),
+    ) -> anyhow::Result<()> {
+        let colliders = self.body_mut(state)?.colliders().to_vec();
+        colliders
+            .into_iter()
+            .filter_map(|h| self.collider_set.get_mut(h))
+            .for_each(op);
+        Ok(())
+    }
+
+    fn wake_dynamic_bodies(&mut self) {
+        for (_, rb) in self.rigid_body_set.iter_mut() {
+            if rb.is_dynamic() {
+                rb.wake_up(true);
+            }
+        }
+    }
+
+    fn step(&mut self, gravity: Vec3, entities: &EntityRegistry) -> Vec<PhysicsEvent> {
+        let physics_hooks = ();
+
+        self.drive_kinematic_bodies(entities);
+
+        self.physics_pipeline.step(
+            &vec2(gravity),
+            &self.integration_parameters,
+            &mut self.island_manager,
+            &mut self.broad_phase,
+            &mut self.narrow_phase,
+            &mut self.rigid_body_set,
+            &mut self.collider_set,
+            &mut self.impulse_joint_set,
+            &mut self.multibody_joint_set,
+            &mut self.ccd_solver,
+            &physics_hooks,
+            &self.collisions,
+        );
+        self.collision_events(entities)
+    }
+
+    fn dynamic_bodies(&self) -> Vec<(Uuid, bool)> {
+        self.entity_bodies
+            .iter()
+            .filter_map(|(id, handle)| {
+                let rb = self.rigid_body_set.get(*handle)?;
+                rb.is_dynamic().then(|| (*id, rb.is_sleeping()))
+            })
+            .collect()
+    }
+
+    fn counts(&self) -> (usize, usize) {
+        let contact_pairs = self
+            .narrow_phase
+            .contact_pairs()
+            .filter(|pair| pair.has_any_active_contact)
+            .count();
+        (
+            self.island_manager.active_dynamic_bodies().len(),
+            contact_pairs,
+        )
+    }
+
+    fn set_debug_lines(&mut self, _lines: Arc<RwLock<PhysicsDebugLines>>) {
+        log::debug!("the 2d physics backend draws no debug lines");
+    }
+
+    /// casts in the xy plane, the hit point keeps the origin's z
+    fn raycast(&self, origin: Vec3, dir: Vec3, max_toi: f32, request_id: Uuid) -> PhysicsEvent {
+        let dir = dir.truncate().normalize_or_zero();
+        let hit = if dir == Vec2::ZERO {
+            None
+        } else {
+            let query_pipeline = self.broad_phase.as_query_pipeline(
+                self.narrow_phase.query_dispatcher(),
+                &self.rigid_body_set,
+                &self.collider_set,
+                QueryFilter::default(),
+            );
+            query_pipeline.cast_ray_and_get_normal(
+                &Ray::new(point![origin.x, origin.y], vector![dir.x, dir.y]),
+                max_toi,
+                true,
+            )
+        };
+
+        match hit {
+            Some((handle, intersection)) => PhysicsEvent::RaycastHit {
+                request_id,
+                entity: self.collider_entities.get(&handle).copied(),
+                point: origin + dir.extend(0.0) * intersection.time_of_impact,
+                normal: Vec3::new(intersection.normal.x, intersection.normal.y, 0.0),
+            },
+            None => PhysicsEvent::RaycastMiss { request_id },
+        }
+    }
+
+    /// sweeps the flattened shape in the xy plane, witness points keep the origin's z
+    fn shape_cast(
+        &self,
+        shape: &rapier3d::prelude::Collider,
+        origin: Vec3,
+        rotation: Quat,
+        dir: Vec3,
+        max_toi: f32,
+        request_id: Uuid,
+    ) -> anyhow::Result<PhysicsEvent> {
+        let shape = flatten_collider(shape)?;
+        let dir = dir.truncate().normalize_or_zero();
+        let shape_pos = isometry(origin, rotation);
+        let hit = if dir == Vec2::ZERO {
+            None
+        } else {
+            let query_pipeline = self.broad_phase.as_query_pipeline(
+                self.narrow_phase.query_dispatcher(),
+                &self.rigid_body_set,
+                &self.collider_set,
+                QueryFilter::default(),
+            );
+            query_pipeline.cast_shape(
+                &shape_pos,
+                &vector![dir.x, dir.y],
+                shape.shape(),
+                ShapeCastOptions::with_max_time_of_impact(max_toi),
+            )
+        };
+
+        Ok(match hit {
+            Some((handle, hit)) => {
+                let shape_pos_at_hit =
+                    isometry(origin + dir.extend(0.0) * hit.time_of_impact, rotation);
+                let witness_shape = shape_pos_at_hit * hit.witness2;
+                PhysicsEvent::ShapeCastHit {
+                    request_id,
+                    entity: self.collider_entities.get(&handle).copied(),
+                    toi: hit.time_of_impact,
+                    witness_hit: Vec3::new(hit.witness1.x, hit.witness1.y, origin.z),
+                    witness_shape: Vec3::new(witness_shape.x, witness_shape.y, origin.z),
+                    normal: Vec3::new(hit.normal1.x, hit.normal1.y, 0.0),
+                }
+            }
+            None => PhysicsEvent::ShapeCastMiss { request_id },
+        })
+    }
+
+    fn overlap_shape(
+        &self,
+        shape: &rapier3d::prelude::Collider,
+        origin: Vec3,
+        rotation: Quat,
+        request_id: Uuid,
+    ) -> anyhow::Result<PhysicsEvent> {
+        let shape = flatten_collider(shape)?;
+        let query_pipeline = self.broad_phase.as_query_pipeline(
+            self.narrow_phase.query_dispatcher(),
+            &self.rigid_body_set,
+            &self.collider_set,
+            QueryFilter::default(),
+        );
+        let mut entities: Vec<Uuid> = query_pipeline
+            .intersect_shape(isometry(origin, rotation), shape.shape())
+            .filter_map(|(handle, _)| self.collider_entities.get(&handle).copied())
+            .collect();
+        entities.sort_unstable();
+        entities.dedup();
+
+        Ok(PhysicsEvent::Overlaps {
+            request_id,
+            entities,
+        })
+    }
+
+    fn handle_world_command(
+        &mut self,
+        command: PhysicsCommand,
+        _entities: &EntityRegistry,
+    ) -> anyhow::Result<Option<PhysicsEvent>> {
+        match command {
+            PhysicsCommand::MoveCharacter { .. } => Err(anyhow::anyhow!(
+                "the 2d backend doesn't support character controllers"
+            )),
+            _ => Err(anyhow::anyhow!("the 2d backend doesn't support joints")),
+        }
+    }
+}
+
+/// the glam side stays 3d, z is dropped going in and 0 coming out
+impl WorldBody for RigidBody {
+    fn is_dynamic(&self) -> bool {
+        RigidBody::is_dynamic(self)
+    }
+
+    fn is_kinematic(&self) -> bool {
+        self.body_type() == RigidBodyType::KinematicPositionBased
+    }
+
+    fn is_sleeping(&self) -> bool {
+        RigidBody::is_sleeping(self)
+    }
+
+    fn mass(&self) -> f32 {
+        RigidBody::mass(self)
+    }
+
+    fn linvel(&self) -> Vec3 {
+        let linvel = RigidBody::linvel(self);
+        Vec3::new(linvel.x, linvel.y, 0.0)
+    }
+
+    fn angvel(&self) -> Vec3 {
+        Vec3::new(0.0, 0.0, RigidBody::angvel(self))
+    }
+
+    /// z is the entity's own like the scale
+    fn write_pose(&self, transform: &mut Transform3D) {
+        let position = self.position();
+        transform.position.x = position.translation.x;
+        transform.position.y = position.translation.y;
+        transform.rotation = Quat::from_rotation_z(position.rotation.angle());
+    }
+
+    fn set_pose(&mut self, translation: Vec3, rotation: Quat) {
+        self.set_position(isometry(translation, rotation), true);
+    }
+
+    fn set_translation(&mut self, translation: Vec3) {
+        RigidBody::set_translation(self, vec2(translation), true);
+    }
+
+    fn set_rotation(&mut self, rotation: Quat) {
+        RigidBody::set_rotation(self, Rotation::new(angle(rotation)), true);
+    }
+
+    fn set_linvel(&mut self, velocity: Vec3) {
+        RigidBody::set_linvel(self, vec2(velocity), true);
+    }
+
+    fn set_angvel(&mut self, velocity: Vec3) {
+        RigidBody::set_angvel(self, velocity.z, true);
+    }
+
+    fn add_force(&mut self, force: Vec3) {
+        RigidBody::add_force(self, vec2(force), true);
+    }
+
+    fn add_torque(&mut self, torque: Vec3) {
+        RigidBody::add_torque(self, torque.z, true);
+    }
+
+    fn apply_impulse(&mut self, impulse: Vec3) {
+        RigidBody::apply_impulse(self, vec2(impulse), true);
+    }
+
+    fn apply_torque_impulse(&mut self, impulse: Vec3) {
+        RigidBody::apply_torque_impulse(self, impulse.z, true);
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        RigidBody::set_enabled(self, enabled);
+    }
+
+    fn set_gravity_scale(&mut self, scale: f32) {
+        RigidBody::set_gravity_scale(self, scale, true);
+    }
+
+    fn set_linear_damping(&mut self, damping: f32) {
+        RigidBody::set_linear_damping(self, damping);
+    }
+
+    fn set_angular_damping(&mut self, damping: f32) {
+        RigidBody::set_angular_damping(self, damping);
+    }
+
+    fn set_additional_mass(&mut self, mass: AdditionalMass, wake_up: bool) {
+        mass.apply_2d(self, wake_up);
+    }
+
+    fn lock_rotations(&mut self, [_, _, z]: [bool; 3]) {
+        RigidBody::lock_rotations(self, z, true);
+    }
+
+    fn lock_translations(&mut self, [x, y, _]: [bool; 3]) {
+        self.set_enabled_translations(!x, !y, true);
+    }
+
+    fn set_dominance_group(&mut self, group: i8) {
+        RigidBody::set_dominance_group(self, group);
+    }
+
+    fn sleep(&mut self) {
+        RigidBody::sleep(self);
+    }
+
+    fn wake_up(&mut self) {
+        RigidBody::wake_up(self, true);
+    }
+}
+
+impl WorldCollider for Collider {
+    fn scale(&mut self, scale: Vec3) -> anyhow::Result<()> {
+        scale_collider(self, scale.truncate())
+    }
+
+    fn place(&mut self, transform: &Transform3D) {
+        let world = isometry(transform.position, transform.rotation);
+        self.set_position(world * self.position());
+    }
+
+    fn apply_material(&mut self, material: &PhysicsMaterial) {
+        material.apply_2d(self);
+    }
+
+    fn set_friction(&mut self, friction: f32) {
+        Collider::set_friction(self, friction);
+    }
+
+    fn set_restitution(&mut self, restitution: f32) {
+        Collider::set_restitution(self, restitution);
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        Collider::set_enabled(self, enabled);
+    }
+
+    fn set_layer(&mut self, layer: &Layer) {
+        self.set_collision_groups(
+            InteractionGroups::all()
+                .with_memberships(Group::from_bits_truncate(layer.layers))
+                .with_filter(Group::from_bits_truncate(layer.collides_with)),
+        );
+    }
+
+    fn set_contact_force_threshold(&mut self, threshold: f32) {
+        self.set_active_events(self.active_events() | ActiveEvents::CONTACT_FORCE_EVENTS);
+        self.set_contact_force_event_threshold(threshold);
+    }
+
+    fn attach_to(&mut self, id: Uuid) {
+        self.set_active_events(self.active_events() | ActiveEvents::COLLISION_EVENTS);
+        self.user_data = id.as_u128();
+    }
+}
+
+/// z of the entity's transform, 0 once it's gone
+fn depth(entities: &EntityRegistry, id: Uuid) -> f32 {
+    entities
+        .get(&id)
+        .and_then(|e| e.read_with(|e| e.components().get::<Transform3D>().copied()))
+        .map_or(0.0, |t| t.position.z)
+}
+
+fn vec2(v: Vec3) -> Vector<Real> {
+    vector![v.x, v.y]
+}
+
+/// turn around z, the rest of the rotation is dropped
+fn angle(rotation: Quat) -> Real {
+    rotation.to_euler(EulerRot::ZYX).0
+}
+
+fn isometry(translation: Vec3, rotation: Quat) -> Isometry<Real> {
+    Isometry::new(vec2(translation), angle(rotation))
+}
+
+fn flatten_isometry(iso: &rapier3d::prelude::Isometry<Real>) -> Isometry<Real> {
+    let translation = iso.translation.vector;
+    Isometry::new(
+        vector![translation.x, translation.y],
+        angle(Quat::from(iso.rotation)),
+    )
+}
+
+/// the 2d shape a 3d collider leaves in the xy plane, keeping its material and sensor flag
+///
+/// balls, cuboids, round cuboids, capsules and convex hulls can be flattened, anything else
+/// needs a `PhysicsBody::with_collider_2d`
+pub fn flatten_collider(collider: &rapier3d::prelude::Collider) -> anyhow::Result<Collider> {
+    let shape = collider.shape();
+    let builder = if let Some(ball) = shape.as_ball() {
+        ColliderBuilder::ball(ball.radius)
+    } else if let Some(cuboid) = shape.as_cuboid() {
+        ColliderBuilder::cuboid(cuboid.half_extents.x, cuboid.half_extents.y)
+    } else if let Some(rounded) = shape.as_round_cuboid() {
+        let half_extents = rounded.inner_shape.half_extents;
+        ColliderBuilder::round_cuboid(half_extents.x, half_extents.y, rounded.border_radius)
+    } else if let Some(capsule) = shape.as_capsule() {
+        let (a, b) = (capsule.segment.a, capsule.segment.b);
+        ColliderBuilder::capsule_from_endpoints(point![a.x, a.y], point![b.x, b.y], capsule.radius)
+    } else if let Some(hull) = shape.as_convex_polyhedron() {
+        let points: Vec<Point<Real>> = hull.points().iter().map(|p| point![p.x, p.y]).collect();
+        ColliderBuilder::convex_hull(&points)
+            .ok_or(anyhow::anyhow!("convex hull has no area in the xy plane"))?
+    } else {
+        return Err(anyhow::anyhow!(
+            "{:?} colliders can't be flattened",
+            shape.shape_type()
+        ));
+    };
+
+    Ok(builder
+        .position(flatten_isometry(collider.position()))
+        .friction(collider.friction())
+        .restitution(collider.restitution())
+        .density(collider.density())
+        .sensor(collider.is_sensor())
+        .build())
+}
+
+/// the 3d body's type and motion with only what moves in the xy plane
+fn flatten_rigid_body(rb: &rapier3d::prelude::RigidBody) -> RigidBody {
+    let body_type = match rb.body_type() {
+        rapier3d::prelude::RigidBodyType::Dynamic => RigidBodyType::Dynamic,
+        rapier3d::prelude::RigidBodyType::Fixed => RigidBodyType::Fixed,
+        rapier3d::prelude::RigidBodyType::KinematicPositionBased => {
+            RigidBodyType::KinematicPositionBased
+        }
+        rapier3d::prelude::RigidBodyType::KinematicVelocityBased => {
+            RigidBodyType::KinematicVelocityBased
+        }
+    };
+    let mut builder = RigidBodyBuilder::new(body_type)
+        .linvel(vector![rb.linvel().x, rb.linvel().y])
+        .angvel(rb.angvel().z)
+        .linear_damping(rb.linear_damping())
+        .angular_damping(rb.angular_damping())
+        .gravity_scale(rb.gravity_scale())
+        .dominance_group(rb.dominance_group())
+        .ccd_enabled(rb.is_ccd_enabled())
+        .enabled(rb.is_enabled());
+    if rb.is_rotation_locked()[2] {
+        builder = builder.lock_rotations();
+    }
+    builder.build()
+}
+
+/// the 3d backend's `scale_collider` in the xy plane
+fn scale_collider(collider: &mut Collider, scale: Vec2) -> anyhow::Result<()> {
+    if scale == Vec2::ONE {
+        return Ok(());
+    }
+
+    let scale = vector![scale.x, scale.y];
+    let shape = collider
+        .shape()
+        .scale_dyn(&scale, SCALE_SUBDIVISIONS)
+        .ok_or(anyhow::anyhow!(
+            "{:?} colliders can't be scaled by {scale:?}",
+            collider.shape().shape_type()
+        ))?;
+    collider.set_shape(SharedShape(Arc::from(shape)));
+
+    let mut position = *collider.position();
+    position.translation.vector.component_mul_assign(&scale);
+    collider.set_position(position);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{atomic::AtomicUsize, mpsc::Receiver};
+
+    use super::*;
+    use crate::engine::{component::ComponentSet, entity::tests::Dummy};
+
+    fn engine(entities: &EntityRegistry) -> (Rapier2dEngine, Receiver<PhysicsEvent>) {
+        let (_command_tx, command_rx) = std::sync::mpsc::channel();
+        let (event_tx, event_rx) = std::sync::mpsc::channel();
+        let engine = Rapier2dEngine::new(
+            Vec3::new(0.0, -9.81, 0.0),
+            entities.clone(),
+            command_rx,
+            event_tx,
+            Arc::new(AtomicUsize::new(0)),
+            PhysicsSettings::default(),
+        );
+        (engine, event_rx)
+    }
+
+    fn spawn(
+        entities: &mut EntityRegistry,
+        position: Vec3,
+        collider: rapier3d::prelude::Collider,
+        rigid_body: rapier3d::prelude::RigidBody,
+    ) -> Uuid {
+        let mut components = ComponentSet::new();
+        components.add(Transform3D::new(position, Quat::IDENTITY, Vec3::ONE));
+        components.add(PhysicsBody::new(collider, rigid_body));
+        let entity = Dummy::new(components);
+        let id = entity.id();
+        entities.add(entity.into_container());
+        id
+    }
+
+    fn position(entities: &EntityRegistry, id: Uuid) -> Vec3 {
+        entities
+            .get(&id)
+            .unwrap()
+            .read_with(|e| e.components().get::<Transform3D>().unwrap().position)
+    }
+
+    #[test]
+    fn ball_lands_and_keeps_its_z() {
+        use rapier3d::prelude::{ColliderBuilder, RigidBodyBuilder};
+
+        let mut entities = EntityRegistry::new();
+        spawn(
+            &mut entities,
+            Vec3::new(0.0, 0.0, 5.0),
+            ColliderBuilder::cuboid(10.0, 0.5, 10.0).build(),
+            RigidBodyBuilder::fixed().build(),
+        );
+        // a different z from the ground, it's dropped in the simulation anyway
+        let ball = spawn(
+            &mut entities,
+            Vec3::new(0.0, 3.0, -2.0),
+            ColliderBuilder::ball(0.5).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        let (mut engine, _events) = engine(&entities);
+
+        for _ in 0..180 {
+            engine.step().unwrap();
+        }
+
+        let position = position(&entities, ball);
+        assert!((position.y - 1.0).abs() < 0.05, "{position}");
+        assert_eq!(position.z, -2.0);
+    }
+
+    #[test]
+    fn velocity_z_is_dropped() {
+        use rapier3d::prelude::{ColliderBuilder, RigidBodyBuilder};
+
+        let mut entities = EntityRegistry::new();
+        let ball = spawn(
+            &mut entities,
+            Vec3::new(0.0, 0.0, 1.0),
+            ColliderBuilder::ball(0.5).build(),
+            RigidBodyBuilder::dynamic().gravity_scale(0.0).build(),
+        );
+        let (mut engine, events) = engine(&entities);
+        engine
+            .handle_command(PhysicsCommand::SetLinearVelocity {
+                id: ball,
+                velocity: Vec3::new(2.0, 0.0, 7.0),
+            })
+            .unwrap();
+        engine.step().unwrap();
+
+        let request_id = Uuid::new_v4();
+        engine
+            .handle_command(PhysicsCommand::QueryBody {
+                id: ball,
+                request_id,
+            })
+            .unwrap();
+        let linvel = events
+            .try_iter()
+            .find_map(|e| match e {
+                PhysicsEvent::BodyState { linvel, .. } => Some(linvel),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(linvel, Vec3::new(2.0, 0.0, 0.0));
+
+        let position = position(&entities, ball);
+        assert!(position.x > 0.0);
+        assert_eq!(position.z, 1.0);
+    }
+}
@@ -1,27 +1,103 @@
-use std::sync::mpsc::{Receiver, Sender};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, RwLock},
+};
 
 use glam::{Quat, Vec3};
 use rapier3d::prelude::*;
 use uuid::Uuid;
 
 use crate::{
-    engine::entity::EntityRegistry,
+    engine::{
+        component::{Layer, Transform3D},
+        entity::EntityRegistry,
+    },
     physics::{
-        PhysicsBody, RigidBodyState,
+        AdditionalMass, CharacterController, PhysicsBody, PhysicsMaterial, RigidBodyState,
+        StaticCollider,
         commands::{PhysicsCommand, PhysicsEvent},
+        debug_render::{LineCollector, PhysicsDebugLines},
+        settings::PhysicsSettings,
+        simulator::{Simulator, World, WorldBody, WorldCollider, with_body_state},
     },
 };
 
-pub struct RapierEngine {
-    pub gravity: Vec3,
+/// subdivisions used when a shape has to be approximated to scale it
+pub(crate) const SCALE_SUBDIVISIONS: u32 = 16;
+
+/// the default physics backend, rapier in 3d
+pub type RapierEngine = Simulator<RapierWorld>;
+
+/// a contact force over a collider's threshold, with the contact point that took the biggest
+/// impulse in world space
+struct ContactForce {
+    collider1: ColliderHandle,
+    collider2: ColliderHandle,
+    magnitude: f32,
+    largest_contact_point: Vec3,
+}
+
+/// collects collision events during a step, rapier calls it from the solver threads
+#[derive(Default)]
+struct CollisionCollector {
+    events: Mutex<Vec<CollisionEvent>>,
+    forces: Mutex<Vec<ContactForce>>,
+}
+
+impl EventHandler for CollisionCollector {
+    fn handle_collision_event(
+        &self,
+        _bodies: &RigidBodySet,
+        _colliders: &ColliderSet,
+        event: CollisionEvent,
+        _contact_pair: Option<&ContactPair>,
+    ) {
+        self.events.lock().unwrap().push(event);
+    }
 
-    command_receiver: Receiver<PhysicsCommand>,
-    event_sender: Sender<PhysicsEvent>,
+    fn handle_contact_force_event(
+        &self,
+        _dt: Real,
+        _bodies: &RigidBodySet,
+        colliders: &ColliderSet,
+        contact_pair: &ContactPair,
+        total_force_magnitude: Real,
+    ) {
+        let largest = contact_pair
+            .manifolds
+            .iter()
+            .flat_map(|m| m.points.iter())
+            .max_by(|a, b| a.data.impulse.total_cmp(&b.data.impulse));
+        let largest_contact_point = match (largest, colliders.get(contact_pair.collider1)) {
+            (Some(point), Some(collider)) => Vec3::from(collider.position() * point.local_p1),
+            _ => Vec3::ZERO,
+        };
 
-    entities: EntityRegistry,
+        self.forces.lock().unwrap().push(ContactForce {
+            collider1: contact_pair.collider1,
+            collider2: contact_pair.collider2,
+            magnitude: total_force_magnitude,
+            largest_contact_point,
+        });
+    }
+}
 
+/// rapier's 3d sets and pipeline, with joints, character controllers and carriers
+pub struct RapierWorld {
     pub rigid_body_set: RigidBodySet,
     pub collider_set: ColliderSet,
+    /// entity each collider belongs to, kept after a collider is removed until its last
+    /// collision event has gone out
+    collider_entities: HashMap<ColliderHandle, Uuid>,
+    /// rigid body of every entity in the simulation, works after the entity is despawned
+    entity_bodies: HashMap<Uuid, RigidBodyHandle>,
+    /// parentless colliders of every entity with a `StaticCollider`
+    static_colliders: HashMap<Uuid, Vec<ColliderHandle>>,
+    /// joints made through commands, dropped when either body is removed
+    joints: HashMap<Uuid, ImpulseJointHandle>,
+    collisions: CollisionCollector,
+    /// outlines for the renderer, only drawn while the shared lines are enabled
+    debug_render: Option<(DebugRenderPipeline, Arc<RwLock<PhysicsDebugLines>>)>,
 
     integration_parameters: IntegrationParameters,
     physics_pipeline: PhysicsPipeline,
@@ -33,83 +109,462 @@ pub struct RapierEngine {
     ccd_solver: CCDSolver,
 }
 
-impl RapierEngine {
-    pub fn new(
-        gravity: Vec3,
-        entities: EntityRegistry,
-        command_receiver: Receiver<PhysicsCommand>,
-        event_sender: Sender<PhysicsEvent>,
-    ) -> Self {
-        let mut rigid_body_set = RigidBodySet::new();
-        let mut collider_set = ColliderSet::new();
+impl Default for RapierWorld {
+    fn default() -> Self {
+        Self {
+            rigid_body_set: RigidBodySet::new(),
+            collider_set: ColliderSet::new(),
+            collider_entities: HashMap::new(),
+            entity_bodies: HashMap::new(),
+            static_colliders: HashMap::new(),
+            joints: HashMap::new(),
+            collisions: CollisionCollector::default(),
+            debug_render: None,
+            integration_parameters: IntegrationParameters::default(),
+            physics_pipeline: PhysicsPipeline::new(),
+            island_manager: IslandManager::new(),
+            broad_phase: DefaultBroadPhase::new(),
+            narrow_phase: NarrowPhase::new(),
+            impulse_joint_set: ImpulseJointSet::new(),
+            multibody_joint_set: MultibodyJointSet::new(),
+            ccd_solver: CCDSolver::new(),
+        }
+    }
+}
+
+impl RapierWorld {
+    /// moves position based kinematic bodies to where their entity's transform is
+    fn drive_kinematic_bodies(&mut self, gravity: Vec3, entities: &EntityRegistry) {
+        let mut carried: Vec<(RigidBodyHandle, Isometry<Real>)> = Vec::new();
 
         for e in entities.clone().into_iter() {
-            let transform = e.lock().unwrap().transform();
-            let mut entity = e.lock().unwrap();
-            let body: &mut PhysicsBody = match entity.components_mut().get_mut::<PhysicsBody>() {
-                Some(pb) => pb,
-                None => {
-                    continue;
-                }
+            let target = e.read_with(|e| {
+                let pb = e.components().get::<PhysicsBody>()?;
+                let RigidBodyState::Active(handle) = pb.rigid_body else {
+                    return None;
+                };
+                let transform = e.components().get::<Transform3D>()?;
+                Some((handle, pb.carrier, transform.position, transform.rotation))
+            });
+            let Some((handle, carrier, position, rotation)) = target else {
+                continue;
             };
-            let rigid_body = match &mut body.rigid_body {
-                RigidBodyState::Pending(rb) => rb,
-                RigidBodyState::Active(_) => {
-                    log::debug!(
-                        "Weird: entity body skipped in rapier engine creation because rigid body is already active"
-                    );
+            let kinematic = self
+                .rigid_body_set
+                .get(handle)
+                .is_some_and(|rb| rb.body_type() == RigidBodyType::KinematicPositionBased);
+            if !kinematic {
+                continue;
+            }
+            let target: Isometry<Real> = (position, rotation).into();
+
+            if !carrier {
+                self.rigid_body_set[handle].set_next_kinematic_position(target);
+                continue;
+            }
+
+            // carriers jump there without a velocity, otherwise friction would drag the riders
+            // along on top of the carry
+            let riders = self.riders(handle, gravity);
+            let rb = &mut self.rigid_body_set[handle];
+            let displacement = target * rb.position().inverse();
+            rb.set_position(target, true);
+            carried.extend(riders.into_iter().map(|rider| (rider, displacement)));
+        }
+
+        for (rider, displacement) in carried {
+            if let Some(rb) = self.rigid_body_set.get_mut(rider) {
+                let position = displacement * rb.position();
+                rb.set_position(position, true);
+            }
+        }
+    }
+
+    /// dynamic bodies touching the top of the body's colliders as of the last step, against
+    /// gravity is up
+    fn riders(&self, carrier: RigidBodyHandle, gravity: Vec3) -> Vec<RigidBodyHandle> {
+        let up = (-gravity).normalize_or(Vec3::Y);
+        let Some(rb) = self.rigid_body_set.get(carrier) else {
+            return Vec::new();
+        };
+
+        let mut riders = Vec::new();
+        for &collider in rb.colliders() {
+            for pair in self.narrow_phase.contact_pairs_with(collider) {
+                // manifold normals point from collider1 to collider2
+                let (other, sign) = match pair.collider1 == collider {
+                    true => (pair.collider2, 1.0),
+                    false => (pair.collider1, -1.0),
+                };
+                let Some(body) = self.collider_set.get(other).and_then(|c| c.parent()) else {
                     continue;
-                }
-                RigidBodyState::Removed => {
-                    log::debug!(
-                        "Weird: entity body skipped in rapier engine creation because it has been removed"
-                    );
+                };
+                if !self
+                    .rigid_body_set
+                    .get(body)
+                    .is_some_and(|b| b.is_dynamic())
+                {
                     continue;
                 }
+                let on_top = pair.manifolds.iter().any(|m| {
+                    !m.data.solver_contacts.is_empty()
+                        && (Vec3::from(m.data.normal) * sign).dot(up) > 0.7
+                });
+                if on_top && !riders.contains(&body) {
+                    riders.push(body);
+                }
+            }
+        }
+        riders
+    }
+
+    /// redraws the debug lines if the renderer wants them
+    fn render_debug_lines(&mut self) {
+        let Some((pipeline, shared)) = self.debug_render.as_mut() else {
+            return;
+        };
+        if !shared.read().unwrap().enabled {
+            return;
+        }
+
+        let _span = tracy_client::span!("physics debug render");
+        // drawn outside the lock so the renderer isn't kept waiting
+        let mut collector = LineCollector::default();
+        pipeline.render(
+            &mut collector,
+            &self.rigid_body_set,
+            &self.collider_set,
+            &self.impulse_joint_set,
+            &self.multibody_joint_set,
+            &self.narrow_phase,
+        );
+        let mut shared = shared.write().unwrap();
+        if shared.lines != collector.lines {
+            shared.lines = collector.lines;
+            shared.revision += 1;
+        }
+    }
+
+    /// turns the collisions and contact forces from the last step into `PhysicsEvent`s
+    fn collision_events(&mut self) -> Vec<PhysicsEvent> {
+        let mut physics_events = Vec::new();
+
+        let events = std::mem::take(&mut *self.collisions.events.lock().unwrap());
+        for event in events {
+            let (h1, h2) = (event.collider1(), event.collider2());
+            let (Some(a), Some(b)) = (
+                self.collider_entities.get(&h1).copied(),
+                self.collider_entities.get(&h2).copied(),
+            ) else {
+                log::debug!("collision event for a collider without an entity");
+                continue;
             };
 
-            rigid_body.set_position((transform.position, transform.rotation).into(), true);
+            physics_events.push(match event {
+                CollisionEvent::Started(..) => PhysicsEvent::CollisionStarted { a, b },
+                CollisionEvent::Stopped(..) => PhysicsEvent::CollisionEnded { a, b },
+            });
+        }
+
+        let forces = std::mem::take(&mut *self.collisions.forces.lock().unwrap());
+        for force in forces {
+            let (Some(a), Some(b)) = (
+                self.collider_entities.get(&force.collider1).copied(),
+                self.collider_entities.get(&force.collider2).copied(),
+            ) else {
+                log::debug!("contact force event for a collider without an entity");
+                continue;
+            };
 
-            let rb_handle = rigid_body_set.insert(rigid_body.clone());
-            body.rigid_body = RigidBodyState::Active(rb_handle);
-            collider_set.insert_with_parent(body.collider.clone(), rb_handle, &mut rigid_body_set);
+            physics_events.push(PhysicsEvent::ContactForce {
+                a,
+                b,
+                force_magnitude: force.magnitude,
+                largest_contact_point: force.largest_contact_point,
+            });
         }
 
-        Self {
-            gravity,
-            command_receiver,
-            event_sender,
-            entities,
-            rigid_body_set,
-            collider_set,
-            integration_parameters: IntegrationParameters::default(),
-            physics_pipeline: PhysicsPipeline::new(),
-            island_manager: IslandManager::new(),
-            broad_phase: DefaultBroadPhase::new(),
-            narrow_phase: NarrowPhase::new(),
-            impulse_joint_set: ImpulseJointSet::new(),
-            multibody_joint_set: MultibodyJointSet::new(),
-            ccd_solver: CCDSolver::new(),
+        // removed colliders' last events have gone out now
+        let colliders = &self.collider_set;
+        self.collider_entities
+            .retain(|handle, _| colliders.get(*handle).is_some());
+
+        physics_events
+    }
+
+    /// runs rapier's character controller against the colliders as of the last step and puts
+    /// the result in the transform, which the kinematic body then follows
+    fn move_character(
+        &mut self,
+        entities: &EntityRegistry,
+        id: Uuid,
+        desired_translation: Vec3,
+    ) -> anyhow::Result<PhysicsEvent> {
+        let entity = entities
+            .get(&id)
+            .ok_or(anyhow::anyhow!("no entity with provided id found"))?;
+        let (controller, transform) = entity.read_with(|e| {
+            (
+                e.components().get::<CharacterController>().cloned(),
+                e.components().get::<Transform3D>().copied(),
+            )
+        });
+        let controller = controller.ok_or(anyhow::anyhow!("entity has no character controller"))?;
+        let transform = transform.ok_or(anyhow::anyhow!("character has no transform"))?;
+
+        let handle = body_handle(entities, id)?;
+        let rb = &self.rigid_body_set[handle];
+        if rb.body_type() != RigidBodyType::KinematicPositionBased {
+            return Err(anyhow::anyhow!(
+                "character controllers need a kinematic position based body"
+            ));
         }
+        let collider = rb
+            .colliders()
+            .first()
+            .map(|h| &self.collider_set[*h])
+            .ok_or(anyhow::anyhow!("character body has no collider"))?;
+
+        let body_pos: Isometry<Real> = (transform.position, transform.rotation).into();
+        let character_pos = body_pos * collider.position_wrt_parent().copied().unwrap_or_default();
+        let query_pipeline = self.broad_phase.as_query_pipeline(
+            self.narrow_phase.query_dispatcher(),
+            &self.rigid_body_set,
+            &self.collider_set,
+            QueryFilter::default().exclude_rigid_body(handle),
+        );
+        let movement = controller.kinematic_controller().move_shape(
+            self.integration_parameters.dt,
+            &query_pipeline,
+            collider.shape(),
+            &character_pos,
+            desired_translation.into(),
+            |_| {},
+        );
+
+        let translation = Vec3::from(movement.translation);
+        entity.write_with(|e| {
+            if let Some(transform) = e.components_mut().get_mut::<Transform3D>() {
+                transform.position += translation;
+            }
+        });
+        Ok(PhysicsEvent::CharacterMoved {
+            id,
+            translation,
+            grounded: movement.grounded,
+        })
     }
 
-    pub fn step(&mut self, delta: f64) -> anyhow::Result<()> {
-        let physics_hooks = ();
-        let event_handler = ();
+    fn create_joint(
+        &mut self,
+        entities: &EntityRegistry,
+        a: Uuid,
+        b: Uuid,
+        joint: impl Into<GenericJoint>,
+    ) -> anyhow::Result<PhysicsEvent> {
+        let body_a = body_handle(entities, a)?;
+        let body_b = body_handle(entities, b)?;
+        let handle = self.impulse_joint_set.insert(body_a, body_b, joint, true);
 
-        let commands: Vec<PhysicsCommand> = self.command_receiver.try_iter().collect();
+        let joint_id = Uuid::new_v4();
+        self.joints.insert(joint_id, handle);
+        Ok(PhysicsEvent::JointCreated { joint_id, a, b })
+    }
 
-        for pc in commands {
-            match self.handle_command(pc) {
-                Ok(()) => (),
-                Err(e) => {
-                    log::debug!("skipped physics command: {}", e);
-                }
+    fn remove_joint(&mut self, joint_id: Uuid) -> anyhow::Result<()> {
+        let handle = self
+            .joints
+            .remove(&joint_id)
+            .ok_or(anyhow::anyhow!("no joint with id {joint_id}"))?;
+        self.impulse_joint_set.remove(handle, true);
+        Ok(())
+    }
+
+    fn set_joint_motor(
+        &mut self,
+        joint_id: Uuid,
+        target_velocity: f32,
+        factor: f32,
+    ) -> anyhow::Result<()> {
+        let joint = self
+            .joints
+            .get(&joint_id)
+            .and_then(|h| self.impulse_joint_set.get_mut(*h, true))
+            .ok_or(anyhow::anyhow!("no joint with id {joint_id}"))?;
+
+        // the free axis is the one a revolute joint turns around or a prismatic one slides on
+        let locked = joint.data.locked_axes;
+        let axis = if !locked.contains(JointAxesMask::LIN_X) {
+            JointAxis::LinX
+        } else if !locked.contains(JointAxesMask::ANG_X) {
+            JointAxis::AngX
+        } else {
+            return Err(anyhow::anyhow!(
+                "joint {joint_id} has no free axis to drive"
+            ));
+        };
+        joint.data.set_motor_velocity(axis, target_velocity, factor);
+        Ok(())
+    }
+}
+
+impl World for RapierWorld {
+    type Body = RigidBody;
+    type Collider = Collider;
+
+    fn configure(&mut self, dt: Option<f32>, settings: &PhysicsSettings) {
+        if let Some(dt) = dt {
+            self.integration_parameters.dt = dt;
+        }
+        settings.apply(&mut self.integration_parameters);
+    }
+
+    fn dt(&self) -> f32 {
+        self.integration_parameters.dt
+    }
+
+    fn build_body(
+        &self,
+        rigid_body: &RigidBody,
+        body: &PhysicsBody,
+    ) -> anyhow::Result<(RigidBody, Vec<Collider>)> {
+        let colliders = body
+            .colliders
+            .iter()
+            .map(|(collider, offset)| {
+                let mut collider = collider.clone();
+                // a collider's own position is relative to the body once it has a parent
+                collider.set_position(offset * collider.position());
+                collider
+            })
+            .collect();
+        Ok((rigid_body.clone(), colliders))
+    }
+
+    fn build_static_colliders(&self, _id: Uuid, statics: &StaticCollider) -> Vec<Collider> {
+        statics
+            .colliders
+            .iter()
+            .map(|(collider, offset)| {
+                let mut collider = collider.clone();
+                collider.set_position(offset * collider.position());
+                collider
+            })
+            .collect()
+    }
+
+    fn insert_body(
+        &mut self,
+        id: Uuid,
+        body: RigidBody,
+        colliders: Vec<Collider>,
+    ) -> RigidBodyState {
+        let rb_handle = self.rigid_body_set.insert(body);
+        for collider in colliders {
+            let collider_handle =
+                self.collider_set
+                    .insert_with_parent(collider, rb_handle, &mut self.rigid_body_set);
+            self.collider_entities.insert(collider_handle, id);
+        }
+        self.entity_bodies.insert(id, rb_handle);
+        RigidBodyState::Active(rb_handle)
+    }
+
+    fn insert_static_colliders(&mut self, id: Uuid, colliders: Vec<Collider>) {
+        let handles: Vec<ColliderHandle> = colliders
+            .into_iter()
+            .map(|collider| {
+                let handle = self.collider_set.insert(collider);
+                self.collider_entities.insert(handle, id);
+                handle
+            })
+            .collect();
+        self.static_colliders.entry(id).or_default().extend(handles);
+    }
+
+    fn remove_body(&mut self, id: Uuid) -> anyhow::Result<bool> {
+        let statics = self.static_colliders.remove(&id);
+        for handle in statics.iter().flatten() {
+            self.collider_set.remove(
+                *handle,
+                &mut self.island_manager,
+                &mut self.rigid_body_set,
+                true,
+            );
+        }
+        let handle = match self.entity_bodies.remove(&id) {
+            Some(handle) => handle,
+            None if statics.is_some() => return Ok(false),
+            None => return Err(anyhow::anyhow!("no rigid body for entity {id}")),
+        };
+        self.rigid_body_set.remove(
+            handle,
+            &mut self.island_manager,
+            &mut self.collider_set,
+            &mut self.impulse_joint_set,
+            &mut self.multibody_joint_set,
+            true,
+        );
+        let joints = &self.impulse_joint_set;
+        self.joints
+            .retain(|_, handle| joints.get(*handle).is_some());
+        Ok(true)
+    }
+
+    fn body<'a>(&'a self, state: &'a RigidBodyState) -> Option<&'a RigidBody> {
+        match state {
+            RigidBodyState::Active(handle) => self.rigid_body_set.get(*handle),
+            RigidBodyState::Pending(rb) => Some(rb),
+            RigidBodyState::Removed | RigidBodyState::Active2D(_) => None,
+        }
+    }
+
+    fn body_mut(&mut self, state: &RigidBodyState) -> anyhow::Result<&mut RigidBody> {
+        let handle = active_handle(state)?;
+        self.rigid_body_set
+            .get_mut(handle)
+            .ok_or(anyhow::anyhow!("rigid body handle leads to no rigid body"))
+    }
+
+    fn static_colliders_mut(&mut self, id: Uuid, op: &mut dyn FnMut(&mut Collider)) -> bool {
+        let Some(handles) = self.static_colliders.get(&id) else {
+            return false;
+        };
+        handles
+            .iter()
+            .filter_map(|h| self.collider_set.get_mut(*h))
+            .for_each(op);
+        true
+    }
+
+    fn body_colliders_mut(
+        &mut self,
+        state: &RigidBodyState,
+        op: &mut dyn FnMut(&mut Collider),
+    ) -> anyhow::Result<()> {
+        let colliders = self.body_mut(state)?.colliders().to_vec();
+        colliders
+            .into_iter()
+            .filter_map(|h| self.collider_set.get_mut(h))
+            .for_each(op);
+        Ok(())
+    }
+
+    fn wake_dynamic_bodies(&mut self) {
+        for (_, rb) in self.rigid_body_set.iter_mut() {
+            if rb.is_dynamic() {
+                rb.wake_up(true);
             }
         }
+    }
+
+    fn step(&mut self, gravity: Vec3, entities: &EntityRegistry) -> Vec<PhysicsEvent> {
+        let physics_hooks = ();
+
+        self.drive_kinematic_bodies(gravity, entities);
 
         self.physics_pipeline.step(
-            &self.gravity.into(),
+            &gravity.into(),
             &self.integration_parameters,
             &mut self.island_manager,
             &mut self.broad_phase,
@@ -120,147 +575,2134 @@ impl RapierEngine {
             &mut self.multibody_joint_set,
             &mut self.ccd_solver,
             &physics_hooks,
-            &event_handler,
+            &self.collisions,
         );
+        let events = self.collision_events();
+        self.render_debug_lines();
+        events
+    }
 
-        for e in self.entities.clone().into_iter() {
-            let _span = tracy_client::span!("modifying entities");
-            let mut entity = e.lock().unwrap();
-            let pb = match entity.components().get::<PhysicsBody>() {
-                Some(pb) => pb,
-                None => continue,
-            };
-            let rb = match &pb.rigid_body {
-                RigidBodyState::Active(handle) => self.rigid_body_set.get(*handle).unwrap(),
-                RigidBodyState::Pending(rb) => rb,
-                RigidBodyState::Removed => {
-                    log::debug!("skipped update for removed rigid body");
-                    continue;
-                }
-            };
+    fn dynamic_bodies(&self) -> Vec<(Uuid, bool)> {
+        self.entity_bodies
+            .iter()
+            .filter_map(|(id, handle)| {
+                let rb = self.rigid_body_set.get(*handle)?;
+                rb.is_dynamic().then(|| (*id, rb.is_sleeping()))
+            })
+            .collect()
+    }
 
-            let rb_pos = *rb.position();
+    fn counts(&self) -> (usize, usize) {
+        let contact_pairs = self
+            .narrow_phase
+            .contact_pairs()
+            .filter(|pair| pair.has_any_active_contact)
+            .count();
+        (
+            self.island_manager.active_dynamic_bodies().len(),
+            contact_pairs,
+        )
+    }
 
-            entity.transform_mut().position = Vec3 {
-                x: rb_pos.translation.x,
-                y: rb_pos.translation.y,
-                z: rb_pos.translation.z,
-            };
+    fn set_debug_lines(&mut self, lines: Arc<RwLock<PhysicsDebugLines>>) {
+        self.debug_render = Some((DebugRenderPipeline::default(), lines));
+    }
+
+    /// casts against the colliders as of the last step, the bvh broad phase doubles as the
+    /// query pipeline so it's always up to date
+    fn raycast(&self, origin: Vec3, dir: Vec3, max_toi: f32, request_id: Uuid) -> PhysicsEvent {
+        let dir = dir.normalize_or_zero();
+        let hit = if dir == Vec3::ZERO {
+            None
+        } else {
+            let query_pipeline = self.broad_phase.as_query_pipeline(
+                self.narrow_phase.query_dispatcher(),
+                &self.rigid_body_set,
+                &self.collider_set,
+                QueryFilter::default(),
+            );
+            query_pipeline.cast_ray_and_get_normal(
+                &Ray::new(origin.into(), dir.into()),
+                max_toi,
+                true,
+            )
+        };
 
-            entity.transform_mut().rotation = Quat::from(rb_pos.rotation);
+        match hit {
+            Some((handle, intersection)) => PhysicsEvent::RaycastHit {
+                request_id,
+                entity: self.collider_entities.get(&handle).copied(),
+                point: origin + dir * intersection.time_of_impact,
+                normal: Vec3::from(intersection.normal),
+            },
+            None => PhysicsEvent::RaycastMiss { request_id },
         }
+    }
 
-        Ok(())
+    /// sweeps the collider's shape like `raycast`, `toi` is a distance since `dir` is normalized
+    fn shape_cast(
+        &self,
+        shape: &Collider,
+        origin: Vec3,
+        rotation: Quat,
+        dir: Vec3,
+        max_toi: f32,
+        request_id: Uuid,
+    ) -> anyhow::Result<PhysicsEvent> {
+        let dir = dir.normalize_or_zero();
+        let shape_pos: Isometry<Real> = (origin, rotation).into();
+        let hit = if dir == Vec3::ZERO {
+            None
+        } else {
+            let query_pipeline = self.broad_phase.as_query_pipeline(
+                self.narrow_phase.query_dispatcher(),
+                &self.rigid_body_set,
+                &self.collider_set,
+                QueryFilter::default(),
+            );
+            query_pipeline.cast_shape(
+                &shape_pos,
+                &dir.into(),
+                shape.shape(),
+                ShapeCastOptions::with_max_time_of_impact(max_toi),
+            )
+        };
+
+        Ok(match hit {
+            Some((handle, hit)) => {
+                // rapier gives the hit collider's side in world space already, the cast shape's
+                // is local to it
+                let shape_pos_at_hit: Isometry<Real> =
+                    (origin + dir * hit.time_of_impact, rotation).into();
+                PhysicsEvent::ShapeCastHit {
+                    request_id,
+                    entity: self.collider_entities.get(&handle).copied(),
+                    toi: hit.time_of_impact,
+                    witness_hit: Vec3::from(hit.witness1),
+                    witness_shape: Vec3::from(shape_pos_at_hit * hit.witness2),
+                    normal: Vec3::from(*hit.normal1),
+                }
+            }
+            None => PhysicsEvent::ShapeCastMiss { request_id },
+        })
+    }
+
+    /// every entity with a collider overlapping the shape, each one listed once
+    fn overlap_shape(
+        &self,
+        shape: &Collider,
+        origin: Vec3,
+        rotation: Quat,
+        request_id: Uuid,
+    ) -> anyhow::Result<PhysicsEvent> {
+        let query_pipeline = self.broad_phase.as_query_pipeline(
+            self.narrow_phase.query_dispatcher(),
+            &self.rigid_body_set,
+            &self.collider_set,
+            QueryFilter::default(),
+        );
+        let mut entities: Vec<Uuid> = query_pipeline
+            .intersect_shape((origin, rotation).into(), shape.shape())
+            .filter_map(|(handle, _)| self.collider_entities.get(&handle).copied())
+            .collect();
+        entities.sort_unstable();
+        entities.dedup();
+
+        Ok(PhysicsEvent::Overlaps {
+            request_id,
+            entities,
+        })
     }
 
-    fn handle_command(&mut self, command: PhysicsCommand) -> anyhow::Result<()> {
-        let _span = tracy_client::span!("handling command");
+    fn handle_world_command(
+        &mut self,
+        command: PhysicsCommand,
+        entities: &EntityRegistry,
+    ) -> anyhow::Result<Option<PhysicsEvent>> {
         match command {
-            PhysicsCommand::ApplyForce { id, force } => self.apply_force(id, force),
-            PhysicsCommand::ApplyTorque { id, torque } => self.apply_torque(id, torque),
-            PhysicsCommand::ApplyImpulse { id, impulse } => self.apply_impulse(id, impulse),
-            PhysicsCommand::ApplyTorqueImpulse { id, impulse } => {
-                self.apply_torque_impulse(id, impulse)
+            PhysicsCommand::CreateFixedJoint {
+                a,
+                b,
+                anchor_a,
+                anchor_b,
+            } => self
+                .create_joint(
+                    entities,
+                    a,
+                    b,
+                    FixedJointBuilder::new()
+                        .local_anchor1(anchor_a.into())
+                        .local_anchor2(anchor_b.into()),
+                )
+                .map(Some),
+            PhysicsCommand::CreateRevoluteJoint {
+                a,
+                b,
+                anchor_a,
+                anchor_b,
+                axis,
+                limits,
+            } => {
+                let mut joint = RevoluteJointBuilder::new(joint_axis(axis)?)
+                    .local_anchor1(anchor_a.into())
+                    .local_anchor2(anchor_b.into());
+                if let Some(limits) = limits {
+                    joint = joint.limits(limits);
+                }
+                self.create_joint(entities, a, b, joint).map(Some)
             }
-            PhysicsCommand::SetLinearVelocity { id, velocity } => {
-                self.set_linear_velocity(id, velocity)
+            PhysicsCommand::CreateSphericalJoint {
+                a,
+                b,
+                anchor_a,
+                anchor_b,
+            } => self
+                .create_joint(
+                    entities,
+                    a,
+                    b,
+                    SphericalJointBuilder::new()
+                        .local_anchor1(anchor_a.into())
+                        .local_anchor2(anchor_b.into()),
+                )
+                .map(Some),
+            PhysicsCommand::CreatePrismaticJoint {
+                a,
+                b,
+                anchor_a,
+                anchor_b,
+                axis,
+                limits,
+            } => {
+                let mut joint = PrismaticJointBuilder::new(joint_axis(axis)?)
+                    .local_anchor1(anchor_a.into())
+                    .local_anchor2(anchor_b.into());
+                if let Some(limits) = limits {
+                    joint = joint.limits(limits);
+                }
+                self.create_joint(entities, a, b, joint).map(Some)
             }
-            PhysicsCommand::SetAngularVelocity { id, velocity } => {
-                self.set_angular_velocity(id, velocity)
+            PhysicsCommand::RemoveJoint { joint_id } => self.remove_joint(joint_id).map(|_| None),
+            PhysicsCommand::SetJointMotor {
+                joint_id,
+                target_velocity,
+                factor,
+            } => self
+                .set_joint_motor(joint_id, target_velocity, factor)
+                .map(|_| None),
+            PhysicsCommand::MoveCharacter {
+                id,
+                desired_translation,
+            } => self
+                .move_character(entities, id, desired_translation)
+                .map(Some),
+            command => Err(anyhow::anyhow!("{command:?} isn't handled by the 3d world")),
+        }
+    }
+}
+
+impl WorldBody for RigidBody {
+    fn is_dynamic(&self) -> bool {
+        RigidBody::is_dynamic(self)
+    }
+
+    fn is_kinematic(&self) -> bool {
+        self.body_type() == RigidBodyType::KinematicPositionBased
+    }
+
+    fn is_sleeping(&self) -> bool {
+        RigidBody::is_sleeping(self)
+    }
+
+    fn mass(&self) -> f32 {
+        RigidBody::mass(self)
+    }
+
+    fn linvel(&self) -> Vec3 {
+        Vec3::from(*RigidBody::linvel(self))
+    }
+
+    fn angvel(&self) -> Vec3 {
+        Vec3::from(*RigidBody::angvel(self))
+    }
+
+    fn write_pose(&self, transform: &mut Transform3D) {
+        let position = self.position();
+        transform.position = Vec3::from(position.translation.vector);
+        transform.rotation = Quat::from(position.rotation);
+    }
+
+    fn set_pose(&mut self, translation: Vec3, rotation: Quat) {
+        self.set_position((translation, rotation).into(), true);
+    }
+
+    fn set_translation(&mut self, translation: Vec3) {
+        RigidBody::set_translation(self, translation.into(), true);
+    }
+
+    fn set_rotation(&mut self, rotation: Quat) {
+        RigidBody::set_rotation(self, rotation.into(), true);
+    }
+
+    fn set_linvel(&mut self, velocity: Vec3) {
+        RigidBody::set_linvel(self, velocity.into(), true);
+    }
+
+    fn set_angvel(&mut self, velocity: Vec3) {
+        RigidBody::set_angvel(self, velocity.into(), true);
+    }
+
+    fn add_force(&mut self, force: Vec3) {
+        RigidBody::add_force(self, force.into(), true);
+    }
+
+    fn add_torque(&mut self, torque: Vec3) {
+        RigidBody::add_torque(self, torque.into(), true);
+    }
+
+    fn apply_impulse(&mut self, impulse: Vec3) {
+        RigidBody::apply_impulse(self, impulse.into(), true);
+    }
+
+    fn apply_torque_impulse(&mut self, impulse: Vec3) {
+        RigidBody::apply_torque_impulse(self, impulse.into(), true);
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        RigidBody::set_enabled(self, enabled);
+    }
+
+    fn set_gravity_scale(&mut self, scale: f32) {
+        RigidBody::set_gravity_scale(self, scale, true);
+    }
+
+    fn set_linear_damping(&mut self, damping: f32) {
+        RigidBody::set_linear_damping(self, damping);
+    }
+
+    fn set_angular_damping(&mut self, damping: f32) {
+        RigidBody::set_angular_damping(self, damping);
+    }
+
+    fn set_additional_mass(&mut self, mass: AdditionalMass, wake_up: bool) {
+        mass.apply(self, wake_up);
+    }
+
+    fn lock_rotations(&mut self, [x, y, z]: [bool; 3]) {
+        self.set_enabled_rotations(!x, !y, !z, true);
+    }
+
+    fn lock_translations(&mut self, [x, y, z]: [bool; 3]) {
+        self.set_enabled_translations(!x, !y, !z, true);
+    }
+
+    fn set_dominance_group(&mut self, group: i8) {
+        RigidBody::set_dominance_group(self, group);
+    }
+
+    fn sleep(&mut self) {
+        RigidBody::sleep(self);
+    }
+
+    fn wake_up(&mut self) {
+        RigidBody::wake_up(self, true);
+    }
+}
+
+impl WorldCollider for Collider {
+    fn scale(&mut self, scale: Vec3) -> anyhow::Result<()> {
+        scale_collider(self, scale)
+    }
+
+    fn place(&mut self, transform: &Transform3D) {
+        let world: Isometry<Real> = (transform.position, transform.rotation).into();
+        self.set_position(world * self.position());
+    }
+
+    fn apply_material(&mut self, material: &PhysicsMaterial) {
+        material.apply(self);
+    }
+
+    fn set_friction(&mut self, friction: f32) {
+        Collider::set_friction(self, friction);
+    }
+
+    fn set_restitution(&mut self, restitution: f32) {
+        Collider::set_restitution(self, restitution);
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        Collider::set_enabled(self, enabled);
+    }
+
+    fn set_layer(&mut self, layer: &Layer) {
+        self.set_collision_groups(
+            InteractionGroups::all()
+                .with_memberships(Group::from_bits_truncate(layer.layers))
+                .with_filter(Group::from_bits_truncate(layer.collides_with)),
+        );
+    }
+
+    fn set_contact_force_threshold(&mut self, threshold: f32) {
+        self.set_active_events(self.active_events() | ActiveEvents::CONTACT_FORCE_EVENTS);
+        self.set_contact_force_event_threshold(threshold);
+    }
+
+    fn attach_to(&mut self, id: Uuid) {
+        self.set_active_events(self.active_events() | ActiveEvents::COLLISION_EVENTS);
+        self.user_data = id.as_u128();
+    }
+}
+
+/// the handle in an active 3d body's state
+fn active_handle(state: &RigidBodyState) -> anyhow::Result<RigidBodyHandle> {
+    match state {
+        RigidBodyState::Active(handle) => Ok(*handle),
+        RigidBodyState::Removed => Err(anyhow::anyhow!("rigid body has been removed")),
+        RigidBodyState::Pending(_rb) => Err(anyhow::anyhow!("cannot mutate pending body")),
+        RigidBodyState::Active2D(_) => {
+            Err(anyhow::anyhow!("rigid body belongs to a 2d simulation"))
+        }
+    }
+}
+
+/// handle of the entity's active rigid body
+fn body_handle(entities: &EntityRegistry, id: Uuid) -> anyhow::Result<RigidBodyHandle> {
+    with_body_state(entities, id, active_handle)
+}
+
+/// scales the collider's shape and its position on the body
+///
+/// shapes that can't be scaled exactly, like balls scaled unevenly, are turned into convex
+/// approximations
+fn scale_collider(collider: &mut Collider, scale: Vec3) -> anyhow::Result<()> {
+    if scale == Vec3::ONE {
+        return Ok(());
+    }
+
+    let scale = Vector::from(scale);
+    let shape = collider
+        .shape()
+        .scale_dyn(&scale, SCALE_SUBDIVISIONS)
+        .ok_or(anyhow::anyhow!(
+            "{:?} colliders can't be scaled by {scale:?}",
+            collider.shape().shape_type()
+        ))?;
+    collider.set_shape(SharedShape(Arc::from(shape)));
+
+    let mut position = *collider.position();
+    position.translation.vector.component_mul_assign(&scale);
+    collider.set_position(position);
+    Ok(())
+}
+
+fn joint_axis(axis: Vec3) -> anyhow::Result<UnitVector<Real>> {
+    UnitVector::try_new(axis.into(), f32::EPSILON)
+        .ok_or(anyhow::anyhow!("joint axis can't be zero"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::AtomicUsize,
+            mpsc::{Receiver, Sender},
+        },
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use super::*;
+    use crate::{
+        engine::{component::ComponentSet, entity::tests::Dummy},
+        physics::{
+            DEFAULT_GRAVITY, IsSleeping, PhysicsEngine, PhysicsVelocity, Simulation,
+            model_collider::heightfield_collider,
+            recording::{PhysicsRecorder, PhysicsRecording},
+            stats::PhysicsStats,
+        },
+    };
+
+    fn engine(
+        entities: &EntityRegistry,
+    ) -> (RapierEngine, Sender<PhysicsCommand>, Receiver<PhysicsEvent>) {
+        let (command_tx, command_rx) = std::sync::mpsc::channel();
+        let (event_tx, event_rx) = std::sync::mpsc::channel();
+        let engine = RapierEngine::new(
+            DEFAULT_GRAVITY,
+            entities.clone(),
+            command_rx,
+            event_tx,
+            Arc::new(AtomicUsize::new(0)),
+            PhysicsSettings::default(),
+        );
+        (engine, command_tx, event_rx)
+    }
+
+    fn spawn(
+        entities: &mut EntityRegistry,
+        position: Vec3,
+        collider: Collider,
+        rigid_body: RigidBody,
+    ) -> Uuid {
+        spawn_body(
+            entities,
+            Transform3D::new(position, Quat::IDENTITY, Vec3::ONE),
+            PhysicsBody::new(collider, rigid_body),
+        )
+    }
+
+    /// `spawn` for bodies that need more than a collider and a rigid body
+    fn spawn_body(
+        entities: &mut EntityRegistry,
+        transform: Transform3D,
+        body: PhysicsBody,
+    ) -> Uuid {
+        let mut components = ComponentSet::new();
+        components.add(transform);
+        components.add(body);
+        let entity = Dummy::new(components);
+        let id = entity.id();
+        entities.add(entity.into_container());
+        id
+    }
+
+    fn transform(entities: &EntityRegistry, id: Uuid) -> Transform3D {
+        entities
+            .get(&id)
+            .unwrap()
+            .read_with(|e| *e.components().get::<Transform3D>().unwrap())
+    }
+
+    fn position(entities: &EntityRegistry, id: Uuid) -> Vec3 {
+        transform(entities, id).position
+    }
+
+    #[test]
+    fn removed_body_leaves_the_sets() {
+        let mut entities = EntityRegistry::new();
+        let ground = spawn(
+            &mut entities,
+            Vec3::ZERO,
+            ColliderBuilder::cuboid(10.0, 0.5, 10.0).build(),
+            RigidBodyBuilder::fixed().build(),
+        );
+        let (mut engine, _commands, _events) = engine(&entities);
+        engine.step().unwrap();
+
+        engine
+            .handle_command(PhysicsCommand::RemoveBody { id: ground })
+            .unwrap();
+        assert_eq!(engine.world.rigid_body_set.len(), 0);
+        assert_eq!(engine.world.collider_set.len(), 0);
+        entities.get(&ground).unwrap().read_with(|e| {
+            assert!(matches!(
+                e.components().get::<PhysicsBody>().unwrap().rigid_body,
+                RigidBodyState::Removed
+            ))
+        });
+        assert!(
+            engine
+                .handle_command(PhysicsCommand::RemoveBody { id: ground })
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn disabled_body_stops_falling() {
+        let mut entities = EntityRegistry::new();
+        let ball = spawn(
+            &mut entities,
+            Vec3::new(0.0, 10.0, 0.0),
+            ColliderBuilder::ball(0.5).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        let (mut engine, _commands, _events) = engine(&entities);
+        (0..10).for_each(|_| engine.step().unwrap());
+        assert!(position(&entities, ball).y < 10.0);
+
+        engine
+            .handle_command(PhysicsCommand::Disable { id: ball })
+            .unwrap();
+        let disabled_at = position(&entities, ball);
+        (0..10).for_each(|_| engine.step().unwrap());
+        assert_eq!(position(&entities, ball), disabled_at);
+
+        engine
+            .handle_command(PhysicsCommand::Enable { id: ball })
+            .unwrap();
+        (0..10).for_each(|_| engine.step().unwrap());
+        assert!(position(&entities, ball).y < disabled_at.y);
+    }
+
+    #[test]
+    fn enable_needs_an_active_body() {
+        let mut entities = EntityRegistry::new();
+        let (mut engine, _commands, _events) = engine(&entities);
+        let pending = spawn(
+            &mut entities,
+            Vec3::ZERO,
+            ColliderBuilder::ball(0.5).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        let err = engine
+            .handle_command(PhysicsCommand::Disable { id: pending })
+            .unwrap_err();
+        assert!(err.to_string().contains("pending"));
+    }
+
+    /// highest point the ball reaches in the second half of a 3 second drop onto the ground
+    fn bounce_height(material: PhysicsMaterial) -> f32 {
+        let mut entities = EntityRegistry::new();
+        spawn(
+            &mut entities,
+            Vec3::new(0.0, -0.5, 0.0),
+            ColliderBuilder::cuboid(10.0, 0.5, 10.0).build(),
+            RigidBodyBuilder::fixed().build(),
+        );
+        let id = spawn_body(
+            &mut entities,
+            Transform3D::new(Vec3::new(0.0, 5.0, 0.0), Quat::IDENTITY, Vec3::ONE),
+            PhysicsBody::new(
+                ColliderBuilder::ball(0.5).build(),
+                RigidBodyBuilder::dynamic().build(),
+            )
+            .with_material(material),
+        );
+
+        let (mut engine, _commands, _events) = engine(&entities);
+        (0..180)
+            .map(|_| {
+                engine.step().unwrap();
+                position(&entities, id).y
+            })
+            .skip(90)
+            .fold(f32::MIN, f32::max)
+    }
+
+    #[test]
+    fn restitution_changes_bounce() {
+        let bouncy = bounce_height(PhysicsMaterial::new(0.5, 0.9, 1.0));
+        let dead = bounce_height(PhysicsMaterial::new(0.5, 0.0, 1.0));
+        assert!(dead < 0.6, "{dead}");
+        assert!(bouncy > dead + 0.5, "{bouncy} vs {dead}");
+    }
+
+    #[test]
+    fn kinematic_body_follows_transform() {
+        let mut entities = EntityRegistry::new();
+        let platform = spawn(
+            &mut entities,
+            Vec3::ZERO,
+            ColliderBuilder::cuboid(2.0, 0.5, 2.0).build(),
+            RigidBodyBuilder::kinematic_position_based().build(),
+        );
+        let (mut engine, _commands, _events) = engine(&entities);
+        engine.step().unwrap();
+
+        let target = Vec3::new(1.0, 2.0, 0.0);
+        entities.get(&platform).unwrap().write_with(|e| {
+            e.components_mut()
+                .add(Transform3D::new(target, Quat::IDENTITY, Vec3::ONE))
+        });
+        engine.step().unwrap();
+
+        assert_eq!(position(&entities, platform), target);
+        let handle = engine.world.entity_bodies[&platform];
+        let translation = engine.world.rigid_body_set[handle].translation();
+        assert_eq!(Vec3::from(*translation), target);
+    }
+
+    #[test]
+    fn carrier_keeps_ball_centered() {
+        let mut entities = EntityRegistry::new();
+        let platform_id = spawn_body(
+            &mut entities,
+            Transform3D::default(),
+            PhysicsBody::new(
+                ColliderBuilder::cuboid(3.0, 0.5, 3.0).build(),
+                RigidBodyBuilder::kinematic_position_based().build(),
+            )
+            .as_carrier(),
+        );
+        let ball = spawn(
+            &mut entities,
+            Vec3::new(0.0, 1.0, 0.0),
+            ColliderBuilder::ball(0.5).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+
+        let (mut engine, _commands, _events) = engine(&entities);
+        let dt = engine.world.integration_parameters.dt;
+        for i in 0..300 {
+            let x = (i as f32 * dt * 2.0).sin() * 2.0;
+            entities.get(&platform_id).unwrap().write_with(|e| {
+                e.components_mut()
+                    .get_mut::<Transform3D>()
+                    .unwrap()
+                    .position
+                    .x = x;
+            });
+            engine.step().unwrap();
+
+            let offset = position(&entities, ball) - position(&entities, platform_id);
+            assert!(
+                offset.x.abs() < 0.1 && offset.z.abs() < 0.1,
+                "step {i}: {offset}"
+            );
+            assert!((offset.y - 1.0).abs() < 0.1, "step {i}: {offset}");
+        }
+    }
+
+    #[test]
+    fn hinge_stays_within_limits() {
+        let mut entities = EntityRegistry::new();
+        let post = spawn(
+            &mut entities,
+            Vec3::ZERO,
+            ColliderBuilder::cuboid(0.25, 0.25, 0.25).build(),
+            RigidBodyBuilder::fixed().build(),
+        );
+        let door = spawn(
+            &mut entities,
+            Vec3::new(2.0, 0.0, 0.0),
+            ColliderBuilder::cuboid(0.25, 0.25, 0.25).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        let (mut engine, _commands, _events) = engine(&entities);
+
+        engine
+            .handle_command(PhysicsCommand::CreateRevoluteJoint {
+                a: post,
+                b: door,
+                anchor_a: Vec3::new(1.0, 0.0, 0.0),
+                anchor_b: Vec3::new(-1.0, 0.0, 0.0),
+                axis: Vec3::Z,
+                limits: Some([-0.5, 0.5]),
+            })
+            .unwrap();
+        (0..300).for_each(|_| engine.step().unwrap());
+
+        let joint_id = received(&entities, door)
+            .into_iter()
+            .find_map(|e| match e {
+                PhysicsEvent::JointCreated { joint_id, a, b } => {
+                    assert_eq!((a, b), (post, door));
+                    Some(joint_id)
+                }
+                _ => None,
+            })
+            .expect("no JointCreated");
+
+        // gravity swings the door down until it hits the lower limit
+        let rotation = transform(&entities, door).rotation;
+        let angle = 2.0 * rotation.z.atan2(rotation.w);
+        assert!((-0.55..=-0.4).contains(&angle), "{angle}");
+        assert!(rotation.x.abs() < 0.01 && rotation.y.abs() < 0.01);
+        let hinge = position(&entities, door) + rotation * Vec3::new(-1.0, 0.0, 0.0);
+        assert!(hinge.distance(Vec3::new(1.0, 0.0, 0.0)) < 0.05, "{hinge}");
+
+        engine
+            .handle_command(PhysicsCommand::RemoveJoint { joint_id })
+            .unwrap();
+        assert_eq!(engine.world.impulse_joint_set.len(), 0);
+    }
+
+    #[test]
+    fn character_walks_on_the_ground() {
+        let mut entities = EntityRegistry::new();
+        spawn(
+            &mut entities,
+            Vec3::new(0.0, -0.5, 0.0),
+            ColliderBuilder::cuboid(10.0, 0.5, 10.0).build(),
+            RigidBodyBuilder::fixed().build(),
+        );
+        let id = spawn(
+            &mut entities,
+            Vec3::new(0.0, 0.85, 0.0),
+            ColliderBuilder::capsule_y(0.5, 0.3).build(),
+            RigidBodyBuilder::kinematic_position_based().build(),
+        );
+        entities
+            .get(&id)
+            .unwrap()
+            .write_with(|e| e.components_mut().add(CharacterController::default()));
+
+        let (mut engine, _commands, _events) = engine(&entities);
+        engine.step().unwrap();
+
+        engine
+            .handle_command(PhysicsCommand::MoveCharacter {
+                id,
+                desired_translation: Vec3::new(1.0, -0.5, 0.0),
+            })
+            .unwrap();
+
+        // slid along the floor instead of going through it
+        let moved = position(&entities, id);
+        assert!((moved.x - 1.0).abs() < 0.01, "{moved}");
+        assert!(moved.y > 0.79, "{moved}");
+
+        engine.step().unwrap();
+        let grounded = received(&entities, id).into_iter().find_map(|e| match e {
+            PhysicsEvent::CharacterMoved { grounded, .. } => Some(grounded),
+            _ => None,
+        });
+        assert_eq!(grounded, Some(true));
+        let handle = engine.world.entity_bodies[&id];
+        let translation = engine.world.rigid_body_set[handle].translation();
+        assert_eq!(Vec3::from(*translation), moved);
+    }
+
+    #[test]
+    fn flipped_gravity_lifts_sleeping_bodies() {
+        let mut entities = EntityRegistry::new();
+        spawn(
+            &mut entities,
+            Vec3::new(0.0, -0.5, 0.0),
+            ColliderBuilder::cuboid(10.0, 0.5, 10.0).build(),
+            RigidBodyBuilder::fixed().build(),
+        );
+        let ball = spawn(
+            &mut entities,
+            Vec3::new(0.0, 0.5, 0.0),
+            ColliderBuilder::ball(0.5).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        let (mut engine, _commands, _events) = engine(&entities);
+        (0..300).for_each(|_| engine.step().unwrap());
+        let handle = engine.world.entity_bodies[&ball];
+        assert!(engine.world.rigid_body_set[handle].is_sleeping());
+
+        engine
+            .handle_command(PhysicsCommand::SetGravity {
+                gravity: Vec3::new(0.0, 9.81, 0.0),
+            })
+            .unwrap();
+        let resting = position(&entities, ball);
+        (0..30).for_each(|_| engine.step().unwrap());
+        assert!(position(&entities, ball).y > resting.y + 0.5);
+        assert!(engine.world.rigid_body_set[handle].linvel().y > 0.0);
+    }
+
+    /// positions of a small pile of boxes after two seconds
+    fn pile_up() -> Vec<(Vec3, Quat)> {
+        let mut entities = EntityRegistry::new();
+        spawn(
+            &mut entities,
+            Vec3::new(0.0, -0.5, 0.0),
+            ColliderBuilder::cuboid(10.0, 0.5, 10.0).build(),
+            RigidBodyBuilder::fixed().build(),
+        );
+        let boxes: Vec<Uuid> = (0..5)
+            .map(|i| {
+                spawn(
+                    &mut entities,
+                    Vec3::new(i as f32 * 0.3, 1.0 + i as f32 * 1.2, 0.0),
+                    ColliderBuilder::cuboid(0.5, 0.5, 0.5).build(),
+                    RigidBodyBuilder::dynamic().build(),
+                )
+            })
+            .collect();
+
+        let (mut engine, _commands, _events) = engine(&entities);
+        engine.set_timestep(Duration::from_millis(10));
+        (0..200).for_each(|_| engine.step().unwrap());
+        boxes
+            .iter()
+            .map(|id| {
+                let transform = transform(&entities, *id);
+                (transform.position, transform.rotation)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_scene_same_result() {
+        assert_eq!(pile_up(), pile_up());
+    }
+
+    #[test]
+    fn damping_brings_bodies_to_rest() {
+        let mut entities = EntityRegistry::new();
+        let mut slide = |damping: Option<f32>| {
+            let mut body = PhysicsBody::new(
+                ColliderBuilder::ball(0.5).build(),
+                RigidBodyBuilder::dynamic()
+                    .gravity_scale(0.0)
+                    .linvel(vector![5.0, 0.0, 0.0])
+                    .build(),
+            );
+            if let Some(damping) = damping {
+                body = body.with_damping(damping, damping);
             }
-            PhysicsCommand::SetPosition {
+            spawn_body(&mut entities, Transform3D::default(), body)
+        };
+        let damped = slide(Some(5.0));
+        let undamped = slide(None);
+
+        let (mut engine, _commands, _events) = engine(&entities);
+        (0..120).for_each(|_| engine.step().unwrap());
+
+        let speed = |id: Uuid| {
+            engine.world.rigid_body_set[engine.world.entity_bodies[&id]]
+                .linvel()
+                .norm()
+        };
+        assert!(speed(damped) < 0.01, "{}", speed(damped));
+        assert!((speed(undamped) - 5.0).abs() < 1e-3, "{}", speed(undamped));
+    }
+
+    #[test]
+    fn locked_axes_stay_put() {
+        let mut entities = EntityRegistry::new();
+        let id = spawn_body(
+            &mut entities,
+            Transform3D::default(),
+            PhysicsBody::new(
+                ColliderBuilder::cuboid(0.5, 0.5, 0.5).build(),
+                RigidBodyBuilder::dynamic().gravity_scale(0.0).build(),
+            )
+            .with_locked_translations(false, false, true)
+            .with_locked_rotations(true, false, true),
+        );
+
+        let (mut engine, _commands, _events) = engine(&entities);
+        engine
+            .handle_command(PhysicsCommand::ApplyImpulse {
                 id,
-                translation,
-                rotation,
-            } => self.set_position(id, translation, rotation),
-            PhysicsCommand::SetTranslation { id, translation } => {
-                self.set_translation(id, translation)
+                impulse: Vec3::new(2.0, 0.0, 2.0),
+            })
+            .unwrap();
+        engine
+            .handle_command(PhysicsCommand::ApplyTorqueImpulse {
+                id,
+                impulse: Vec3::splat(0.1),
+            })
+            .unwrap();
+        (0..200).for_each(|_| engine.step().unwrap());
+
+        let Transform3D {
+            position: moved,
+            rotation,
+            ..
+        } = transform(&entities, id);
+        assert!(moved.x > 1.0, "{moved}");
+        assert_eq!(moved.z, 0.0);
+        assert!(
+            rotation.x.abs() < 1e-6 && rotation.z.abs() < 1e-6,
+            "{rotation}"
+        );
+        assert!(rotation.y.abs() > 0.1, "{rotation}");
+
+        engine
+            .handle_command(PhysicsCommand::LockTranslations {
+                id,
+                x: false,
+                y: false,
+                z: false,
+            })
+            .unwrap();
+        engine
+            .handle_command(PhysicsCommand::ApplyImpulse {
+                id,
+                impulse: Vec3::new(0.0, 0.0, 2.0),
+            })
+            .unwrap();
+        (0..60).for_each(|_| engine.step().unwrap());
+        assert!(position(&entities, id).z > 0.5);
+    }
+
+    #[test]
+    fn velocity_is_written_back() {
+        let mut entities = EntityRegistry::new();
+        let ball = spawn(
+            &mut entities,
+            Vec3::new(0.0, 10.0, 0.0),
+            ColliderBuilder::ball(0.5).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        let (mut engine, _commands, events) = engine(&entities);
+        (0..30).for_each(|_| engine.step().unwrap());
+
+        let velocity = entities.get(&ball).unwrap().read_with(|e| {
+            e.components()
+                .get::<PhysicsVelocity>()
+                .map(|v| v.linear)
+                .unwrap()
+        });
+        assert!(velocity.y < -4.0, "{velocity}");
+
+        let request_id = Uuid::new_v4();
+        engine
+            .handle_command(PhysicsCommand::QueryBody {
+                id: ball,
+                request_id,
+            })
+            .unwrap();
+        match events.try_recv().unwrap() {
+            PhysicsEvent::BodyState {
+                request_id: id,
+                linvel,
+                mass,
+                is_sleeping,
+                ..
+            } => {
+                assert_eq!(id, request_id);
+                assert_eq!(linvel, velocity);
+                assert!(mass > 0.0);
+                assert!(!is_sleeping);
             }
-            PhysicsCommand::SetRotation { id, rotation } => self.set_rotation(id, rotation),
+            other => panic!("expected BodyState, got {other:?}"),
+        }
+    }
+
+    fn body_state(
+        engine: &mut RapierEngine,
+        events: &Receiver<PhysicsEvent>,
+        id: Uuid,
+    ) -> (Vec3, bool) {
+        let request_id = Uuid::new_v4();
+        engine
+            .handle_command(PhysicsCommand::QueryBody { id, request_id })
+            .unwrap();
+        events
+            .try_iter()
+            .find_map(|e| match e {
+                PhysicsEvent::BodyState {
+                    request_id: r,
+                    linvel,
+                    is_sleeping,
+                    ..
+                } if r == request_id => Some((linvel, is_sleeping)),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn initial_velocity_waits_for_pending_body() {
+        let mut entities = EntityRegistry::new();
+        let (mut engine, _commands, _events) = engine(&entities);
+        engine.gravity = Vec3::ZERO;
 
-            _ => Err(anyhow::anyhow!(
-                "i haven't done this physics command yet lol"
-            )),
+        // added after the engine started, so still pending when the command comes in
+        let bullet = spawn(
+            &mut entities,
+            Vec3::ZERO,
+            ColliderBuilder::ball(0.1).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        engine
+            .handle_command(PhysicsCommand::SetInitialVelocity {
+                id: bullet,
+                linvel: Vec3::new(0.0, 0.0, -30.0),
+                angvel: Vec3::Y,
+            })
+            .unwrap();
+        engine.step().unwrap();
+
+        let velocity = entities
+            .get(&bullet)
+            .unwrap()
+            .read_with(|e| e.components().get::<PhysicsVelocity>().copied().unwrap());
+        assert_eq!(velocity.linear, Vec3::new(0.0, 0.0, -30.0));
+        assert_eq!(velocity.angular, Vec3::Y);
+        assert!(position(&entities, bullet).z < 0.0);
+        assert!(engine.pending_velocities.is_empty());
+
+        // removed before it ever got inserted
+        let dud = spawn(
+            &mut entities,
+            Vec3::ZERO,
+            ColliderBuilder::ball(0.1).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        engine
+            .handle_command(PhysicsCommand::SetInitialVelocity {
+                id: dud,
+                linvel: Vec3::X,
+                angvel: Vec3::ZERO,
+            })
+            .unwrap();
+        assert!(engine.pending_velocities.contains_key(&dud));
+        engine
+            .handle_command(PhysicsCommand::RemoveBody { id: dud })
+            .unwrap();
+        assert!(engine.pending_velocities.is_empty());
+
+        // and stays out once the engine gets around to inserting bodies
+        engine.step().unwrap();
+        assert!(!engine.world.entity_bodies.contains_key(&dud));
+        let removed = entities.get(&dud).unwrap().read_with(|e| {
+            matches!(
+                e.components().get::<PhysicsBody>().unwrap().rigid_body,
+                RigidBodyState::Removed
+            )
+        });
+        assert!(removed);
+    }
+
+    #[test]
+    fn shape_cast_stops_at_the_wall() {
+        let mut entities = EntityRegistry::new();
+        let wall = spawn(
+            &mut entities,
+            Vec3::new(5.0, 0.0, 0.0),
+            ColliderBuilder::cuboid(0.5, 2.0, 2.0).build(),
+            RigidBodyBuilder::fixed().build(),
+        );
+        let (mut engine, _commands, events) = engine(&entities);
+        engine.step().unwrap();
+
+        let request_id = Uuid::new_v4();
+        engine
+            .handle_command(PhysicsCommand::ShapeCast {
+                shape: ColliderBuilder::capsule_y(0.5, 0.5).build(),
+                origin: Vec3::ZERO,
+                rotation: Quat::IDENTITY,
+                dir: Vec3::X * 3.0,
+                max_toi: 10.0,
+                request_id,
+            })
+            .unwrap();
+        match events.try_recv().unwrap() {
+            PhysicsEvent::ShapeCastHit {
+                request_id: id,
+                entity,
+                toi,
+                witness_hit,
+                witness_shape,
+                normal,
+            } => {
+                assert_eq!(id, request_id);
+                assert_eq!(entity, Some(wall));
+                // wall face at x = 4.5, capsule radius 0.5
+                assert!((toi - 4.0).abs() < 1e-3, "{toi}");
+                assert!((witness_hit.x - 4.5).abs() < 1e-3, "{witness_hit}");
+                assert!(witness_hit.distance(witness_shape) < 1e-3);
+                assert!(normal.distance(Vec3::NEG_X) < 1e-3, "{normal}");
+            }
+            other => panic!("expected ShapeCastHit, got {other:?}"),
         }
+
+        engine
+            .handle_command(PhysicsCommand::ShapeCast {
+                shape: ColliderBuilder::ball(0.5).build(),
+                origin: Vec3::ZERO,
+                rotation: Quat::IDENTITY,
+                dir: Vec3::NEG_X,
+                max_toi: 10.0,
+                request_id,
+            })
+            .unwrap();
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            PhysicsEvent::ShapeCastMiss { .. }
+        ));
     }
 
-    fn apply_force(&mut self, id: Uuid, force: Vec3) -> anyhow::Result<()> {
-        self.run_on_rb(id, |rb| {
-            rb.add_force(force.into(), true);
-        })
+    #[test]
+    fn overlap_lists_entities_in_range() {
+        let mut entities = EntityRegistry::new();
+        let spawn_ball = |entities: &mut EntityRegistry, x: f32| {
+            spawn(
+                entities,
+                Vec3::new(x, 0.0, 0.0),
+                ColliderBuilder::ball(0.5).build(),
+                RigidBodyBuilder::fixed().build(),
+            )
+        };
+        let near = spawn_ball(&mut entities, 1.0);
+        let edge = spawn_ball(&mut entities, -2.2);
+        spawn_ball(&mut entities, 10.0);
+        let (mut engine, _commands, events) = engine(&entities);
+        engine.step().unwrap();
+
+        let request_id = Uuid::new_v4();
+        engine
+            .handle_command(PhysicsCommand::OverlapShape {
+                shape: ColliderBuilder::ball(2.0).build(),
+                origin: Vec3::ZERO,
+                rotation: Quat::IDENTITY,
+                request_id,
+            })
+            .unwrap();
+        match events.try_recv().unwrap() {
+            PhysicsEvent::Overlaps {
+                request_id: id,
+                entities: mut hit,
+            } => {
+                assert_eq!(id, request_id);
+                let mut expected = vec![near, edge];
+                expected.sort_unstable();
+                hit.sort_unstable();
+                assert_eq!(hit, expected);
+            }
+            other => panic!("expected Overlaps, got {other:?}"),
+        }
     }
 
-    fn apply_torque(&mut self, id: Uuid, torque: Vec3) -> anyhow::Result<()> {
-        self.run_on_rb(id, |rb| {
-            rb.add_torque(torque.into(), true);
-        })
+    #[test]
+    fn dominant_body_is_not_pushed() {
+        let mut entities = EntityRegistry::new();
+        let wall = spawn(
+            &mut entities,
+            Vec3::ZERO,
+            ColliderBuilder::cuboid(0.5, 2.0, 2.0).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        let ball = spawn(
+            &mut entities,
+            Vec3::new(-3.0, 0.0, 0.0),
+            ColliderBuilder::ball(0.5).density(100.0).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        let (mut engine, _commands, events) = engine(&entities);
+        engine.gravity = Vec3::ZERO;
+        engine.step().unwrap();
+
+        engine
+            .handle_command(PhysicsCommand::SetDominance {
+                id: wall,
+                group: 127,
+            })
+            .unwrap();
+        engine
+            .handle_command(PhysicsCommand::SetLinearVelocity {
+                id: ball,
+                velocity: Vec3::X * 10.0,
+            })
+            .unwrap();
+        (0..60).for_each(|_| engine.step().unwrap());
+
+        let (wall_velocity, _) = body_state(&mut engine, &events, wall);
+        assert_eq!(wall_velocity, Vec3::ZERO);
+        assert!(position(&entities, wall).length() < 1e-4);
+        // the ball bounced off instead of pushing through
+        assert!(
+            position(&entities, ball).x < -0.9,
+            "{}",
+            position(&entities, ball)
+        );
     }
 
-    fn apply_impulse(&mut self, id: Uuid, impulse: Vec3) -> anyhow::Result<()> {
-        self.run_on_rb(id, |rb| {
-            rb.apply_impulse(impulse.into(), true);
-        })
+    #[test]
+    fn sleep_shows_in_body_state() {
+        let mut entities = EntityRegistry::new();
+        let ball = spawn(
+            &mut entities,
+            Vec3::new(0.0, 10.0, 0.0),
+            ColliderBuilder::ball(0.5).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        let (mut engine, _commands, events) = engine(&entities);
+        engine.step().unwrap();
+
+        engine
+            .handle_command(PhysicsCommand::Sleep { id: ball })
+            .unwrap();
+        let asleep_at = position(&entities, ball);
+        (0..10).for_each(|_| engine.step().unwrap());
+        assert_eq!(position(&entities, ball), asleep_at);
+        assert!(body_state(&mut engine, &events, ball).1);
+
+        engine
+            .handle_command(PhysicsCommand::WakeUp { id: ball })
+            .unwrap();
+        assert!(!body_state(&mut engine, &events, ball).1);
+        (0..10).for_each(|_| engine.step().unwrap());
+        assert!(position(&entities, ball).y < asleep_at.y);
     }
 
-    fn apply_torque_impulse(&mut self, id: Uuid, impulse: Vec3) -> anyhow::Result<()> {
-        self.run_on_rb(id, |rb| {
-            rb.apply_torque_impulse(impulse.into(), true);
-        })
+    #[test]
+    fn ball_rolls_under_compound_table() {
+        let mut entities = EntityRegistry::new();
+        spawn(
+            &mut entities,
+            Vec3::new(0.0, -0.5, 0.0),
+            ColliderBuilder::cuboid(20.0, 0.5, 20.0).build(),
+            RigidBodyBuilder::fixed().build(),
+        );
+
+        let leg = |x: f32, z: f32| {
+            (
+                ColliderBuilder::cuboid(0.1, 0.7, 0.1).build(),
+                Isometry::translation(x, 0.7, z),
+            )
+        };
+        let table = PhysicsBody::compound(
+            vec![
+                (
+                    ColliderBuilder::cuboid(2.0, 0.1, 2.0).build(),
+                    Isometry::translation(0.0, 1.5, 0.0),
+                ),
+                leg(-1.8, -1.8),
+                leg(-1.8, 1.8),
+                leg(1.8, -1.8),
+                leg(1.8, 1.8),
+            ],
+            RigidBodyBuilder::fixed().build(),
+        );
+        let table_id = spawn_body(&mut entities, Transform3D::default(), table);
+
+        let ball = spawn(
+            &mut entities,
+            Vec3::new(-5.0, 0.3, 0.0),
+            ColliderBuilder::ball(0.3).build(),
+            RigidBodyBuilder::dynamic()
+                .linvel(vector![5.0, 0.0, 0.0])
+                .build(),
+        );
+
+        let (mut engine, _commands, _events) = engine(&entities);
+        let table_body = &engine.world.rigid_body_set[engine.world.entity_bodies[&table_id]];
+        assert_eq!(table_body.colliders().len(), 5);
+
+        (0..180).for_each(|_| engine.step().unwrap());
+        let rolled = position(&entities, ball);
+        assert!(rolled.x > 3.0, "{rolled}");
+        assert!(rolled.z.abs() < 0.1 && rolled.y < 0.5, "{rolled}");
+
+        // a ball dropped on the top lands on it instead of falling through
+        let dropped = spawn(
+            &mut entities,
+            Vec3::new(0.0, 3.0, 0.0),
+            ColliderBuilder::ball(0.3).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        (0..120).for_each(|_| engine.step().unwrap());
+        assert!(position(&entities, dropped).y > 1.8);
     }
 
-    fn set_linear_velocity(&mut self, id: Uuid, velocity: Vec3) -> anyhow::Result<()> {
-        self.run_on_rb(id, |rb| {
-            rb.set_linvel(velocity.into(), true);
-        })
+    #[test]
+    fn colliders_follow_transform_scale() {
+        let mut entities = EntityRegistry::new();
+        let mut scaled = |scale: Vec3, body: PhysicsBody| {
+            spawn_body(
+                &mut entities,
+                Transform3D::new(Vec3::ZERO, Quat::IDENTITY, scale),
+                body,
+            )
+        };
+        let ball = scaled(
+            Vec3::splat(10.0),
+            PhysicsBody::new(
+                ColliderBuilder::ball(1.0).build(),
+                RigidBodyBuilder::fixed().build(),
+            ),
+        );
+        let egg = scaled(
+            Vec3::new(1.0, 2.0, 1.0),
+            PhysicsBody::new(
+                ColliderBuilder::ball(1.0).build(),
+                RigidBodyBuilder::fixed().build(),
+            ),
+        );
+        let pair = scaled(
+            Vec3::splat(2.0),
+            PhysicsBody::compound(
+                vec![
+                    (
+                        ColliderBuilder::cuboid(0.5, 0.5, 0.5).build(),
+                        Isometry::translation(-1.0, 0.0, 0.0),
+                    ),
+                    (
+                        ColliderBuilder::cuboid(0.5, 0.5, 0.5).build(),
+                        Isometry::translation(1.0, 0.0, 0.0),
+                    ),
+                ],
+                RigidBodyBuilder::fixed().build(),
+            ),
+        );
+
+        let (engine, _commands, _events) = engine(&entities);
+        let aabb = |id: Uuid| {
+            let rb = &engine.world.rigid_body_set[engine.world.entity_bodies[&id]];
+            rb.colliders()
+                .iter()
+                .map(|h| engine.world.collider_set[*h].compute_aabb())
+                .reduce(|a, b| a.merged(&b))
+                .unwrap()
+        };
+
+        let ball_shape = engine.world.collider_set
+            [engine.world.rigid_body_set[engine.world.entity_bodies[&ball]].colliders()[0]]
+            .shape();
+        assert_eq!(ball_shape.as_ball().unwrap().radius, 10.0);
+
+        let egg = aabb(egg);
+        assert!((egg.maxs.y - 2.0).abs() < 0.01, "{egg:?}");
+        assert!((egg.maxs.x - 1.0).abs() < 0.01, "{egg:?}");
+
+        let pair = aabb(pair);
+        assert_eq!(pair.mins, point![-3.0, -1.0, -1.0]);
+        assert_eq!(pair.maxs, point![3.0, 1.0, 1.0]);
     }
 
-    fn set_angular_velocity(&mut self, id: Uuid, velocity: Vec3) -> anyhow::Result<()> {
-        self.run_on_rb(id, |rb| {
-            rb.set_angvel(velocity.into(), true);
-        })
+    /// contact force events from dropping a ball with the given threshold on the ground
+    fn impact_forces(threshold: f32) -> Vec<(f32, Vec3)> {
+        let mut entities = EntityRegistry::new();
+        spawn(
+            &mut entities,
+            Vec3::new(0.0, -0.5, 0.0),
+            ColliderBuilder::cuboid(10.0, 0.5, 10.0).build(),
+            RigidBodyBuilder::fixed().build(),
+        );
+        let id = spawn_body(
+            &mut entities,
+            Transform3D::new(Vec3::new(1.0, 3.0, 0.0), Quat::IDENTITY, Vec3::ONE),
+            PhysicsBody::new(
+                ColliderBuilder::ball(0.5).build(),
+                RigidBodyBuilder::dynamic().build(),
+            )
+            .with_contact_force_threshold(threshold),
+        );
+
+        let (mut engine, _commands, _events) = engine(&entities);
+        (0..90).for_each(|_| engine.step().unwrap());
+        received(&entities, id)
+            .into_iter()
+            .filter_map(|e| match e {
+                PhysicsEvent::ContactForce {
+                    force_magnitude,
+                    largest_contact_point,
+                    ..
+                } => Some((force_magnitude, largest_contact_point)),
+                _ => None,
+            })
+            .collect()
     }
 
-    fn set_position(&mut self, id: Uuid, translation: Vec3, rotation: Quat) -> anyhow::Result<()> {
-        self.run_on_rb(id, |rb| {
-            rb.set_position((translation, rotation).into(), true);
-        })
+    #[test]
+    fn hard_impacts_send_contact_forces() {
+        let forces = impact_forces(10.0);
+        let (magnitude, point) = forces
+            .iter()
+            .copied()
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .expect("no contact force events");
+        assert!(magnitude >= 10.0);
+        // under the ball, on the ground
+        assert!(point.distance(Vec3::new(1.0, 0.0, 0.0)) < 0.1, "{point}");
+
+        assert!(impact_forces(1.0e6).is_empty());
     }
 
-    fn set_translation(&mut self, id: Uuid, translation: Vec3) -> anyhow::Result<()> {
-        self.run_on_rb(id, |rb| {
-            rb.set_translation(translation.into(), true);
-        })
+    #[test]
+    fn falling_body_moves_its_transform_component() {
+        let mut entities = EntityRegistry::new();
+        let id = spawn_body(
+            &mut entities,
+            Transform3D::new(Vec3::new(0.0, 10.0, 0.0), Quat::IDENTITY, Vec3::splat(2.0)),
+            PhysicsBody::new(
+                ColliderBuilder::ball(0.5).build(),
+                RigidBodyBuilder::dynamic().build(),
+            ),
+        );
+
+        let (mut engine, _commands, _events) = engine(&entities);
+        let mut last = transform(&entities, id);
+        for _ in 0..10 {
+            engine.step().unwrap();
+            let now = transform(&entities, id);
+            assert!(now.position.y < last.position.y);
+            assert_eq!(now.scale, Vec3::splat(2.0));
+            last = now;
+        }
     }
 
-    fn set_rotation(&mut self, id: Uuid, rotation: Quat) -> anyhow::Result<()> {
-        self.run_on_rb(id, |rb| {
-            rb.set_rotation(rotation.into(), true);
+    fn physics_updates(entities: &EntityRegistry, id: Uuid) -> Vec<(f64, Vec<PhysicsEvent>)> {
+        entities.get(&id).unwrap().read_with(|e| {
+            e.as_any()
+                .downcast_ref::<Dummy>()
+                .unwrap()
+                .physics_updates
+                .clone()
         })
     }
 
-    fn run_on_rb<F>(&mut self, id: Uuid, mut op: F) -> anyhow::Result<()>
-    where
-        F: FnMut(&mut RigidBody),
-    {
-        match self.entities.get(&id) {
-            Some(e) => match e.lock().unwrap().components().get::<PhysicsBody>() {
-                Some(pb) => match &pb.rigid_body {
-                    RigidBodyState::Active(handle) => {
-                        match self.rigid_body_set.get_mut(handle.clone()) {
-                            Some(rb) => Ok(op(rb)),
-                            None => {
-                                Err(anyhow::anyhow!("rigid body handle leads to no rigid body"))
-                            }
-                        }
-                    }
-                    RigidBodyState::Removed => Err(anyhow::anyhow!("rigid body has been removed")),
-                    RigidBodyState::Pending(_rb) => {
-                        Err(anyhow::anyhow!("cannot mutate pending body"))
-                    }
+    /// every event the entity got in `physics_update` so far
+    fn received(entities: &EntityRegistry, id: Uuid) -> Vec<PhysicsEvent> {
+        physics_updates(entities, id)
+            .into_iter()
+            .flat_map(|(_, events)| events)
+            .collect()
+    }
+
+    #[test]
+    fn landing_reaches_physics_update() {
+        let mut entities = EntityRegistry::new();
+        let ground = spawn(
+            &mut entities,
+            Vec3::new(0.0, -0.5, 0.0),
+            ColliderBuilder::cuboid(10.0, 0.5, 10.0).build(),
+            RigidBodyBuilder::fixed().build(),
+        );
+        let ball = spawn(
+            &mut entities,
+            Vec3::new(0.0, 2.0, 0.0),
+            ColliderBuilder::ball(0.5).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        let bystander = Dummy::new(ComponentSet::new());
+        let bystander_id = bystander.id();
+        entities.add(bystander.into_container());
+
+        let (mut engine, _commands, _events) = engine(&entities);
+        (0..60).for_each(|_| engine.step().unwrap());
+
+        let updates = physics_updates(&entities, ball);
+        assert_eq!(updates.len(), 60);
+        let dt = engine.world.integration_parameters.dt as f64 * 1000.0;
+        assert!(updates.iter().all(|(delta, _)| *delta == dt));
+
+        let landed = |updates: &[(f64, Vec<PhysicsEvent>)]| {
+            updates
+                .iter()
+                .flat_map(|(_, events)| events)
+                .filter(|e| matches!(e, PhysicsEvent::CollisionStarted { .. }))
+                .count()
+        };
+        assert!(landed(&updates) > 0);
+        assert_eq!(
+            landed(&physics_updates(&entities, ground)),
+            landed(&updates)
+        );
+        // no body, no physics_update
+        assert!(physics_updates(&entities, bystander_id).is_empty());
+    }
+
+    #[test]
+    fn ball_rests_on_heightfield_plateau() {
+        // 2 high plateau over the middle 3x3 samples, sloping down to 0 around it
+        let heights: Vec<Vec<f32>> = (0..9)
+            .map(|r| {
+                (0..9)
+                    .map(|c| match (3..=5).contains(&r) && (3..=5).contains(&c) {
+                        true => 2.0,
+                        false => 0.0,
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut entities = EntityRegistry::new();
+        spawn(
+            &mut entities,
+            Vec3::ZERO,
+            heightfield_collider(&heights, 1.0).unwrap(),
+            RigidBodyBuilder::fixed().build(),
+        );
+        let ball = spawn(
+            &mut entities,
+            Vec3::new(0.2, 5.0, -0.1),
+            ColliderBuilder::ball(0.25).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+
+        let (mut engine, _commands, _events) = engine(&entities);
+        (0..180).for_each(|_| engine.step().unwrap());
+
+        let rest = position(&entities, ball);
+        assert!((rest.y - 2.25).abs() < 0.05, "{rest}");
+        assert!(rest.x.abs() < 1.0 && rest.z.abs() < 1.0, "{rest}");
+    }
+
+    #[test]
+    fn ball_settles_where_the_terrain_mesh_is_lowest() {
+        use crate::assets::basic_models::{HeightSource, TerrainBuilder};
+
+        // a bowl with its bottom off the center, so a collider and mesh that didn't line up
+        // would put the ball somewhere else than the mesh's lowest vertex
+        let (bottom_row, bottom_column) = (3.0, 6.0);
+        let heights = (0..9)
+            .map(|r| {
+                (0..11)
+                    .map(|c| {
+                        let (dr, dc) = (r as f32 - bottom_row, c as f32 - bottom_column);
+                        0.3 + (dr * dr + dc * dc) / 64.0
+                    })
+                    .collect()
+            })
+            .collect();
+        let terrain = TerrainBuilder::new(HeightSource::Grid(heights))
+            .cell_size(1.5)
+            .height_scale(4.0)
+            .build()
+            .unwrap();
+        let lowest = terrain
+            .model
+            .nodes
+            .iter()
+            .flat_map(|n| &n.meshes)
+            .flat_map(|m| &m.primitives)
+            .flat_map(|p| p.positions.iter().copied())
+            .min_by(|a, b| a.y.total_cmp(&b.y))
+            .unwrap();
+
+        let mut entities = EntityRegistry::new();
+        spawn(
+            &mut entities,
+            Vec3::ZERO,
+            terrain.collider(),
+            RigidBodyBuilder::fixed().build(),
+        );
+        let ball = spawn(
+            &mut entities,
+            lowest + Vec3::new(0.4, 3.0, -0.3),
+            ColliderBuilder::ball(0.25).build(),
+            RigidBodyBuilder::dynamic()
+                .linear_damping(1.0)
+                .angular_damping(2.0)
+                .build(),
+        );
+
+        let (mut engine, _commands, _events) = engine(&entities);
+        (0..600).for_each(|_| engine.step().unwrap());
+
+        // the slopes around the bottom hold the ball a little above it
+        let rest = position(&entities, ball);
+        assert!((rest.y - 0.25 - lowest.y).abs() < 0.1, "{rest} {lowest}");
+        assert!(
+            (rest.x - lowest.x).abs() < 0.5 && (rest.z - lowest.z).abs() < 0.5,
+            "{rest} {lowest}"
+        );
+    }
+
+    #[test]
+    fn settings_apply_on_the_next_step() {
+        let mut entities = EntityRegistry::new();
+        let body = spawn(
+            &mut entities,
+            Vec3::new(0.0, 10.0, 0.0),
+            ColliderBuilder::ball(0.5).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        let (mut engine, _commands, _events) = engine(&entities);
+        engine.step().unwrap();
+
+        let settings = PhysicsSettings {
+            timestep: Duration::from_millis(5),
+            solver_iterations: 8,
+            erp: 0.5,
+            ..PhysicsSettings::default()
+        };
+        engine
+            .handle_command(PhysicsCommand::SetIntegrationParameters(settings))
+            .unwrap();
+        let before = position(&entities, body);
+        engine.step().unwrap();
+
+        let params = engine.world.integration_parameters;
+        assert_eq!(params.dt, 0.005);
+        assert_eq!(usize::from(params.num_solver_iterations), 8);
+        assert!((params.contact_erp() - 0.5).abs() < 1e-4);
+        // a shorter step moves the body less than a 1/60 s one would
+        let moved = before.y - position(&entities, body).y;
+        assert!(moved > 0.0 && moved < 0.003, "{moved}");
+
+        // the erp stays put when time scale changes dt
+        engine
+            .handle_command(PhysicsCommand::SetTimeScale(0.5))
+            .unwrap();
+        assert!((engine.world.integration_parameters.contact_erp() - 0.5).abs() < 1e-4);
+        assert_eq!(engine.settings(), settings);
+    }
+
+    #[test]
+    fn paused_bodies_stay_put() {
+        let mut entities = EntityRegistry::new();
+        let ball = spawn(
+            &mut entities,
+            Vec3::new(0.0, 10.0, 0.0),
+            ColliderBuilder::ball(0.5).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        let (mut engine, command_tx, _events) = engine(&entities);
+        (0..5).for_each(|_| engine.step().unwrap());
+
+        command_tx.send(PhysicsCommand::Pause).unwrap();
+        engine.step().unwrap();
+        let paused_at = position(&entities, ball);
+        (0..30).for_each(|_| engine.step().unwrap());
+        assert_eq!(position(&entities, ball), paused_at);
+
+        // commands still go through while paused
+        command_tx
+            .send(PhysicsCommand::SetLinearVelocity {
+                id: ball,
+                velocity: Vec3::new(0.0, 0.0, 60.0),
+            })
+            .unwrap();
+        command_tx.send(PhysicsCommand::StepOnce).unwrap();
+        engine.step().unwrap();
+        let stepped = position(&entities, ball);
+        assert!((stepped.z - 1.0).abs() < 0.01, "{stepped}");
+        (0..30).for_each(|_| engine.step().unwrap());
+        assert_eq!(position(&entities, ball), stepped);
+
+        command_tx.send(PhysicsCommand::Resume).unwrap();
+        engine.step().unwrap();
+        assert!(position(&entities, ball).z > stepped.z);
+    }
+
+    /// distance a body coasting at 1 m/s covers in 60 steps at the given time scale
+    fn coast(scale: f32) -> f32 {
+        let mut entities = EntityRegistry::new();
+        let body = spawn(
+            &mut entities,
+            Vec3::ZERO,
+            ColliderBuilder::ball(0.5).build(),
+            RigidBodyBuilder::dynamic()
+                .gravity_scale(0.0)
+                .linvel(vector![1.0, 0.0, 0.0])
+                .build(),
+        );
+        let (mut engine, _commands, _events) = engine(&entities);
+        engine
+            .handle_command(PhysicsCommand::SetTimeScale(scale))
+            .unwrap();
+        (0..60).for_each(|_| engine.step().unwrap());
+        position(&entities, body).x
+    }
+
+    #[test]
+    fn time_scale_slows_the_simulation() {
+        let real_time = coast(1.0);
+        assert!((real_time - 1.0).abs() < 0.01, "{real_time}");
+        assert!((coast(0.5) - real_time / 2.0).abs() < 0.01);
+        assert_eq!(coast(0.0), 0.0);
+        assert!((coast(10.0) - 4.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn debug_lines_only_while_enabled() {
+        let mut entities = EntityRegistry::new();
+        spawn(
+            &mut entities,
+            Vec3::ZERO,
+            ColliderBuilder::cuboid(1.0, 1.0, 1.0).build(),
+            RigidBodyBuilder::fixed().build(),
+        );
+        let (mut engine, _commands, _events) = engine(&entities);
+        let lines = Arc::new(RwLock::new(PhysicsDebugLines::default()));
+        engine.set_debug_lines(lines.clone());
+
+        engine.step().unwrap();
+        assert!(lines.read().unwrap().lines.is_empty());
+
+        lines.write().unwrap().enabled = true;
+        engine.step().unwrap();
+        assert_eq!(lines.read().unwrap().revision, 1);
+        // nothing moved, so the lines stay the same
+        engine.step().unwrap();
+        assert_eq!(lines.read().unwrap().revision, 1);
+        let lines = lines.read().unwrap();
+        // at least the 12 edges of the cuboid
+        assert!(lines.lines.len() >= 12, "{}", lines.lines.len());
+        assert!(
+            lines
+                .lines
+                .iter()
+                .all(|l| l.a.abs().max_element() <= 1.0 + 1e-4)
+        );
+    }
+
+    #[test]
+    fn body_spawned_after_start_falls() {
+        let mut entities = EntityRegistry::new();
+        let mut physics = PhysicsEngine::new(
+            Vec3::new(0.0, -9.81, 0.0),
+            PhysicsSettings::default(),
+            entities.clone(),
+        );
+        physics.start_physics().unwrap();
+
+        let mut components = ComponentSet::new();
+        components.add(PhysicsBody::new(
+            ColliderBuilder::ball(0.5).build(),
+            RigidBodyBuilder::dynamic().build(),
+        ));
+        let ball = Dummy::new(components);
+        let id = ball.id();
+        entities.add(ball.into_container());
+
+        // the thread picks it up on its own schedule, give it a generous deadline
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while position(&entities, id).y >= -0.1 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let ball = entities.get(&id).unwrap();
+        assert!(position(&entities, id).y < -0.1);
+        ball.read_with(|b| {
+            assert!(matches!(
+                b.components().get::<PhysicsBody>().unwrap().rigid_body,
+                RigidBodyState::Active(_)
+            ))
+        });
+    }
+
+    #[test]
+    fn replay_matches_the_recorded_run() {
+        let mut entities = EntityRegistry::new();
+        spawn(
+            &mut entities,
+            Vec3::ZERO,
+            ColliderBuilder::cuboid(10.0, 0.5, 10.0).build(),
+            RigidBodyBuilder::fixed().build(),
+        );
+        let ball = spawn(
+            &mut entities,
+            Vec3::new(0.0, 3.0, 0.0),
+            ColliderBuilder::ball(0.5).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        let initial: Vec<(Uuid, Transform3D, PhysicsBody)> = entities
+            .clone()
+            .into_iter()
+            .map(|e| {
+                e.read_with(|e| {
+                    let components = e.components();
+                    (
+                        e.id(),
+                        *components.get::<Transform3D>().unwrap(),
+                        components.get::<PhysicsBody>().unwrap().clone(),
+                    )
+                })
+            })
+            .collect();
+
+        let (mut live, command_tx, _events) = engine(&entities);
+        let recorder = PhysicsRecorder::default();
+        live.set_recorder(recorder.clone());
+        recorder.start();
+        for step in 0..90 {
+            let command = match step {
+                10 => PhysicsCommand::ApplyImpulse {
+                    id: ball,
+                    impulse: Vec3::new(1.0, 2.0, 0.5),
                 },
-                None => Err(anyhow::anyhow!("entity has no physics body component")),
-            },
-            None => Err(anyhow::anyhow!("no entity with provided id found")),
+                40 => PhysicsCommand::SetTimeScale(0.5),
+                60 => PhysicsCommand::ApplyTorqueImpulse {
+                    id: ball,
+                    impulse: Vec3::new(0.0, 0.0, 0.3),
+                },
+                _ => {
+                    live.step().unwrap();
+                    continue;
+                }
+            };
+            command_tx.send(command).unwrap();
+            live.step().unwrap();
+        }
+        let recorded = transform(&entities, ball);
+        let recording = recorder.stop().unwrap();
+        assert_eq!(recording.steps, 90);
+        assert_eq!(recording.commands.len(), 3);
+
+        // through json like a saved recording, then back to the scene it started on
+        let recording: PhysicsRecording =
+            serde_json::from_str(&serde_json::to_string(&recording).unwrap()).unwrap();
+        for (id, transform, body) in initial {
+            entities.get(&id).unwrap().write_with(|e| {
+                e.components_mut().add(transform);
+                e.components_mut().add(body);
+            });
+        }
+        let (mut replayed, _commands, _events) = engine(&entities);
+        replayed.replay(&recording).unwrap();
+
+        assert_ne!(recorded.position, Vec3::new(0.0, 3.0, 0.0));
+        assert_eq!(transform(&entities, ball), recorded);
+    }
+
+    #[test]
+    fn layers_filter_collisions() {
+        let mut entities = EntityRegistry::new();
+        let ground = spawn(
+            &mut entities,
+            Vec3::ZERO,
+            ColliderBuilder::cuboid(10.0, 0.5, 10.0).build(),
+            RigidBodyBuilder::fixed().build(),
+        );
+        entities
+            .get(&ground)
+            .unwrap()
+            .write_with(|e| e.components_mut().add(Layer::default()));
+        let ghost = spawn(
+            &mut entities,
+            Vec3::new(0.0, 3.0, 0.0),
+            ColliderBuilder::ball(0.5).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        let solid = spawn(
+            &mut entities,
+            Vec3::new(3.0, 3.0, 0.0),
+            ColliderBuilder::ball(0.5).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        for id in [ghost, solid] {
+            entities.get(&id).unwrap().write_with(|e| {
+                e.components_mut()
+                    .add(Layer::new(0b10).with_collides_with(0b10))
+            });
         }
+        let (mut engine, _commands, _events) = engine(&entities);
+        engine.step().unwrap();
+
+        // the ground is on the default layer 1
+        engine
+            .handle_command(PhysicsCommand::SetLayer {
+                id: solid,
+                layer: Layer::new(0b10),
+            })
+            .unwrap();
+        for _ in 0..90 {
+            engine.step().unwrap();
+        }
+
+        assert!(position(&entities, ghost).y < 0.0);
+        assert!((position(&entities, solid).y - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn builder_collision_groups_stay_without_a_layer() {
+        let mut entities = EntityRegistry::new();
+        spawn(
+            &mut entities,
+            Vec3::ZERO,
+            ColliderBuilder::cuboid(10.0, 0.5, 10.0).build(),
+            RigidBodyBuilder::fixed().build(),
+        );
+        let ghost = spawn(
+            &mut entities,
+            Vec3::new(0.0, 3.0, 0.0),
+            ColliderBuilder::ball(0.5)
+                .collision_groups(
+                    InteractionGroups::all()
+                        .with_memberships(Group::GROUP_2)
+                        .with_filter(Group::GROUP_2),
+                )
+                .build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        let (mut engine, _commands, _events) = engine(&entities);
+        for _ in 0..90 {
+            engine.step().unwrap();
+        }
+
+        assert!(position(&entities, ghost).y < 0.0);
+    }
+
+    #[test]
+    fn teleport_moves_body_and_transform() {
+        let mut entities = EntityRegistry::new();
+        let ball = spawn(
+            &mut entities,
+            Vec3::new(0.0, 3.0, 0.0),
+            ColliderBuilder::ball(0.5).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        let (mut engine, _commands, events) = engine(&entities);
+        for _ in 0..20 {
+            engine.step().unwrap();
+        }
+
+        let target = Transform3D::new(
+            Vec3::new(5.0, 10.0, -2.0),
+            Quat::from_rotation_y(1.0),
+            Vec3::ONE,
+        );
+        engine
+            .handle_command(PhysicsCommand::Teleport {
+                id: ball,
+                transform: target,
+                reset_velocity: true,
+            })
+            .unwrap();
+        assert_eq!(transform(&entities, ball), target);
+
+        let (linvel, _) = body_state(&mut engine, &events, ball);
+        assert_eq!(linvel, Vec3::ZERO);
+        engine.step().unwrap();
+        let position = position(&entities, ball);
+        assert!(position.distance(target.position) < 0.01, "{position}");
+    }
+
+    #[test]
+    fn static_colliders_have_no_rigid_bodies() {
+        let mut entities = EntityRegistry::new();
+        for i in 0..500 {
+            let mut components = ComponentSet::new();
+            components.add(Transform3D::new(
+                Vec3::new((i % 25) as f32 * 2.0, 0.0, (i / 25) as f32 * 2.0),
+                Quat::IDENTITY,
+                Vec3::ONE,
+            ));
+            components.add(StaticCollider::new(
+                ColliderBuilder::cuboid(1.0, 0.5, 1.0).build(),
+            ));
+            entities.add(Dummy::new(components).into_container());
+        }
+        let (mut engine, _commands, _events) = engine(&entities);
+        assert_eq!(engine.world.rigid_body_set.len(), 0);
+        assert_eq!(engine.world.collider_set.len(), 500);
+
+        let ball = spawn(
+            &mut entities,
+            Vec3::new(10.0, 3.0, 10.0),
+            ColliderBuilder::ball(0.5).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        for _ in 0..120 {
+            engine.step().unwrap();
+        }
+        assert_eq!(engine.world.rigid_body_set.len(), 1);
+        // stepping doesn't insert them again
+        assert_eq!(engine.world.collider_set.len(), 501);
+        assert!((position(&entities, ball).y - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn low_center_of_mass_keeps_a_tall_box_upright() {
+        let mut entities = EntityRegistry::new();
+        spawn(
+            &mut entities,
+            Vec3::ZERO,
+            ColliderBuilder::cuboid(20.0, 0.5, 20.0).build(),
+            RigidBodyBuilder::fixed().build(),
+        );
+        let mut tall_box = |x: f32, mass: Option<AdditionalMass>| {
+            let mut body = PhysicsBody::new(
+                ColliderBuilder::cuboid(0.25, 1.0, 0.25).build(),
+                RigidBodyBuilder::dynamic().build(),
+            );
+            body.additional_mass = mass;
+            spawn_body(
+                &mut entities,
+                Transform3D::new(Vec3::new(x, 1.5, 0.0), Quat::IDENTITY, Vec3::ONE),
+                body,
+            )
+        };
+        let plain = tall_box(-5.0, None);
+        let weighted = tall_box(
+            5.0,
+            Some(AdditionalMass::new(10.0).with_center_of_mass(Vec3::new(0.0, -0.9, 0.0))),
+        );
+
+        let (mut engine, _commands, _events) = engine(&entities);
+        engine.step().unwrap();
+        // the same shove for both, friction at the base tips the plain one over
+        for id in [plain, weighted] {
+            engine
+                .handle_command(PhysicsCommand::SetLinearVelocity {
+                    id,
+                    velocity: Vec3::new(5.0, 0.0, 0.0),
+                })
+                .unwrap();
+        }
+        for _ in 0..240 {
+            engine.step().unwrap();
+        }
+
+        let up = |id| (transform(&entities, id).rotation * Vec3::Y).y;
+        assert!(up(plain) < 0.5, "{}", up(plain));
+        assert!(up(weighted) > 0.95, "{}", up(weighted));
+    }
+
+    #[test]
+    fn set_additional_mass_updates_the_component() {
+        let mut entities = EntityRegistry::new();
+        let ball = spawn(
+            &mut entities,
+            Vec3::ZERO,
+            ColliderBuilder::ball(0.5).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        let (mut engine, _commands, _events) = engine(&entities);
+        let mass = AdditionalMass::new(4.0);
+        engine
+            .handle_command(PhysicsCommand::SetAdditionalMass {
+                id: ball,
+                mass: Some(mass),
+            })
+            .unwrap();
+
+        let component = entities.get(&ball).unwrap().read_with(|e| {
+            e.components()
+                .get::<PhysicsBody>()
+                .and_then(|pb| pb.additional_mass)
+        });
+        assert_eq!(component, Some(mass));
+    }
+
+    #[test]
+    fn resting_body_sleeps_and_wakes() {
+        let mut entities = EntityRegistry::new();
+        spawn(
+            &mut entities,
+            Vec3::ZERO,
+            ColliderBuilder::cuboid(10.0, 0.5, 10.0).build(),
+            RigidBodyBuilder::fixed().build(),
+        );
+        let crate_id = spawn(
+            &mut entities,
+            Vec3::new(0.0, 1.5, 0.0),
+            ColliderBuilder::cuboid(0.5, 0.5, 0.5).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        let sleeping = |entities: &EntityRegistry| {
+            entities
+                .get(&crate_id)
+                .unwrap()
+                .read_with(|e| e.components().get::<IsSleeping>().is_some())
+        };
+        let (mut engine, _commands, _events) = engine(&entities);
+
+        let slept = |entities: &EntityRegistry| {
+            received(entities, crate_id).contains(&PhysicsEvent::BodySlept { id: crate_id })
+        };
+        for _ in 0..600 {
+            engine.step().unwrap();
+            if slept(&entities) {
+                break;
+            }
+        }
+        assert!(slept(&entities));
+        assert!(sleeping(&entities));
+
+        engine
+            .handle_command(PhysicsCommand::ApplyImpulse {
+                id: crate_id,
+                impulse: Vec3::new(0.0, 5.0, 0.0),
+            })
+            .unwrap();
+        engine.step().unwrap();
+        assert!(received(&entities, crate_id).contains(&PhysicsEvent::BodyWoke { id: crate_id }));
+        assert!(!sleeping(&entities));
+    }
+
+    #[test]
+    fn stats_count_every_step() {
+        let mut entities = EntityRegistry::new();
+        spawn(
+            &mut entities,
+            Vec3::new(0.0, 5.0, 0.0),
+            ColliderBuilder::ball(0.5).build(),
+            RigidBodyBuilder::dynamic().build(),
+        );
+        let (mut engine, _commands, _events) = engine(&entities);
+        let stats = Arc::new(RwLock::new(PhysicsStats::new(4)));
+        engine.set_stats(stats.clone());
+        for _ in 0..5 {
+            engine.step().unwrap();
+        }
+
+        let stats = stats.read().unwrap();
+        assert_eq!(stats.steps(), 5);
+        assert_eq!(stats.last().unwrap().active_bodies, 1);
+        assert_eq!(stats.average_contact_pairs(), 0.0);
     }
 }
@@ -0,0 +1,125 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use crate::physics::{DEFAULT_GRAVITY, commands::PhysicsCommand, settings::PhysicsSettings};
+
+/// a command with the step it was handled on, counted from the start of the recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedPhysicsCommand {
+    pub step: u64,
+    pub command: PhysicsCommand,
+}
+
+/// every command the physics thread handled while recording, along with the state it started
+/// from
+///
+/// steps are counted by calls to `step`, paused ones too, so replaying it against the scene
+/// the recording started on gives the same results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhysicsRecording {
+    pub gravity: Vec3,
+    pub settings: PhysicsSettings,
+    pub time_scale: f32,
+    pub paused: bool,
+    /// steps taken while recording
+    pub steps: u64,
+    /// in the order they were handled
+    pub commands: Vec<RecordedPhysicsCommand>,
+}
+
+impl Default for PhysicsRecording {
+    fn default() -> Self {
+        Self {
+            gravity: DEFAULT_GRAVITY,
+            settings: PhysicsSettings::default(),
+            time_scale: 1.0,
+            paused: false,
+            steps: 0,
+            commands: Vec::new(),
+        }
+    }
+}
+
+impl PhysicsRecording {
+    /// commands handled on `step`
+    pub fn commands_on(&self, step: u64) -> impl Iterator<Item = &PhysicsCommand> {
+        let start = self.commands.partition_point(|c| c.step < step);
+        self.commands[start..]
+            .iter()
+            .take_while(move |c| c.step == step)
+            .map(|c| &c.command)
+    }
+
+    /// writes the recording to `path` as json
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+}
+
+/// shared between `PhysicsEngine` and the physics thread, holds the recording while one is
+/// going
+#[derive(Debug, Clone, Default)]
+pub struct PhysicsRecorder(Arc<Mutex<Option<PhysicsRecording>>>);
+
+impl PhysicsRecorder {
+    /// drops any recording that was going and starts over from the next step
+    pub fn start(&self) {
+        *self.0.lock().unwrap() = Some(PhysicsRecording::default());
+    }
+
+    pub fn stop(&self) -> Option<PhysicsRecording> {
+        self.0.lock().unwrap().take()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.0.lock().unwrap().is_some()
+    }
+
+    /// adds a change that didn't come through the command channel to the next step
+    pub(crate) fn insert(&self, command: PhysicsCommand) {
+        if let Some(recording) = self.0.lock().unwrap().as_mut() {
+            let step = recording.steps;
+            recording
+                .commands
+                .push(RecordedPhysicsCommand { step, command });
+        }
+    }
+
+    /// adds a step that handled `commands`, `start` fills in the simulation's state on the
+    /// first one
+    pub(crate) fn record(
+        &self,
+        commands: &[PhysicsCommand],
+        start: impl FnOnce(&mut PhysicsRecording),
+    ) {
+        let mut recording = self.0.lock().unwrap();
+        let Some(recording) = recording.as_mut() else {
+            return;
+        };
+        if recording.steps == 0 {
+            start(recording);
+        }
+        let step = recording.steps;
+        recording
+            .commands
+            .extend(commands.iter().map(|command| RecordedPhysicsCommand {
+                step,
+                command: command.clone(),
+            }));
+        recording.steps += 1;
+    }
+}
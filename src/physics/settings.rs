@@ -0,0 +1,134 @@
+use std::{f32::consts::TAU, time::Duration};
+
+use rapier3d::prelude::IntegrationParameters;
+use serde::{Deserialize, Serialize};
+
+use crate::physics::timestep::DEFAULT_TIMESTEP;
+
+/// solver settings, more iterations and shorter steps trade speed for steadier stacks
+///
+/// lengths are in meters, rapier's `length_unit` is left at 1
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PhysicsSettings {
+    /// wall clock length of a step, time scale is applied on top
+    pub timestep: Duration,
+    /// constraint solver iterations per step, at least 1
+    pub solver_iterations: usize,
+    /// gauss seidel passes in every solver iteration
+    pub pgs_iterations: usize,
+    /// penetration correction passes in every solver iteration
+    pub stabilization_iterations: usize,
+    /// fraction of contact penetration fixed each step, 0..1
+    pub erp: f32,
+    /// penetration the solver leaves alone
+    pub allowed_linear_error: f32,
+    /// fastest the solver pushes overlapping bodies apart
+    pub max_corrective_velocity: f32,
+    /// how far apart contacts start being generated
+    pub prediction_distance: f32,
+    /// ccd substeps for fast bodies
+    pub max_ccd_substeps: usize,
+}
+
+impl Default for PhysicsSettings {
+    /// rapier's defaults at `DEFAULT_TIMESTEP`
+    fn default() -> Self {
+        let params = IntegrationParameters {
+            dt: DEFAULT_TIMESTEP.as_secs_f32(),
+            ..IntegrationParameters::default()
+        };
+        Self {
+            timestep: DEFAULT_TIMESTEP,
+            solver_iterations: params.num_solver_iterations.into(),
+            pgs_iterations: params.num_internal_pgs_iterations,
+            stabilization_iterations: params.num_internal_stabilization_iterations,
+            erp: params.contact_erp(),
+            allowed_linear_error: params.allowed_linear_error(),
+            max_corrective_velocity: params.max_corrective_velocity(),
+            prediction_distance: params.prediction_distance(),
+            max_ccd_substeps: params.max_ccd_substeps,
+        }
+    }
+}
+
+/// both rapiers have the same fields on differently typed parameters
+macro_rules! apply_settings {
+    ($settings:expr, $params:expr) => {{
+        let settings = $settings;
+        let params = $params;
+        params.length_unit = 1.0;
+        params.num_solver_iterations = settings
+            .solver_iterations
+            .max(1)
+            .try_into()
+            .expect("solver iterations are at least 1");
+        params.num_internal_pgs_iterations = settings.pgs_iterations;
+        params.num_internal_stabilization_iterations = settings.stabilization_iterations;
+        params.normalized_allowed_linear_error = settings.allowed_linear_error;
+        params.normalized_max_corrective_velocity = settings.max_corrective_velocity;
+        params.normalized_prediction_distance = settings.prediction_distance;
+        params.max_ccd_substeps = settings.max_ccd_substeps;
+
+        // rapier derives the erp from a spring, erp = dt * w / (dt * w + 2 * damping ratio)
+        let erp = settings.erp.clamp(0.01, 0.99);
+        let angular_frequency =
+            2.0 * params.contact_damping_ratio * erp / (params.dt * (1.0 - erp));
+        params.contact_natural_frequency = angular_frequency / TAU;
+    }};
+}
+
+impl PhysicsSettings {
+    /// writes everything but the timestep into `params`, the erp depends on `params.dt` so it
+    /// has to be up to date
+    pub(crate) fn apply(&self, params: &mut IntegrationParameters) {
+        apply_settings!(self, params);
+    }
+
+    /// `apply` for the 2d backend's parameters
+    pub(crate) fn apply_2d(&self, params: &mut rapier2d::prelude::IntegrationParameters) {
+        apply_settings!(self, params);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_match_rapier() {
+        let mut params = IntegrationParameters {
+            dt: DEFAULT_TIMESTEP.as_secs_f32(),
+            ..IntegrationParameters::default()
+        };
+        let expected = params;
+        PhysicsSettings::default().apply(&mut params);
+
+        assert_eq!(params.num_solver_iterations, expected.num_solver_iterations);
+        assert_eq!(
+            params.normalized_allowed_linear_error,
+            expected.normalized_allowed_linear_error
+        );
+        assert!(
+            (params.contact_natural_frequency - expected.contact_natural_frequency).abs() < 1e-2,
+            "{} {}",
+            params.contact_natural_frequency,
+            expected.contact_natural_frequency
+        );
+    }
+
+    #[test]
+    fn erp_holds_for_any_dt() {
+        let settings = PhysicsSettings {
+            erp: 0.6,
+            ..PhysicsSettings::default()
+        };
+        for dt in [1.0 / 30.0, 1.0 / 60.0, 1.0 / 240.0] {
+            let mut params = IntegrationParameters {
+                dt,
+                ..IntegrationParameters::default()
+            };
+            settings.apply(&mut params);
+            assert!((params.contact_erp() - 0.6).abs() < 1e-4, "{dt}");
+        }
+    }
+}
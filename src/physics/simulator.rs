@@ -0,0 +1,957 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{Receiver, Sender},
+    },
+    time::{Duration, Instant},
+};
+
+use glam::{Quat, Vec3};
+use rapier3d::prelude::Collider;
+use uuid::Uuid;
+
+use crate::{
+    engine::{
+        component::{Layer, Transform3D},
+        entity::{Entity, EntityRegistry},
+    },
+    physics::{
+        AdditionalMass, IsSleeping, PhysicsBody, PhysicsMaterial, PhysicsVelocity, RigidBodyState,
+        Simulation, StaticCollider,
+        commands::{PhysicsCommand, PhysicsEvent},
+        debug_render::PhysicsDebugLines,
+        interpolation::InterpolatedTransform,
+        recording::{PhysicsRecorder, PhysicsRecording},
+        settings::PhysicsSettings,
+        stats::{PhysicsStats, StepStats},
+    },
+};
+
+/// fastest `SetTimeScale` goes, faster makes big steps that tunnel and explode
+const MAX_TIME_SCALE: f32 = 4.0;
+
+/// how far a transform can drift from where the last step put it before debug builds warn
+const DESYNC_DISTANCE: f32 = 0.01;
+const DESYNC_ANGLE: f32 = 0.01;
+
+/// a rapier rigid body through glam types, the 2d one drops whatever is out of the xy plane
+pub trait WorldBody {
+    fn is_dynamic(&self) -> bool;
+    /// position based kinematic, the body follows the transform instead of the other way around
+    fn is_kinematic(&self) -> bool;
+    fn is_sleeping(&self) -> bool;
+    fn mass(&self) -> f32;
+    fn linvel(&self) -> Vec3;
+    fn angvel(&self) -> Vec3;
+    /// puts the body's pose in `transform`, scale is the entity's own
+    fn write_pose(&self, transform: &mut Transform3D);
+
+    fn set_pose(&mut self, translation: Vec3, rotation: Quat);
+    fn set_translation(&mut self, translation: Vec3);
+    fn set_rotation(&mut self, rotation: Quat);
+    fn set_linvel(&mut self, velocity: Vec3);
+    fn set_angvel(&mut self, velocity: Vec3);
+    fn add_force(&mut self, force: Vec3);
+    fn add_torque(&mut self, torque: Vec3);
+    fn apply_impulse(&mut self, impulse: Vec3);
+    fn apply_torque_impulse(&mut self, impulse: Vec3);
+    fn set_enabled(&mut self, enabled: bool);
+    fn set_gravity_scale(&mut self, scale: f32);
+    fn set_linear_damping(&mut self, damping: f32);
+    fn set_angular_damping(&mut self, damping: f32);
+    fn set_additional_mass(&mut self, mass: AdditionalMass, wake_up: bool);
+    /// `true` locks the axis
+    fn lock_rotations(&mut self, locked: [bool; 3]);
+    fn lock_translations(&mut self, locked: [bool; 3]);
+    fn set_dominance_group(&mut self, group: i8);
+    fn sleep(&mut self);
+    fn wake_up(&mut self);
+}
+
+/// a rapier collider through glam types
+pub trait WorldCollider {
+    /// scales the shape and its position on the body
+    fn scale(&mut self, scale: Vec3) -> anyhow::Result<()>;
+    /// moves the collider from the entity's space into the world, for colliders without a body
+    fn place(&mut self, transform: &Transform3D);
+    fn apply_material(&mut self, material: &PhysicsMaterial);
+    fn set_friction(&mut self, friction: f32);
+    fn set_restitution(&mut self, restitution: f32);
+    fn set_enabled(&mut self, enabled: bool);
+    fn set_layer(&mut self, layer: &Layer);
+    /// sends `ContactForce` when the total force on the collider goes over `threshold`
+    fn set_contact_force_threshold(&mut self, threshold: f32);
+    /// turns collision events on and tags the collider with its entity
+    fn attach_to(&mut self, id: Uuid);
+}
+
+/// the rapier world a `Simulator` drives, one for each `PhysicsBackend`
+///
+/// only what depends on the dimension lives here, bodies and colliders, the pipeline, queries
+/// and the commands only one backend knows
+pub trait World: Default + Send {
+    type Body: WorldBody;
+    type Collider: WorldCollider;
+
+    /// sets the length of the next steps, `None` keeps the last one, and the rest of `settings`
+    fn configure(&mut self, dt: Option<f32>, settings: &PhysicsSettings);
+    /// length of the next step in seconds
+    fn dt(&self) -> f32;
+
+    /// the pending body and its colliders in this world, colliders at their offset on the body
+    fn build_body(
+        &self,
+        rigid_body: &rapier3d::prelude::RigidBody,
+        body: &PhysicsBody,
+    ) -> anyhow::Result<(Self::Body, Vec<Self::Collider>)>;
+    /// the colliders at their offset from the entity, ones that don't fit this world are left out
+    fn build_static_colliders(&self, id: Uuid, statics: &StaticCollider) -> Vec<Self::Collider>;
+    /// adds the body with its colliders attached, gives the state for the entity's `PhysicsBody`
+    fn insert_body(
+        &mut self,
+        id: Uuid,
+        body: Self::Body,
+        colliders: Vec<Self::Collider>,
+    ) -> RigidBodyState;
+    fn insert_static_colliders(&mut self, id: Uuid, colliders: Vec<Self::Collider>);
+    /// removes the entity's body and static colliders, whether it had a rigid body
+    fn remove_body(&mut self, id: Uuid) -> anyhow::Result<bool>;
+
+    /// the body the state points at, pending ones too if they're already in this world's types
+    fn body<'a>(&'a self, state: &'a RigidBodyState) -> Option<&'a Self::Body>;
+    /// the active body the state points at
+    fn body_mut(&mut self, state: &RigidBodyState) -> anyhow::Result<&mut Self::Body>;
+    /// runs `op` on the entity's static colliders, `false` if it has none
+    fn static_colliders_mut(&mut self, id: Uuid, op: &mut dyn FnMut(&mut Self::Collider)) -> bool;
+    fn body_colliders_mut(
+        &mut self,
+        state: &RigidBodyState,
+        op: &mut dyn FnMut(&mut Self::Collider),
+    ) -> anyhow::Result<()>;
+    fn wake_dynamic_bodies(&mut self);
+
+    /// takes a step, gives the collision and contact force events it caused
+    fn step(&mut self, gravity: Vec3, entities: &EntityRegistry) -> Vec<PhysicsEvent>;
+    /// every entity with a dynamic body and whether it's asleep
+    fn dynamic_bodies(&self) -> Vec<(Uuid, bool)>;
+    /// dynamic bodies that weren't asleep and collider pairs touching, for `StepStats`
+    fn counts(&self) -> (usize, usize);
+    fn set_debug_lines(&mut self, lines: Arc<RwLock<PhysicsDebugLines>>);
+
+    fn raycast(&self, origin: Vec3, dir: Vec3, max_toi: f32, request_id: Uuid) -> PhysicsEvent;
+    fn shape_cast(
+        &self,
+        shape: &Collider,
+        origin: Vec3,
+        rotation: Quat,
+        dir: Vec3,
+        max_toi: f32,
+        request_id: Uuid,
+    ) -> anyhow::Result<PhysicsEvent>;
+    fn overlap_shape(
+        &self,
+        shape: &Collider,
+        origin: Vec3,
+        rotation: Quat,
+        request_id: Uuid,
+    ) -> anyhow::Result<PhysicsEvent>;
+
+    /// joints and character controllers, the event that comes back is sent to the engine
+    fn handle_world_command(
+        &mut self,
+        command: PhysicsCommand,
+        entities: &EntityRegistry,
+    ) -> anyhow::Result<Option<PhysicsEvent>>;
+}
+
+/// the part of a physics backend that's the same for every `World`: the command channel,
+/// recording, stats, pausing and time scale, and writing the bodies back to the entities
+pub struct Simulator<W: World> {
+    pub gravity: Vec3,
+    pub world: W,
+
+    command_receiver: Receiver<PhysicsCommand>,
+    /// answers to queries, for the `PhysicsEngine` waiting on them
+    event_sender: Sender<PhysicsEvent>,
+    pending_commands: Arc<AtomicUsize>,
+    /// entity events since the last step, handed to the entities in `physics_update`
+    step_events: Vec<PhysicsEvent>,
+
+    entities: EntityRegistry,
+
+    /// linear and angular velocity for bodies that were still pending when it was set
+    pub(crate) pending_velocities: HashMap<Uuid, (Vec3, Vec3)>,
+    /// transform each entity got on the last write back, debug builds warn when something else
+    /// moved it in between
+    written_transforms: HashMap<Uuid, Transform3D>,
+    /// whether each dynamic body was asleep after the last step, for `BodySlept` and `BodyWoke`
+    sleeping: HashMap<Uuid, bool>,
+    /// entities whose `StaticCollider` has been inserted, each one is only tried once
+    placed_statics: HashSet<Uuid>,
+    paused: bool,
+    /// the world's dt is `settings.timestep` times `time_scale`
+    settings: PhysicsSettings,
+    time_scale: f32,
+    /// steps still to take while paused
+    single_steps: u32,
+    recorder: PhysicsRecorder,
+    stats: Option<Arc<RwLock<PhysicsStats>>>,
+}
+
+impl<W: World> Simulator<W> {
+    pub fn new(
+        gravity: Vec3,
+        entities: EntityRegistry,
+        command_receiver: Receiver<PhysicsCommand>,
+        event_sender: Sender<PhysicsEvent>,
+        pending_commands: Arc<AtomicUsize>,
+        settings: PhysicsSettings,
+    ) -> Self {
+        let mut simulator = Self {
+            gravity,
+            world: W::default(),
+            command_receiver,
+            event_sender,
+            pending_commands,
+            step_events: Vec::new(),
+            entities: entities.clone(),
+            pending_velocities: HashMap::new(),
+            written_transforms: HashMap::new(),
+            sleeping: HashMap::new(),
+            placed_statics: HashSet::new(),
+            paused: false,
+            settings,
+            time_scale: 1.0,
+            single_steps: 0,
+            recorder: PhysicsRecorder::default(),
+            stats: None,
+        };
+        simulator.set_settings(settings);
+
+        for e in entities.into_iter() {
+            e.write_with(|e| {
+                simulator.insert_pending_body(e);
+                simulator.insert_static_colliders(e);
+            });
+        }
+
+        simulator
+    }
+
+    /// picks up bodies of entities added to the registry after the simulator was made
+    ///
+    /// entities added from another thread while this runs are caught on the next step
+    fn insert_spawned_bodies(&mut self) {
+        for e in self.entities.clone().into_iter() {
+            let (pending, unplaced) = e.read_with(|e| {
+                let components = e.components();
+                (
+                    matches!(
+                        components.get::<PhysicsBody>().map(|pb| &pb.rigid_body),
+                        Some(RigidBodyState::Pending(_))
+                    ),
+                    components.get::<StaticCollider>().is_some()
+                        && !self.placed_statics.contains(&e.id()),
+                )
+            });
+            if pending {
+                e.write_with(|e| self.insert_pending_body(e));
+            }
+            if unplaced {
+                e.write_with(|e| self.insert_static_colliders(e));
+            }
+        }
+    }
+
+    /// adds the entity's pending rigid body and colliders to the world, bodies the world can't
+    /// take are marked removed so they're only warned about once
+    fn insert_pending_body(&mut self, entity: &mut dyn Entity) {
+        let id = entity.id();
+        // bodies start where the entity is, entities without a transform get one to write to
+        let transform = *entity
+            .components_mut()
+            .get_or_insert_with(Transform3D::default);
+        // without a layer the colliders keep the groups they were built with
+        let layer = entity.components().get::<Layer>().copied();
+        let Some(body) = entity.components_mut().get_mut::<PhysicsBody>() else {
+            return;
+        };
+        let RigidBodyState::Pending(pending) = &body.rigid_body else {
+            log::debug!("Weird: entity body skipped on insertion because it isn't pending");
+            return;
+        };
+        let (mut rigid_body, colliders) = match self.world.build_body(pending, body) {
+            Ok(built) => built,
+            Err(e) => {
+                log::warn!("entity {id} left out of the simulation: {e}");
+                body.rigid_body = RigidBodyState::Removed;
+                return;
+            }
+        };
+
+        rigid_body.set_pose(transform.position, transform.rotation);
+        if let Some(damping) = body.linear_damping {
+            rigid_body.set_linear_damping(damping);
+        }
+        if let Some(damping) = body.angular_damping {
+            rigid_body.set_angular_damping(damping);
+        }
+        if let Some(mass) = body.additional_mass {
+            rigid_body.set_additional_mass(mass, false);
+        }
+        if let Some(locked) = body.locked_rotations {
+            rigid_body.lock_rotations(locked);
+        }
+        if let Some(locked) = body.locked_translations {
+            rigid_body.lock_translations(locked);
+        }
+        if let Some((linvel, angvel)) = self.pending_velocities.remove(&id) {
+            rigid_body.set_linvel(linvel);
+            rigid_body.set_angvel(angvel);
+        }
+        let dynamic = rigid_body.is_dynamic();
+
+        let colliders = colliders
+            .into_iter()
+            .map(|mut collider| {
+                if let Err(e) = collider.scale(transform.scale) {
+                    log::warn!("inserting entity {id}'s collider unscaled: {e}");
+                }
+                if let Some(material) = body.material.as_ref() {
+                    collider.apply_material(material);
+                }
+                if let Some(threshold) = body.contact_force_threshold {
+                    collider.set_contact_force_threshold(threshold);
+                }
+                if let Some(layer) = layer.as_ref() {
+                    collider.set_layer(layer);
+                }
+                collider.attach_to(id);
+                collider
+            })
+            .collect();
+        body.rigid_body = self.world.insert_body(id, rigid_body, colliders);
+
+        if dynamic {
+            entity
+                .components_mut()
+                .add(InterpolatedTransform::new(transform));
+        }
+    }
+
+    /// adds the entity's static colliders at its transform, without a rigid body
+    fn insert_static_colliders(&mut self, entity: &mut dyn Entity) {
+        let id = entity.id();
+        let transform = entity
+            .components()
+            .get::<Transform3D>()
+            .copied()
+            .unwrap_or_default();
+        // without a layer the colliders keep the groups they were built with
+        let layer = entity.components().get::<Layer>().copied();
+        if self.placed_statics.contains(&id) {
+            return;
+        }
+        let Some(statics) = entity.components().get::<StaticCollider>() else {
+            return;
+        };
+
+        let colliders = self
+            .world
+            .build_static_colliders(id, statics)
+            .into_iter()
+            .map(|mut collider| {
+                if let Err(e) = collider.scale(transform.scale) {
+                    log::warn!("inserting entity {id}'s static collider unscaled: {e}");
+                }
+                collider.place(&transform);
+                if let Some(material) = statics.material.as_ref() {
+                    collider.apply_material(material);
+                }
+                if let Some(layer) = layer.as_ref() {
+                    collider.set_layer(layer);
+                }
+                collider.attach_to(id);
+                collider
+            })
+            .collect();
+        // only tried once, colliders the world can't take have been warned about
+        self.placed_statics.insert(id);
+        self.world.insert_static_colliders(id, colliders);
+    }
+
+    /// length of the next steps, rapier's default is 1/60 s
+    pub fn set_timestep(&mut self, timestep: Duration) {
+        self.settings.timestep = timestep;
+        self.set_time_scale(self.time_scale);
+        // it doesn't come in as a command, but replays have to step at the same rate
+        self.recorder
+            .insert(PhysicsCommand::SetIntegrationParameters(self.settings));
+    }
+
+    pub fn settings(&self) -> PhysicsSettings {
+        self.settings
+    }
+
+    /// used from the next step on, the pipeline keeps going as it is
+    pub fn set_settings(&mut self, settings: PhysicsSettings) {
+        self.settings = settings;
+        self.set_time_scale(self.time_scale);
+    }
+
+    /// a scale of 0 stops the simulation but keeps the last dt so nothing divides by zero
+    fn set_time_scale(&mut self, scale: f32) {
+        self.time_scale = scale.clamp(0.0, MAX_TIME_SCALE);
+        let dt =
+            (self.time_scale > 0.0).then(|| self.settings.timestep.as_secs_f32() * self.time_scale);
+        // the erp is per step, so it follows dt
+        self.world.configure(dt, &self.settings);
+    }
+
+    /// wakes every dynamic body so resting ones notice
+    pub fn set_gravity(&mut self, gravity: Vec3) {
+        self.gravity = gravity;
+        self.world.wake_dynamic_bodies();
+    }
+
+    /// advances the simulation by one timestep, or only handles commands while paused
+    pub fn step(&mut self) -> anyhow::Result<()> {
+        let commands: Vec<PhysicsCommand> = self.command_receiver.try_iter().collect();
+        self.pending_commands
+            .fetch_sub(commands.len(), Ordering::Relaxed);
+        self.recorder.record(&commands, |recording| {
+            recording.gravity = self.gravity;
+            recording.settings = self.settings;
+            recording.time_scale = self.time_scale;
+            recording.paused = self.paused;
+        });
+
+        let started = Instant::now();
+        let command_count = commands.len();
+        let result = self.step_with(commands);
+        self.record_stats(started.elapsed(), command_count);
+        result
+    }
+
+    /// puts the step into the shared `PhysicsStats`, if there are any
+    fn record_stats(&self, duration: Duration, commands: usize) {
+        let Some(stats) = self.stats.as_ref() else {
+            return;
+        };
+        let (active_bodies, contact_pairs) = self.world.counts();
+        stats.write().unwrap().push(StepStats {
+            duration,
+            active_bodies,
+            contact_pairs,
+            commands,
+        });
+    }
+
+    /// runs the recording's steps with its commands instead of the channel's, on a simulator
+    /// made from the scene the recording started on it ends up where the recorded run did
+    pub fn replay(&mut self, recording: &PhysicsRecording) -> anyhow::Result<()> {
+        self.gravity = recording.gravity;
+        self.settings = recording.settings;
+        self.set_time_scale(recording.time_scale);
+        self.paused = recording.paused;
+        self.single_steps = 0;
+
+        for step in 0..recording.steps {
+            self.step_with(recording.commands_on(step).cloned().collect())?;
+        }
+        Ok(())
+    }
+
+    /// a step that handles `commands`
+    fn step_with(&mut self, commands: Vec<PhysicsCommand>) -> anyhow::Result<()> {
+        self.insert_spawned_bodies();
+
+        for pc in commands {
+            match self.handle_command(pc) {
+                Ok(()) => (),
+                Err(e) => {
+                    log::debug!("skipped physics command: {}", e);
+                }
+            }
+        }
+
+        if self.paused || self.time_scale == 0.0 {
+            if self.single_steps == 0 {
+                // events from commands wait for the next step's physics_update
+                return Ok(());
+            }
+            self.single_steps -= 1;
+        }
+
+        let events = self.world.step(self.gravity, &self.entities);
+        self.step_events.extend(events);
+        self.send_sleep_events();
+
+        // interpolation blends over wall clock time, so scaled steps still look smooth
+        let timestep = self.settings.timestep;
+        let dt = self.world.dt() as f64 * 1000.0;
+        let events = std::mem::take(&mut self.step_events);
+
+        for e in self.entities.clone().into_iter() {
+            let _span = tracy_client::span!("modifying entities");
+            e.write_with(|entity| self.write_back(entity, dt, timestep, &events));
+        }
+
+        Ok(())
+    }
+
+    /// puts the body's velocity, sleep state and pose on the entity and hands it its events
+    fn write_back(
+        &mut self,
+        entity: &mut dyn Entity,
+        dt: f64,
+        timestep: Duration,
+        events: &[PhysicsEvent],
+    ) {
+        let id = entity.id();
+        let current = entity.components().get::<Transform3D>().copied();
+        let Some(pb) = entity.components().get::<PhysicsBody>() else {
+            return;
+        };
+        let Some(rb) = self.world.body(&pb.rigid_body) else {
+            log::debug!("skipped update for a body that isn't in the simulation");
+            return;
+        };
+
+        let velocity = PhysicsVelocity {
+            linear: rb.linvel(),
+            angular: rb.angvel(),
+        };
+        let kinematic = rb.is_kinematic();
+        let sleeping = rb.is_dynamic() && rb.is_sleeping();
+        let moved = current.map(|mut transform| {
+            rb.write_pose(&mut transform);
+            transform
+        });
+
+        *entity
+            .components_mut()
+            .get_or_insert_with(PhysicsVelocity::default) = velocity;
+        if sleeping {
+            entity.components_mut().get_or_insert_with(|| IsSleeping);
+        } else {
+            entity.components_mut().remove::<IsSleeping>();
+        }
+
+        // kinematic bodies follow the transform, not the other way around
+        if let (false, Some(current), Some(moved)) = (kinematic, current, moved) {
+            if cfg!(debug_assertions)
+                && self
+                    .written_transforms
+                    .get(&id)
+                    .is_some_and(|written| desynced(written, &current))
+            {
+                log::warn!(
+                    "entity {id}'s transform was moved outside of physics and is being overwritten, use PhysicsCommand::Teleport to move a body"
+                );
+            }
+            if let Some(transform) = entity.components_mut().get_mut::<Transform3D>() {
+                *transform = moved;
+            }
+            self.written_transforms.insert(id, moved);
+
+            if let Some(interpolated) = entity.components_mut().get_mut::<InterpolatedTransform>() {
+                interpolated.push(moved, timestep);
+            }
+        }
+
+        let own_events: Vec<PhysicsEvent> = events
+            .iter()
+            .filter(|e| e.entities().is_some_and(|ids| ids.contains(&id)))
+            .cloned()
+            .collect();
+        entity.physics_update(dt, &own_events);
+    }
+
+    /// answers go back to the `PhysicsEngine` waiting on them, everything else goes to the
+    /// entities it's about in the next `physics_update`
+    fn emit(&mut self, event: PhysicsEvent) -> anyhow::Result<()> {
+        match event.request_id() {
+            Some(_) => self.event_sender.send(event)?,
+            None => self.step_events.push(event),
+        }
+        Ok(())
+    }
+
+    /// `BodySlept` and `BodyWoke` for every dynamic body that fell asleep or woke up since the
+    /// last step
+    fn send_sleep_events(&mut self) {
+        for (id, sleeping) in self.world.dynamic_bodies() {
+            // bodies start out awake
+            let was_sleeping = self.sleeping.entry(id).or_insert(false);
+            if *was_sleeping == sleeping {
+                continue;
+            }
+            *was_sleeping = sleeping;
+
+            let event = match sleeping {
+                true => PhysicsEvent::BodySlept { id },
+                false => PhysicsEvent::BodyWoke { id },
+            };
+            self.step_events.push(event);
+        }
+    }
+
+    pub(crate) fn handle_command(&mut self, command: PhysicsCommand) -> anyhow::Result<()> {
+        let _span = tracy_client::span!("handling command");
+        match command {
+            PhysicsCommand::ApplyForce { id, force } => {
+                self.run_on_rb(id, |rb| rb.add_force(force))
+            }
+            PhysicsCommand::ApplyTorque { id, torque } => {
+                self.run_on_rb(id, |rb| rb.add_torque(torque))
+            }
+            PhysicsCommand::ApplyImpulse { id, impulse } => {
+                self.run_on_rb(id, |rb| rb.apply_impulse(impulse))
+            }
+            PhysicsCommand::ApplyTorqueImpulse { id, impulse } => {
+                self.run_on_rb(id, |rb| rb.apply_torque_impulse(impulse))
+            }
+            PhysicsCommand::SetLinearVelocity { id, velocity } => {
+                self.run_on_rb(id, |rb| rb.set_linvel(velocity))
+            }
+            PhysicsCommand::SetAngularVelocity { id, velocity } => {
+                self.run_on_rb(id, |rb| rb.set_angvel(velocity))
+            }
+            PhysicsCommand::SetPosition {
+                id,
+                translation,
+                rotation,
+            } => self.run_on_rb(id, |rb| rb.set_pose(translation, rotation)),
+            PhysicsCommand::SetTranslation { id, translation } => {
+                self.run_on_rb(id, |rb| rb.set_translation(translation))
+            }
+            PhysicsCommand::SetRotation { id, rotation } => {
+                self.run_on_rb(id, |rb| rb.set_rotation(rotation))
+            }
+            PhysicsCommand::Teleport {
+                id,
+                transform,
+                reset_velocity,
+            } => self.teleport(id, transform, reset_velocity),
+            PhysicsCommand::Enable { id } => self.set_body_enabled(id, true),
+            PhysicsCommand::Disable { id } => self.set_body_enabled(id, false),
+            PhysicsCommand::SetFriction { id, value } => {
+                self.run_on_colliders(id, |c| c.set_friction(value))
+            }
+            PhysicsCommand::SetRestitution { id, value } => {
+                self.run_on_colliders(id, |c| c.set_restitution(value))
+            }
+            PhysicsCommand::SetLayer { id, layer } => {
+                self.run_on_colliders(id, |c| c.set_layer(&layer))
+            }
+            PhysicsCommand::RemoveBody { id } => self.remove_body(id),
+            PhysicsCommand::Raycast {
+                origin,
+                dir,
+                max_toi,
+                request_id,
+            } => {
+                let event = self.world.raycast(origin, dir, max_toi, request_id);
+                self.emit(event)
+            }
+            PhysicsCommand::ShapeCast {
+                shape,
+                origin,
+                rotation,
+                dir,
+                max_toi,
+                request_id,
+            } => {
+                let event = self
+                    .world
+                    .shape_cast(&shape, origin, rotation, dir, max_toi, request_id)?;
+                self.emit(event)
+            }
+            PhysicsCommand::OverlapShape {
+                shape,
+                origin,
+                rotation,
+                request_id,
+            } => {
+                let event = self
+                    .world
+                    .overlap_shape(&shape, origin, rotation, request_id)?;
+                self.emit(event)
+            }
+            command @ (PhysicsCommand::CreateFixedJoint { .. }
+            | PhysicsCommand::CreateRevoluteJoint { .. }
+            | PhysicsCommand::CreateSphericalJoint { .. }
+            | PhysicsCommand::CreatePrismaticJoint { .. }
+            | PhysicsCommand::RemoveJoint { .. }
+            | PhysicsCommand::SetJointMotor { .. }
+            | PhysicsCommand::MoveCharacter { .. }) => {
+                match self.world.handle_world_command(command, &self.entities)? {
+                    Some(event) => self.emit(event),
+                    None => Ok(()),
+                }
+            }
+            PhysicsCommand::SetGravity { gravity } => {
+                self.set_gravity(gravity);
+                Ok(())
+            }
+            PhysicsCommand::SetGravityScale { id, scale } => {
+                self.run_on_rb(id, |rb| rb.set_gravity_scale(scale))
+            }
+            PhysicsCommand::SetLinearDamping { id, damping } => {
+                self.run_on_rb(id, |rb| rb.set_linear_damping(damping))
+            }
+            PhysicsCommand::SetAngularDamping { id, damping } => {
+                self.run_on_rb(id, |rb| rb.set_angular_damping(damping))
+            }
+            PhysicsCommand::SetAdditionalMass { id, mass } => {
+                self.run_on_rb(id, |rb| {
+                    rb.set_additional_mass(mass.unwrap_or_default(), true);
+                })?;
+                // so the component still says what the body has
+                if let Some(e) = self.entities.get(&id) {
+                    e.write_with(|e| {
+                        if let Some(pb) = e.components_mut().get_mut::<PhysicsBody>() {
+                            pb.additional_mass = mass;
+                        }
+                    });
+                }
+                Ok(())
+            }
+            PhysicsCommand::QueryBody { id, request_id } => {
+                let event = self.run_on_rb(id, |rb| PhysicsEvent::BodyState {
+                    request_id,
+                    linvel: rb.linvel(),
+                    angvel: rb.angvel(),
+                    mass: rb.mass(),
+                    is_sleeping: rb.is_sleeping(),
+                })?;
+                self.emit(event)
+            }
+            PhysicsCommand::SetTimeScale(scale) => {
+                self.set_time_scale(scale);
+                Ok(())
+            }
+            PhysicsCommand::SetIntegrationParameters(settings) => {
+                self.set_settings(settings);
+                Ok(())
+            }
+            PhysicsCommand::Pause => {
+                self.paused = true;
+                Ok(())
+            }
+            PhysicsCommand::Resume => {
+                self.paused = false;
+                self.single_steps = 0;
+                Ok(())
+            }
+            PhysicsCommand::StepOnce => {
+                if self.paused {
+                    self.single_steps += 1;
+                }
+                Ok(())
+            }
+            PhysicsCommand::LockRotations { id, x, y, z } => {
+                self.run_on_rb(id, |rb| rb.lock_rotations([x, y, z]))
+            }
+            PhysicsCommand::LockTranslations { id, x, y, z } => {
+                self.run_on_rb(id, |rb| rb.lock_translations([x, y, z]))
+            }
+            PhysicsCommand::SetInitialVelocity { id, linvel, angvel } => {
+                self.set_initial_velocity(id, linvel, angvel)
+            }
+            PhysicsCommand::SetDominance { id, group } => self.run_on_rb(id, |rb| {
+                // i8::MIN is outside rapier's range
+                rb.set_dominance_group(group.max(-127));
+            }),
+            PhysicsCommand::Sleep { id } => self.run_on_rb(id, |rb| rb.sleep()),
+            PhysicsCommand::WakeUp { id } => self.run_on_rb(id, |rb| rb.wake_up()),
+        }
+    }
+
+    /// turns the body and its colliders off or back on, enabled bodies are woken up
+    fn set_body_enabled(&mut self, id: Uuid, enabled: bool) -> anyhow::Result<()> {
+        self.run_on_rb(id, |rb| {
+            rb.set_enabled(enabled);
+            if enabled {
+                rb.wake_up();
+            }
+        })?;
+        self.run_on_colliders(id, |c| c.set_enabled(enabled))
+    }
+
+    /// sets the velocity straight away on an active body, keeps it for `insert_pending_body`
+    /// on a pending one
+    fn set_initial_velocity(&mut self, id: Uuid, linvel: Vec3, angvel: Vec3) -> anyhow::Result<()> {
+        let entity = self
+            .entities
+            .get(&id)
+            .ok_or(anyhow::anyhow!("no entity with id {id}"))?;
+        let pending = entity.read_with(|e| {
+            matches!(
+                e.components().get::<PhysicsBody>().map(|pb| &pb.rigid_body),
+                Some(RigidBodyState::Pending(_))
+            )
+        });
+        if pending {
+            self.pending_velocities.insert(id, (linvel, angvel));
+            return Ok(());
+        }
+        self.run_on_rb(id, |rb| {
+            rb.set_linvel(linvel);
+            rb.set_angvel(angvel);
+        })
+    }
+
+    /// removes the body with its colliders and joints, the entity doesn't have to exist anymore
+    fn remove_body(&mut self, id: Uuid) -> anyhow::Result<()> {
+        self.pending_velocities.remove(&id);
+        self.written_transforms.remove(&id);
+        self.sleeping.remove(&id);
+        let entity = self.entities.get(&id);
+        // removed before it was ever inserted, marking it is enough to keep it out of the world
+        let pending = entity.as_ref().is_some_and(|e| {
+            e.read_with(|e| {
+                matches!(
+                    e.components().get::<PhysicsBody>().map(|pb| &pb.rigid_body),
+                    Some(RigidBodyState::Pending(_))
+                )
+            })
+        });
+        if !pending && !self.world.remove_body(id)? {
+            return Ok(());
+        }
+
+        if let Some(e) = entity {
+            e.write_with(|e| {
+                if let Some(pb) = e.components_mut().get_mut::<PhysicsBody>() {
+                    pb.rigid_body = RigidBodyState::Removed;
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// sets the body's pose and writes it into the transform, interpolation jumps there instead
+    /// of sliding
+    fn teleport(
+        &mut self,
+        id: Uuid,
+        transform: Transform3D,
+        reset_velocity: bool,
+    ) -> anyhow::Result<()> {
+        // what the body made of the pose, 2d bodies only turn around z
+        let mut pose = transform;
+        self.run_on_rb(id, |rb| {
+            rb.set_pose(transform.position, transform.rotation);
+            if reset_velocity {
+                rb.set_linvel(Vec3::ZERO);
+                rb.set_angvel(Vec3::ZERO);
+            }
+            rb.write_pose(&mut pose);
+        })?;
+
+        let entity = self
+            .entities
+            .get(&id)
+            .ok_or(anyhow::anyhow!("no entity with provided id found"))?;
+        let moved = entity.write_with(|e| {
+            let components = e.components_mut();
+            let current = components.get_or_insert_with(Transform3D::default);
+            current.position = transform.position;
+            current.rotation = pose.rotation;
+            let moved = *current;
+            if components.has::<InterpolatedTransform>() {
+                components.add(InterpolatedTransform::new(moved));
+            }
+            moved
+        });
+        self.written_transforms.insert(id, moved);
+        Ok(())
+    }
+
+    /// runs `op` on the entity's static colliders, or every collider attached to its body
+    fn run_on_colliders<F>(&mut self, id: Uuid, mut op: F) -> anyhow::Result<()>
+    where
+        F: FnMut(&mut W::Collider),
+    {
+        if self.world.static_colliders_mut(id, &mut op) {
+            return Ok(());
+        }
+        let world = &mut self.world;
+        with_body_state(&self.entities, id, |state| {
+            world.body_colliders_mut(state, &mut op)
+        })
+    }
+
+    /// runs `op` on the entity's active rigid body
+    fn run_on_rb<R>(&mut self, id: Uuid, op: impl FnOnce(&mut W::Body) -> R) -> anyhow::Result<R> {
+        let world = &mut self.world;
+        with_body_state(&self.entities, id, |state| world.body_mut(state).map(op))
+    }
+}
+
+impl<W: World> Simulation for Simulator<W> {
+    fn step(&mut self) -> anyhow::Result<()> {
+        Simulator::step(self)
+    }
+
+    fn set_timestep(&mut self, timestep: Duration) {
+        Simulator::set_timestep(self, timestep);
+    }
+
+    fn gravity(&self) -> Vec3 {
+        self.gravity
+    }
+
+    fn set_gravity(&mut self, gravity: Vec3) {
+        Simulator::set_gravity(self, gravity);
+    }
+
+    fn settings(&self) -> PhysicsSettings {
+        self.settings
+    }
+
+    fn set_settings(&mut self, settings: PhysicsSettings) {
+        Simulator::set_settings(self, settings);
+    }
+
+    fn set_debug_lines(&mut self, lines: Arc<RwLock<PhysicsDebugLines>>) {
+        self.world.set_debug_lines(lines);
+    }
+
+    fn set_stats(&mut self, stats: Arc<RwLock<PhysicsStats>>) {
+        self.stats = Some(stats);
+    }
+
+    fn set_recorder(&mut self, recorder: PhysicsRecorder) {
+        self.recorder = recorder;
+    }
+
+    fn replay(&mut self, recording: &PhysicsRecording) -> anyhow::Result<()> {
+        Simulator::replay(self, recording)
+    }
+}
+
+/// runs `f` on the state of the entity's `PhysicsBody`
+pub(crate) fn with_body_state<R>(
+    entities: &EntityRegistry,
+    id: Uuid,
+    f: impl FnOnce(&RigidBodyState) -> anyhow::Result<R>,
+) -> anyhow::Result<R> {
+    let entity = entities
+        .get(&id)
+        .ok_or(anyhow::anyhow!("no entity with provided id found"))?;
+    entity.read_with(|e| match e.components().get::<PhysicsBody>() {
+        Some(pb) => f(&pb.rigid_body),
+        None => Err(anyhow::anyhow!("entity has no physics body component")),
+    })
+}
+
+/// whether a transform is far enough from the one physics wrote to have been moved by hand
+fn desynced(written: &Transform3D, current: &Transform3D) -> bool {
+    written.position.distance(current.position) > DESYNC_DISTANCE
+        || written.rotation.angle_between(current.rotation) > DESYNC_ANGLE
+}
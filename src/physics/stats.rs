@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+use crate::engine::context::ContextItem;
+
+/// steps `PhysicsStats` averages over by default
+pub const DEFAULT_STATS_WINDOW: usize = 60;
+
+/// what a single physics step did
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StepStats {
+    /// wall clock time of the whole step, commands and write back included
+    pub duration: Duration,
+    /// dynamic bodies that weren't asleep
+    pub active_bodies: usize,
+    /// collider pairs with at least one contact
+    pub contact_pairs: usize,
+    pub commands: usize,
+}
+
+/// counters the physics thread fills in after every step, kept in the engine `Context`
+///
+/// the last `window` steps are kept in a ring buffer for the averages, nothing allocates
+/// after `new`
+#[derive(Debug)]
+pub struct PhysicsStats {
+    steps: u64,
+    samples: Vec<StepStats>,
+    next: usize,
+    len: usize,
+}
+
+impl ContextItem for PhysicsStats {}
+
+impl Default for PhysicsStats {
+    fn default() -> Self {
+        Self::new(DEFAULT_STATS_WINDOW)
+    }
+}
+
+impl PhysicsStats {
+    /// averages over the last `window` steps
+    pub fn new(window: usize) -> Self {
+        let window = window.max(1);
+        Self {
+            steps: 0,
+            samples: vec![StepStats::default(); window],
+            next: 0,
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, step: StepStats) {
+        self.samples[self.next] = step;
+        self.next = (self.next + 1) % self.samples.len();
+        self.len = (self.len + 1).min(self.samples.len());
+        self.steps += 1;
+    }
+
+    /// steps taken since physics started, paused ones too
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn window(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// the most recent step
+    pub fn last(&self) -> Option<StepStats> {
+        if self.len == 0 {
+            return None;
+        }
+        let index = (self.next + self.samples.len() - 1) % self.samples.len();
+        Some(self.samples[index])
+    }
+
+    /// oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &StepStats> {
+        let start = (self.next + self.samples.len() - self.len) % self.samples.len();
+        (0..self.len).map(move |i| &self.samples[(start + i) % self.samples.len()])
+    }
+
+    pub fn average_duration(&self) -> Duration {
+        Duration::from_secs_f64(self.average(|s| s.duration.as_secs_f64()))
+    }
+
+    pub fn average_active_bodies(&self) -> f64 {
+        self.average(|s| s.active_bodies as f64)
+    }
+
+    pub fn average_contact_pairs(&self) -> f64 {
+        self.average(|s| s.contact_pairs as f64)
+    }
+
+    pub fn average_commands(&self) -> f64 {
+        self.average(|s| s.commands as f64)
+    }
+
+    fn average(&self, f: impl Fn(&StepStats) -> f64) -> f64 {
+        if self.len == 0 {
+            return 0.0;
+        }
+        self.iter().map(f).sum::<f64>() / self.len as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn averages_only_the_window() {
+        let mut stats = PhysicsStats::new(4);
+        assert_eq!(stats.last(), None);
+        assert_eq!(stats.average_commands(), 0.0);
+
+        for i in 0..10 {
+            stats.push(StepStats {
+                duration: Duration::from_millis(i),
+                active_bodies: i as usize,
+                contact_pairs: 1,
+                commands: i as usize * 2,
+            });
+        }
+        assert_eq!(stats.steps(), 10);
+        assert_eq!(stats.last().unwrap().active_bodies, 9);
+        // 6, 7, 8 and 9
+        assert_eq!(stats.average_active_bodies(), 7.5);
+        assert_eq!(stats.average_commands(), 15.0);
+        assert_eq!(stats.average_contact_pairs(), 1.0);
+        let average = stats.average_duration().as_secs_f64();
+        assert!((average - 0.0075).abs() < 1e-9, "{average}");
+    }
+}
@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+/// 60 steps a second
+pub const DEFAULT_TIMESTEP: Duration = Duration::from_nanos(16_666_667);
+
+/// shortest timestep allowed, anything shorter (zero included) is clamped up to it so the physics
+/// thread never spins waiting for a step that can't come
+pub const MIN_TIMESTEP: Duration = Duration::from_millis(1);
+
+/// most steps taken for one `advance` when the physics thread falls behind, the rest of the
+/// backlog is dropped so a long stall doesn't turn into a spiral of catching up
+pub const MAX_SUBSTEPS: u32 = 5;
+
+/// turns wall clock time into a whole number of fixed size physics steps
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    timestep: Duration,
+    accumulator: Duration,
+    max_substeps: u32,
+}
+
+impl FixedTimestep {
+    pub fn new(timestep: Duration, max_substeps: u32) -> Self {
+        Self {
+            timestep: clamp_timestep(timestep),
+            accumulator: Duration::ZERO,
+            max_substeps,
+        }
+    }
+
+    pub fn timestep(&self) -> Duration {
+        self.timestep
+    }
+
+    pub fn set_timestep(&mut self, timestep: Duration) {
+        self.timestep = clamp_timestep(timestep);
+    }
+
+    /// adds `elapsed` and returns how many steps to take now
+    pub fn advance(&mut self, elapsed: Duration) -> u32 {
+        self.accumulator += elapsed;
+        let mut steps = 0;
+        while self.accumulator >= self.timestep && steps < self.max_substeps {
+            self.accumulator -= self.timestep;
+            steps += 1;
+        }
+
+        if self.accumulator >= self.timestep {
+            log::debug!(
+                "physics fell behind, dropping {:?} of simulation time",
+                self.accumulator
+            );
+            self.accumulator = Duration::ZERO;
+        }
+        steps
+    }
+
+    /// time until the next step is due
+    pub fn until_next_step(&self) -> Duration {
+        self.timestep.saturating_sub(self.accumulator)
+    }
+}
+
+/// `timestep`, or `MIN_TIMESTEP` with a warning if it's shorter
+pub fn clamp_timestep(timestep: Duration) -> Duration {
+    if timestep < MIN_TIMESTEP {
+        log::warn!("physics timestep {timestep:?} is too short, using {MIN_TIMESTEP:?}");
+    }
+    timestep.max(MIN_TIMESTEP)
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new(DEFAULT_TIMESTEP, MAX_SUBSTEPS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_are_capped_when_behind() {
+        let ms = Duration::from_millis;
+        let mut timestep = FixedTimestep::new(ms(10), 3);
+        assert_eq!(timestep.advance(ms(5)), 0);
+        assert_eq!(timestep.advance(ms(6)), 1);
+        assert_eq!(timestep.until_next_step(), ms(9));
+        assert_eq!(timestep.advance(ms(25)), 2);
+
+        assert_eq!(timestep.advance(ms(1000)), 3);
+        assert_eq!(timestep.until_next_step(), ms(10));
+    }
+
+    #[test]
+    fn zero_timestep_is_clamped() {
+        let mut timestep = FixedTimestep::new(Duration::ZERO, 3);
+        assert_eq!(timestep.timestep(), MIN_TIMESTEP);
+        assert_eq!(timestep.until_next_step(), MIN_TIMESTEP);
+        assert_eq!(timestep.advance(MIN_TIMESTEP * 2), 2);
+
+        timestep.set_timestep(Duration::ZERO);
+        assert_eq!(timestep.timestep(), MIN_TIMESTEP);
+    }
+}
@@ -0,0 +1,78 @@
+use glam::{Quat, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::component::{Component, Transform3D};
+
+/// turns the entity's model to face the camera every frame, for nameplates and health bars.
+/// the model's front is +z, like `basic_models::QuadBuilder`'s quads
+///
+/// the renderer replaces the rotation of the entity's `Transform3D` for each view, position and
+/// scale stay. billboards aren't instanced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, Component)]
+pub enum Billboard {
+    /// lies in the camera's view plane, with the camera's up
+    #[default]
+    Spherical,
+    /// only turns around the world's y, stays upright when the camera looks down on it
+    Cylindrical,
+}
+
+impl Billboard {
+    /// `transform` turned towards a camera at `camera_position` with the world space rotation
+    /// `camera_rotation`
+    pub fn facing(
+        &self,
+        transform: &Transform3D,
+        camera_position: Vec3,
+        camera_rotation: Quat,
+    ) -> Transform3D {
+        let rotation = match self {
+            Billboard::Spherical => camera_rotation,
+            Billboard::Cylindrical => {
+                let to_camera = camera_position - transform.position;
+                Quat::from_rotation_y(to_camera.x.atan2(to_camera.z))
+            }
+        };
+        Transform3D {
+            rotation,
+            ..*transform
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Mat4;
+
+    use super::*;
+
+    #[test]
+    fn quad_keeps_facing_an_orbiting_camera() {
+        let quad = Transform3D::new(
+            Vec3::new(1.0, 2.0, 3.0),
+            Quat::from_rotation_x(0.7),
+            Vec3::new(2.0, 0.5, 1.0),
+        );
+        for step in 0..16 {
+            let angle = step as f32 / 16.0 * std::f32::consts::TAU;
+            let eye = quad.position + Vec3::new(angle.cos() * 6.0, 3.0, angle.sin() * 6.0);
+            let view = Mat4::look_at_rh(eye, quad.position, Vec3::Y);
+            let camera_rotation = Quat::from_mat4(&view.inverse());
+
+            let spherical = Billboard::Spherical.facing(&quad, eye, camera_rotation);
+            assert_eq!(spherical.position, quad.position);
+            assert_eq!(spherical.scale, quad.scale);
+            let front = spherical.rotation * Vec3::Z;
+            assert!(
+                front.dot((eye - quad.position).normalize()) > 0.9999,
+                "step {step}: {front}"
+            );
+
+            let cylindrical = Billboard::Cylindrical.facing(&quad, eye, camera_rotation);
+            let front = cylindrical.rotation * Vec3::Z;
+            let flat = (eye - quad.position).with_y(0.0).normalize();
+            assert!(front.dot(flat) > 0.9999, "step {step}: {front}");
+            assert!((cylindrical.rotation * Vec3::Y).abs_diff_eq(Vec3::Y, 1e-5));
+        }
+    }
+}
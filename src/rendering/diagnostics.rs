@@ -0,0 +1,185 @@
+use std::{
+    fmt::Write,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    engine::{context::Context, frame_timings::FrameTimings, message_stats::MessageStats},
+    physics::stats::PhysicsStats,
+    rendering::stats::RenderStats,
+};
+
+const SPARKLINE_CHARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const SPARKLINE_LEN: usize = 32;
+
+/// runtime stats overlay, toggled with `RendererCommand::ShowDiagnostics` and drawn in the top
+/// left corner over the scene and the ui
+///
+/// the text is only rebuilt a few times a second so it stays readable, and nothing is done at all
+/// while it's hidden.
+#[derive(Debug)]
+pub struct DiagnosticsOverlay {
+    visible: bool,
+    refresh_interval: Duration,
+    last_refresh: Option<Instant>,
+    text: String,
+}
+
+impl DiagnosticsOverlay {
+    pub fn new() -> Self {
+        Self {
+            visible: false,
+            refresh_interval: Duration::from_millis(250),
+            last_refresh: None,
+            text: String::new(),
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+        self.last_refresh = None;
+    }
+
+    /// rebuilds the text if it's due, returns true if it changed
+    pub fn update(&mut self, context: &Context, entity_count: usize) -> bool {
+        if !self.visible {
+            return false;
+        }
+        if self
+            .last_refresh
+            .is_some_and(|last| last.elapsed() < self.refresh_interval)
+        {
+            return false;
+        }
+        self.last_refresh = Some(Instant::now());
+        self.text.clear();
+        Self::write_text(&mut self.text, context, entity_count);
+        true
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// generates the overlay text from whatever stats are in the context
+    pub fn generate(context: &Context, entity_count: usize) -> String {
+        let mut text = String::new();
+        Self::write_text(&mut text, context, entity_count);
+        text
+    }
+
+    fn write_text(text: &mut String, context: &Context, entity_count: usize) {
+        let ms = |d: Option<Duration>| d.map(|d| d.as_secs_f64() * 1000.0).unwrap_or(0.0);
+        context.with(|timings: &FrameTimings| {
+            let _ = writeln!(
+                text,
+                "fps: {:.1} | frame: {:.2}ms p99: {:.2}ms worst: {:.2}ms",
+                timings.fps(),
+                ms(timings.average()),
+                ms(timings.percentile(99.0)),
+                ms(timings.worst()),
+            );
+            write_sparkline(text, timings);
+            text.push('\n');
+        });
+        let _ = write!(text, "entities: {}", entity_count);
+        context.with(|stats: &RenderStats| {
+            if let Some(frame) = stats.last() {
+                let _ = write!(
+                    text,
+                    " drawn: {} culled: {} triangles: {}",
+                    frame.drawn, frame.culled, frame.triangles
+                );
+            }
+        });
+        context.with(|stats: &PhysicsStats| {
+            if stats.steps() > 0 {
+                let _ = write!(
+                    text,
+                    "\nphysics step: {:.2}ms active bodies: {}",
+                    ms(Some(stats.average_duration())),
+                    stats.last().map(|s| s.active_bodies).unwrap_or(0),
+                );
+            }
+        });
+        context.with(|stats: &MessageStats| {
+            let _ = write!(
+                text,
+                "\nmessages: {} peak queue: {} physics pending: {}",
+                stats.processed(),
+                stats.peak_queue_depth(),
+                stats.physics_pending(),
+            );
+        });
+    }
+}
+
+fn write_sparkline(text: &mut String, timings: &FrameTimings) {
+    let worst = timings.iter_ms().fold(0.0, f64::max);
+    if worst <= 0.0 {
+        return;
+    }
+    timings
+        .iter_ms()
+        .skip(timings.len().saturating_sub(SPARKLINE_LEN))
+        .for_each(|ms| {
+            let level = ((ms / worst) * (SPARKLINE_CHARS.len() - 1) as f64).round() as usize;
+            text.push(SPARKLINE_CHARS[level.min(SPARKLINE_CHARS.len() - 1)]);
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{physics::stats::StepStats, rendering::stats::FrameStats};
+
+    #[test]
+    fn overlay_text_headless() {
+        let context = Context::new();
+        assert_eq!(DiagnosticsOverlay::generate(&context, 3), "entities: 3");
+
+        let mut timings = FrameTimings::new(8);
+        for ms in [10, 20, 10, 20] {
+            timings.push(Duration::from_millis(ms));
+        }
+        context.insert(timings);
+
+        let mut overlay = DiagnosticsOverlay::new();
+        assert!(!overlay.update(&context, 3));
+        overlay.set_visible(true);
+        assert!(overlay.update(&context, 3));
+        // refreshes are rate limited
+        assert!(!overlay.update(&context, 3));
+
+        let text = overlay.text();
+        assert!(text.starts_with("fps: 66.7"));
+        assert!(text.contains("worst: 20.00ms"));
+        assert!(text.contains("▅█▅█"));
+        assert!(text.ends_with("entities: 3"));
+
+        let mut render_stats = RenderStats::new(4);
+        render_stats.push(FrameStats {
+            drawn: 2,
+            culled: 1,
+            triangles: 24,
+            ..Default::default()
+        });
+        context.insert(render_stats);
+        let mut physics_stats = PhysicsStats::new(4);
+        physics_stats.push(StepStats {
+            duration: Duration::from_micros(1500),
+            active_bodies: 5,
+            ..Default::default()
+        });
+        context.insert(physics_stats);
+
+        let text = DiagnosticsOverlay::generate(&context, 3);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[2], "entities: 3 drawn: 2 culled: 1 triangles: 24");
+        assert_eq!(lines[3], "physics step: 1.50ms active bodies: 5");
+    }
+}
@@ -0,0 +1,110 @@
+use glam::{Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::context::ContextItem;
+
+/// background and ambient light the renderer reads every frame, kept in the engine `Context`
+///
+/// change it through `RendererCommand::SetClearColor`, `SetAmbientLight` and `SetFog` or
+/// straight in the context, either shows up on the next frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnvironmentSettings {
+    /// rgba, 0 to 1
+    pub clear_color: Vec4,
+    /// rgb, 0 to 1
+    pub ambient_color: Vec3,
+    pub ambient_intensity: f32,
+    pub fog: Fog,
+}
+
+impl ContextItem for EnvironmentSettings {}
+
+impl Default for EnvironmentSettings {
+    fn default() -> Self {
+        Self {
+            clear_color: Vec4::new(0.5, 0.8, 0.8, 1.0),
+            ambient_color: Vec3::ONE,
+            ambient_intensity: 0.2,
+            fog: Fog::Off,
+        }
+    }
+}
+
+/// distant geometry fading into a color, which should usually be the clear color so nothing
+/// stands out against the background
+///
+/// distances are from the camera in world units. fog is always full at the camera's far plane,
+/// so models fade out instead of popping when they cross it
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Fog {
+    #[default]
+    Off,
+    /// none before `start`, full at `end`
+    Linear { color: Vec3, start: f32, end: f32 },
+    /// `1 - e^(-density * distance)`, stretched to reach full fog at the far plane
+    Exponential { color: Vec3, density: f32 },
+}
+
+impl Fog {
+    /// rgb, 0 to 1, `None` when the fog is off
+    pub fn color(&self) -> Option<Vec3> {
+        match self {
+            Fog::Off => None,
+            Fog::Linear { color, .. } | Fog::Exponential { color, .. } => Some(*color),
+        }
+    }
+
+    /// how much of a fragment `distance` away is covered by fog, 0 to 1, with the camera's far
+    /// plane at `far`. the fog shader does the same
+    pub fn amount(&self, distance: f32, far: f32) -> f32 {
+        let amount = match *self {
+            Fog::Off => return 0.0,
+            Fog::Linear { start, end, .. } => {
+                let end = end.min(far);
+                let start = start.min(end);
+                if end <= start {
+                    return if distance >= end { 1.0 } else { 0.0 };
+                }
+                (distance - start) / (end - start)
+            }
+            Fog::Exponential { density, .. } => {
+                let at_far = 1.0 - (-density * far).exp();
+                if at_far <= 0.0 {
+                    return 0.0;
+                }
+                (1.0 - (-density * distance).exp()) / at_far
+            }
+        };
+        amount.clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_fog_ends_at_the_far_plane() {
+        let fog = Fog::Linear {
+            color: Vec3::ONE,
+            start: 10.0,
+            end: 500.0,
+        };
+        assert_eq!(fog.amount(5.0, 100.0), 0.0);
+        assert_eq!(fog.amount(55.0, 100.0), 0.5);
+        assert_eq!(fog.amount(100.0, 100.0), 1.0);
+        assert_eq!(fog.amount(55.0, 1000.0), 45.0 / 490.0);
+    }
+
+    #[test]
+    fn exponential_fog_is_full_at_the_far_plane() {
+        let fog = Fog::Exponential {
+            color: Vec3::ONE,
+            density: 0.01,
+        };
+        assert_eq!(fog.amount(0.0, 100.0), 0.0);
+        assert!((fog.amount(100.0, 100.0) - 1.0).abs() < 1e-6);
+        assert!(fog.amount(20.0, 100.0) < fog.amount(40.0, 100.0));
+        assert_eq!(Fog::Off.amount(100.0, 100.0), 0.0);
+    }
+}
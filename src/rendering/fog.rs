@@ -0,0 +1,130 @@
+use cgmath::SquareMatrix;
+use three_d::{
+    Blend, ColorTexture, Cull, DepthTexture, Effect, EffectMaterialId, FragmentAttributes, Light,
+    Program, RenderStates, Vec4, Viewer, WriteMask,
+};
+
+use super::environment::Fog;
+
+/// blends `Fog` over a rendered scene using its depth, after the scene and before any ui
+pub(crate) struct FogEffect {
+    pub fog: Fog,
+}
+
+impl FogEffect {
+    /// the shader's `fogMode`
+    fn mode(&self) -> i32 {
+        match self.fog {
+            Fog::Off => 0,
+            Fog::Linear { .. } => 1,
+            Fog::Exponential { .. } => 2,
+        }
+    }
+}
+
+impl Effect for FogEffect {
+    fn fragment_shader_source(
+        &self,
+        _lights: &[&dyn Light],
+        _color_texture: Option<ColorTexture>,
+        depth_texture: Option<DepthTexture>,
+    ) -> String {
+        format!(
+            "{}\n{}",
+            depth_texture
+                .expect("fog needs the scene's depth")
+                .fragment_shader_source(),
+            FOG_SHADER
+        )
+    }
+
+    fn id(
+        &self,
+        _color_texture: Option<ColorTexture>,
+        _depth_texture: Option<DepthTexture>,
+    ) -> EffectMaterialId {
+        EffectMaterialId(FOG_EFFECT_ID)
+    }
+
+    fn fragment_attributes(&self) -> FragmentAttributes {
+        FragmentAttributes::NONE
+    }
+
+    fn use_uniforms(
+        &self,
+        program: &Program,
+        viewer: &dyn Viewer,
+        _lights: &[&dyn Light],
+        _color_texture: Option<ColorTexture>,
+        depth_texture: Option<DepthTexture>,
+    ) {
+        depth_texture
+            .expect("fog needs the scene's depth")
+            .use_uniforms(program);
+        let view_projection = viewer.projection() * viewer.view();
+        program.use_uniform(
+            "viewProjectionInverse",
+            view_projection.invert().unwrap_or(view_projection),
+        );
+        program.use_uniform("eyePosition", viewer.position());
+        program.use_uniform("far", viewer.z_far());
+        program.use_uniform("fogMode", self.mode());
+
+        let color = self.fog.color().unwrap_or_default();
+        program.use_uniform("fogColor", Vec4::new(color.x, color.y, color.z, 1.0));
+        let (start, end, density) = match self.fog {
+            Fog::Off => (0.0, 0.0, 0.0),
+            Fog::Linear { start, end, .. } => (start, end, 0.0),
+            Fog::Exponential { density, .. } => (0.0, 0.0, density),
+        };
+        program.use_uniform("fogStart", start);
+        program.use_uniform("fogEnd", end);
+        program.use_uniform("fogDensity", density);
+    }
+
+    fn render_states(&self) -> RenderStates {
+        RenderStates {
+            write_mask: WriteMask::COLOR,
+            blend: Blend::TRANSPARENCY,
+            cull: Cull::Back,
+            ..Default::default()
+        }
+    }
+}
+
+/// outside the range three_d uses for its own effects and materials
+const FOG_EFFECT_ID: u16 = 0x0F06;
+
+/// same math as `Fog::amount`
+const FOG_SHADER: &str = r#"
+uniform mat4 viewProjectionInverse;
+uniform vec3 eyePosition;
+uniform float far;
+uniform int fogMode;
+uniform vec4 fogColor;
+uniform float fogStart;
+uniform float fogEnd;
+uniform float fogDensity;
+
+in vec2 uvs;
+
+layout (location = 0) out vec4 outColor;
+
+void main()
+{
+    float depth = sample_depth(uvs);
+    vec4 world = viewProjectionInverse * vec4(uvs * 2.0 - 1.0, depth * 2.0 - 1.0, 1.0);
+    float dist = distance(world.xyz / world.w, eyePosition);
+
+    float amount = 0.0;
+    if (fogMode == 1) {
+        float end = min(fogEnd, far);
+        float start = min(fogStart, end);
+        amount = end <= start ? step(end, dist) : (dist - start) / (end - start);
+    } else if (fogMode == 2) {
+        float atFar = 1.0 - exp(-fogDensity * far);
+        amount = atFar <= 0.0 ? 0.0 : (1.0 - exp(-fogDensity * dist)) / atFar;
+    }
+    outColor = vec4(fogColor.rgb, clamp(amount, 0.0, 1.0));
+}
+"#;
@@ -0,0 +1,205 @@
+use std::f32::consts::TAU;
+
+use glam::{Vec3, Vec4Swizzles};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    engine::{component::Transform3D, context::ContextItem},
+    physics::debug_render::DebugLine,
+};
+
+/// segments in each of a sphere gizmo's circles
+const SPHERE_SEGMENTS: usize = 24;
+
+/// a debug shape drawn for a single frame, colors are rgba, 0 to 1
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Gizmo {
+    Line {
+        from: Vec3,
+        to: Vec3,
+        color: [f32; 4],
+    },
+    /// box outline with the transform's position, rotation and scale
+    WireBox {
+        transform: Transform3D,
+        half_extents: Vec3,
+        color: [f32; 4],
+    },
+    /// one circle around each axis
+    Sphere {
+        center: Vec3,
+        radius: f32,
+        color: [f32; 4],
+    },
+}
+
+impl Gizmo {
+    /// pushes the line segments that make up the gizmo onto `lines`
+    pub fn push_lines(&self, lines: &mut Vec<DebugLine>) {
+        match *self {
+            Gizmo::Line { from, to, color } => lines.push(DebugLine {
+                a: from,
+                b: to,
+                color,
+            }),
+            Gizmo::WireBox {
+                transform,
+                half_extents,
+                color,
+            } => {
+                let matrix = transform.transform_matrix();
+                let corner = |i: usize| {
+                    let sign = Vec3::new(
+                        if i & 1 == 0 { -1.0 } else { 1.0 },
+                        if i & 2 == 0 { -1.0 } else { 1.0 },
+                        if i & 4 == 0 { -1.0 } else { 1.0 },
+                    );
+                    (matrix * (sign * half_extents).extend(1.0)).xyz()
+                };
+                // corners that differ in exactly one bit share an edge
+                for i in 0..8 {
+                    for bit in [1, 2, 4] {
+                        if i & bit == 0 {
+                            lines.push(DebugLine {
+                                a: corner(i),
+                                b: corner(i | bit),
+                                color,
+                            });
+                        }
+                    }
+                }
+            }
+            Gizmo::Sphere {
+                center,
+                radius,
+                color,
+            } => {
+                for (u, v) in [(Vec3::X, Vec3::Y), (Vec3::Y, Vec3::Z), (Vec3::Z, Vec3::X)] {
+                    let point = |i: usize| {
+                        let angle = i as f32 / SPHERE_SEGMENTS as f32 * TAU;
+                        center + (u * angle.cos() + v * angle.sin()) * radius
+                    };
+                    for i in 0..SPHERE_SEGMENTS {
+                        lines.push(DebugLine {
+                            a: point(i),
+                            b: point(i + 1),
+                            color,
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// gizmos waiting for the next frame, kept in the engine `Context`
+///
+/// push to it from anywhere with the context or through `RendererCommand::Gizmo`, the renderer
+/// draws everything in it over the scene and empties it every frame
+#[derive(Debug, Clone, Default)]
+pub struct GizmoQueue {
+    pub gizmos: Vec<Gizmo>,
+}
+
+impl ContextItem for GizmoQueue {}
+
+impl GizmoQueue {
+    pub fn push(&mut self, gizmo: Gizmo) {
+        self.gizmos.push(gizmo);
+    }
+
+    pub fn draw_line(&mut self, from: Vec3, to: Vec3, color: [f32; 4]) {
+        self.push(Gizmo::Line { from, to, color });
+    }
+
+    pub fn draw_wire_box(&mut self, transform: Transform3D, half_extents: Vec3, color: [f32; 4]) {
+        self.push(Gizmo::WireBox {
+            transform,
+            half_extents,
+            color,
+        });
+    }
+
+    pub fn draw_sphere(&mut self, center: Vec3, radius: f32, color: [f32; 4]) {
+        self.push(Gizmo::Sphere {
+            center,
+            radius,
+            color,
+        });
+    }
+
+    /// empties the queue and returns the lines of everything that was in it
+    pub fn take_lines(&mut self) -> Vec<DebugLine> {
+        let mut lines = Vec::new();
+        for gizmo in self.gizmos.drain(..) {
+            gizmo.push_lines(&mut lines);
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Quat;
+
+    use super::*;
+
+    const WHITE: [f32; 4] = [1.0; 4];
+
+    #[test]
+    fn wire_box_edges() {
+        let mut lines = Vec::new();
+        Gizmo::WireBox {
+            transform: Transform3D::new(
+                Vec3::new(0.0, 5.0, 0.0),
+                Quat::IDENTITY,
+                Vec3::new(2.0, 1.0, 1.0),
+            ),
+            half_extents: Vec3::ONE,
+            color: WHITE,
+        }
+        .push_lines(&mut lines);
+
+        assert_eq!(lines.len(), 12);
+        for line in &lines {
+            // every edge runs along one axis
+            let d = (line.b - line.a).abs();
+            assert_eq!((d.cmpgt(Vec3::ZERO)).bitmask().count_ones(), 1);
+            for p in [line.a, line.b] {
+                assert_eq!(p.x.abs(), 2.0);
+                assert!(p.y == 4.0 || p.y == 6.0);
+                assert_eq!(p.z.abs(), 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn sphere_points_are_on_the_surface() {
+        let center = Vec3::new(1.0, 2.0, 3.0);
+        let mut lines = Vec::new();
+        Gizmo::Sphere {
+            center,
+            radius: 0.5,
+            color: WHITE,
+        }
+        .push_lines(&mut lines);
+
+        assert_eq!(lines.len(), SPHERE_SEGMENTS * 3);
+        assert!(
+            lines
+                .iter()
+                .all(|l| (l.a.distance(center) - 0.5).abs() < 1e-5)
+        );
+    }
+
+    #[test]
+    fn taking_lines_clears_the_queue() {
+        let mut queue = GizmoQueue::default();
+        queue.draw_line(Vec3::ZERO, Vec3::X, WHITE);
+        queue.draw_sphere(Vec3::ZERO, 1.0, WHITE);
+
+        assert_eq!(queue.take_lines().len(), 1 + SPHERE_SEGMENTS * 3);
+        assert!(queue.gizmos.is_empty());
+        assert!(queue.take_lines().is_empty());
+    }
+}
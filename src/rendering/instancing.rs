@@ -0,0 +1,113 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use uuid::Uuid;
+
+use crate::assets::asset_manager::{Material, Model, Texture};
+
+/// what entities drawn as instances of one mesh have in common, the model's asset and a
+/// fingerprint of its materials so entities with edited materials end up in a group of their own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InstanceKey {
+    pub asset_id: Uuid,
+    pub materials: u64,
+}
+
+impl InstanceKey {
+    /// `None` for models that weren't loaded through the `AssetManager`
+    ///
+    /// textures go in by their pixels, so a texture edited in place gets a group of its own.
+    /// each material is hashed once, the renderer keeps the key for the entity's lifetime
+    pub fn for_model(model: &Model) -> Option<Self> {
+        let asset_id = model.asset_id?;
+        let mut hasher = DefaultHasher::new();
+        for material in &model.materials {
+            hash_material(material, &mut hasher);
+        }
+        Some(Self {
+            asset_id,
+            materials: hasher.finish(),
+        })
+    }
+}
+
+fn hash_material(material: &Material, hasher: &mut impl Hasher) {
+    let factors = material
+        .base_color_factor
+        .to_array()
+        .into_iter()
+        .chain(material.emissive_factor.to_array())
+        .chain([
+            material.normal_scale,
+            material.metallic_factor,
+            material.roughness_factor,
+            material.occlusion_strength,
+        ]);
+    for factor in factors {
+        factor.to_bits().hash(hasher);
+    }
+    material.unlit.hash(hasher);
+
+    hash_texture(Some(&material.albedo), hasher);
+    for texture in [
+        &material.normals,
+        &material.metallic_roughness,
+        &material.emissive,
+        &material.occlusion,
+    ] {
+        hash_texture(texture.as_ref(), hasher);
+    }
+}
+
+fn hash_texture(texture: Option<&Texture>, hasher: &mut impl Hasher) {
+    texture.map(|t| (t.width, t.height, &t.data)).hash(hasher);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::basic_models::CuboidBuilder;
+
+    fn loaded() -> Model {
+        Model {
+            asset_id: Some(Uuid::new_v4()),
+            ..CuboidBuilder::new().build()
+        }
+    }
+
+    #[test]
+    fn copies_share_a_key() {
+        let model = loaded();
+        assert_eq!(
+            InstanceKey::for_model(&model),
+            InstanceKey::for_model(&model.clone())
+        );
+        assert_eq!(InstanceKey::for_model(&CuboidBuilder::new().build()), None);
+    }
+
+    #[test]
+    fn edited_materials_change_the_key() {
+        let model = loaded();
+        let mut tinted = model.clone();
+        tinted.materials[0].base_color_factor.x = 0.5;
+        assert_ne!(
+            InstanceKey::for_model(&model),
+            InstanceKey::for_model(&tinted)
+        );
+    }
+
+    #[test]
+    fn edited_pixels_change_the_key() {
+        let model = loaded();
+        let mut repainted = model.clone();
+        let albedo = &mut repainted.materials[0].albedo;
+        albedo
+            .data
+            .iter_mut()
+            .for_each(|byte| *byte = byte.wrapping_add(1));
+        assert!(!albedo.data.is_empty());
+        assert_ne!(
+            InstanceKey::for_model(&model),
+            InstanceKey::for_model(&repainted)
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use glam::{Quat, Vec3};
+use serde::{Deserialize, Serialize};
+use three_d::{Attenuation, Srgba};
+
+use crate::engine::component::Component;
+
+/// most lights the renderer passes to three_d in one frame, the sun included
+///
+/// three_d builds a shader for every light count it sees and every light is a pass over every
+/// fragment, so past this the farthest ones from the camera are dropped
+pub const MAX_LIGHTS: usize = 16;
+
+/// sun the renderer uses while no entity has a `DirectionalLight`, dim enough that a scene that
+/// lost its lights looks off without going black
+pub const FALLBACK_LIGHT_INTENSITY: f32 = 0.4;
+pub const FALLBACK_LIGHT_DIRECTION: Vec3 = Vec3::new(0.0, -0.5, -0.5);
+
+/// light from infinitely far away along the entity's forward (-z) like the sun, the entity's
+/// position doesn't matter
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Component)]
+pub struct DirectionalLight {
+    /// rgb, 0 to 1
+    pub color: Vec3,
+    pub intensity: f32,
+}
+
+impl DirectionalLight {
+    pub fn new(color: Vec3, intensity: f32) -> Self {
+        Self { color, intensity }
+    }
+
+    /// rotation for the entity's `Transform3D` that points the light along `direction`
+    pub fn rotation_towards(direction: Vec3) -> Quat {
+        Quat::from_rotation_arc(Vec3::NEG_Z, direction.normalize())
+    }
+}
+
+impl Default for DirectionalLight {
+    fn default() -> Self {
+        Self::new(Vec3::ONE, 1.0)
+    }
+}
+
+/// light shining from the entity's position in every direction
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Component)]
+pub struct PointLight {
+    /// rgb, 0 to 1
+    pub color: Vec3,
+    pub intensity: f32,
+    /// distance where the light has faded to almost nothing
+    pub range: f32,
+}
+
+impl PointLight {
+    pub fn new(color: Vec3, intensity: f32, range: f32) -> Self {
+        Self {
+            color,
+            intensity,
+            range,
+        }
+    }
+}
+
+impl Default for PointLight {
+    fn default() -> Self {
+        Self::new(Vec3::ONE, 1.0, 10.0)
+    }
+}
+
+/// cone of light from the entity's position along its forward (-z)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Component)]
+pub struct SpotLight {
+    /// rgb, 0 to 1
+    pub color: Vec3,
+    pub intensity: f32,
+    pub range: f32,
+    /// angle between the cone's edge and its middle in radians
+    pub angle: f32,
+}
+
+impl SpotLight {
+    pub fn new(color: Vec3, intensity: f32, range: f32, angle: f32) -> Self {
+        Self {
+            color,
+            intensity,
+            range,
+            angle,
+        }
+    }
+}
+
+impl Default for SpotLight {
+    fn default() -> Self {
+        Self::new(Vec3::ONE, 1.0, 10.0, std::f32::consts::FRAC_PI_6)
+    }
+}
+
+/// falloff that's down to about 1% at `range`
+pub(crate) fn range_attenuation(range: f32) -> Attenuation {
+    let range = range.max(f32::EPSILON);
+    Attenuation {
+        constant: 1.0,
+        linear: 2.0 / range,
+        quadratic: 97.0 / (range * range),
+    }
+}
+
+pub(crate) fn light_color(color: Vec3) -> Srgba {
+    let [r, g, b] = color.to_array().map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8);
+    Srgba::new(r, g, b, 255)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attenuation_fades_out_at_range() {
+        let at = |a: &Attenuation, d: f32| 1.0 / (a.constant + a.linear * d + a.quadratic * d * d);
+        for range in [1.0, 10.0, 250.0] {
+            let attenuation = range_attenuation(range);
+            assert_eq!(at(&attenuation, 0.0), 1.0);
+            assert!((at(&attenuation, range) - 0.01).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn rotation_points_the_sun() {
+        let direction = Vec3::new(1.0, -2.0, 0.5);
+        let rotation = DirectionalLight::rotation_towards(direction);
+        assert!((rotation * Vec3::NEG_Z).abs_diff_eq(direction.normalize(), 1e-5));
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{assets::asset_manager::Model, engine::component::Component};
+
+/// how far past a switch distance the camera has to go before the level changes, as a fraction
+/// of the distance
+pub const DEFAULT_LOD_HYSTERESIS: f32 = 0.1;
+
+/// a simpler model the renderer swaps in from `distance` on
+#[derive(Debug, Clone)]
+pub struct LodLevel {
+    /// world units from the active camera
+    pub distance: f32,
+    pub model: Model,
+}
+
+/// lower detail models for an entity, level 0 is the entity's own model and level `i` is
+/// `levels[i - 1]`
+///
+/// levels switch `hysteresis` past their distance in either direction, so an entity sitting on
+/// a switch distance doesn't flicker between two levels
+#[derive(Debug, Clone, Component)]
+pub struct Lod {
+    /// nearest first
+    pub levels: Vec<LodLevel>,
+    pub hysteresis: f32,
+}
+
+impl Lod {
+    /// sorts `levels` by distance
+    pub fn new(mut levels: Vec<LodLevel>) -> Self {
+        levels.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        Self {
+            levels,
+            hysteresis: DEFAULT_LOD_HYSTERESIS,
+        }
+    }
+
+    /// simplified copies of `model`, one per `(distance, ratio)`, see `Model::generate_lods`
+    pub fn generate(model: &Model, levels: &[(f32, f32)]) -> Self {
+        let ratios: Vec<f32> = levels.iter().map(|(_, ratio)| *ratio).collect();
+        Self::new(
+            levels
+                .iter()
+                .zip(model.generate_lods(&ratios))
+                .map(|((distance, _), model)| LodLevel {
+                    distance: *distance,
+                    model,
+                })
+                .collect(),
+        )
+    }
+
+    pub fn with_hysteresis(mut self, hysteresis: f32) -> Self {
+        self.hysteresis = hysteresis.max(0.0);
+        self
+    }
+
+    /// the level to draw at `distance` when `current` was drawn last frame
+    pub fn select(&self, current: usize, distance: f32) -> usize {
+        let mut level = current.min(self.levels.len());
+        while level < self.levels.len()
+            && distance > self.levels[level].distance * (1.0 + self.hysteresis)
+        {
+            level += 1;
+        }
+        while level > 0 && distance < self.levels[level - 1].distance * (1.0 - self.hysteresis) {
+            level -= 1;
+        }
+        level
+    }
+
+    /// `None` for level 0, that's the entity's own model
+    pub fn model(&self, level: usize) -> Option<&Model> {
+        level
+            .checked_sub(1)
+            .and_then(|i| self.levels.get(i))
+            .map(|l| &l.model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::basic_models::CuboidBuilder;
+
+    fn lod() -> Lod {
+        let model = CuboidBuilder::new().build();
+        Lod::new(vec![
+            LodLevel {
+                distance: 200.0,
+                model: model.clone(),
+            },
+            LodLevel {
+                distance: 50.0,
+                model,
+            },
+        ])
+    }
+
+    #[test]
+    fn levels_follow_the_distance() {
+        let lod = lod();
+        assert_eq!(lod.levels[0].distance, 50.0);
+        assert_eq!(lod.select(0, 10.0), 0);
+        assert_eq!(lod.select(0, 100.0), 1);
+        assert_eq!(lod.select(0, 1000.0), 2);
+        assert_eq!(lod.select(2, 10.0), 0);
+        assert!(lod.model(0).is_none());
+        assert!(lod.model(2).is_some());
+        assert!(lod.model(3).is_none());
+    }
+
+    #[test]
+    fn hysteresis_keeps_the_level_near_a_switch() {
+        let lod = lod();
+        // 5 either side of 50 stays put
+        assert_eq!(lod.select(0, 53.0), 0);
+        assert_eq!(lod.select(1, 47.0), 1);
+        assert_eq!(lod.select(0, 56.0), 1);
+        assert_eq!(lod.select(1, 44.0), 0);
+    }
+}
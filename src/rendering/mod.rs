@@ -1,4 +1,23 @@
+pub mod billboard;
+pub mod diagnostics;
+pub mod environment;
+mod fog;
+pub mod gizmo;
+pub mod instancing;
+pub mod light;
+pub mod lod;
+mod null_renderer;
+mod offscreen;
+pub mod picking;
+pub mod screen_space;
+pub mod screenshot;
+pub mod settings;
+pub mod sprite;
+pub mod stats;
 mod three_d_renderer;
+pub mod ui;
+pub mod viewport;
+mod wireframe;
 
 use std::{
     collections::VecDeque,
@@ -6,24 +25,40 @@ use std::{
     sync::{Arc, Mutex, Weak},
 };
 
+pub use null_renderer::NullRenderer;
+pub use offscreen::OffscreenRenderer;
+use screenshot::Screenshot;
+use serde::{Deserialize, Serialize};
+use settings::RendererSettings;
 use three_d_renderer::ThreedRenderer;
+pub use three_d_renderer::{AaMode, ViewMode};
+use uuid::Uuid;
 use winit::{
     event::WindowEvent,
     window::{Window, WindowId},
 };
 
 use crate::{
+    assets::hot_reload::AssetReloaded,
     engine::{
         Engine,
+        component::Component,
+        context::Context,
         entity::{Entity, EntityRegistry},
         messages::Message,
     },
     utils::{SharedBox, WeakShared},
 };
 
-/// trait for renderers, not really used yet
+/// marker that tells the renderer the entity's model was swapped, it rebuilds the entity's
+/// meshes on the next frame and takes the marker off
+#[derive(Debug, Clone, Copy, Default, Component)]
+pub struct DirtyModel;
+
+/// a rendering backend, plug your own in with `RendererType::Custom`
 pub trait Renderer {
-    // fn start_render(self) -> anyhow::Result<()>;
+    /// called once the window exists, before the first frame
+    fn init(&mut self, window: &Window, camera_id: &Uuid) -> anyhow::Result<()>;
     fn render(&mut self, window: Arc<Window>) -> anyhow::Result<()>;
     fn handle_resize(&mut self, window: Arc<Window>, event: &WindowEvent) -> anyhow::Result<()>;
     fn handle_scale_factor_change(
@@ -32,41 +67,133 @@ pub trait Renderer {
         event: &WindowEvent,
     ) -> anyhow::Result<()>;
     fn handle_close(&mut self, window: Arc<Window>, event: &WindowEvent) -> anyhow::Result<()>;
+
+    /// the window's surface is going away, drops everything made for it so `init` can be called
+    /// again with a new window
+    fn suspend(&mut self) {}
+
     fn set_objects(&mut self, objects: EntityRegistry);
 
+    /// every `RendererCommand` that doesn't need a window, backends ignore the ones they have no
+    /// use for
+    fn handle_command(&mut self, command: RendererCommand) -> anyhow::Result<()>;
+
+    /// window events before the entities see them, for backends with their own ui
+    fn handle_window_event(&mut self, _event: &WindowEvent) {}
+
+    /// surface settings for when the window opens
+    fn set_settings(&mut self, _settings: RendererSettings) {}
+
+    /// the camera entity frames are rendered from
+    fn active_camera(&self) -> Option<Uuid> {
+        None
+    }
+
+    /// one frame from `camera_id` as an image, for backends that can render without a window
+    fn render_to_image(
+        &mut self,
+        _camera_id: &Uuid,
+        _width: u32,
+        _height: u32,
+    ) -> anyhow::Result<Screenshot> {
+        Err(anyhow::anyhow!("this renderer can't render offscreen"))
+    }
+
     fn get_messages(&self) -> &VecDeque<Message>;
     fn get_messages_mut(&mut self) -> &mut VecDeque<Message>;
     fn clear_messages(&mut self);
 }
 
-#[derive(Debug, Clone)]
+/// the variants tied to a window or a channel can't be serialized, so they're left out of
+/// recordings
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RendererCommand {
+    #[serde(skip)]
     Render(WindowId),
+    #[serde(skip)]
     HandleResize((WindowId, WindowEvent)),
+    #[serde(skip)]
     HandleScaleChange((WindowId, WindowEvent)),
+    #[serde(skip)]
     HandleClose((WindowId, WindowEvent)),
+    ShowDiagnostics(bool),
+    SetAntiAliasing(AaMode),
+    /// draws collider outlines from the physics engine over the scene
+    SetPhysicsDebug(bool),
+    /// rgba, 0 to 1, see `EnvironmentSettings`
+    SetClearColor(glam::Vec4),
+    /// fades distant geometry, see `environment::Fog`
+    SetFog(environment::Fog),
+    /// rgb color, 0 to 1
+    SetAmbientLight {
+        color: glam::Vec3,
+        intensity: f32,
+    },
+    /// renders from this camera entity instead, ignored with a warning if it isn't one
+    SetActiveCamera(Uuid),
+    /// splits the window between cameras for split screen, see `ViewportConfig`. an empty list
+    /// goes back to the active camera on the whole window
+    SetViewports(Vec<viewport::ViewportConfig>),
+    /// shows the axes at the origin, on by default in debug builds
+    SetDebugAxes(bool),
+    /// draws the gizmo for the next frame only, see `GizmoQueue`
+    Gizmo(gizmo::Gizmo),
+    /// debug views that don't touch the models' materials
+    SetViewMode(ViewMode),
+    /// saves the next presented frame, the format comes from the extension
+    CaptureScreenshot {
+        path: std::path::PathBuf,
+    },
+    /// sends the next presented frame's pixels, nothing is sent if the capture fails
+    #[serde(skip)]
+    CaptureScreenshotBytes(std::sync::mpsc::SyncSender<screenshot::Screenshot>),
+    /// the asset changed on disk, entities drawn with it are rebuilt from the new one. sent by
+    /// the engine for the reloads of the `AssetManager`
+    AssetReloaded(AssetReloaded),
 }
 
-#[derive(Debug, Clone)]
 pub enum RendererType {
     ThreeD,
+    /// no window, frames only come out of `Engine::render_to_image`
+    Offscreen,
+    /// a backend of your own, it gets the engine's entities through `set_objects`
+    Custom(Box<dyn Renderer>),
+}
+
+impl std::fmt::Debug for RendererType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RendererType::ThreeD => write!(f, "ThreeD"),
+            RendererType::Offscreen => write!(f, "Offscreen"),
+            RendererType::Custom(_) => write!(f, "Custom"),
+        }
+    }
 }
 
 /// basic renderer abstraction
 pub struct EngineRenderer {
     pub objects: EntityRegistry,
-    pub renderer: ThreedRenderer,
+    pub renderer: Box<dyn Renderer>,
 }
 
 impl EngineRenderer {
     /// create new EngineRenderer
-    pub fn new(renderer_type: RendererType, objects: EntityRegistry) -> Self {
-        let renderer = match renderer_type {
-            RendererType::ThreeD => ThreedRenderer::new(objects.clone()),
+    pub fn new(renderer_type: RendererType, objects: EntityRegistry, context: Context) -> Self {
+        let renderer: Box<dyn Renderer> = match renderer_type {
+            RendererType::ThreeD => Box::new(ThreedRenderer::new(objects.clone(), context)),
+            RendererType::Offscreen => Box::new(OffscreenRenderer::new(objects.clone(), context)),
+            RendererType::Custom(mut renderer) => {
+                renderer.set_objects(objects.clone());
+                renderer
+            }
         };
         Self { objects, renderer }
     }
 
+    pub fn init(&mut self, window: &Window, camera_id: &Uuid) -> anyhow::Result<()> {
+        self.renderer.init(window, camera_id)
+    }
+
     /// sets objects to render
     pub fn set_objects(&mut self, objects: EntityRegistry) {
         self.objects = objects.clone();
@@ -79,6 +206,56 @@ impl EngineRenderer {
         self.renderer.render(window)
     }
 
+    pub fn handle_resize(
+        &mut self,
+        window: Arc<Window>,
+        event: &WindowEvent,
+    ) -> anyhow::Result<()> {
+        self.renderer.handle_resize(window, event)
+    }
+
+    pub fn handle_scale_factor_change(
+        &mut self,
+        window: Arc<Window>,
+        event: &WindowEvent,
+    ) -> anyhow::Result<()> {
+        self.renderer.handle_scale_factor_change(window, event)
+    }
+
+    pub fn handle_close(&mut self, window: Arc<Window>, event: &WindowEvent) -> anyhow::Result<()> {
+        self.renderer.handle_close(window, event)
+    }
+
+    pub fn suspend(&mut self) {
+        self.renderer.suspend();
+    }
+
+    pub fn handle_command(&mut self, command: RendererCommand) -> anyhow::Result<()> {
+        self.renderer.handle_command(command)
+    }
+
+    pub fn handle_window_event(&mut self, event: &WindowEvent) {
+        self.renderer.handle_window_event(event);
+    }
+
+    pub fn set_settings(&mut self, settings: RendererSettings) {
+        self.renderer.set_settings(settings);
+    }
+
+    pub fn active_camera(&self) -> Option<Uuid> {
+        self.renderer.active_camera()
+    }
+
+    pub fn render_to_image(
+        &mut self,
+        camera_id: &Uuid,
+        width: u32,
+        height: u32,
+    ) -> anyhow::Result<Screenshot> {
+        let _span = tracy_client::span!("Offscreen Render");
+        self.renderer.render_to_image(camera_id, width, height)
+    }
+
     pub fn get_messages(&self) -> &VecDeque<Message> {
         self.renderer.get_messages()
     }
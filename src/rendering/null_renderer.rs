@@ -0,0 +1,83 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use uuid::Uuid;
+use winit::{event::WindowEvent, window::Window};
+
+use crate::engine::{entity::EntityRegistry, messages::Message};
+
+use super::{Renderer, RendererCommand};
+
+/// renderer that draws nothing, for running the engine without a gpu, in tests or on a server.
+/// it only keeps track of the active camera
+#[derive(Debug)]
+pub struct NullRenderer {
+    messages: VecDeque<Message>,
+    camera_id: Option<Uuid>,
+}
+
+impl NullRenderer {
+    pub fn new() -> Self {
+        Self {
+            messages: VecDeque::new(),
+            camera_id: None,
+        }
+    }
+}
+
+impl Default for NullRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Renderer for NullRenderer {
+    fn init(&mut self, _window: &Window, camera_id: &Uuid) -> anyhow::Result<()> {
+        self.camera_id = Some(*camera_id);
+        Ok(())
+    }
+
+    fn render(&mut self, _window: Arc<Window>) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn handle_resize(&mut self, _window: Arc<Window>, _event: &WindowEvent) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn handle_scale_factor_change(
+        &mut self,
+        _window: Arc<Window>,
+        _event: &WindowEvent,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn handle_close(&mut self, _window: Arc<Window>, _event: &WindowEvent) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn set_objects(&mut self, _objects: EntityRegistry) {}
+
+    fn handle_command(&mut self, command: RendererCommand) -> anyhow::Result<()> {
+        if let RendererCommand::SetActiveCamera(id) = command {
+            self.camera_id = Some(id);
+        }
+        Ok(())
+    }
+
+    fn active_camera(&self) -> Option<Uuid> {
+        self.camera_id
+    }
+
+    fn get_messages(&self) -> &VecDeque<Message> {
+        &self.messages
+    }
+
+    fn get_messages_mut(&mut self) -> &mut VecDeque<Message> {
+        &mut self.messages
+    }
+
+    fn clear_messages(&mut self) {
+        self.messages.clear();
+    }
+}
@@ -0,0 +1,365 @@
+use std::{collections::VecDeque, sync::Arc};
+
+use glam::Vec3;
+use three_d::{
+    AmbientLight, ClearState, DepthTexture, DepthTexture2D, DirectionalLight, HeadlessContext,
+    Interpolation, Light, Mesh, Object, RenderTarget, Texture2D, Viewport, Wrapping,
+};
+use uuid::Uuid;
+use winit::{event::WindowEvent, window::Window};
+
+use crate::{
+    engine::{
+        component::{Layer, LayerMask, Transform3D},
+        context::Context as EngineContext,
+        entity::EntityRegistry,
+        messages::Message,
+    },
+    utils::IntoCgmath,
+};
+
+use super::{
+    Renderer, RendererCommand,
+    environment::{EnvironmentSettings, Fog},
+    fog::FogEffect,
+    light::{self, light_color},
+    screenshot::Screenshot,
+    three_d_renderer::{
+        ModelGm, camera_from_entity, fallback_light, gm_update_transform, object_get_gm_list,
+    },
+};
+
+/// renders the entities into images without a window, for thumbnails and golden image tests.
+/// frames come out of `render_to_image`, `render` does nothing
+///
+/// meshes are built again for every image, it's meant for a handful of frames and not a loop
+pub struct OffscreenRenderer {
+    /// made on the first image, so creating the renderer never needs a gpu
+    context: Option<HeadlessContext>,
+    objects: EntityRegistry,
+    engine_context: EngineContext,
+    camera_id: Option<Uuid>,
+    messages: VecDeque<Message>,
+}
+
+impl OffscreenRenderer {
+    pub fn new(objects: EntityRegistry, engine_context: EngineContext) -> Self {
+        engine_context.get_or_insert_with(EnvironmentSettings::default);
+        Self {
+            context: None,
+            objects,
+            engine_context,
+            camera_id: None,
+            messages: VecDeque::new(),
+        }
+    }
+
+    fn context(&mut self) -> anyhow::Result<&HeadlessContext> {
+        if self.context.is_none() {
+            self.context = Some(HeadlessContext::new()?);
+        }
+        Ok(self.context.as_ref().unwrap())
+    }
+}
+
+impl Renderer for OffscreenRenderer {
+    fn init(&mut self, _window: &Window, camera_id: &Uuid) -> anyhow::Result<()> {
+        self.camera_id = Some(*camera_id);
+        Ok(())
+    }
+
+    fn render(&mut self, _window: Arc<Window>) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn render_to_image(
+        &mut self,
+        camera_id: &Uuid,
+        width: u32,
+        height: u32,
+    ) -> anyhow::Result<Screenshot> {
+        if width == 0 || height == 0 {
+            return Err(anyhow::anyhow!("can't render a {width} by {height} image"));
+        }
+        let environment = self
+            .engine_context
+            .with(|settings: &EnvironmentSettings| *settings)
+            .unwrap_or_default();
+        let objects = self.objects.clone();
+        let mut camera = camera_from_entity(&objects, camera_id)?;
+        camera.set_viewport(Viewport::new_at_origo(width, height));
+        let layer_mask = objects
+            .get(camera_id)
+            .and_then(|c| c.read_with(|c| c.components().get::<LayerMask>().copied()))
+            .unwrap_or_default();
+        let context: &three_d::Context = self.context()?;
+
+        let hidden = objects.hidden();
+        let gms: Vec<ModelGm> = objects
+            .clone()
+            .into_iter()
+            .filter(|o| {
+                o.read_with(|o| {
+                    o.components()
+                        .get::<Layer>()
+                        .copied()
+                        .unwrap_or_default()
+                        .visible_to(&layer_mask)
+                }) && !hidden.contains(&o.id())
+            })
+            .filter_map(|o| {
+                let transform = o.read_with(|o| {
+                    o.components()
+                        .get::<Transform3D>()
+                        .copied()
+                        .unwrap_or_default()
+                });
+                let mut gms =
+                    object_get_gm_list(o, context, |cpu_mesh| Mesh::new(context, cpu_mesh)).ok()?;
+                gms.iter_mut()
+                    .for_each(|gm| gm_update_transform(gm, &transform));
+                Some(gms)
+            })
+            .flatten()
+            .collect();
+        let scene: Vec<&dyn Object> = gms.iter().map(|gm| gm.as_object()).collect();
+
+        // the ambient and sun lights a window gets, point and spot lights are left out
+        let ambient = AmbientLight::new(
+            context,
+            environment.ambient_intensity,
+            light_color(environment.ambient_color),
+        );
+        let mut suns: Vec<DirectionalLight> = objects
+            .clone()
+            .into_iter()
+            .filter_map(|o| {
+                o.read_with(|o| {
+                    let components = o.components();
+                    let light = components.get::<light::DirectionalLight>().copied()?;
+                    let transform = components.get::<Transform3D>().copied().unwrap_or_default();
+                    Some(DirectionalLight::new(
+                        context,
+                        light.intensity,
+                        light_color(light.color),
+                        (transform.rotation * Vec3::NEG_Z).into_cgmath(),
+                    ))
+                })
+            })
+            .collect();
+        if suns.is_empty() {
+            suns.push(fallback_light(context));
+        }
+        let lights: Vec<&dyn Light> = std::iter::once(&ambient as &dyn Light)
+            .chain(suns.iter().map(|l| l as &dyn Light))
+            .collect();
+
+        let mut color = Texture2D::new_empty::<[u8; 4]>(
+            context,
+            width,
+            height,
+            Interpolation::Nearest,
+            Interpolation::Nearest,
+            None,
+            Wrapping::ClampToEdge,
+            Wrapping::ClampToEdge,
+        );
+        let mut depth = DepthTexture2D::new::<f32>(
+            context,
+            width,
+            height,
+            Wrapping::ClampToEdge,
+            Wrapping::ClampToEdge,
+        );
+        let [r, g, b, a] = environment.clear_color.to_array();
+        RenderTarget::new(color.as_color_target(None), depth.as_depth_target())
+            .clear(ClearState::color_and_depth(r, g, b, a, 1.0))
+            .render(&camera, &scene, &lights);
+        // blended over the scene with its depth, like the window does
+        if environment.fog != Fog::Off {
+            color.as_color_target(None).apply_screen_effect(
+                &FogEffect {
+                    fog: environment.fog,
+                },
+                &camera,
+                &[],
+                None,
+                Some(DepthTexture::Single(&depth)),
+            );
+        }
+        let pixels = color.as_color_target(None).read::<[u8; 4]>();
+
+        Ok(Screenshot::new(width, height, &pixels))
+    }
+
+    fn handle_resize(&mut self, _window: Arc<Window>, _event: &WindowEvent) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn handle_scale_factor_change(
+        &mut self,
+        _window: Arc<Window>,
+        _event: &WindowEvent,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn handle_close(&mut self, _window: Arc<Window>, _event: &WindowEvent) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn set_objects(&mut self, objects: EntityRegistry) {
+        self.objects = objects;
+    }
+
+    fn handle_command(&mut self, command: RendererCommand) -> anyhow::Result<()> {
+        match command {
+            RendererCommand::SetClearColor(color) => {
+                self.engine_context
+                    .with_mut(|settings: &mut EnvironmentSettings| settings.clear_color = color);
+            }
+            RendererCommand::SetAmbientLight { color, intensity } => {
+                self.engine_context
+                    .with_mut(|settings: &mut EnvironmentSettings| {
+                        settings.ambient_color = color;
+                        settings.ambient_intensity = intensity;
+                    });
+            }
+            RendererCommand::SetFog(fog) => {
+                self.engine_context
+                    .with_mut(|settings: &mut EnvironmentSettings| settings.fog = fog);
+            }
+            RendererCommand::SetActiveCamera(id) => match camera_from_entity(&self.objects, &id) {
+                Ok(_) => self.camera_id = Some(id),
+                Err(e) => log::warn!("kept the active camera, can't switch to {id}: {e}"),
+            },
+            command => log::debug!("offscreen renderer ignored {command:?}"),
+        }
+        Ok(())
+    }
+
+    fn active_camera(&self) -> Option<Uuid> {
+        self.camera_id
+    }
+
+    fn get_messages(&self) -> &VecDeque<Message> {
+        &self.messages
+    }
+
+    fn get_messages_mut(&mut self) -> &mut VecDeque<Message> {
+        &mut self.messages
+    }
+
+    fn clear_messages(&mut self) {
+        self.messages.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assets::basic_models::CuboidBuilder,
+        engine::{
+            component::ComponentSet,
+            entity::{DefaultCamera, Entity, tests::Dummy},
+        },
+    };
+
+    /// a 64 by 64 camera at z 4 looking down -z at a unit cube placed at `cube_position`
+    fn cuboid_scene(cube_position: Vec3) -> (EntityRegistry, Uuid) {
+        let mut objects = EntityRegistry::new();
+        let camera = DefaultCamera::new(
+            Transform3D::new(Vec3::new(0.0, 0.0, 4.0), glam::Quat::IDENTITY, Vec3::ONE),
+            64.0,
+            64.0,
+            Vec3::Y,
+            Vec3::NEG_Z,
+            1.0,
+            0.1,
+            100.0,
+        );
+        let camera_id = camera.id();
+        objects.add(camera.into_container());
+        let mut components = ComponentSet::new();
+        components.add(Transform3D::new(
+            cube_position,
+            glam::Quat::IDENTITY,
+            Vec3::ONE,
+        ));
+        objects.add(
+            Dummy::new(components)
+                .with_model(CuboidBuilder::new().build())
+                .into_container(),
+        );
+        (objects, camera_id)
+    }
+
+    fn background() -> [u8; 4] {
+        EnvironmentSettings::default()
+            .clear_color
+            .to_array()
+            .map(|c| (c * 255.0).round() as u8)
+    }
+
+    fn pixel(image: &Screenshot, x: u32, y: u32) -> [u8; 4] {
+        let start = ((y * image.width + x) * 4) as usize;
+        image.rgba[start..start + 4].try_into().unwrap()
+    }
+
+    fn is_background(pixel: [u8; 4]) -> bool {
+        pixel
+            .iter()
+            .zip(background())
+            .all(|(p, b)| p.abs_diff(b) <= 2)
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn renders_the_cuboid_scene() {
+        let (objects, camera_id) = cuboid_scene(Vec3::ZERO);
+        let mut renderer = OffscreenRenderer::new(objects, EngineContext::new());
+        let image = renderer.render_to_image(&camera_id, 64, 64).unwrap();
+
+        assert_eq!((image.width, image.height), (64, 64));
+        assert!(!is_background(pixel(&image, 32, 32)));
+        assert!(is_background(pixel(&image, 0, 0)));
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn fog_covers_the_scene() {
+        let (objects, camera_id) = cuboid_scene(Vec3::ZERO);
+        let mut renderer = OffscreenRenderer::new(objects, EngineContext::new());
+        // full fog well before the cube
+        renderer
+            .handle_command(RendererCommand::SetFog(Fog::Linear {
+                color: Vec3::X,
+                start: 0.0,
+                end: 1.0,
+            }))
+            .unwrap();
+        let image = renderer.render_to_image(&camera_id, 64, 64).unwrap();
+
+        let center = pixel(&image, 32, 32);
+        assert!(
+            center
+                .iter()
+                .zip([255, 0, 0, 255])
+                .all(|(p, f)| p.abs_diff(f) <= 2),
+            "{center:?}"
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a gpu"]
+    fn top_row_comes_first() {
+        // above the middle, so it only covers the upper part of the image
+        let (objects, camera_id) = cuboid_scene(Vec3::new(0.0, 1.2, 0.0));
+        let mut renderer = OffscreenRenderer::new(objects, EngineContext::new());
+        let image = renderer.render_to_image(&camera_id, 64, 64).unwrap();
+
+        assert!(!is_background(pixel(&image, 32, 14)));
+        assert!(is_background(pixel(&image, 32, 50)));
+    }
+}
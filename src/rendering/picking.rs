@@ -0,0 +1,177 @@
+use glam::{Mat4, Vec2, Vec3};
+use uuid::Uuid;
+
+use crate::engine::{
+    component::{Layer, LayerMask, Transform3D},
+    entity::{Camera, DefaultCamera, EntityContainer, EntityRegistry},
+};
+
+use super::viewport::ViewportMapper;
+
+/// half line in world space, `dir` is normalized
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub dir: Vec3,
+}
+
+impl Ray {
+    /// ray from the near plane through `ndc`, by unprojecting it with the inverse of
+    /// `view_projection`
+    pub fn from_ndc(view_projection: Mat4, ndc: Vec2) -> Self {
+        let inverse = view_projection.inverse();
+        let near = inverse.project_point3(ndc.extend(-1.0));
+        let far = inverse.project_point3(ndc.extend(1.0));
+        Self {
+            origin: near,
+            dir: (far - near).normalize(),
+        }
+    }
+
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.dir * distance
+    }
+
+    /// distance to where the ray enters the box, 0 when it starts inside
+    pub fn intersect_aabb(&self, min: Vec3, max: Vec3) -> Option<f32> {
+        // slabs, axis aligned rays give infinities that the min and max take care of
+        let inverse = self.dir.recip();
+        let a = (min - self.origin) * inverse;
+        let b = (max - self.origin) * inverse;
+        let enter = a.min(b).max_element();
+        let exit = a.max(b).min_element();
+        (exit >= enter.max(0.0)).then_some(enter.max(0.0))
+    }
+}
+
+/// ray from `camera` through `cursor`, in physical window pixels like winit cursor positions.
+/// `None` in letterbox bars
+pub fn screen_ray(camera: &DefaultCamera, mapper: &ViewportMapper, cursor: Vec2) -> Option<Ray> {
+    let ndc = mapper.viewport_to_ndc(mapper.window_to_viewport(cursor)?);
+    Some(Ray::from_ndc(camera.view_projection_matrix_rh(), ndc))
+}
+
+/// whether a camera with `layer_mask` shows `object`, hidden entities and ones on layers
+/// outside the mask can't be picked
+pub fn pickable(
+    objects: &EntityRegistry,
+    object: &EntityContainer,
+    layer_mask: &LayerMask,
+) -> bool {
+    !objects.is_hidden(object)
+        && object.read_with(|o| {
+            o.components()
+                .get::<Layer>()
+                .copied()
+                .unwrap_or_default()
+                .visible_to(layer_mask)
+        })
+}
+
+/// closest `pickable` entity whose model's bounding box the ray goes through
+pub fn pick_bounds(objects: &EntityRegistry, ray: &Ray, layer_mask: &LayerMask) -> Option<Uuid> {
+    objects
+        .clone()
+        .into_iter()
+        .filter(|o| pickable(objects, o, layer_mask))
+        .filter_map(|o| {
+            o.read_with(|o| {
+                let components = o.components();
+                let (min, max) = o.model().as_ref()?.bounds()?;
+                let transform = components
+                    .get::<Transform3D>()
+                    .copied()
+                    .unwrap_or_default()
+                    .transform_matrix();
+                let (min, max) = world_bounds(transform, min, max);
+                Some((ray.intersect_aabb(min, max)?, o.id()))
+            })
+        })
+        .min_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, id)| id)
+}
+
+/// box around the transformed corners of a model space box
+fn world_bounds(transform: Mat4, min: Vec3, max: Vec3) -> (Vec3, Vec3) {
+    (0..8)
+        .map(|i| {
+            let corner = Vec3::new(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            );
+            transform.transform_point3(corner)
+        })
+        .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), p| {
+            (min.min(p), max.max(p))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Quat;
+
+    use super::*;
+    use crate::{
+        assets::basic_models::CuboidBuilder,
+        engine::{
+            component::ComponentSet,
+            entity::{Entity, tests::Dummy},
+        },
+    };
+
+    #[test]
+    fn center_of_the_screen_picks_the_cuboid_in_front() {
+        let camera = DefaultCamera::new(
+            Transform3D::new(Vec3::new(2.0, 0.0, 10.0), Quat::IDENTITY, Vec3::ONE),
+            800.0,
+            600.0,
+            Vec3::Y,
+            Vec3::NEG_Z,
+            1.0,
+            0.1,
+            100.0,
+        );
+        let mut objects = EntityRegistry::new();
+        let mut cuboid = |x: f32, z: f32| {
+            let mut components = ComponentSet::new();
+            components.add(Transform3D::new(
+                Vec3::new(x, 0.0, z),
+                Quat::IDENTITY,
+                Vec3::ONE,
+            ));
+            let dummy = Dummy::new(components).with_model(CuboidBuilder::new().build());
+            let id = dummy.id();
+            objects.add(dummy.into_container());
+            id
+        };
+        let behind = cuboid(2.0, -5.0);
+        let front = cuboid(2.0, 0.0);
+        let aside = cuboid(-2.0, 0.0);
+
+        let mapper = ViewportMapper::new(Vec2::new(800.0, 600.0), 1.0);
+        let ray = screen_ray(&camera, &mapper, Vec2::new(400.0, 300.0)).unwrap();
+        assert!(ray.origin.abs_diff_eq(Vec3::new(2.0, 0.0, 9.9), 1e-4));
+        assert!(ray.dir.abs_diff_eq(Vec3::NEG_Z, 1e-5));
+        assert_eq!(
+            pick_bounds(&objects, &ray, &LayerMask::default()),
+            Some(front)
+        );
+
+        objects.remove(&front);
+        assert_eq!(
+            pick_bounds(&objects, &ray, &LayerMask::default()),
+            Some(behind)
+        );
+
+        // 4 to the left at 10 away is about a quarter of the way in from the left edge
+        let ray = screen_ray(&camera, &mapper, Vec2::new(180.0, 300.0)).unwrap();
+        assert_eq!(
+            pick_bounds(&objects, &ray, &LayerMask::default()),
+            Some(aside)
+        );
+
+        let ray = screen_ray(&camera, &mapper, Vec2::new(400.0, 20.0)).unwrap();
+        assert_eq!(pick_bounds(&objects, &ray, &LayerMask::default()), None);
+    }
+}
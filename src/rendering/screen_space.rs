@@ -0,0 +1,144 @@
+use std::path::PathBuf;
+
+use glam::{Vec2, Vec4};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::component::Component;
+
+/// point of the viewport a screen space element is pinned to, the element's own matching
+/// corner or edge sits on it so it stays put when the window is resized
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Anchor {
+    #[default]
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// where on the viewport (and on the element) the anchor is, 0 to 1 from the top left
+    pub fn fraction(self) -> Vec2 {
+        match self {
+            Anchor::TopLeft => Vec2::new(0.0, 0.0),
+            Anchor::Top => Vec2::new(0.5, 0.0),
+            Anchor::TopRight => Vec2::new(1.0, 0.0),
+            Anchor::Left => Vec2::new(0.0, 0.5),
+            Anchor::Center => Vec2::new(0.5, 0.5),
+            Anchor::Right => Vec2::new(1.0, 0.5),
+            Anchor::BottomLeft => Vec2::new(0.0, 1.0),
+            Anchor::Bottom => Vec2::new(0.5, 1.0),
+            Anchor::BottomRight => Vec2::new(1.0, 1.0),
+        }
+    }
+
+    /// the point on a `viewport` sized screen the element is pinned to, moved by `offset`
+    pub fn point(self, viewport: Vec2, offset: Vec2) -> Vec2 {
+        viewport * self.fraction() + offset
+    }
+
+    /// top left corner of a `size` element pinned to this anchor
+    pub fn place(self, viewport: Vec2, size: Vec2, offset: Vec2) -> Vec2 {
+        self.point(viewport, offset) - size * self.fraction()
+    }
+}
+
+/// text drawn over the scene, sizes and offsets are in logical pixels with y going down
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Component)]
+pub struct TextLabel {
+    pub text: String,
+    pub font_size: f32,
+    /// rgba, 0 to 1
+    pub color: Vec4,
+    pub anchor: Anchor,
+    pub offset: Vec2,
+}
+
+impl TextLabel {
+    /// white 16 px text in the top left corner
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            font_size: 16.0,
+            color: Vec4::ONE,
+            anchor: Anchor::TopLeft,
+            offset: Vec2::ZERO,
+        }
+    }
+
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    pub fn with_color(mut self, color: Vec4) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn anchored(mut self, anchor: Anchor, offset: Vec2) -> Self {
+        self.anchor = anchor;
+        self.offset = offset;
+        self
+    }
+}
+
+/// image drawn over the scene, unlike `Sprite` it doesn't live in the world
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Component)]
+pub struct ScreenSprite {
+    /// path in the asset directory
+    pub texture: PathBuf,
+    /// logical pixels
+    pub size: Vec2,
+    pub anchor: Anchor,
+    pub offset: Vec2,
+}
+
+impl ScreenSprite {
+    pub fn new(texture: impl Into<PathBuf>, size: Vec2) -> Self {
+        Self {
+            texture: texture.into(),
+            size,
+            anchor: Anchor::TopLeft,
+            offset: Vec2::ZERO,
+        }
+    }
+
+    pub fn anchored(mut self, anchor: Anchor, offset: Vec2) -> Self {
+        self.anchor = anchor;
+        self.offset = offset;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corners_follow_the_viewport() {
+        let size = Vec2::new(20.0, 10.0);
+        let offset = Vec2::new(-5.0, -5.0);
+        for viewport in [Vec2::new(1280.0, 720.0), Vec2::new(800.0, 600.0)] {
+            let min = Anchor::BottomRight.place(viewport, size, offset);
+            assert_eq!(min + size, viewport + offset);
+        }
+        assert_eq!(
+            Anchor::TopLeft.place(Vec2::new(800.0, 600.0), size, Vec2::ONE),
+            Vec2::ONE
+        );
+    }
+
+    #[test]
+    fn center_is_centered() {
+        let viewport = Vec2::new(800.0, 600.0);
+        let size = Vec2::new(100.0, 50.0);
+        let min = Anchor::Center.place(viewport, size, Vec2::ZERO);
+        assert_eq!(min + size / 2.0, viewport / 2.0);
+    }
+}
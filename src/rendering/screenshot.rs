@@ -0,0 +1,91 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::SyncSender,
+};
+
+/// a captured frame, rgba with the top row first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl Screenshot {
+    /// pixels as three_d reads them back, it already puts the top row first
+    pub fn new(width: u32, height: u32, pixels: &[[u8; 4]]) -> Self {
+        Self {
+            width,
+            height,
+            rgba: pixels.iter().flatten().copied().collect(),
+        }
+    }
+
+    /// encodes the screenshot in the format the path's extension asks for
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let image = image::RgbaImage::from_raw(self.width, self.height, self.rgba.clone())
+            .ok_or(anyhow::anyhow!("screenshot buffer doesn't match its size"))?;
+        image.save(path)?;
+        Ok(())
+    }
+}
+
+/// where a capture requested through `RendererCommand` goes once the frame is presented
+#[derive(Debug, Clone)]
+pub enum ScreenshotTarget {
+    File(PathBuf),
+    Bytes(SyncSender<Screenshot>),
+}
+
+impl ScreenshotTarget {
+    /// hands the screenshot over, files are written on their own thread so the render loop
+    /// doesn't wait on the encoder. failures are logged
+    pub fn deliver(self, screenshot: Screenshot) {
+        match self {
+            ScreenshotTarget::File(path) => {
+                std::thread::spawn(move || match screenshot.save(&path) {
+                    Ok(()) => log::info!("saved screenshot to {}", path.display()),
+                    Err(e) => log::error!("couldn't save screenshot to {}: {e}", path.display()),
+                });
+            }
+            ScreenshotTarget::Bytes(sender) => {
+                if sender.try_send(screenshot).is_err() {
+                    log::error!("screenshot receiver is gone or full, dropped the capture");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_keep_their_order() {
+        let pixels = [[1, 1, 1, 1], [2, 2, 2, 2], [3, 3, 3, 3], [4, 4, 4, 4]];
+        let screenshot = Screenshot::new(2, 2, &pixels);
+        assert_eq!(
+            screenshot.rgba,
+            [1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4]
+        );
+    }
+
+    #[test]
+    fn bytes_go_through_the_channel() {
+        let (sender, receiver) = std::sync::mpsc::sync_channel(1);
+        let screenshot = Screenshot::new(1, 1, &[[9, 8, 7, 255]]);
+        ScreenshotTarget::Bytes(sender).deliver(screenshot.clone());
+        assert_eq!(receiver.try_recv().unwrap(), screenshot);
+    }
+
+    #[test]
+    fn saving_to_an_unwritable_path_errors() {
+        let screenshot = Screenshot::new(1, 1, &[[0, 0, 0, 255]]);
+        assert!(
+            screenshot
+                .save(Path::new("/nonexistent/dir/shot.png"))
+                .is_err()
+        );
+    }
+}
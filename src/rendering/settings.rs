@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+use three_d::SurfaceSettings;
+
+/// sample counts the window surface can be created with, 0 is off
+const SUPPORTED_MULTISAMPLES: [u8; 5] = [0, 2, 4, 8, 16];
+const SUPPORTED_DEPTH_BITS: [u8; 4] = [0, 16, 24, 32];
+
+/// window surface settings, used when the renderer makes its context in `init`, and how models
+/// get onto the gpu, which applies from the next frame
+///
+/// set them with `Engine::with_renderer_settings` before the window opens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RendererSettings {
+    /// samples of the window's own framebuffer, separate from `AaMode`
+    pub multisamples: u8,
+    /// off lets frames go as fast as they render, for benchmarking
+    pub vsync: bool,
+    pub depth_bits: u8,
+    /// milliseconds a frame may spend uploading models that haven't been drawn yet, nearest to
+    /// the camera first. the rest wait for the next frames, but at least one goes every frame.
+    /// `None` uploads everything in the frame it shows up in
+    pub gm_budget_ms: Option<u32>,
+    /// gray boxes over the bounds of entities waiting for their upload
+    pub placeholders: bool,
+}
+
+impl Default for RendererSettings {
+    /// three_d's surface defaults
+    fn default() -> Self {
+        let surface = SurfaceSettings::default();
+        Self {
+            multisamples: surface.multisamples,
+            vsync: surface.vsync,
+            depth_bits: surface.depth_buffer,
+            gm_budget_ms: Some(4),
+            placeholders: false,
+        }
+    }
+}
+
+impl RendererSettings {
+    /// the closest supported sample count and depth size, with a warning for anything changed
+    pub fn validated(self) -> Self {
+        let multisamples = nearest(&SUPPORTED_MULTISAMPLES, self.multisamples);
+        if multisamples != self.multisamples {
+            log::warn!(
+                "{} multisamples aren't supported, using {multisamples}",
+                self.multisamples
+            );
+        }
+        let depth_bits = nearest(&SUPPORTED_DEPTH_BITS, self.depth_bits);
+        if depth_bits != self.depth_bits {
+            log::warn!(
+                "{} bit depth buffers aren't supported, using {depth_bits}",
+                self.depth_bits
+            );
+        }
+        Self {
+            multisamples,
+            depth_bits,
+            ..self
+        }
+    }
+
+    pub(crate) fn surface_settings(&self) -> SurfaceSettings {
+        let settings = self.validated();
+        SurfaceSettings {
+            vsync: settings.vsync,
+            depth_buffer: settings.depth_bits,
+            multisamples: settings.multisamples,
+            ..SurfaceSettings::default()
+        }
+    }
+}
+
+/// ties go to the smaller value
+fn nearest(supported: &[u8], value: u8) -> u8 {
+    *supported
+        .iter()
+        .min_by_key(|s| s.abs_diff(value))
+        .expect("supported values aren't empty")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn supported_values_are_kept() {
+        let settings = RendererSettings {
+            multisamples: 8,
+            vsync: false,
+            depth_bits: 32,
+            ..RendererSettings::default()
+        };
+        assert_eq!(settings.validated(), settings);
+    }
+
+    #[test]
+    fn unsupported_values_fall_back_to_the_nearest() {
+        let settings = RendererSettings {
+            multisamples: 5,
+            vsync: true,
+            depth_bits: 20,
+            ..RendererSettings::default()
+        }
+        .validated();
+        assert_eq!(settings.multisamples, 4);
+        assert_eq!(settings.depth_bits, 16);
+
+        let settings = RendererSettings {
+            multisamples: 64,
+            ..RendererSettings::default()
+        }
+        .validated();
+        assert_eq!(settings.multisamples, 16);
+    }
+}
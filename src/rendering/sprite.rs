@@ -0,0 +1,375 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    path::{Path, PathBuf},
+};
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets::{
+        asset_manager::{Model, Texture},
+        basic_models::QuadBuilder,
+    },
+    engine::component::{Component, ComponentSet},
+    rendering::DirtyModel,
+};
+
+/// normalized rect on a texture, origin at the top left
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct UvRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl UvRect {
+    pub const FULL: UvRect = UvRect {
+        x: 0.0,
+        y: 0.0,
+        width: 1.0,
+        height: 1.0,
+    };
+}
+
+impl Default for UvRect {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+/// flat textured quad, `uv_rect` picks the part of the texture that's shown
+///
+/// the renderer draws it in place of the entity's model
+#[derive(Debug, Clone, Component)]
+pub struct Sprite {
+    pub texture: Option<Texture>,
+    pub uv_rect: UvRect,
+    /// size in world units
+    pub size: Vec2,
+}
+
+impl Sprite {
+    pub fn new(texture: Option<Texture>, size: Vec2) -> Self {
+        Self {
+            texture,
+            uv_rect: UvRect::FULL,
+            size,
+        }
+    }
+
+    /// the quad the renderer draws
+    pub fn model(&self) -> Model {
+        let quad = QuadBuilder::new()
+            .size(self.size.x, self.size.y)
+            .uv_rect(self.uv_rect);
+        match self.texture.clone() {
+            Some(texture) => quad.texture(texture),
+            None => quad,
+        }
+        .build()
+    }
+}
+
+/// a run of frames on a sprite sheet, `first` and `last` are both included
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpriteClip {
+    pub first: usize,
+    pub last: usize,
+    pub fps: f32,
+    #[serde(default)]
+    pub looping: bool,
+}
+
+impl SpriteClip {
+    pub fn new(first: usize, last: usize, fps: f32, looping: bool) -> Self {
+        Self {
+            first,
+            last,
+            fps,
+            looping,
+        }
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.last.saturating_sub(self.first) + 1
+    }
+}
+
+/// grid layout of a sprite sheet and the clips on it
+///
+/// frames are numbered left to right, top to bottom. can be loaded from a json sidecar next to
+/// the texture, `walker.png` -> `walker.sprite.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpriteSheet {
+    pub columns: u32,
+    pub rows: u32,
+    #[serde(default)]
+    pub clips: HashMap<String, SpriteClip>,
+}
+
+impl SpriteSheet {
+    pub fn new(columns: u32, rows: u32) -> Self {
+        Self {
+            columns: columns.max(1),
+            rows: rows.max(1),
+            clips: HashMap::new(),
+        }
+    }
+
+    pub fn clip(mut self, name: &str, clip: SpriteClip) -> Self {
+        self.clips.insert(name.to_string(), clip);
+        self
+    }
+
+    pub fn frame_uv(&self, frame: usize) -> UvRect {
+        let columns = self.columns.max(1) as usize;
+        let rows = self.rows.max(1) as usize;
+        let frame = frame % (columns * rows);
+        let width = 1.0 / columns as f32;
+        let height = 1.0 / rows as f32;
+        UvRect {
+            x: (frame % columns) as f32 * width,
+            y: (frame / columns) as f32 * height,
+            width,
+            height,
+        }
+    }
+
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn sidecar_path(texture_path: &Path) -> PathBuf {
+        texture_path.with_extension("sprite.json")
+    }
+
+    /// loads the sidecar for the texture at `texture_path`
+    pub fn load_sidecar(texture_path: &Path) -> anyhow::Result<Self> {
+        let path = Self::sidecar_path(texture_path);
+        let json = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("failed to read sprite sheet {}: {e}", path.display()))?;
+        Self::from_json(&json)
+    }
+}
+
+/// kept on the `SpriteAnimation` until the entity drains them, the engine produces them while
+/// advancing the animation after the entity's `update`
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpriteAnimationEvent {
+    /// a clip reached its last frame, sent every loop for looping clips
+    ClipFinished(String),
+}
+
+/// plays clips from a `SpriteSheet` by moving a `Sprite`'s uv rect
+#[derive(Debug, Clone, Component)]
+pub struct SpriteAnimation {
+    pub sheet: SpriteSheet,
+    current: Option<String>,
+    frame: usize,
+    elapsed: f32,
+    playing: bool,
+    events: VecDeque<SpriteAnimationEvent>,
+}
+
+impl SpriteAnimation {
+    pub fn new(sheet: SpriteSheet) -> Self {
+        Self {
+            sheet,
+            current: None,
+            frame: 0,
+            elapsed: 0.0,
+            playing: false,
+            events: VecDeque::new(),
+        }
+    }
+
+    /// plays the clip called `name`
+    ///
+    /// switching to a different clip starts it from its first frame, playing the current clip
+    /// again just resumes it.
+    pub fn play(&mut self, name: &str) -> anyhow::Result<()> {
+        let clip = self
+            .sheet
+            .clips
+            .get(name)
+            .ok_or(anyhow::anyhow!("no sprite clip named {name}"))?;
+
+        if self.current.as_deref() != Some(name) {
+            self.current = Some(name.to_string());
+            self.frame = clip.first;
+            self.elapsed = 0.0;
+        }
+        self.playing = true;
+        Ok(())
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn current_clip(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    /// current frame on the sheet
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    pub fn uv_rect(&self) -> UvRect {
+        self.sheet.frame_uv(self.frame)
+    }
+
+    /// advances the current clip by `delta_seconds`
+    pub fn update(&mut self, delta_seconds: f32) {
+        if !self.playing {
+            return;
+        }
+        let Some(clip) = self.current.as_ref().and_then(|c| self.sheet.clips.get(c)) else {
+            return;
+        };
+        if clip.fps <= 0.0 {
+            return;
+        }
+
+        let frame_time = 1.0 / clip.fps;
+        self.elapsed += delta_seconds;
+        while self.elapsed >= frame_time {
+            self.elapsed -= frame_time;
+            if self.frame < clip.last {
+                self.frame += 1;
+                continue;
+            }
+
+            self.events.push_back(SpriteAnimationEvent::ClipFinished(
+                self.current.clone().unwrap_or_default(),
+            ));
+            if clip.looping {
+                self.frame = clip.first;
+            } else {
+                self.playing = false;
+                self.elapsed = 0.0;
+                break;
+            }
+        }
+    }
+
+    /// takes the events produced since the last call
+    pub fn drain_events(&mut self) -> impl Iterator<Item = SpriteAnimationEvent> + '_ {
+        self.events.drain(..)
+    }
+
+    /// advances the animation in `components` and writes the frame into its `Sprite`, marking
+    /// the model dirty when the frame changed
+    ///
+    /// the engine calls it for every entity after `update`, `delta` is in milliseconds like
+    /// `update`'s
+    pub fn update_components(components: &mut ComponentSet, delta: f64) {
+        let Some(animation) = components.get_mut::<SpriteAnimation>() else {
+            return;
+        };
+        animation.update((delta / 1000.0) as f32);
+        let uv_rect = animation.uv_rect();
+        let Some(sprite) = components.get_mut::<Sprite>() else {
+            return;
+        };
+        if sprite.uv_rect != uv_rect {
+            sprite.uv_rect = uv_rect;
+            components.add(DirtyModel);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn walker() -> SpriteAnimation {
+        SpriteAnimation::new(
+            SpriteSheet::new(4, 4)
+                .clip("walk", SpriteClip::new(0, 7, 10.0, true))
+                .clip("jump", SpriteClip::new(8, 10, 10.0, false)),
+        )
+    }
+
+    #[test]
+    fn walk_loops_and_reports_completion() {
+        let mut animation = walker();
+        animation.play("walk").unwrap();
+        for _ in 0..9 {
+            animation.update(0.1);
+        }
+        assert_eq!(animation.frame(), 1);
+        assert_eq!(
+            animation.drain_events().collect::<Vec<_>>(),
+            vec![SpriteAnimationEvent::ClipFinished("walk".to_string())]
+        );
+        assert_eq!(
+            animation.uv_rect(),
+            UvRect {
+                x: 0.25,
+                y: 0.0,
+                width: 0.25,
+                height: 0.25
+            }
+        );
+    }
+
+    #[test]
+    fn switching_clips_resets_and_one_shots_stop() {
+        let mut animation = walker();
+        animation.play("walk").unwrap();
+        animation.update(0.35);
+        animation.play("jump").unwrap();
+        assert_eq!(animation.frame(), 8);
+
+        animation.update(1.0);
+        assert_eq!(animation.frame(), 10);
+        assert!(!animation.is_playing());
+        assert!(animation.play("run").is_err());
+    }
+
+    #[test]
+    fn update_components_moves_the_sprite() {
+        let mut components = ComponentSet::new();
+        components.add(Sprite::new(None, Vec2::ONE));
+        let mut animation = walker();
+        animation.play("walk").unwrap();
+        components.add(animation);
+
+        SpriteAnimation::update_components(&mut components, 50.0);
+        assert_eq!(components.get::<Sprite>().unwrap().uv_rect.x, 0.0);
+        assert!(components.get::<DirtyModel>().is_some());
+
+        components.remove::<DirtyModel>();
+        SpriteAnimation::update_components(&mut components, 60.0);
+        let sprite = components.get::<Sprite>().unwrap();
+        assert_eq!(sprite.uv_rect.x, 0.25);
+        assert!(components.get::<DirtyModel>().is_some());
+
+        // the quad shows the frame
+        let model = sprite.model();
+        let tex_coords = &model.nodes[0].meshes[0].primitives[0].tex_coords;
+        assert_eq!(tex_coords[3], Vec2::new(0.25, 0.0));
+        assert_eq!(tex_coords[1], Vec2::new(0.5, 0.25));
+    }
+
+    #[test]
+    fn sidecar_json() {
+        let sheet = SpriteSheet::from_json(
+            r#"{"columns": 8, "rows": 1, "clips": {"walk": {"first": 0, "last": 7, "fps": 12.0, "looping": true}}}"#,
+        )
+        .unwrap();
+        assert_eq!(sheet.clips["walk"].frame_count(), 8);
+        assert_eq!(
+            SpriteSheet::sidecar_path(Path::new("assets/walker.png")),
+            PathBuf::from("assets/walker.sprite.json")
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use std::time::Duration;
+
+use crate::engine::context::ContextItem;
+
+/// frames `RenderStats` keeps by default, two seconds at 60 fps
+pub const DEFAULT_RENDER_STATS_WINDOW: usize = 120;
+
+/// levels of detail `FrameStats` counts separately, deeper levels go in the last one
+pub const LOD_STATS_LEVELS: usize = 4;
+
+/// what the renderer did for a single frame
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameStats {
+    /// entities the renderer looked at
+    pub entities: usize,
+    /// entities with gms that were drawn, on their own or as instances. with split screen
+    /// `drawn`, `culled`, `triangles` and `lod_levels` add up over the viewports
+    pub drawn: usize,
+    /// entities left out by the camera's layer mask or `Visibility`
+    pub culled: usize,
+    /// triangles of the drawn entities' models, debug overlays not included
+    pub triangles: usize,
+    /// gms held for entities and instance groups after the frame
+    pub cached_gms: usize,
+    /// entities not drawn because the frame ran out of `RendererSettings::gm_budget_ms`
+    pub pending_gms: usize,
+    /// drawn entities by level of detail, ones without a `Lod` are level 0
+    pub lod_levels: [usize; LOD_STATS_LEVELS],
+    /// cpu time from the start of the frame until the buffers are swapped
+    pub cpu_time: Duration,
+    /// time spent in `swap_buffers`, mostly waiting on the gpu and vsync
+    pub swap_time: Duration,
+}
+
+impl FrameStats {
+    pub(crate) fn count_lod_level(&mut self, level: usize) {
+        self.lod_levels[level.min(LOD_STATS_LEVELS - 1)] += 1;
+    }
+
+    pub fn frame_time(&self) -> Duration {
+        self.cpu_time + self.swap_time
+    }
+}
+
+/// per frame counters the renderer fills in at the end of every frame, kept in the engine
+/// `Context`
+///
+/// the last `window` frames are kept in a ring buffer for graphs and averages, nothing
+/// allocates after `new`
+#[derive(Debug)]
+pub struct RenderStats {
+    frames: u64,
+    samples: Vec<FrameStats>,
+    next: usize,
+    len: usize,
+}
+
+impl ContextItem for RenderStats {}
+
+impl Default for RenderStats {
+    fn default() -> Self {
+        Self::new(DEFAULT_RENDER_STATS_WINDOW)
+    }
+}
+
+impl RenderStats {
+    /// keeps the last `window` frames
+    pub fn new(window: usize) -> Self {
+        let window = window.max(1);
+        Self {
+            frames: 0,
+            samples: vec![FrameStats::default(); window],
+            next: 0,
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, frame: FrameStats) {
+        self.samples[self.next] = frame;
+        self.next = (self.next + 1) % self.samples.len();
+        self.len = (self.len + 1).min(self.samples.len());
+        self.frames += 1;
+    }
+
+    /// frames rendered since the renderer started
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn window(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// the most recent frame
+    pub fn last(&self) -> Option<FrameStats> {
+        if self.len == 0 {
+            return None;
+        }
+        let index = (self.next + self.samples.len() - 1) % self.samples.len();
+        Some(self.samples[index])
+    }
+
+    /// oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &FrameStats> {
+        let start = (self.next + self.samples.len() - self.len) % self.samples.len();
+        (0..self.len).map(move |i| &self.samples[(start + i) % self.samples.len()])
+    }
+
+    pub fn average_frame_time(&self) -> Duration {
+        Duration::from_secs_f64(self.average(|f| f.frame_time().as_secs_f64()))
+    }
+
+    /// frames per second from the average frame time, 0 before the first frame
+    pub fn fps(&self) -> f64 {
+        match self.average_frame_time() {
+            average if average.is_zero() => 0.0,
+            average => 1.0 / average.as_secs_f64(),
+        }
+    }
+
+    pub fn average_triangles(&self) -> f64 {
+        self.average(|f| f.triangles as f64)
+    }
+
+    pub fn average_drawn(&self) -> f64 {
+        self.average(|f| f.drawn as f64)
+    }
+
+    fn average(&self, f: impl Fn(&FrameStats) -> f64) -> f64 {
+        if self.len == 0 {
+            return 0.0;
+        }
+        self.iter().map(f).sum::<f64>() / self.len as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_keeps_the_latest_frames() {
+        let mut stats = RenderStats::new(3);
+        assert_eq!(stats.last(), None);
+        assert_eq!(stats.fps(), 0.0);
+
+        for i in 1..=5 {
+            stats.push(FrameStats {
+                drawn: i,
+                triangles: i * 12,
+                cpu_time: Duration::from_millis(8),
+                swap_time: Duration::from_millis(2),
+                ..Default::default()
+            });
+        }
+
+        assert_eq!(stats.frames(), 5);
+        assert_eq!(
+            stats.iter().map(|f| f.drawn).collect::<Vec<_>>(),
+            vec![3, 4, 5]
+        );
+        assert_eq!(stats.last().unwrap().triangles, 60);
+        assert_eq!(stats.average_drawn(), 4.0);
+        assert!((stats.fps() - 100.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn deep_lod_levels_share_the_last_bucket() {
+        let mut frame = FrameStats::default();
+        for level in [0, 1, 1, 3, 7] {
+            frame.count_lod_level(level);
+        }
+        assert_eq!(frame.lod_levels, [1, 2, 0, 2]);
+    }
+}
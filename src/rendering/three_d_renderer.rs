@@ -1,15 +1,20 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use anyhow::anyhow;
 
-use cgmath::vec3;
-use glam::{Mat4, Vec3};
+use glam::{Mat4, Quat, Vec3};
 use log::info;
+use serde::{Deserialize, Serialize};
 use three_d::{
-    Axes, Camera, ClearState, ColorMaterial, Context, CpuMaterial, CpuMesh, CpuTexture,
-    DirectionalLight, FlyControl, FrameInput, FrameInputGenerator, FrameOutput, Gm, Mesh, Srgba,
-    SurfaceSettings, TextureData, WindowSettings, WindowedContext, degrees, geometry, radians,
+    AmbientLight, Axes, Camera, ClearState, ColorMaterial, ColorTexture, Context, CopyEffect,
+    CpuMaterial, CpuMesh, CpuTexture, DepthTexture, DepthTexture2D, FlyControl, FrameInput,
+    FrameInputGenerator, FrameOutput, FxaaEffect, GUI, Geometry, Gm, InstancedMesh, Instances,
+    Interpolation, Light, Mesh, NormalMaterial, PhysicalMaterial, RenderTarget,
+    RenderTargetMultisample, ScissorBox, Srgba, Texture2D, TextureData, WindowSettings,
+    WindowedContext, Wrapping, degrees, egui, geometry, radians,
 };
 
 use three_d::Object;
@@ -19,149 +24,1001 @@ use winit::{
     window::{Window, WindowId},
 };
 
-use crate::engine::component::Transform3D;
+use crate::engine::component::{Layer, LayerMask, Transform3D};
+use crate::engine::context::Context as EngineContext;
 use crate::engine::entity::{DefaultCamera, EntityContainer, EntityRegistry};
 use crate::engine::messages::Message;
 use crate::{
-    assets::asset_manager::Model,
+    assets::{
+        asset_manager::{
+            Asset, AssetManager, ImageFormat, Material, MeshPrimitive, Model, PrimitiveTopology,
+            Texture,
+        },
+        basic_models::CuboidBuilder,
+        hot_reload::AssetReloaded,
+        loading::AssetHandle,
+        streaming::StreamingModel,
+    },
     engine::{Engine, entity::Entity},
-    utils::{IntoCgmath, SharedBox, WeakShared},
+    physics::{
+        debug_render::{DebugLine, PhysicsDebugLines},
+        interpolation::{InterpolatedTransform, InterpolationSettings},
+    },
+    utils::{IntoCgmath, IntoGlam, SharedBox, WeakShared},
 };
 
-use super::Renderer;
+use super::billboard::Billboard;
+use super::diagnostics::DiagnosticsOverlay;
+use super::environment::{EnvironmentSettings, Fog};
+use super::fog::FogEffect;
+use super::gizmo::{Gizmo, GizmoQueue};
+use super::instancing::InstanceKey;
+use super::light::{
+    DirectionalLight, FALLBACK_LIGHT_DIRECTION, FALLBACK_LIGHT_INTENSITY, MAX_LIGHTS, PointLight,
+    SpotLight, light_color, range_attenuation,
+};
+use super::lod::Lod;
+use super::screen_space::{Anchor, ScreenSprite, TextLabel};
+use super::screenshot::{Screenshot, ScreenshotTarget};
+use super::settings::RendererSettings;
+use super::sprite::Sprite;
+use super::stats::{FrameStats, RenderStats};
+use super::ui::{UiInputCapture, UiLayer};
+use super::viewport::{ClearMode, ViewportConfig, ViewportMapper, ViewportRect};
+use super::wireframe::{WireframeMaterial, view_mode_mesh};
+use super::{DirtyModel, Renderer, RendererCommand};
 
 /// three_d renderer
 pub struct ThreedRenderer {
     // window_id: WindowId,
     pub context: Option<WindowedContext>,
-    camera: Option<Camera>,
     camera_id: Option<Uuid>,
+    /// split screen cameras, the active camera gets the whole frame when this is empty
+    viewports: Vec<ViewportConfig>,
     control: FlyControl,
-    lights: Vec<DirectionalLight>,
+    /// drawn while no entity has a `DirectionalLight`, made with the context
+    fallback_light: Option<three_d::DirectionalLight>,
+    /// follows `EnvironmentSettings`
+    ambient: Option<AmbientLight>,
+    /// one for every entity with a `DirectionalLight`, `PointLight` or `SpotLight`, kept in sync
+    /// every frame
+    directional_lights: HashMap<Uuid, three_d::DirectionalLight>,
+    point_lights: HashMap<Uuid, three_d::PointLight>,
+    spot_lights: HashMap<Uuid, three_d::SpotLight>,
 
     objects: EntityRegistry,
-    object_gm_cache: HashMap<Uuid, Vec<Gm<Mesh, ColorMaterial>>>,
+    object_gm_cache: HashMap<Uuid, Vec<ModelGm>>,
+    /// `StreamingModel` version each cached gm list was built from
+    streamed_versions: HashMap<Uuid, u64>,
+    /// entities that share a key are drawn together from `instance_groups` instead of having
+    /// gms of their own
+    instance_keys: HashMap<Uuid, Option<InstanceKey>>,
+    instance_groups: HashMap<InstanceKey, Vec<ModelGm<InstancedMesh>>>,
+    /// triangles in each entity's model, worked out when its gms are built
+    triangle_counts: HashMap<Uuid, usize>,
+    /// level each entity with a `Lod` was drawn at last frame
+    lod_levels: HashMap<Uuid, usize>,
+    /// gms for the levels past 0, which live in `object_gm_cache`
+    lod_gm_cache: HashMap<(Uuid, usize), LodGms>,
+    /// unit cube drawn over every entity still waiting for its gms, made the first time one is
+    placeholder_gms: Vec<ModelGm<InstancedMesh>>,
+    reloaded_models: ReloadedModels,
     messages: VecDeque<Message>,
+
+    engine_context: EngineContext,
+    diagnostics: DiagnosticsOverlay,
+
+    anti_aliasing: AaMode,
+    /// one for each view, in the order of the views, with the size they were made for
+    aa_targets: Vec<((u32, u32), AaTargets)>,
+    /// the same for `AaMode::Msaa`, with the sample count too
+    msaa_targets: Vec<((u32, u32, u8), RenderTargetMultisample<[u8; 4], f32>)>,
+
+    /// made once the render context exists
+    axes: Option<Axes>,
+    /// the physics debug lines' mesh with the `PhysicsDebugLines::revision` it was built from
+    physics_debug: Option<(u64, Option<Gm<Mesh, ColorMaterial>>)>,
+    show_axes: bool,
+
+    view_mode: ViewMode,
+    /// captures waiting for the next presented frame
+    screenshots: Vec<ScreenshotTarget>,
+    /// used by `init`, changes after it only apply to the next context
+    settings: RendererSettings,
+
+    /// kept across frames so window events reach the ui and frame times add up
+    frame_input_generator: Option<FrameInputGenerator>,
+    gui: Option<GUI>,
+    /// `ScreenSprite` textures by path, `None` for ones that failed to load
+    screen_textures: HashMap<PathBuf, Option<egui::TextureHandle>>,
+}
+
+/// anti-aliasing used when rendering the scene
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AaMode {
+    Off,
+    /// multisampled offscreen target with this many samples, resolved to the screen
+    Msaa(u8),
+    /// fxaa post pass over the offscreen color target
+    Fxaa,
+}
+
+/// how models are drawn, everything but `Shaded` is for debugging
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ViewMode {
+    /// models' own materials and the scene's lights
+    #[default]
+    Shaded,
+    /// only the triangle edges, in one flat color
+    Wireframe,
+    /// world space normals as colors
+    Normals,
+    /// albedo only, no lighting
+    Unlit,
+}
+
+/// a camera and the part of the screen it renders into for one frame
+struct FrameView {
+    camera: Camera,
+    position: Vec3,
+    layer_mask: LayerMask,
+    clear: ClearMode,
+}
+
+/// one cached level of detail of an entity
+/// assets that changed on disk since the entities loaded their copies, the new ones are read
+/// from the `AssetManager`'s cache and drawn in their place
+struct ReloadedModels {
+    ids: HashSet<Uuid>,
+    engine_context: EngineContext,
+}
+
+impl ReloadedModels {
+    /// the reloaded model with the id, `None` when it wasn't reloaded
+    fn model(&self, asset_id: Option<Uuid>) -> Option<Model> {
+        let id = asset_id.filter(|id| self.ids.contains(id))?;
+        let asset = self
+            .engine_context
+            .with(|assets: &AssetManager| assets.get_asset_by_id(id))
+            .flatten()?;
+        match asset.as_ref() {
+            Asset::Model(model) => Some(model.clone()),
+            _ => None,
+        }
+    }
+}
+
+struct LodGms {
+    gms: Vec<ModelGm>,
+    triangles: usize,
+}
+
+/// time a frame has spent building gms, once it's used up the rest of the entities wait
+struct GmBudget {
+    budget: Option<Duration>,
+    spent: Duration,
+    built: bool,
+    /// entities turned away this frame
+    pending: usize,
+}
+
+impl GmBudget {
+    fn new(budget_ms: Option<u32>) -> Self {
+        Self {
+            budget: budget_ms.map(|ms| Duration::from_millis(ms as u64)),
+            spent: Duration::ZERO,
+            built: false,
+            pending: 0,
+        }
+    }
+
+    /// whether another entity's gms can be built, the first one of a frame always can so
+    /// models too big for the budget still show up
+    fn allows(&mut self) -> bool {
+        let allowed = !self.built || self.budget.is_none_or(|budget| self.spent < budget);
+        if !allowed {
+            self.pending += 1;
+        }
+        allowed
+    }
+
+    fn spend(&mut self, started: Instant) {
+        self.spent += started.elapsed();
+        self.built = true;
+    }
+}
+
+/// offscreen targets the scene gets rendered into before the fxaa pass
+struct AaTargets {
+    color: Texture2D,
+    depth: DepthTexture2D,
+}
+
+impl AaTargets {
+    fn new(context: &three_d::Context, width: u32, height: u32) -> Self {
+        Self {
+            color: Texture2D::new_empty::<[u8; 4]>(
+                context,
+                width,
+                height,
+                Interpolation::Linear,
+                Interpolation::Linear,
+                None,
+                Wrapping::ClampToEdge,
+                Wrapping::ClampToEdge,
+            ),
+            depth: DepthTexture2D::new::<f32>(
+                context,
+                width,
+                height,
+                Wrapping::ClampToEdge,
+                Wrapping::ClampToEdge,
+            ),
+        }
+    }
+}
+
+/// the last frame's target for a view when it was made for `key`, otherwise a new one from
+/// `make`. making the targets every frame would allocate them on the gpu every frame
+fn reuse_target<K: PartialEq, T>(
+    previous: Option<(K, T)>,
+    key: K,
+    make: impl FnOnce() -> T,
+) -> (K, T) {
+    match previous {
+        Some((previous_key, target)) if previous_key == key => (key, target),
+        _ => (key, make()),
+    }
 }
 
 impl ThreedRenderer {
     /// creates new three_d renderer
-    pub fn new(objects: EntityRegistry) -> Self {
+    pub fn new(objects: EntityRegistry, engine_context: EngineContext) -> Self {
         let mut control = FlyControl::new(10.);
 
-        let lights = Vec::new();
+        engine_context.insert(ViewportMapper::default());
+        engine_context.get_or_insert_with(EnvironmentSettings::default);
+        engine_context.get_or_insert_with(GizmoQueue::default);
+        engine_context.get_or_insert_with(UiLayer::default);
+        engine_context.get_or_insert_with(RenderStats::default);
 
         Self {
             context: None,
-            camera: None,
             camera_id: None,
+            viewports: Vec::new(),
             control,
-            lights,
+            fallback_light: None,
+            ambient: None,
+            directional_lights: HashMap::new(),
+            point_lights: HashMap::new(),
+            spot_lights: HashMap::new(),
 
             objects,
             object_gm_cache: HashMap::new(),
+            streamed_versions: HashMap::new(),
+            instance_keys: HashMap::new(),
+            instance_groups: HashMap::new(),
+            triangle_counts: HashMap::new(),
+            lod_levels: HashMap::new(),
+            lod_gm_cache: HashMap::new(),
+            placeholder_gms: Vec::new(),
+            reloaded_models: ReloadedModels {
+                ids: HashSet::new(),
+                engine_context: engine_context.clone(),
+            },
             messages: VecDeque::new(),
+
+            engine_context,
+            diagnostics: DiagnosticsOverlay::new(),
+
+            anti_aliasing: AaMode::Off,
+            aa_targets: Vec::new(),
+            msaa_targets: Vec::new(),
+            physics_debug: None,
+
+            axes: None,
+            show_axes: cfg!(debug_assertions),
+
+            view_mode: ViewMode::Shaded,
+            screenshots: Vec::new(),
+            settings: RendererSettings::default(),
+
+            frame_input_generator: None,
+            gui: None,
+            screen_textures: HashMap::new(),
         }
     }
 
-    pub fn init(&mut self, window: &Window, camera_id: &Uuid) -> anyhow::Result<()> {
-        let camera_container = self
-            .objects
-            .get(camera_id)
-            .ok_or(anyhow::anyhow!("camera not found from provided id"))?;
+    pub fn set_anti_aliasing(&mut self, mode: AaMode) {
+        self.anti_aliasing = match mode {
+            AaMode::Msaa(0) | AaMode::Msaa(1) => AaMode::Off,
+            AaMode::Msaa(samples) => AaMode::Msaa(samples.next_power_of_two().min(16)),
+            m => m,
+        };
+        if self.anti_aliasing != AaMode::Fxaa {
+            self.aa_targets.clear();
+        }
+        if !matches!(self.anti_aliasing, AaMode::Msaa(_)) {
+            self.msaa_targets.clear();
+        }
+    }
 
-        let camera_lock = camera_container.lock().expect("mutex lock failed");
+    pub fn anti_aliasing(&self) -> AaMode {
+        self.anti_aliasing
+    }
 
-        let camera_entity = camera_lock
-            .as_any()
-            .downcast_ref::<DefaultCamera>()
-            .ok_or(anyhow::anyhow!("provided entity is not a camera"))?;
+    pub fn set_view_mode(&mut self, mode: ViewMode) {
+        // wireframes are drawn from meshes of their own, so the gms are built again
+        if (mode == ViewMode::Wireframe) != (self.view_mode == ViewMode::Wireframe) {
+            self.object_gm_cache.clear();
+            self.lod_gm_cache.clear();
+            self.instance_groups.clear();
+            self.placeholder_gms.clear();
+        }
+        self.view_mode = mode;
+    }
 
-        let mut camera = {
-            let pos = camera_entity.transform().position;
-            let rotation = camera_entity.transform().rotation;
-            let target = Vec3::from(pos + rotation * camera_entity.forward);
+    pub fn view_mode(&self) -> ViewMode {
+        self.view_mode
+    }
 
-            Camera::new_perspective(
-                three_d::Viewport::new_at_origo(1, 1),
-                pos.into_cgmath(),
-                target.into_cgmath(),
-                camera_entity.up.into_cgmath(),
-                radians(camera_entity.fov),
-                camera_entity.near,
-                camera_entity.far,
-            )
+    pub fn settings(&self) -> RendererSettings {
+        self.settings
+    }
+
+    /// rebuilds the entities drawn with the reloaded asset from its new data on the next frame
+    pub fn asset_reloaded(&mut self, reloaded: AssetReloaded) {
+        let asset = self
+            .engine_context
+            .with(|assets: &AssetManager| assets.get_asset_by_id(reloaded.id))
+            .flatten();
+        let Some(Asset::Model(_)) = asset.as_deref() else {
+            log::debug!("nothing to rebuild for {}", reloaded.path.display());
+            return;
         };
+        self.reloaded_models.ids.insert(reloaded.id);
 
-        let context =
-            WindowedContext::from_winit_window(window, SurfaceSettings::default()).unwrap();
+        for o in self.objects.clone() {
+            let uses_asset = o.read_with(|o| {
+                entity_model(o).is_some_and(|model| model.asset_id == Some(reloaded.id))
+            });
+            if uses_asset {
+                self.object_gm_cache.remove(&o.id());
+                self.instance_keys.remove(&o.id());
+                self.triangle_counts.remove(&o.id());
+                self.lod_gm_cache.retain(|(id, _), _| *id != o.id());
+            }
+        }
+        self.instance_groups
+            .retain(|key, _| key.asset_id != reloaded.id);
+    }
 
-        let lights = [DirectionalLight::new(
-            &context,
-            1.0,
-            Srgba::WHITE,
-            vec3(0.0, -0.5, -0.5),
-        )];
+    /// captures the next frame exactly as it's presented
+    pub fn capture_screenshot(&mut self, target: ScreenshotTarget) {
+        self.screenshots.push(target);
+    }
 
-        self.context = Some(context);
-        self.lights = Vec::from(lights);
-        self.camera = Some(camera);
-        self.camera_id = Some(*camera_id);
+    pub fn show_diagnostics(&mut self, show: bool) {
+        self.diagnostics.set_visible(show);
+    }
 
-        Ok(())
+    pub fn show_physics_debug(&mut self, show: bool) {
+        self.engine_context
+            .get_or_insert_with(PhysicsDebugLines::default)
+            .write()
+            .unwrap()
+            .enabled = show;
     }
 
-    fn render_internal(&mut self, frame_input: &mut FrameInput) -> anyhow::Result<()> {
-        let context = self.context.as_ref().ok_or(anyhow::anyhow!("no context"))?;
-        let axes = Axes::new(context, 0.5, 10.0);
+    pub fn set_clear_color(&mut self, color: glam::Vec4) {
+        self.engine_context
+            .get_or_insert_with(EnvironmentSettings::default)
+            .write()
+            .unwrap()
+            .clear_color = color;
+    }
+
+    pub fn set_ambient_light(&mut self, color: Vec3, intensity: f32) {
+        let settings = self
+            .engine_context
+            .get_or_insert_with(EnvironmentSettings::default);
+        let mut settings = settings.write().unwrap();
+        settings.ambient_color = color;
+        settings.ambient_intensity = intensity;
+    }
+
+    /// takes effect on the next frame, so it can change every frame for weather
+    pub fn set_fog(&mut self, fog: Fog) {
+        self.engine_context
+            .get_or_insert_with(EnvironmentSettings::default)
+            .write()
+            .unwrap()
+            .fog = fog;
+    }
+
+    pub fn show_debug_axes(&mut self, show: bool) {
+        self.show_axes = show;
+    }
+
+    pub fn debug_axes(&self) -> bool {
+        self.show_axes
+    }
+
+    /// queues `gizmo` for the next frame
+    pub fn draw_gizmo(&self, gizmo: Gizmo) {
+        self.engine_context
+            .get_or_insert_with(GizmoQueue::default)
+            .write()
+            .unwrap()
+            .push(gizmo);
+    }
+
+    pub fn draw_line(&self, from: Vec3, to: Vec3, color: [f32; 4]) {
+        self.draw_gizmo(Gizmo::Line { from, to, color });
+    }
+
+    pub fn draw_wire_box(&self, transform: Transform3D, half_extents: Vec3, color: [f32; 4]) {
+        self.draw_gizmo(Gizmo::WireBox {
+            transform,
+            half_extents,
+            color,
+        });
+    }
+
+    pub fn draw_sphere(&self, center: Vec3, radius: f32, color: [f32; 4]) {
+        self.draw_gizmo(Gizmo::Sphere {
+            center,
+            radius,
+            color,
+        });
+    }
+
+    /// renders from the camera entity `id` from the next frame on, anything that isn't a camera
+    /// entity is logged and the old camera is kept
+    pub fn set_active_camera(&mut self, id: Uuid) {
+        match camera_from_entity(&self.objects, &id) {
+            Ok(_) => {
+                self.camera_id = Some(id);
+                // the new camera may have been sized for another window or not at all
+                self.resize_cameras_to_window();
+            }
+            Err(e) => log::warn!("kept the active camera, can't switch to {id}: {e}"),
+        }
+    }
+
+    /// splits the window between cameras, an empty list goes back to the active camera on the
+    /// whole window. viewports whose entity isn't a camera are left out with a warning
+    pub fn set_viewports(&mut self, viewports: Vec<ViewportConfig>) {
+        let objects = &self.objects;
+        self.viewports = viewports
+            .into_iter()
+            .filter(|config| match camera_from_entity(objects, &config.camera) {
+                Ok(_) => true,
+                Err(e) => {
+                    log::warn!("left out the viewport for {}: {e}", config.camera);
+                    false
+                }
+            })
+            .collect();
+        // the cameras' aspect ratios change with the split, not just on resizes
+        self.resize_cameras_to_window();
+    }
+
+    /// drops what was built from the models of entities with a `DirtyModel` and takes the
+    /// marker off, their gms are built again when they're drawn next
+    fn evict_dirty_models(&mut self) {
+        for o in self.objects.clone() {
+            let dirty = o.write_with(|o| o.components_mut().remove::<DirtyModel>().is_some());
+            if !dirty {
+                continue;
+            }
+            self.object_gm_cache.remove(&o.id());
+            self.instance_keys.remove(&o.id());
+            self.triangle_counts.remove(&o.id());
+            self.lod_gm_cache.retain(|(id, _), _| *id != o.id());
+        }
+    }
+
+    /// `resize_camera_entity` with the window's last known size
+    fn resize_cameras_to_window(&self) {
+        if let Some(mapper) = self.engine_context.with(|mapper: &ViewportMapper| *mapper) {
+            self.resize_camera_entity(mapper.window_size.x as u32, mapper.window_size.y as u32);
+        }
+    }
+
+    /// keeps the camera entities' sizes in step with the window, so their projection matrices
+    /// have the aspect ratio of the part of the window they render into
+    pub(crate) fn resize_camera_entity(&self, width: u32, height: u32) {
+        let sizes: Vec<(Uuid, u32, u32)> = if self.viewports.is_empty() {
+            self.camera_id
+                .map(|id| (id, width, height))
+                .into_iter()
+                .collect()
+        } else {
+            self.viewports
+                .iter()
+                .map(|config| {
+                    let rect = config.rect_in(width, height);
+                    (config.camera, rect.width as u32, rect.height as u32)
+                })
+                .collect()
+        };
+        for (id, width, height) in sizes {
+            // minimized windows are 0 by 0
+            if width == 0 || height == 0 {
+                continue;
+            }
+            let Some(camera) = self.objects.get(&id) else {
+                continue;
+            };
+            camera.with_downcast_mut(|camera: &mut DefaultCamera| {
+                camera.width = width as f32;
+                camera.height = height as f32;
+            });
+        }
+    }
 
-        let camera_container = self
+    /// what gets rendered this frame, the active camera on the whole frame unless viewports
+    /// are set
+    fn frame_views(&self, frame: three_d::Viewport) -> anyhow::Result<Vec<FrameView>> {
+        if self.viewports.is_empty() {
+            let id = self.camera_id.ok_or(anyhow::anyhow!("no camera id"))?;
+            return Ok(vec![frame_view(
+                &self.objects,
+                &id,
+                frame,
+                ClearMode::default(),
+            )?]);
+        }
+        // cameras despawned since the split was set are skipped
+        let views: Vec<FrameView> = self
+            .viewports
+            .iter()
+            .map(|config| (config, split_viewport(frame, config)))
+            .filter(|(_, viewport)| viewport.width > 0 && viewport.height > 0)
+            .filter_map(|(config, viewport)| {
+                frame_view(&self.objects, &config.camera, viewport, config.clear).ok()
+            })
+            .collect();
+        if views.is_empty() {
+            return Err(anyhow::anyhow!("no viewport has a camera"));
+        }
+        Ok(views)
+    }
+
+    /// runs the `UiLayer` callbacks, lays out the screen space elements and records what input
+    /// the ui wants, `false` when there's no ui to draw this frame
+    fn update_ui(&mut self, frame_input: &mut FrameInput) -> bool {
+        let diagnostics = self.update_diagnostics();
+        let Some(gui) = self.gui.as_mut() else {
+            return false;
+        };
+        let hidden = self.objects.hidden();
+        let elements: Vec<_> = self
             .objects
-            .get(
-                &self
-                    .camera_id
-                    .ok_or(anyhow::anyhow!("no camera id"))
-                    .unwrap(),
-            )
-            .ok_or(anyhow::anyhow!("no camera entity"))
-            .unwrap();
-        let camera_transform = camera_container
-            .lock()
-            .expect("mutex lock failed")
-            .transform();
-
-        let pos = camera_transform.position;
-        let rotation = camera_transform.rotation;
-        let target = Vec3::from(pos + rotation * Vec3::new(0.0, 0.0, -1.0));
-
-        self.camera
-            .as_mut()
-            .ok_or(anyhow::anyhow!("no camera"))?
-            .set_view(
-                pos.into_cgmath(),
-                target.into_cgmath(),
-                Vec3::new(0.0, 1.0, 0.0).into_cgmath(),
+            .clone()
+            .into_iter()
+            .filter(|o| !hidden.contains(&o.id()))
+            .filter_map(|o| {
+                o.read_with(|o| {
+                    let components = o.components();
+                    let label = components.get::<TextLabel>().cloned();
+                    let sprite = components.get::<ScreenSprite>().cloned();
+                    (label.is_some() || sprite.is_some()).then_some((label, sprite))
+                })
+            })
+            .collect();
+        let layer = self.engine_context.get_or_insert_with(UiLayer::default);
+        let mut layer = layer.write().unwrap();
+        let draw = !layer.is_empty() || !elements.is_empty() || diagnostics.is_some();
+
+        let mut capture = UiInputCapture::default();
+        if draw {
+            gui.update(
+                &mut frame_input.events,
+                frame_input.accumulated_time,
+                frame_input.viewport,
+                frame_input.device_pixel_ratio,
+                |ctx| {
+                    draw_screen_space(
+                        ctx,
+                        &self.engine_context,
+                        &elements,
+                        &mut self.screen_textures,
+                    );
+                    layer.run(ctx);
+                    if let Some(text) = &diagnostics {
+                        draw_diagnostics(ctx, text);
+                    }
+                    capture = UiInputCapture::from_context(ctx);
+                },
             );
+        }
+        // written in place, the event handler holds on to this one
+        self.engine_context
+            .with_mut(|current: &mut UiInputCapture| *current = capture);
+        draw
+    }
 
-        self.camera
-            .as_mut()
-            .ok_or(anyhow::anyhow!("no camera"))?
-            .set_viewport(frame_input.viewport);
+    /// keeps the `ViewportMapper` in the engine context in sync with the frame's viewport
+    fn update_viewport_mapper(&self, frame_input: &FrameInput) {
+        let scale_factor = frame_input.device_pixel_ratio as f32;
+        let window_size = glam::Vec2::new(
+            frame_input.window_width as f32,
+            frame_input.window_height as f32,
+        ) * scale_factor;
+        let viewport = frame_input.viewport;
+        self.engine_context.with_mut(|mapper: &mut ViewportMapper| {
+            mapper.window_size = window_size;
+            mapper.scale_factor = scale_factor;
+            // three_d viewports start at the bottom left
+            mapper.viewport = ViewportRect {
+                x: viewport.x as f32,
+                y: window_size.y - (viewport.y as f32 + viewport.height as f32),
+                width: viewport.width as f32,
+                height: viewport.height as f32,
+            };
+        });
+    }
+
+    /// the overlay text when it's shown, refreshed if it's due
+    fn update_diagnostics(&mut self) -> Option<String> {
+        self.diagnostics
+            .update(&self.engine_context, self.objects.len());
+        self.diagnostics
+            .is_visible()
+            .then(|| self.diagnostics.text().to_string())
+    }
+
+    /// drops every gm, light, target and the context with its window surface. the entities keep
+    /// their models, so the gms are built again as they're drawn after the next `init`
+    fn release_gpu_resources(&mut self) {
+        // gl objects are deleted in whichever context is current when they drop
+        if let Some(Err(e)) = self.context.as_ref().map(|c| c.make_current()) {
+            log::warn!("releasing gpu resources without a current context: {e:?}");
+        }
+        self.object_gm_cache.clear();
+        self.streamed_versions.clear();
+        self.instance_keys.clear();
+        self.instance_groups.clear();
+        self.triangle_counts.clear();
+        self.lod_levels.clear();
+        self.lod_gm_cache.clear();
+        self.placeholder_gms.clear();
+        self.fallback_light = None;
+        self.ambient = None;
+        self.directional_lights.clear();
+        self.point_lights.clear();
+        self.spot_lights.clear();
+        self.aa_targets.clear();
+        self.msaa_targets.clear();
+        self.axes = None;
+        self.physics_debug = None;
+        self.screen_textures.clear();
+        self.gui = None;
+        self.frame_input_generator = None;
+        self.context = None;
+    }
+
+    fn update_ambient(&mut self, environment: &EnvironmentSettings) -> anyhow::Result<()> {
+        let context = self.context.as_ref().ok_or(anyhow::anyhow!("no context"))?;
+        let color = light_color(environment.ambient_color);
+        match self.ambient.as_mut() {
+            Some(ambient) => {
+                ambient.color = color;
+                ambient.intensity = environment.ambient_intensity;
+            }
+            None => {
+                self.ambient = Some(AmbientLight::new(
+                    context,
+                    environment.ambient_intensity,
+                    color,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// creates, moves and drops three_d lights to match the entities' light components
+    fn update_lights(&mut self) -> anyhow::Result<()> {
+        let context = self.context.as_ref().ok_or(anyhow::anyhow!("no context"))?;
+        let mut seen_suns = HashSet::new();
+        let mut seen_points = HashSet::new();
+        let mut seen_spots = HashSet::new();
+
+        for o in self.objects.clone().into_iter() {
+            let (id, transform, sun, point, spot) = o.read_with(|o| {
+                let components = o.components();
+                (
+                    o.id(),
+                    components.get::<Transform3D>().copied().unwrap_or_default(),
+                    components.get::<DirectionalLight>().copied(),
+                    components.get::<PointLight>().copied(),
+                    components.get::<SpotLight>().copied(),
+                )
+            });
+            let position = transform.position.into_cgmath();
+
+            if let Some(light) = sun {
+                seen_suns.insert(id);
+                let color = light_color(light.color);
+                let direction = (transform.rotation * Vec3::NEG_Z).into_cgmath();
+                match self.directional_lights.get_mut(&id) {
+                    Some(existing) => {
+                        existing.color = color;
+                        existing.intensity = light.intensity;
+                        existing.direction = direction;
+                    }
+                    None => {
+                        let new = three_d::DirectionalLight::new(
+                            context,
+                            light.intensity,
+                            color,
+                            direction,
+                        );
+                        self.directional_lights.insert(id, new);
+                    }
+                }
+            }
+
+            if let Some(light) = point {
+                seen_points.insert(id);
+                let color = light_color(light.color);
+                let attenuation = range_attenuation(light.range);
+                match self.point_lights.get_mut(&id) {
+                    Some(existing) => {
+                        existing.color = color;
+                        existing.intensity = light.intensity;
+                        existing.position = position;
+                        existing.attenuation = attenuation;
+                    }
+                    None => {
+                        let new = three_d::PointLight::new(
+                            context,
+                            light.intensity,
+                            color,
+                            position,
+                            attenuation,
+                        );
+                        self.point_lights.insert(id, new);
+                    }
+                }
+            }
+
+            if let Some(light) = spot {
+                seen_spots.insert(id);
+                let color = light_color(light.color);
+                let attenuation = range_attenuation(light.range);
+                let direction = (transform.rotation * Vec3::NEG_Z).into_cgmath();
+                match self.spot_lights.get_mut(&id) {
+                    Some(existing) => {
+                        existing.color = color;
+                        existing.intensity = light.intensity;
+                        existing.position = position;
+                        existing.direction = direction;
+                        existing.cutoff = radians(light.angle);
+                        existing.attenuation = attenuation;
+                    }
+                    None => {
+                        let new = three_d::SpotLight::new(
+                            context,
+                            light.intensity,
+                            color,
+                            position,
+                            direction,
+                            radians(light.angle),
+                            attenuation,
+                        );
+                        self.spot_lights.insert(id, new);
+                    }
+                }
+            }
+        }
+
+        self.directional_lights
+            .retain(|id, _| seen_suns.contains(id));
+        self.point_lights.retain(|id, _| seen_points.contains(id));
+        self.spot_lights.retain(|id, _| seen_spots.contains(id));
+        Ok(())
+    }
+
+    fn render_internal(&mut self, frame_input: &mut FrameInput) -> anyhow::Result<()> {
+        let frame_start = Instant::now();
+        let mut stats = FrameStats::default();
+        self.update_lights()?;
+        let environment = self
+            .engine_context
+            .with(|settings: &EnvironmentSettings| *settings)
+            .unwrap_or_default();
+        self.update_ambient(&environment)?;
+        // laid out before the scene so it sees this frame's events, drawn after it
+        let draw_ui = self.update_ui(frame_input);
+        // taken first so the queue is emptied even when this frame bails out early
+        let gizmo_lines = self
+            .engine_context
+            .with_mut(|queue: &mut GizmoQueue| queue.take_lines())
+            .unwrap_or_default();
+        self.evict_dirty_models();
+        let context = self.context.as_ref().ok_or(anyhow::anyhow!("no context"))?;
+
+        let views = self.frame_views(frame_input.viewport)?;
+        // levels of detail and streaming go by the closest camera
+        let closest = |position: Vec3| {
+            views
+                .iter()
+                .map(|view| view.position.distance(position))
+                .fold(f32::INFINITY, f32::min)
+        };
 
         // self.control
         //     .handle_events(self.camera.as_mut().unwrap(), &mut frame_input.events);
 
-        let delta = frame_input.elapsed_time;
+        let interpolate = self
+            .engine_context
+            .with(|settings: &InterpolationSettings| settings.enabled)
+            .unwrap_or(false);
+        let now = Instant::now();
+        // every instanced entity this frame with its group and transformation
+        let mut instances = Vec::new();
+        // turned to each view's camera once the views are drawn
+        let mut billboards = Vec::new();
+        let mut budget = GmBudget::new(self.settings.gm_budget_ms);
+        // entities that ran out of budget, with where their placeholder goes
+        let mut pending = Vec::new();
+        let view_mode = self.view_mode;
 
-        self.objects.clone().into_iter().for_each(|o| {
-            o.lock().expect("poisoned mutex").update(delta);
+        // nearest first, so that's what shows up first when the budget runs out. distances
+        // aren't negative, so their bits sort the same as they do
+        let mut objects: Vec<EntityContainer> = self.objects.clone().into_iter().collect();
+        objects.sort_by_cached_key(|o| {
+            let position = o.read_with(|o| {
+                o.components()
+                    .get::<Transform3D>()
+                    .map(|t| t.position)
+                    .unwrap_or_default()
+            });
+            closest(position).to_bits()
         });
 
-        self.objects.clone().into_iter().for_each(|o| {
-            let transform = o.lock().expect("poisoned mutex").transform();
+        objects.into_iter().for_each(|o| {
+            let transform = o.read_with(|o| match o.components().get::<InterpolatedTransform>() {
+                Some(interpolated) if interpolate => interpolated.at(now),
+                _ => o
+                    .components()
+                    .get::<Transform3D>()
+                    .copied()
+                    .unwrap_or_default(),
+            });
+
+            let streamed = o.read_with(|o| o.components().get::<StreamingModel>().cloned());
+
+            // models still decoding on a loader thread wait like the ones over the budget
+            let loading = o.read_with(|o| {
+                o.components()
+                    .get::<AssetHandle>()
+                    .is_some_and(AssetHandle::is_loading)
+            });
+            if loading {
+                pending.push((o.clone(), transform));
+                return;
+            }
+
+            // only the entity's own model is drawn at level 0
+            let level = o.read_with(|o| {
+                o.components().get::<Lod>().map(|lod| {
+                    let current = self.lod_levels.get(&o.id()).copied().unwrap_or(0);
+                    lod.select(current, closest(transform.position))
+                })
+            });
+            match level {
+                Some(level) => self.lod_levels.insert(o.id(), level),
+                None => self.lod_levels.remove(&o.id()),
+            };
+            let billboard = o.read_with(|o| o.components().get::<Billboard>().copied());
+            if let Some(billboard) = billboard {
+                billboards.push((o.id(), transform, billboard));
+            }
+            if let Some(level @ 1..) = level {
+                let key = (o.id(), level);
+                if !self.lod_gm_cache.contains_key(&key) {
+                    if !budget.allows() {
+                        pending.push((o.clone(), transform));
+                        return;
+                    }
+                    let started = Instant::now();
+                    let context = self.context.as_ref().unwrap();
+                    let built = o.read_with(|o| {
+                        let model = o.components().get::<Lod>()?.model(level)?;
+                        Some(LodGms {
+                            gms: model_gm_list(model, context, |cpu_mesh| {
+                                Mesh::new(context, &view_mode_mesh(view_mode, cpu_mesh))
+                            }),
+                            triangles: model.triangle_count(),
+                        })
+                    });
+                    budget.spend(started);
+                    let Some(built) = built else {
+                        return;
+                    };
+                    self.lod_gm_cache.insert(key, built);
+                }
+                if let Some(lod) = self.lod_gm_cache.get_mut(&key) {
+                    lod.gms
+                        .iter_mut()
+                        .for_each(|gm| gm_update_transform(gm, &transform));
+                }
+                return;
+            }
+
+            // streamed models change under the entity, so they always get gms of their own, as
+            // do entities switching between levels of detail and billboards
+            let key = match &streamed {
+                Some(_) => None,
+                None if level.is_some() || billboard.is_some() => None,
+                None => *self.instance_keys.entry(o.id()).or_insert_with(|| {
+                    o.read_with(|o| {
+                        current_model(o, &self.reloaded_models)
+                            .as_ref()
+                            .and_then(InstanceKey::for_model)
+                    })
+                }),
+            };
+            if let Some(key) = key {
+                if !self.instance_groups.contains_key(&key) {
+                    if !budget.allows() {
+                        pending.push((o.clone(), transform));
+                        return;
+                    }
+                    let started = Instant::now();
+                    let context = self.context.as_ref().unwrap();
+                    let built = current_gm_list(&o, &self.reloaded_models, context, |cpu_mesh| {
+                        InstancedMesh::new(
+                            context,
+                            &Instances::default(),
+                            &view_mode_mesh(view_mode, cpu_mesh),
+                        )
+                    });
+                    budget.spend(started);
+                    match built {
+                        Ok(gms) => {
+                            self.instance_groups.insert(key, gms);
+                        }
+                        Err(e) => {
+                            log::info!("skipped object render because unable to get gm list: {e}");
+                            return;
+                        }
+                    }
+                }
+                self.object_gm_cache.remove(&o.id());
+                self.triangle_counts
+                    .entry(o.id())
+                    .or_insert_with(|| entity_triangles(&o, &self.reloaded_models));
+                instances.push((key, o, transform.transform_matrix().into_cgmath()));
+                return;
+            }
+            if let Some(streamed) = streamed.as_ref() {
+                if !streamed.is_ready() {
+                    streamed.set_priority(closest(transform.position));
+                }
+                if self.streamed_versions.get(&o.id()) != Some(&streamed.version()) {
+                    self.object_gm_cache.remove(&o.id());
+                    self.triangle_counts.remove(&o.id());
+                }
+            }
 
             if !self.object_gm_cache.contains_key(&o.id()) {
-                let mut gms = match object_get_gm_list(o.clone(), &self.context.as_ref().unwrap()) {
+                if !budget.allows() {
+                    pending.push((o.clone(), transform));
+                    return;
+                }
+                let started = Instant::now();
+                let version = streamed.as_ref().map(|s| s.version());
+                let context = self.context.as_ref().unwrap();
+                let built = current_gm_list(&o, &self.reloaded_models, context, |cpu_mesh| {
+                    Mesh::new(context, &view_mode_mesh(view_mode, cpu_mesh))
+                });
+                budget.spend(started);
+                let mut gms = match built {
                     Ok(g) => g,
                     Err(e) => {
                         log::info!("skipped object render because unable to get gm list: {e}");
@@ -171,6 +1028,11 @@ impl ThreedRenderer {
                 gms.iter_mut()
                     .for_each(|gm| gm_update_transform(gm, &transform));
                 self.object_gm_cache.insert(o.id(), gms);
+                self.triangle_counts
+                    .insert(o.id(), entity_triangles(&o, &self.reloaded_models));
+                if let Some(version) = version {
+                    self.streamed_versions.insert(o.id(), version);
+                }
             };
 
             if let Some(gms) = self.object_gm_cache.get_mut(&o.id()) {
@@ -179,45 +1041,385 @@ impl ThreedRenderer {
             };
         });
 
-        let objs_gms: Vec<&Vec<_>> = self
-            .objects
-            .clone()
-            .into_iter()
-            .filter_map(|o| {
-                let gms = match self.object_gm_cache.get(&o.id()) {
-                    Some(g) => g,
-                    None => return None,
-                };
+        stats.pending_gms = budget.pending;
+        // boxes the size of the models' bounds, a unit cube when there aren't any
+        let placeholders: Vec<(EntityContainer, three_d::Mat4)> = if self.settings.placeholders {
+            pending
+                .into_iter()
+                .map(|(o, transform)| {
+                    let (min, max) = o
+                        .read_with(|o| o.model().as_ref().and_then(Model::bounds))
+                        .unwrap_or((Vec3::splat(-0.5), Vec3::splat(0.5)));
+                    let matrix = transform.transform_matrix()
+                        * Mat4::from_translation((min + max) / 2.0)
+                        * Mat4::from_scale((max - min).max(Vec3::splat(0.01)));
+                    (o, matrix.into_cgmath())
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        if !placeholders.is_empty() && self.placeholder_gms.is_empty() {
+            let context = self.context.as_ref().unwrap();
+            let cube = CuboidBuilder::new()
+                .color(image::Rgba([128, 128, 128, 255]))
+                .build();
+            self.placeholder_gms = model_gm_list(&cube, context, |cpu_mesh| {
+                InstancedMesh::new(
+                    context,
+                    &Instances::default(),
+                    &view_mode_mesh(view_mode, cpu_mesh),
+                )
+            });
+        }
 
-                Some(gms)
+        let instanced_keys: HashSet<InstanceKey> = instances.iter().map(|(key, ..)| *key).collect();
+        // groups nobody uses anymore would stay on the gpu otherwise
+        self.instance_groups
+            .retain(|key, _| instanced_keys.contains(key));
+
+        // the lines are copied out under the lock and only turned into a mesh once they change
+        let built = self.physics_debug.as_ref().map(|(revision, _)| *revision);
+        let debug_lines = self
+            .engine_context
+            .with(|debug: &PhysicsDebugLines| {
+                debug.enabled.then(|| {
+                    (built != Some(debug.revision)).then(|| (debug.revision, debug.lines.clone()))
+                })
             })
-            .collect();
+            .flatten();
+        match debug_lines {
+            Some(Some((revision, lines))) => {
+                self.physics_debug = Some((revision, debug_lines_gm(&lines, context)))
+            }
+            Some(None) => {}
+            None => self.physics_debug = None,
+        }
+        let physics_debug = self.physics_debug.as_ref().and_then(|(_, gm)| gm.as_ref());
+        let gizmos = debug_lines_gm(&gizmo_lines, context);
+        let axes = self.axes.as_ref().filter(|_| self.show_axes);
 
-        frame_input
-            .screen()
-            .clear(ClearState::color_and_depth(0.5, 0.8, 0.8, 1.0, 1.0))
-            .write(|| {
-                objs_gms.iter().for_each(|gms| {
-                    gms.iter().for_each(|gm| {
-                        gm.render(&self.camera.as_ref().unwrap(), &[&self.lights[0]])
-                    })
-                });
+        // the debug view modes draw the cached meshes with stand-in materials, only wireframes
+        // need meshes of their own
+        let normal_material = NormalMaterial::default();
+        let wireframe_material = WireframeMaterial::default();
+        let screen = frame_input.screen();
+        let fog = (environment.fog != Fog::Off).then_some(FogEffect {
+            fog: environment.fog,
+        });
+        // split viewports don't have to cover the whole screen
+        if !self.viewports.is_empty() {
+            screen.clear(clear_state(ClearMode::Environment, environment.clear_color));
+        }
+        let mut old_targets = std::mem::take(&mut self.aa_targets).into_iter();
+        let mut old_msaa_targets = std::mem::take(&mut self.msaa_targets).into_iter();
+        // removing the last sun shouldn't leave the scene in the dark
+        let directional: Vec<&three_d::DirectionalLight> = if self.directional_lights.is_empty() {
+            self.fallback_light.iter().collect()
+        } else {
+            self.directional_lights.values().collect()
+        };
+        let triangles = |o: &EntityContainer| self.triangle_counts.get(&o.id()).copied();
+        stats.entities = self.objects.len();
+        // hidden entities keep their cached gms, they're just not drawn
+        let hidden = self.objects.hidden();
 
-                axes.render(&self.camera.as_ref().unwrap(), &[&self.lights[0]]);
-                Ok::<(), std::io::Error>(())
-            })
-            .unwrap();
+        // every view draws the same cached gms, only the instances are set again for each
+        for view in &views {
+            let visible = |o: &EntityContainer| {
+                o.read_with(|o| {
+                    o.components()
+                        .get::<Layer>()
+                        .copied()
+                        .unwrap_or_default()
+                        .visible_to(&view.layer_mask)
+                }) && !hidden.contains(&o.id())
+            };
+
+            let camera_rotation =
+                Quat::from_mat4(&three_d::Viewer::view(&view.camera).into_glam().inverse());
+            for (id, transform, billboard) in &billboards {
+                let transform = billboard.facing(transform, view.position, camera_rotation);
+                let gms = match self.lod_levels.get(id).copied().unwrap_or(0) {
+                    0 => self.object_gm_cache.get_mut(id),
+                    level => self
+                        .lod_gm_cache
+                        .get_mut(&(*id, level))
+                        .map(|lod| &mut lod.gms),
+                };
+                gms.into_iter()
+                    .flatten()
+                    .for_each(|gm| gm_update_transform(gm, &transform));
+            }
 
+            let mut instance_transformations: HashMap<InstanceKey, Vec<three_d::Mat4>> =
+                HashMap::new();
+            for (key, o, transformation) in &instances {
+                if visible(o) {
+                    instance_transformations
+                        .entry(*key)
+                        .or_default()
+                        .push(*transformation);
+                    stats.drawn += 1;
+                    stats.triangles += triangles(o).unwrap_or(0);
+                    stats.count_lod_level(0);
+                }
+            }
+            for (key, gms) in self.instance_groups.iter_mut() {
+                if let Some(transformations) = instance_transformations.get(key) {
+                    let instances = Instances {
+                        transformations: transformations.clone(),
+                        ..Default::default()
+                    };
+                    gms.iter_mut().for_each(|gm| gm.set_instances(&instances));
+                }
+            }
+            let placeholder_transformations: Vec<three_d::Mat4> = placeholders
+                .iter()
+                .filter(|(o, _)| visible(o))
+                .map(|(_, transformation)| *transformation)
+                .collect();
+            let placeholder_gms = if placeholder_transformations.is_empty() {
+                &[][..]
+            } else {
+                let instances = Instances {
+                    transformations: placeholder_transformations,
+                    ..Default::default()
+                };
+                self.placeholder_gms
+                    .iter_mut()
+                    .for_each(|gm| gm.set_instances(&instances));
+                &self.placeholder_gms[..]
+            };
+            let instanced_gms = self
+                .instance_groups
+                .iter()
+                .filter(|(key, _)| instance_transformations.contains_key(key))
+                .flat_map(|(_, gms)| gms.iter())
+                .chain(placeholder_gms);
+
+            let mut objs_gms: Vec<&Vec<_>> = Vec::new();
+            for o in self.objects.clone() {
+                if !visible(&o) {
+                    stats.culled += 1;
+                    continue;
+                }
+                let level = self.lod_levels.get(&o.id()).copied().unwrap_or(0);
+                let drawn = match level {
+                    0 => self
+                        .object_gm_cache
+                        .get(&o.id())
+                        .map(|gms| (gms, triangles(&o).unwrap_or(0))),
+                    level => self
+                        .lod_gm_cache
+                        .get(&(o.id(), level))
+                        .map(|lod| (&lod.gms, lod.triangles)),
+                };
+                if let Some((gms, triangle_count)) = drawn {
+                    objs_gms.push(gms);
+                    stats.drawn += 1;
+                    stats.triangles += triangle_count;
+                    stats.count_lod_level(level);
+                }
+            }
+
+            let lights = frame_lights(
+                self.ambient.as_ref(),
+                &directional,
+                &self.point_lights,
+                &self.spot_lights,
+                view.position,
+            );
+            let model_gms = objs_gms.iter().flat_map(|gms| gms.iter());
+            let stand_ins: Vec<Box<dyn Object + '_>> =
+                model_gms
+                    .clone()
+                    .filter_map(|gm| gm.stand_in(view_mode, &normal_material, &wireframe_material))
+                    .chain(instanced_gms.clone().filter_map(|gm| {
+                        gm.stand_in(view_mode, &normal_material, &wireframe_material)
+                    }))
+                    .collect();
+            let models: Vec<&dyn Object> = match view_mode {
+                ViewMode::Shaded => model_gms
+                    .map(|gm| gm.as_object())
+                    .chain(instanced_gms.map(|gm| gm.as_object()))
+                    .collect(),
+                ViewMode::Wireframe | ViewMode::Normals | ViewMode::Unlit => {
+                    stand_ins.iter().map(|object| object.as_ref()).collect()
+                }
+            };
+            let scene: Vec<&dyn Object> = models
+                .into_iter()
+                .chain(axes.map(|axes| axes as &dyn Object))
+                .chain(physics_debug.map(|gm| gm as &dyn Object))
+                .chain(gizmos.iter().map(|gm| gm as &dyn Object))
+                .collect();
+
+            let camera = &view.camera;
+            let viewport = camera.viewport();
+            let scissor_box = ScissorBox {
+                x: viewport.x,
+                y: viewport.y,
+                width: viewport.width,
+                height: viewport.height,
+            };
+            let (width, height) = (viewport.width, viewport.height);
+            // offscreen targets are the size of the view, so the scene is rendered into them
+            // from the origin and only placed in the screen by the resolve
+            let mut target_camera = camera.clone();
+            target_camera.set_viewport(three_d::Viewport::new_at_origo(width, height));
+            let clear_state = clear_state(view.clear, environment.clear_color);
+            // the offscreen targets are copied over the whole view, which would paint over
+            // the views under an overlay, so overlays skip aa and fog
+            let direct =
+                view.clear.keeps_color() || (self.anti_aliasing == AaMode::Off && fog.is_none());
+
+            match self.anti_aliasing {
+                _ if direct => {
+                    screen
+                        .clear_partially(scissor_box, clear_state)
+                        .render(camera, &scene, &lights);
+                }
+                AaMode::Msaa(samples) => {
+                    let (key, target) =
+                        reuse_target(old_msaa_targets.next(), (width, height, samples), || {
+                            RenderTargetMultisample::new(context, width, height, samples as u32)
+                        });
+                    target
+                        .clear(clear_state)
+                        .render(&target_camera, &scene, &lights);
+                    screen
+                        .clear_partially(scissor_box, ClearState::depth(1.0))
+                        .apply_screen_effect(
+                            &CopyEffect::default(),
+                            camera,
+                            &[],
+                            Some(ColorTexture::Single(&target.resolve_color())),
+                            None,
+                        );
+                    if let Some(fog) = &fog {
+                        screen.apply_screen_effect(
+                            fog,
+                            camera,
+                            &[],
+                            None,
+                            Some(DepthTexture::Single(&target.resolve_depth())),
+                        );
+                    }
+                    self.msaa_targets.push((key, target));
+                }
+                // fog reads the scene's depth, so without aa it goes through the offscreen
+                // target too
+                AaMode::Off | AaMode::Fxaa => {
+                    let (key, mut targets) =
+                        reuse_target(old_targets.next(), (width, height), || {
+                            AaTargets::new(context, width, height)
+                        });
+                    // the scene goes into an offscreen target first, fxaa then resolves it to
+                    // the screen. any upscaling of a lower render resolution has to happen after
+                    // this
+                    {
+                        let AaTargets { color, depth } = &mut targets;
+                        RenderTarget::new(color.as_color_target(None), depth.as_depth_target())
+                            .clear(clear_state)
+                            .render(&target_camera, &scene, &lights);
+                    }
+                    let color = Some(ColorTexture::Single(&targets.color));
+                    let cleared = screen.clear_partially(scissor_box, ClearState::depth(1.0));
+                    match self.anti_aliasing {
+                        AaMode::Fxaa => {
+                            cleared.apply_screen_effect(&FxaaEffect {}, camera, &[], color, None)
+                        }
+                        _ => cleared.apply_screen_effect(
+                            &CopyEffect::default(),
+                            camera,
+                            &[],
+                            color,
+                            None,
+                        ),
+                    };
+                    if let Some(fog) = &fog {
+                        screen.apply_screen_effect(
+                            fog,
+                            camera,
+                            &[],
+                            None,
+                            Some(DepthTexture::Single(&targets.depth)),
+                        );
+                    }
+                    self.aa_targets.push((key, targets));
+                }
+            }
+        }
+
+        if let Some(gui) = self.gui.as_mut().filter(|_| draw_ui) {
+            if let Err(e) = screen.write(|| gui.render()) {
+                log::error!("ui render failed: {e:?}");
+            }
+        }
+
+        if !self.screenshots.is_empty() {
+            // the screen holds the finished frame here, overlays and aa resolve included
+            let pixels = screen.read_color::<[u8; 4]>();
+            let screenshot = Screenshot::new(screen.width(), screen.height(), &pixels);
+            for target in self.screenshots.drain(..) {
+                target.deliver(screenshot.clone());
+            }
+        }
+
+        stats.cpu_time = frame_start.elapsed();
+        let swap_start = Instant::now();
         context.swap_buffers().unwrap();
+        stats.swap_time = swap_start.elapsed();
+
+        // despawned entities' meshes would stay on the gpu otherwise
+        let objects = &self.objects;
+        self.object_gm_cache
+            .retain(|id, _| objects.get(id).is_some());
+        self.streamed_versions
+            .retain(|id, _| objects.get(id).is_some());
+        self.instance_keys.retain(|id, _| objects.get(id).is_some());
+        self.triangle_counts
+            .retain(|id, _| objects.get(id).is_some());
+        self.lod_levels.retain(|id, _| objects.get(id).is_some());
+        self.lod_gm_cache
+            .retain(|(id, _), _| objects.get(id).is_some());
+
+        stats.cached_gms = self.object_gm_cache.values().map(Vec::len).sum::<usize>()
+            + self.instance_groups.values().map(Vec::len).sum::<usize>()
+            + self
+                .lod_gm_cache
+                .values()
+                .map(|l| l.gms.len())
+                .sum::<usize>();
+        self.engine_context
+            .with_mut(|render_stats: &mut RenderStats| render_stats.push(stats));
 
         Ok(())
     }
 }
 
 impl Renderer for ThreedRenderer {
+    fn init(&mut self, window: &Window, camera_id: &Uuid) -> anyhow::Result<()> {
+        camera_from_entity(&self.objects, camera_id)?;
+        // everything from the last window has to go before its context does
+        self.release_gpu_resources();
+
+        let context = WindowedContext::from_winit_window(window, self.settings.surface_settings())
+            .map_err(|e| anyhow!("creating the render context failed: {e:?}"))?;
+
+        self.axes = Some(Axes::new(&context, 0.5, 10.0));
+        self.gui = Some(GUI::new(&context));
+        self.frame_input_generator = Some(FrameInputGenerator::from_winit_window(window));
+        self.fallback_light = Some(fallback_light(&context));
+        self.context = Some(context);
+        self.camera_id = Some(*camera_id);
+
+        Ok(())
+    }
+
     /// prepares models for rendering and starts render loop
     fn render(&mut self, window: Arc<Window>) -> anyhow::Result<()> {
-        let mut frame_input_generator = FrameInputGenerator::from_winit_window(window.as_ref());
         // self.init(window);
         let context = self
             .context
@@ -226,14 +1428,19 @@ impl Renderer for ThreedRenderer {
 
         context.make_current().unwrap();
 
-        self.render_internal(&mut frame_input_generator.generate(context))?;
-        window.request_redraw();
+        let mut frame_input = self
+            .frame_input_generator
+            .get_or_insert_with(|| FrameInputGenerator::from_winit_window(window.as_ref()))
+            .generate(context);
+        self.update_viewport_mapper(&frame_input);
+        self.render_internal(&mut frame_input)?;
         Ok(())
     }
 
     fn handle_resize(&mut self, window: Arc<Window>, event: &WindowEvent) -> anyhow::Result<()> {
         match event {
             WindowEvent::Resized(physical_size) => {
+                self.resize_camera_entity(physical_size.width, physical_size.height);
                 self.context
                     .as_ref()
                     .ok_or(anyhow::anyhow!("no render context"))?
@@ -251,10 +1458,15 @@ impl Renderer for ThreedRenderer {
         event: &WindowEvent,
     ) -> anyhow::Result<()> {
         match event {
-            winit::event::WindowEvent::ScaleFactorChanged {
-                inner_size_writer, ..
-            } => {
-                todo!()
+            winit::event::WindowEvent::ScaleFactorChanged { .. } => {
+                // the physical size changes along with the scale and a Resized doesn't always
+                // follow, frame inputs pick up the new device pixel ratio on their own
+                let size = window.inner_size();
+                self.resize_camera_entity(size.width, size.height);
+                self.context
+                    .as_ref()
+                    .ok_or(anyhow::anyhow!("no render context"))?
+                    .resize(size);
             }
 
             _ => return Err(anyhow::anyhow!("not the correct event")),
@@ -265,23 +1477,68 @@ impl Renderer for ThreedRenderer {
 
     fn handle_close(&mut self, window: Arc<Window>, event: &WindowEvent) -> anyhow::Result<()> {
         match event {
-            WindowEvent::CloseRequested => {
-                self.context
-                    .as_ref()
-                    .ok_or(anyhow::anyhow!("no render context"))?
-                    .make_current()
-                    .unwrap();
-            }
+            WindowEvent::CloseRequested => self.release_gpu_resources(),
             _ => return Err(anyhow::anyhow!("not the correct event")),
         }
 
         Ok(())
     }
 
+    fn suspend(&mut self) {
+        self.release_gpu_resources();
+    }
+
     fn set_objects(&mut self, objects: EntityRegistry) {
         self.objects = objects;
     }
 
+    fn handle_command(&mut self, command: RendererCommand) -> anyhow::Result<()> {
+        match command {
+            RendererCommand::ShowDiagnostics(show) => self.show_diagnostics(show),
+            RendererCommand::SetAntiAliasing(mode) => self.set_anti_aliasing(mode),
+            RendererCommand::SetPhysicsDebug(show) => self.show_physics_debug(show),
+            RendererCommand::SetClearColor(color) => self.set_clear_color(color),
+            RendererCommand::SetAmbientLight { color, intensity } => {
+                self.set_ambient_light(color, intensity)
+            }
+            RendererCommand::SetFog(fog) => self.set_fog(fog),
+            RendererCommand::SetActiveCamera(id) => self.set_active_camera(id),
+            RendererCommand::SetViewports(viewports) => self.set_viewports(viewports),
+            RendererCommand::SetDebugAxes(show) => self.show_debug_axes(show),
+            RendererCommand::Gizmo(gizmo) => self.draw_gizmo(gizmo),
+            RendererCommand::SetViewMode(mode) => self.set_view_mode(mode),
+            RendererCommand::CaptureScreenshot { path } => {
+                self.capture_screenshot(ScreenshotTarget::File(path))
+            }
+            RendererCommand::CaptureScreenshotBytes(sender) => {
+                self.capture_screenshot(ScreenshotTarget::Bytes(sender))
+            }
+            RendererCommand::AssetReloaded(reloaded) => self.asset_reloaded(reloaded),
+            RendererCommand::Render(_)
+            | RendererCommand::HandleResize(_)
+            | RendererCommand::HandleScaleChange(_)
+            | RendererCommand::HandleClose(_) => {
+                return Err(anyhow::anyhow!("{command:?} needs its window"));
+            }
+        }
+        Ok(())
+    }
+
+    /// passes input on to the ui
+    fn handle_window_event(&mut self, event: &WindowEvent) {
+        if let Some(generator) = self.frame_input_generator.as_mut() {
+            generator.handle_winit_window_event(event);
+        }
+    }
+
+    fn set_settings(&mut self, settings: RendererSettings) {
+        self.settings = settings.validated();
+    }
+
+    fn active_camera(&self) -> Option<Uuid> {
+        self.camera_id
+    }
+
     fn get_messages(&self) -> &VecDeque<Message> {
         &self.messages
     }
@@ -295,27 +1552,454 @@ impl Renderer for ThreedRenderer {
     }
 }
 
-fn gm_update_transform(gm: &mut Gm<Mesh, ColorMaterial>, transform: &Transform3D) {
-    let transform_mat = Mat4::from_translation(transform.position)
-        * Mat4::from_quat(transform.rotation)
-        * Mat4::from_scale(transform.scale);
-    gm.set_transformation(transform_mat.into_cgmath());
+/// perspective camera with the camera entity's current parameters, the viewport is set every
+/// frame
+pub(super) fn camera_from_entity(objects: &EntityRegistry, id: &Uuid) -> anyhow::Result<Camera> {
+    let camera_container = objects
+        .get(id)
+        .ok_or(anyhow::anyhow!("camera not found from provided id"))?;
+
+    camera_container
+        .with_downcast(|camera_entity: &DefaultCamera| {
+            let (pos, forward, up) = camera_entity.view_vectors();
+
+            Camera::new_perspective(
+                three_d::Viewport::new_at_origo(1, 1),
+                pos.into_cgmath(),
+                (pos + forward).into_cgmath(),
+                up.into_cgmath(),
+                radians(camera_entity.fov),
+                camera_entity.near,
+                camera_entity.far,
+            )
+        })
+        .ok_or(anyhow::anyhow!("provided entity is not a camera"))
+}
+
+/// dim sun used while no entity has a `DirectionalLight`
+pub(super) fn fallback_light(context: &Context) -> three_d::DirectionalLight {
+    three_d::DirectionalLight::new(
+        context,
+        FALLBACK_LIGHT_INTENSITY,
+        Srgba::WHITE,
+        FALLBACK_LIGHT_DIRECTION.into_cgmath(),
+    )
+}
+
+/// `camera_from_entity` rendering into `viewport`
+fn frame_view(
+    objects: &EntityRegistry,
+    id: &Uuid,
+    viewport: three_d::Viewport,
+    clear: ClearMode,
+) -> anyhow::Result<FrameView> {
+    let mut camera = camera_from_entity(objects, id)?;
+    // the aspect ratio comes from the viewport, so this has to follow it
+    camera.set_viewport(viewport);
+    let layer_mask = objects
+        .get(id)
+        .and_then(|c| c.read_with(|c| c.components().get::<LayerMask>().copied()))
+        .unwrap_or_default();
+    Ok(FrameView {
+        position: camera.position().into_glam(),
+        camera,
+        layer_mask,
+        clear,
+    })
+}
+
+/// what a view clears to, `clear_color` is the environment's
+fn clear_state(clear: ClearMode, clear_color: glam::Vec4) -> ClearState {
+    match (clear, clear.color(clear_color)) {
+        (_, Some(color)) => {
+            let [r, g, b, a] = color.to_array();
+            ClearState::color_and_depth(r, g, b, a, 1.0)
+        }
+        (ClearMode::DepthOnly, None) => ClearState::depth(1.0),
+        (_, None) => ClearState::none(),
+    }
+}
+
+/// the part of `frame` a split screen viewport covers, three_d viewports start at the bottom
+/// left
+fn split_viewport(frame: three_d::Viewport, config: &ViewportConfig) -> three_d::Viewport {
+    let rect = config.rect_in(frame.width, frame.height);
+    three_d::Viewport {
+        x: frame.x + rect.x as i32,
+        y: frame.y + (frame.height as f32 - rect.y - rect.height) as i32,
+        width: rect.width as u32,
+        height: rect.height as u32,
+    }
+}
+
+/// ambient and directional lights first, then the point and spot lights closest to the camera
+/// up to `MAX_LIGHTS`
+fn frame_lights<'a>(
+    ambient: Option<&'a AmbientLight>,
+    directional: &[&'a three_d::DirectionalLight],
+    points: &'a HashMap<Uuid, three_d::PointLight>,
+    spots: &'a HashMap<Uuid, three_d::SpotLight>,
+    camera_pos: Vec3,
+) -> Vec<&'a dyn Light> {
+    let mut local: Vec<(f32, &dyn Light)> = points
+        .values()
+        .map(|l| (l.position.into_glam().distance(camera_pos), l as &dyn Light))
+        .chain(
+            spots
+                .values()
+                .map(|l| (l.position.into_glam().distance(camera_pos), l as &dyn Light)),
+        )
+        .collect();
+    let room = MAX_LIGHTS.saturating_sub(directional.len() + ambient.iter().len());
+    if local.len() > room {
+        log::debug!(
+            "{} lights in view, only the closest {room} are drawn",
+            local.len()
+        );
+        local.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        local.truncate(room);
+    }
+
+    ambient
+        .map(|l| l as &dyn Light)
+        .into_iter()
+        .chain(directional.iter().map(|l| *l as &dyn Light))
+        .chain(local.into_iter().map(|(_, l)| l))
+        .collect()
+}
+
+/// gm for a piece of a model, lit ones are shaded by the scene's lights and unlit ones just
+/// show their albedo
+pub(super) enum ModelGm<G = Mesh> {
+    Lit(Gm<G, PhysicalMaterial>),
+    Unlit(Gm<G, ColorMaterial>),
+}
+
+impl ModelGm {
+    fn set_transformation(&mut self, transformation: three_d::Mat4) {
+        match self {
+            ModelGm::Lit(gm) => gm.set_transformation(transformation),
+            ModelGm::Unlit(gm) => gm.set_transformation(transformation),
+        }
+    }
+}
+
+impl ModelGm<InstancedMesh> {
+    fn set_instances(&mut self, instances: &Instances) {
+        match self {
+            ModelGm::Lit(gm) => gm.set_instances(instances),
+            ModelGm::Unlit(gm) => gm.set_instances(instances),
+        }
+    }
+}
+
+impl<G: Geometry> ModelGm<G> {
+    pub(super) fn as_object(&self) -> &dyn Object {
+        match self {
+            ModelGm::Lit(gm) => gm,
+            ModelGm::Unlit(gm) => gm,
+        }
+    }
+
+    /// the same geometry with the material `view_mode` asks for, `None` when the gm's own
+    /// material is the one to draw with
+    fn stand_in<'a>(
+        &'a self,
+        view_mode: ViewMode,
+        normal_material: &'a NormalMaterial,
+        wireframe_material: &'a WireframeMaterial,
+    ) -> Option<Box<dyn Object + 'a>> {
+        let geometry = match self {
+            ModelGm::Lit(gm) => &gm.geometry,
+            ModelGm::Unlit(gm) => &gm.geometry,
+        };
+        match view_mode {
+            ViewMode::Shaded => None,
+            ViewMode::Wireframe => Some(Box::new(Gm::new(geometry, wireframe_material))),
+            ViewMode::Normals => Some(Box::new(Gm::new(geometry, normal_material))),
+            ViewMode::Unlit => Some(match self {
+                ModelGm::Lit(gm) => Box::new(Gm::new(
+                    geometry,
+                    ColorMaterial::from_physical_material(&gm.material),
+                )),
+                ModelGm::Unlit(gm) => Box::new(Gm::new(geometry, gm.material.clone())),
+            }),
+        }
+    }
+}
+
+/// draws `TextLabel`s and `ScreenSprite`s under any ui windows, textures nothing uses anymore
+/// are dropped
+fn draw_screen_space(
+    ctx: &egui::Context,
+    engine_context: &EngineContext,
+    elements: &[(Option<TextLabel>, Option<ScreenSprite>)],
+    textures: &mut HashMap<PathBuf, Option<egui::TextureHandle>>,
+) {
+    let painter = ctx.layer_painter(egui::LayerId::background());
+    let screen = ctx.screen_rect();
+    let viewport = glam::Vec2::new(screen.width(), screen.height());
+    let to_pos = |p: glam::Vec2| screen.min + egui::vec2(p.x, p.y);
+    let mut used = HashSet::new();
+
+    for (label, sprite) in elements {
+        if let Some(sprite) = sprite {
+            used.insert(sprite.texture.clone());
+            let texture = textures
+                .entry(sprite.texture.clone())
+                .or_insert_with(|| load_screen_texture(ctx, engine_context, &sprite.texture));
+            if let Some(texture) = texture {
+                let min = sprite.anchor.place(viewport, sprite.size, sprite.offset);
+                painter.image(
+                    texture.id(),
+                    egui::Rect::from_min_size(
+                        to_pos(min),
+                        egui::vec2(sprite.size.x, sprite.size.y),
+                    ),
+                    egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                    egui::Color32::WHITE,
+                );
+            }
+        }
+        if let Some(label) = label {
+            let [r, g, b, a] = label
+                .color
+                .to_array()
+                .map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8);
+            painter.text(
+                to_pos(label.anchor.point(viewport, label.offset)),
+                anchor_align(label.anchor),
+                &label.text,
+                egui::FontId::proportional(label.font_size),
+                egui::Color32::from_rgba_unmultiplied(r, g, b, a),
+            );
+        }
+    }
+
+    textures.retain(|path, _| used.contains(path));
+}
+
+/// the diagnostics overlay's text on a dark box in the top left corner, above any ui windows
+fn draw_diagnostics(ctx: &egui::Context, text: &str) {
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("diagnostics"),
+    ));
+    let galley = painter.layout_no_wrap(
+        text.to_string(),
+        egui::FontId::monospace(13.0),
+        egui::Color32::WHITE,
+    );
+    let text_pos = ctx.screen_rect().min + egui::vec2(12.0, 12.0);
+    let background = egui::Rect::from_min_size(text_pos, galley.size()).expand(6.0);
+    painter.rect_filled(background, 4.0, egui::Color32::from_black_alpha(180));
+    painter.galley(text_pos, galley, egui::Color32::WHITE);
+}
+
+fn anchor_align(anchor: Anchor) -> egui::Align2 {
+    match anchor {
+        Anchor::TopLeft => egui::Align2::LEFT_TOP,
+        Anchor::Top => egui::Align2::CENTER_TOP,
+        Anchor::TopRight => egui::Align2::RIGHT_TOP,
+        Anchor::Left => egui::Align2::LEFT_CENTER,
+        Anchor::Center => egui::Align2::CENTER_CENTER,
+        Anchor::Right => egui::Align2::RIGHT_CENTER,
+        Anchor::BottomLeft => egui::Align2::LEFT_BOTTOM,
+        Anchor::Bottom => egui::Align2::CENTER_BOTTOM,
+        Anchor::BottomRight => egui::Align2::RIGHT_BOTTOM,
+    }
+}
+
+/// the texture from the context's `AssetManager`, so it's decoded once for everything using it.
+/// `None` when it couldn't be loaded, the asset manager logs why
+fn load_screen_texture(
+    ctx: &egui::Context,
+    engine_context: &EngineContext,
+    path: &Path,
+) -> Option<egui::TextureHandle> {
+    let texture = engine_context
+        .with(|assets: &AssetManager| assets.get_texture(path))
+        .flatten()?;
+    let size = [texture.width as usize, texture.height as usize];
+    let image = match texture.image_format {
+        ImageFormat::R8G8B8 => egui::ColorImage::from_rgb(size, &texture.data),
+        ImageFormat::R8G8B8A8 => egui::ColorImage::from_rgba_unmultiplied(size, &texture.data),
+    };
+    Some(ctx.load_texture(path.to_string_lossy(), image, egui::TextureOptions::LINEAR))
+}
+
+pub(super) fn gm_update_transform(gm: &mut ModelGm, transform: &Transform3D) {
+    gm.set_transformation(transform.transform_matrix().into_cgmath());
+}
+
+/// physical material from the model's material, plain white when the primitive has none.
+/// primitives without normals can't be shaded and always come out unlit
+fn model_gm<G>(
+    geometry: G,
+    prim: &MeshPrimitive,
+    material: Option<&Material>,
+    context: &Context,
+) -> ModelGm<G> {
+    let lit = !prim.normals.is_empty();
+    let Some(material) = material else {
+        let cpu_material = CpuMaterial {
+            albedo: Srgba::WHITE,
+            metallic: 0.0,
+            roughness: 1.0,
+            ..Default::default()
+        };
+        return match lit {
+            true => ModelGm::Lit(Gm::new(
+                geometry,
+                PhysicalMaterial::new(context, &cpu_material),
+            )),
+            false => ModelGm::Unlit(Gm::new(
+                geometry,
+                ColorMaterial::new(context, &cpu_material),
+            )),
+        };
+    };
+
+    let cpu_material = CpuMaterial {
+        albedo: srgba(material.base_color_factor),
+        albedo_texture: Some(cpu_texture(&material.albedo, "albedo_texture")),
+        // the normal texture is meaningless without tangents to orient it
+        normal_texture: material
+            .normals
+            .as_ref()
+            .filter(|_| !prim.tangents.is_empty())
+            .map(|t| cpu_texture(t, "normal_texture")),
+        normal_scale: material.normal_scale,
+        metallic: material.metallic_factor,
+        roughness: material.roughness_factor,
+        metallic_roughness_texture: material
+            .metallic_roughness
+            .as_ref()
+            .map(|t| cpu_texture(t, "metallic_roughness_texture")),
+        occlusion_strength: material.occlusion_strength,
+        occlusion_texture: material
+            .occlusion
+            .as_ref()
+            .map(|t| cpu_texture(t, "occlusion_texture")),
+        emissive: srgba(material.emissive_factor.extend(1.0)),
+        emissive_texture: material
+            .emissive
+            .as_ref()
+            .map(|t| cpu_texture(t, "emissive_texture")),
+        ..Default::default()
+    };
+
+    if material.unlit || !lit {
+        ModelGm::Unlit(Gm::new(
+            geometry,
+            ColorMaterial::new(context, &cpu_material),
+        ))
+    } else {
+        ModelGm::Lit(Gm::new(
+            geometry,
+            PhysicalMaterial::new(context, &cpu_material),
+        ))
+    }
+}
+
+fn cpu_texture(texture: &Texture, name: &str) -> CpuTexture {
+    let data = match texture.image_format {
+        ImageFormat::R8G8B8 => {
+            TextureData::RgbU8(texture.data.chunks(3).map(|c| [c[0], c[1], c[2]]).collect())
+        }
+        ImageFormat::R8G8B8A8 => TextureData::RgbaU8(
+            texture
+                .data
+                .chunks(4)
+                .map(|c| [c[0], c[1], c[2], c[3]])
+                .collect(),
+        ),
+    };
+    CpuTexture {
+        name: name.into(),
+        data,
+        width: texture.width,
+        height: texture.height,
+        min_filter: Interpolation::Linear,
+        mag_filter: Interpolation::Linear,
+        mipmap: None,
+        wrap_s: Wrapping::Repeat,
+        wrap_t: Wrapping::Repeat,
+    }
+}
+
+/// rgba factor, 0 to 1
+fn srgba(color: glam::Vec4) -> Srgba {
+    let [r, g, b, a] = color.to_array().map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8);
+    Srgba::new(r, g, b, a)
+}
+
+/// triangles in the model the entity is drawn with
+fn entity_triangles(object: &EntityContainer, reloaded: &ReloadedModels) -> usize {
+    object.read_with(|o| {
+        current_model(o, reloaded)
+            .as_ref()
+            .map(Model::triangle_count)
+            .unwrap_or(0)
+    })
+}
+
+/// the model the entity is drawn with, streamed and async loaded models and sprites take the
+/// place of its own
+fn entity_model(o: &dyn Entity) -> Option<Model> {
+    let components = o.components();
+    if let Some(streamed) = components.get::<StreamingModel>() {
+        return Some(streamed.current());
+    }
+    if let Some(sprite) = components.get::<Sprite>() {
+        return Some(sprite.model());
+    }
+    match components.get::<AssetHandle>() {
+        Some(handle) => handle.model(),
+        None => o.model().clone(),
+    }
+}
+
+/// the entity's model, with the data of its asset swapped in if that was reloaded since
+fn current_model(o: &dyn Entity, reloaded: &ReloadedModels) -> Option<Model> {
+    let model = entity_model(o)?;
+    Some(reloaded.model(model.asset_id).unwrap_or(model))
+}
+
+/// `object_get_gm_list` with the reloaded assets in place of the entity's copies
+fn current_gm_list<G>(
+    object: &EntityContainer,
+    reloaded: &ReloadedModels,
+    context: &Context,
+    geometry: impl Fn(&CpuMesh) -> G,
+) -> anyhow::Result<Vec<ModelGm<G>>> {
+    let model = object
+        .read_with(|o| current_model(o, reloaded))
+        .ok_or(anyhow::anyhow!("no model in entity"))?;
+    Ok(model_gm_list(&model, context, geometry))
 }
 
 /// takes a reference to an object and gets a list of GM geometry and material instances
-fn object_get_gm_list(
+pub(super) fn object_get_gm_list<G>(
     object: EntityContainer,
-    context: &WindowedContext,
-) -> anyhow::Result<Vec<Gm<Mesh, ColorMaterial>>> {
+    context: &Context,
+    geometry: impl Fn(&CpuMesh) -> G,
+) -> anyhow::Result<Vec<ModelGm<G>>> {
     let _span = tracy_client::span!("getting geometry and material from entity");
     let obj = object.clone();
     let model = obj
-        .lock()
-        .expect("mutex lock failed")
-        .model()
-        .clone()
+        .read_with(entity_model)
         .ok_or(anyhow::anyhow!("no model in entity"))?;
 
+    Ok(model_gm_list(&model, context, geometry))
+}
+
+/// gms for every primitive of `model`
+fn model_gm_list<G>(
+    model: &Model,
+    context: &Context,
+    geometry: impl Fn(&CpuMesh) -> G,
+) -> Vec<ModelGm<G>> {
     let node_list = model.get_nodes_flattened();
     let gms = node_list
         .iter()
@@ -325,54 +2009,11 @@ fn object_get_gm_list(
                 .map(|mesh| {
                     mesh.primitives
                         .iter()
-                        .map(|prim| {
-                            let geometry = mesh_prim_to_geometry(prim, context)
-                                .ok_or(anyhow::anyhow!("unable to create geometry from primitive"))
-                                .unwrap();
-
-                            let cpu_texture = match prim.material_index {
-                                Some(index) => match model.materials.get(index) {
-                                    Some(mat) => {
-                                        let albedo_data = match mat.albedo.image_format {
-                                            crate::assets::asset_manager::ImageFormat::R8G8B8 => {
-                                                TextureData::RgbU8(mat.albedo.data.chunks(3).map(|c| [c[0], c[1], c[2]]).collect())
-                                            }
-                                            crate::assets::asset_manager::ImageFormat::R8G8B8A8 => {
-                                                TextureData::RgbaU8(mat.albedo.data.chunks(4).map(|c| [c[0], c[1], c[2], c[3]]).collect())
-
-                                            }
-                                        };
-                                        Some(CpuTexture {
-                                        name: "albedo_texture".into(),
-                                        data: albedo_data,
-                                        width: mat.albedo.width,
-                                        height: mat.albedo.height,
-                                        min_filter: three_d::Interpolation::Linear,
-                                        mag_filter: three_d::Interpolation::Linear,
-                                        mipmap: None,
-                                        wrap_s: three_d::Wrapping::Repeat,
-                                        wrap_t: three_d::Wrapping::Repeat,
-                                    })},
-                                    None => None,
-                                },
-                                None => None,
-                            };
-
-                            let material = three_d::ColorMaterial::new(
-                                context,
-                                &CpuMaterial {
-                                    albedo: Srgba {
-                                        r: 255,
-                                        g: 255,
-                                        b: 255,
-                                        a: 255,
-                                    },
-                                    albedo_texture: cpu_texture,
-                                    ..Default::default()
-                                },
-                            );
-
-                            Gm::new(geometry, material)
+                        .filter_map(|prim| {
+                            let geometry = geometry(&mesh_prim_to_cpu_mesh(prim)?);
+
+                            let material = prim.material_index.and_then(|i| model.materials.get(i));
+                            Some(model_gm(geometry, prim, material, context))
                         })
                         .collect::<Vec<_>>()
                 })
@@ -382,23 +2023,361 @@ fn object_get_gm_list(
         .flatten()
         .collect::<Vec<_>>();
 
-    Ok(gms)
+    gms
 }
 
-fn mesh_prim_to_geometry(
-    prim: &crate::assets::asset_manager::MeshPrimitive,
+/// width of the physics debug lines in world units
+const DEBUG_LINE_WIDTH: f32 = 0.05;
+
+/// turns debug line segments into one mesh, every segment is two thin quads crossed along it so
+/// it shows from any side. `None` when there's nothing to draw
+fn debug_lines_gm(
+    lines: &[DebugLine],
     context: &WindowedContext,
-) -> Option<three_d::Mesh> {
+) -> Option<Gm<Mesh, ColorMaterial>> {
+    if lines.is_empty() {
+        return None;
+    }
+
+    let mut positions = Vec::with_capacity(lines.len() * 8);
+    let mut colors = Vec::with_capacity(lines.len() * 8);
+    let mut indices = Vec::with_capacity(lines.len() * 12);
+    for line in lines {
+        let dir = (line.b - line.a).normalize_or_zero();
+        if dir == Vec3::ZERO {
+            continue;
+        }
+        let u = dir.any_orthonormal_vector() * (DEBUG_LINE_WIDTH / 2.0);
+        let v = dir.cross(u);
+        let [r, g, b, a] = line.color.map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8);
+
+        for offset in [u, v] {
+            let first = positions.len() as u32;
+            positions.extend(
+                [
+                    line.a - offset,
+                    line.a + offset,
+                    line.b + offset,
+                    line.b - offset,
+                ]
+                .map(|p| p.into_cgmath()),
+            );
+            colors.extend([Srgba::new(r, g, b, a); 4]);
+            indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+        }
+    }
+
+    let cpu_mesh = CpuMesh {
+        positions: three_d::Positions::F32(positions),
+        indices: three_d::Indices::U32(indices),
+        colors: Some(colors),
+        ..Default::default()
+    };
+    Some(Gm::new(
+        three_d::Mesh::new(context, &cpu_mesh),
+        ColorMaterial::default(),
+    ))
+}
+
+/// makes a triangle mesh from a primitive, line and point primitives are skipped for now
+fn mesh_prim_to_cpu_mesh(prim: &crate::assets::asset_manager::MeshPrimitive) -> Option<CpuMesh> {
+    if prim.topology != PrimitiveTopology::Triangles {
+        log::info!(
+            "skipped {:?} primitive, only triangles can be rendered",
+            prim.topology
+        );
+        return None;
+    }
+
     let cpu_mesh = CpuMesh {
         positions: three_d::Positions::F32(
             prim.positions.iter().map(|p| p.into_cgmath()).collect(),
         ),
         indices: three_d::Indices::U32(prim.indices.clone()),
-        normals: Some(prim.normals.iter().map(|n| n.into_cgmath()).collect()),
-        uvs: Some(prim.tex_coords.iter().map(|tc| tc.into_cgmath()).collect()),
-        tangents: None,
-        colors: None,
+        normals: (!prim.normals.is_empty())
+            .then(|| prim.normals.iter().map(|n| n.into_cgmath()).collect()),
+        uvs: (!prim.tex_coords.is_empty())
+            .then(|| prim.tex_coords.iter().map(|tc| tc.into_cgmath()).collect()),
+        tangents: (!prim.tangents.is_empty())
+            .then(|| prim.tangents.iter().map(|t| t.into_cgmath()).collect()),
+        colors: prim.colors.as_ref().map(|colors| {
+            colors
+                .iter()
+                .map(|&[r, g, b, a]| Srgba::new(r, g, b, a))
+                .collect()
+        }),
     };
 
-    Some(three_d::Mesh::new(context, &cpu_mesh))
+    Some(cpu_mesh)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Quat;
+
+    use super::*;
+    use crate::engine::entity::Camera as _;
+
+    #[test]
+    fn view_follows_the_camera_entity() {
+        let camera = DefaultCamera::new(
+            Transform3D::new(
+                Vec3::new(1.0, 2.0, 3.0),
+                Quat::from_rotation_z(0.4) * Quat::from_rotation_y(1.1),
+                Vec3::ONE,
+            ),
+            16.0,
+            9.0,
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::X,
+            1.0,
+            0.1,
+            100.0,
+        );
+        let expected = camera.view_matrix();
+        let id = camera.id();
+        let mut objects = EntityRegistry::new();
+        objects.add(camera.into_container());
+
+        let camera = camera_from_entity(&objects, &id).unwrap();
+        let view = three_d::Viewer::view(&camera).into_glam();
+        assert!(view.abs_diff_eq(expected, 1e-5), "{view} != {expected}");
+    }
+
+    #[test]
+    fn cameras_take_the_size_of_their_part_of_the_window() {
+        let camera = |width: f32, height: f32| {
+            DefaultCamera::new(
+                Transform3D::default(),
+                width,
+                height,
+                Vec3::Y,
+                Vec3::NEG_Z,
+                1.0,
+                0.1,
+                100.0,
+            )
+        };
+        let size = |objects: &EntityRegistry, id: Uuid| {
+            objects
+                .get(&id)
+                .unwrap()
+                .with_downcast(|c: &DefaultCamera| (c.width, c.height))
+                .unwrap()
+        };
+        let (first, second) = (camera(16.0, 9.0), camera(4.0, 3.0));
+        let (first_id, second_id) = (first.id(), second.id());
+        let mut objects = EntityRegistry::new();
+        objects.add(first.into_container());
+        objects.add(second.into_container());
+        let engine_context = EngineContext::new();
+        let mut renderer = ThreedRenderer::new(objects.clone(), engine_context.clone());
+        renderer.camera_id = Some(first_id);
+        engine_context.insert(ViewportMapper::new(glam::Vec2::new(800.0, 600.0), 1.0));
+
+        renderer.resize_camera_entity(1000, 500);
+        assert_eq!(size(&objects, first_id), (1000.0, 500.0));
+        assert_eq!(size(&objects, second_id), (4.0, 3.0));
+
+        // switching cameras sizes the new one for the window
+        renderer.set_active_camera(second_id);
+        assert_eq!(size(&objects, second_id), (800.0, 600.0));
+
+        renderer.set_viewports(ViewportConfig::side_by_side(&[first_id, second_id]));
+        assert_eq!(size(&objects, first_id), (400.0, 600.0));
+        assert_eq!(size(&objects, second_id), (400.0, 600.0));
+
+        // minimized windows leave the sizes alone
+        renderer.resize_camera_entity(0, 0);
+        assert_eq!(size(&objects, first_id), (400.0, 600.0));
+    }
+
+    #[test]
+    fn dirty_models_are_evicted_once() {
+        let mut objects = EntityRegistry::new();
+        let dummy = |objects: &mut EntityRegistry| {
+            let dummy = crate::engine::entity::tests::Dummy::new(
+                crate::engine::component::ComponentSet::new(),
+            );
+            let id = dummy.id();
+            objects.add(dummy.into_container());
+            id
+        };
+        let (dirty, clean) = (dummy(&mut objects), dummy(&mut objects));
+        let mut renderer = ThreedRenderer::new(objects.clone(), EngineContext::new());
+        for id in [dirty, clean] {
+            renderer.object_gm_cache.insert(id, Vec::new());
+            renderer.instance_keys.insert(id, None);
+            renderer.triangle_counts.insert(id, 12);
+            for level in [1, 2] {
+                renderer.lod_gm_cache.insert(
+                    (id, level),
+                    LodGms {
+                        gms: Vec::new(),
+                        triangles: 6,
+                    },
+                );
+            }
+        }
+        objects
+            .get(&dirty)
+            .unwrap()
+            .write_with(|o| o.components_mut().add(DirtyModel));
+
+        renderer.evict_dirty_models();
+        assert!(!renderer.object_gm_cache.contains_key(&dirty));
+        assert!(!renderer.instance_keys.contains_key(&dirty));
+        assert!(!renderer.triangle_counts.contains_key(&dirty));
+        assert!(renderer.lod_gm_cache.keys().all(|(id, _)| *id != dirty));
+        assert!(
+            objects
+                .get(&dirty)
+                .unwrap()
+                .read_with(|o| o.components().get::<DirtyModel>().is_none())
+        );
+        assert!(renderer.object_gm_cache.contains_key(&clean));
+        assert!(renderer.instance_keys.contains_key(&clean));
+        assert_eq!(renderer.triangle_counts.get(&clean), Some(&12));
+        assert_eq!(renderer.lod_gm_cache.len(), 2);
+
+        // the marker is gone, so what's built next stays
+        renderer.object_gm_cache.insert(dirty, Vec::new());
+        renderer.evict_dirty_models();
+        assert!(renderer.object_gm_cache.contains_key(&dirty));
+    }
+
+    #[test]
+    fn reloads_come_from_the_asset_cache() {
+        let engine_context = EngineContext::new();
+        let assets = engine_context.insert(AssetManager::new());
+        let lantern = assets
+            .read()
+            .unwrap()
+            .get_model(Path::new("Lantern.glb"))
+            .unwrap();
+        let asset_id = lantern.asset_id.unwrap();
+        let mut objects = EntityRegistry::new();
+        // an edited copy, the renderer should draw the cached asset once it's reloaded
+        let edited = Model {
+            materials: Vec::new(),
+            ..lantern.clone()
+        };
+        let dummy =
+            crate::engine::entity::tests::Dummy::new(crate::engine::component::ComponentSet::new())
+                .with_model(edited);
+        let id = dummy.id();
+        objects.add(dummy.into_container());
+        let mut renderer = ThreedRenderer::new(objects.clone(), engine_context);
+        renderer.object_gm_cache.insert(id, Vec::new());
+        renderer.lod_gm_cache.insert(
+            (id, 1),
+            LodGms {
+                gms: Vec::new(),
+                triangles: 6,
+            },
+        );
+        let entity = objects.get(&id).unwrap();
+        let materials = |renderer: &ThreedRenderer| {
+            entity.read_with(|o| {
+                current_model(o, &renderer.reloaded_models)
+                    .unwrap()
+                    .materials
+                    .len()
+            })
+        };
+        assert_eq!(materials(&renderer), 0);
+
+        renderer.asset_reloaded(AssetReloaded {
+            id: asset_id,
+            path: PathBuf::from("Lantern.glb"),
+        });
+        assert!(renderer.object_gm_cache.is_empty());
+        assert!(renderer.lod_gm_cache.is_empty());
+        assert_eq!(materials(&renderer), lantern.materials.len());
+    }
+
+    #[test]
+    fn budget_always_lets_one_build_through() {
+        let mut budget = GmBudget::new(Some(0));
+        assert!(budget.allows());
+        budget.spend(Instant::now());
+        assert!(!budget.allows());
+        assert!(!budget.allows());
+        assert_eq!(budget.pending, 2);
+
+        let mut unlimited = GmBudget::new(None);
+        for _ in 0..3 {
+            assert!(unlimited.allows());
+            unlimited.spend(Instant::now() - Duration::from_secs(1));
+        }
+        assert_eq!(unlimited.pending, 0);
+    }
+
+    #[test]
+    fn targets_are_reused_while_their_view_keeps_its_size() {
+        let mut made = 0;
+        let mut make = || {
+            made += 1;
+            made
+        };
+        let (key, target) = reuse_target(None, (640, 480, 4), &mut make);
+        assert_eq!(target, 1);
+        let (key, target) = reuse_target(Some((key, target)), (640, 480, 4), &mut make);
+        assert_eq!((key, target), ((640, 480, 4), 1));
+        // a resize or another sample count makes a new one
+        let (key, target) = reuse_target(Some((key, target)), (800, 480, 4), &mut make);
+        assert_eq!(target, 2);
+        let (_, target) = reuse_target(Some((key, target)), (800, 480, 8), &mut make);
+        assert_eq!(target, 3);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    #[ignore = "needs a display and a gpu"]
+    #[allow(deprecated)]
+    fn reinits_against_a_new_window_after_closing() {
+        use winit::platform::x11::EventLoopBuilderExtX11;
+
+        let event_loop = winit::event_loop::EventLoop::builder()
+            .with_any_thread(true)
+            .build()
+            .unwrap();
+        let camera = DefaultCamera::new(
+            Transform3D::new(Vec3::new(0.0, 0.0, 4.0), Quat::IDENTITY, Vec3::ONE),
+            64.0,
+            64.0,
+            Vec3::Y,
+            Vec3::NEG_Z,
+            1.0,
+            0.1,
+            100.0,
+        );
+        let camera_id = camera.id();
+        let mut objects = EntityRegistry::new();
+        objects.add(camera.into_container());
+        objects.add(
+            crate::engine::entity::tests::Dummy::new(crate::engine::component::ComponentSet::new())
+                .with_model(crate::assets::basic_models::CuboidBuilder::new().build())
+                .into_container(),
+        );
+        let mut renderer = ThreedRenderer::new(objects, EngineContext::new());
+
+        for _ in 0..2 {
+            let window = Arc::new(
+                event_loop
+                    .create_window(Window::default_attributes().with_visible(false))
+                    .unwrap(),
+            );
+            renderer.init(&window, &camera_id).unwrap();
+            renderer.render(Arc::clone(&window)).unwrap();
+            assert_eq!(renderer.object_gm_cache.len(), 1);
+
+            renderer
+                .handle_close(window, &WindowEvent::CloseRequested)
+                .unwrap();
+            assert!(renderer.context.is_none());
+            assert!(renderer.object_gm_cache.is_empty());
+            assert!(renderer.fallback_light.is_none());
+        }
+    }
 }
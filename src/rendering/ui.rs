@@ -0,0 +1,143 @@
+use std::fmt::Debug;
+
+use three_d::egui;
+use winit::event::WindowEvent;
+
+use crate::engine::context::ContextItem;
+
+/// handle for taking a callback back out of the `UiLayer`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UiCallbackId(u64);
+
+type UiCallback = Box<dyn FnMut(&egui::Context) + Send + Sync>;
+
+/// egui callbacks the renderer runs every frame, in the order they were added, and draws over
+/// the scene. kept in the engine `Context`
+///
+/// the layer is locked while the callbacks run, so they can't add or remove callbacks themselves
+#[derive(Default)]
+pub struct UiLayer {
+    callbacks: Vec<(UiCallbackId, UiCallback)>,
+    next_id: u64,
+}
+
+impl ContextItem for UiLayer {}
+
+impl Debug for UiLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UiLayer")
+            .field("callbacks", &self.callbacks.len())
+            .finish()
+    }
+}
+
+impl UiLayer {
+    pub fn add(
+        &mut self,
+        callback: impl FnMut(&egui::Context) + Send + Sync + 'static,
+    ) -> UiCallbackId {
+        let id = UiCallbackId(self.next_id);
+        self.next_id += 1;
+        self.callbacks.push((id, Box::new(callback)));
+        id
+    }
+
+    /// `false` if there was no callback with that id
+    pub fn remove(&mut self, id: UiCallbackId) -> bool {
+        let before = self.callbacks.len();
+        self.callbacks.retain(|(callback_id, _)| *callback_id != id);
+        self.callbacks.len() != before
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.callbacks.is_empty()
+    }
+
+    pub(crate) fn run(&mut self, ctx: &egui::Context) {
+        for (_, callback) in &mut self.callbacks {
+            callback(ctx);
+        }
+    }
+}
+
+/// what the ui wanted as of the last frame, the event handler keeps those events from the
+/// entities
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct UiInputCapture {
+    /// the cursor is over a window or something is being dragged
+    pub pointer: bool,
+    /// a text field or similar has focus
+    pub keyboard: bool,
+}
+
+impl ContextItem for UiInputCapture {}
+
+impl UiInputCapture {
+    pub(crate) fn from_context(ctx: &egui::Context) -> Self {
+        Self {
+            pointer: ctx.wants_pointer_input(),
+            keyboard: ctx.wants_keyboard_input(),
+        }
+    }
+
+    /// if the ui takes `event`, entering and leaving the window always go through so entities
+    /// don't miss the cursor leaving
+    pub fn captures(&self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { .. }
+            | WindowEvent::MouseInput { .. }
+            | WindowEvent::MouseWheel { .. }
+            | WindowEvent::Touch(_) => self.pointer,
+            WindowEvent::KeyboardInput { .. } | WindowEvent::Ime(_) => self.keyboard,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use winit::event::{DeviceId, ElementState, MouseButton};
+
+    use super::*;
+
+    #[test]
+    fn removed_callbacks_stop_running() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut layer = UiLayer::default();
+        let counter = calls.clone();
+        let id = layer.add(move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+        let ctx = egui::Context::default();
+
+        layer.run(&ctx);
+        assert!(layer.remove(id));
+        assert!(!layer.remove(id));
+        layer.run(&ctx);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert!(layer.is_empty());
+    }
+
+    #[test]
+    fn pointer_capture_only_takes_pointer_events() {
+        let capture = UiInputCapture {
+            pointer: true,
+            keyboard: false,
+        };
+        let device_id = unsafe { DeviceId::dummy() };
+        let click = WindowEvent::MouseInput {
+            device_id,
+            state: ElementState::Pressed,
+            button: MouseButton::Left,
+        };
+        assert!(capture.captures(&click));
+        assert!(!capture.captures(&WindowEvent::CursorLeft { device_id }));
+        assert!(!capture.captures(&WindowEvent::Focused(true)));
+        assert!(!UiInputCapture::default().captures(&click));
+    }
+}
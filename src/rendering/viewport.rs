@@ -0,0 +1,282 @@
+use glam::{Vec2, Vec4};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::engine::context::ContextItem;
+
+/// rect in physical window pixels, origin at the top left like winit cursor positions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewportRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl ViewportRect {
+    pub fn contains(&self, pos: Vec2) -> bool {
+        pos.x >= self.x
+            && pos.y >= self.y
+            && pos.x < self.x + self.width
+            && pos.y < self.y + self.height
+    }
+}
+
+/// maps cursor positions from the window to the rendered viewport
+///
+/// kept up to date by the renderer in the engine context. with letterboxing or a render scale
+/// cursor positions don't line up with viewport pixels anymore, so anything hit testing against
+/// the scene should go through this.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewportMapper {
+    /// physical window size
+    pub window_size: Vec2,
+    pub scale_factor: f32,
+    pub viewport: ViewportRect,
+    /// render target resolution relative to the viewport's physical size
+    pub render_scale: f32,
+}
+
+impl ContextItem for ViewportMapper {}
+
+impl ViewportMapper {
+    /// viewport covering the whole window
+    pub fn new(window_size: Vec2, scale_factor: f32) -> Self {
+        Self {
+            window_size,
+            scale_factor,
+            viewport: ViewportRect {
+                x: 0.0,
+                y: 0.0,
+                width: window_size.x,
+                height: window_size.y,
+            },
+            render_scale: 1.0,
+        }
+    }
+
+    /// largest centered viewport with the aspect ratio `aspect`, bars fill the rest
+    pub fn letterboxed(window_size: Vec2, scale_factor: f32, aspect: f32) -> Self {
+        let (width, height) = if window_size.x / window_size.y > aspect {
+            (window_size.y * aspect, window_size.y)
+        } else {
+            (window_size.x, window_size.x / aspect)
+        };
+        Self {
+            viewport: ViewportRect {
+                x: (window_size.x - width) / 2.0,
+                y: (window_size.y - height) / 2.0,
+                width,
+                height,
+            },
+            ..Self::new(window_size, scale_factor)
+        }
+    }
+
+    pub fn logical_to_physical(&self, pos: Vec2) -> Vec2 {
+        pos * self.scale_factor
+    }
+
+    /// physical window position to render target pixels, `None` in the letterbox bars
+    pub fn window_to_viewport(&self, pos: Vec2) -> Option<Vec2> {
+        if !self.viewport.contains(pos) {
+            return None;
+        }
+        Some((pos - Vec2::new(self.viewport.x, self.viewport.y)) * self.render_scale)
+    }
+
+    /// render target pixels to normalized device coordinates, y up
+    pub fn viewport_to_ndc(&self, pos: Vec2) -> Vec2 {
+        let size = Vec2::new(self.viewport.width, self.viewport.height) * self.render_scale;
+        Vec2::new(pos.x / size.x * 2.0 - 1.0, 1.0 - pos.y / size.y * 2.0)
+    }
+}
+
+impl Default for ViewportMapper {
+    fn default() -> Self {
+        Self::new(Vec2::ONE, 1.0)
+    }
+}
+
+/// what a view clears before its camera draws into it
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum ClearMode {
+    /// color and depth, the color from `EnvironmentSettings::clear_color`
+    #[default]
+    Environment,
+    /// color and depth, with this rgba in 0 to 1
+    Color(Vec4),
+    /// keeps the views drawn before it but sits in front of them, for overlays
+    DepthOnly,
+    /// draws into whatever is there, depth included
+    None,
+}
+
+impl ClearMode {
+    /// whether the views drawn before this one still show under it
+    pub fn keeps_color(&self) -> bool {
+        matches!(self, ClearMode::DepthOnly | ClearMode::None)
+    }
+
+    /// the color cleared to, `None` for the modes that keep it
+    pub fn color(&self, clear_color: Vec4) -> Option<Vec4> {
+        match *self {
+            ClearMode::Environment => Some(clear_color),
+            ClearMode::Color(color) => Some(color),
+            ClearMode::DepthOnly | ClearMode::None => None,
+        }
+    }
+}
+
+/// part of the window a camera renders into for split screen, set with
+/// `RendererCommand::SetViewports`
+///
+/// the rect is in fractions of the window with the origin at the top left, so it follows the
+/// window through resizes. views are drawn in the order they're set, so overlays go last
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ViewportConfig {
+    pub camera: Uuid,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub clear: ClearMode,
+}
+
+impl ViewportConfig {
+    pub fn new(camera: Uuid, x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            camera,
+            x,
+            y,
+            width,
+            height,
+            clear: ClearMode::default(),
+        }
+    }
+
+    pub fn with_clear(mut self, clear: ClearMode) -> Self {
+        self.clear = clear;
+        self
+    }
+
+    /// the whole window
+    pub fn full(camera: Uuid) -> Self {
+        Self::new(camera, 0.0, 0.0, 1.0, 1.0)
+    }
+
+    /// equal columns, left to right
+    pub fn side_by_side(cameras: &[Uuid]) -> Vec<Self> {
+        let width = 1.0 / cameras.len() as f32;
+        cameras
+            .iter()
+            .enumerate()
+            .map(|(i, camera)| Self::new(*camera, i as f32 * width, 0.0, width, 1.0))
+            .collect()
+    }
+
+    /// equal rows, top to bottom
+    pub fn stacked(cameras: &[Uuid]) -> Vec<Self> {
+        let height = 1.0 / cameras.len() as f32;
+        cameras
+            .iter()
+            .enumerate()
+            .map(|(i, camera)| Self::new(*camera, 0.0, i as f32 * height, 1.0, height))
+            .collect()
+    }
+
+    /// the rect in whole pixels of a `width` by `height` window. edges are rounded on their
+    /// own, so viewports that meet in fractions meet in pixels too
+    pub fn rect_in(&self, width: u32, height: u32) -> ViewportRect {
+        let edge = |fraction: f32, size: u32| (fraction.clamp(0.0, 1.0) * size as f32).round();
+        let (left, right) = (edge(self.x, width), edge(self.x + self.width, width));
+        let (top, bottom) = (edge(self.y, height), edge(self.y + self.height, height));
+        ViewportRect {
+            x: left,
+            y: top,
+            width: (right - left).max(0.0),
+            height: (bottom - top).max(0.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letterboxed_square_window_at_2x() {
+        // 1000x1000 logical window
+        let mut mapper = ViewportMapper::letterboxed(Vec2::splat(2000.0), 2.0, 16.0 / 9.0);
+        assert_eq!(mapper.viewport.y, 437.5);
+        assert_eq!(mapper.viewport.height, 1125.0);
+
+        let center = mapper.logical_to_physical(Vec2::new(500.0, 500.0));
+        let in_viewport = mapper.window_to_viewport(center).unwrap();
+        assert_eq!(in_viewport, Vec2::new(1000.0, 562.5));
+        assert_eq!(mapper.viewport_to_ndc(in_viewport), Vec2::ZERO);
+
+        let top_bar = mapper.logical_to_physical(Vec2::new(500.0, 100.0));
+        assert_eq!(mapper.window_to_viewport(top_bar), None);
+
+        let corner = mapper.logical_to_physical(Vec2::new(0.0, 218.75));
+        assert_eq!(
+            mapper.viewport_to_ndc(mapper.window_to_viewport(corner).unwrap()),
+            Vec2::new(-1.0, 1.0)
+        );
+
+        mapper.render_scale = 0.5;
+        let scaled = mapper.window_to_viewport(center).unwrap();
+        assert_eq!(scaled, Vec2::new(500.0, 281.25));
+        assert_eq!(mapper.viewport_to_ndc(scaled), Vec2::ZERO);
+    }
+
+    #[test]
+    fn split_viewports_follow_resizes() {
+        let [left, right] = [Uuid::new_v4(), Uuid::new_v4()];
+        let split = ViewportConfig::side_by_side(&[left, right]);
+        assert_eq!(split[1].camera, right);
+
+        let rects = |width, height| {
+            split
+                .iter()
+                .map(|config| config.rect_in(width, height))
+                .collect::<Vec<_>>()
+        };
+        let halves = rects(1280, 720);
+        assert_eq!(
+            halves[0],
+            ViewportRect {
+                x: 0.0,
+                y: 0.0,
+                width: 640.0,
+                height: 720.0
+            }
+        );
+        assert_eq!(halves[1].x, 640.0);
+
+        // odd widths still cover the window without a gap
+        let halves = rects(1001, 500);
+        assert_eq!(halves[0].width + halves[1].width, 1001.0);
+        assert_eq!(halves[1].x, halves[0].width);
+
+        let rows = ViewportConfig::stacked(&[left, right]);
+        assert_eq!(rows[1].rect_in(800, 600).y, 300.0);
+        assert_eq!(ViewportConfig::full(left).rect_in(0, 0).width, 0.0);
+    }
+
+    #[test]
+    fn overlays_keep_the_color_under_them() {
+        let sky = Vec4::new(0.5, 0.8, 0.8, 1.0);
+        let main = ViewportConfig::full(Uuid::new_v4());
+        let minimap = ViewportConfig::new(Uuid::new_v4(), 0.75, 0.0, 0.25, 0.25)
+            .with_clear(ClearMode::Color(Vec4::new(0.0, 0.0, 0.0, 1.0)));
+        let overlay = ViewportConfig::full(Uuid::new_v4()).with_clear(ClearMode::DepthOnly);
+
+        assert_eq!(main.clear.color(sky), Some(sky));
+        assert_eq!(minimap.clear.color(sky), Some(Vec4::W));
+        assert!(!minimap.clear.keeps_color());
+        assert_eq!(overlay.clear.color(sky), None);
+        assert!(overlay.clear.keeps_color() && ClearMode::None.keeps_color());
+    }
+}
@@ -0,0 +1,178 @@
+use std::borrow::Cow;
+
+use three_d::{
+    CpuMesh, Cull, EffectMaterialId, FragmentAttributes, Indices, Light, Material, MaterialType,
+    Positions, Program, RenderStates, Srgba, Viewer,
+};
+
+use super::ViewMode;
+
+/// the mesh to build gms from in `view_mode`, wireframes need the corners `WireframeMaterial`
+/// reads the edges from
+pub(crate) fn view_mode_mesh(view_mode: ViewMode, cpu_mesh: &CpuMesh) -> Cow<'_, CpuMesh> {
+    if view_mode == ViewMode::Wireframe {
+        Cow::Owned(wireframe_cpu_mesh(cpu_mesh))
+    } else {
+        Cow::Borrowed(cpu_mesh)
+    }
+}
+
+/// every triangle with vertices of its own, each corner colored red, green or blue so the
+/// interpolated color is the position inside the triangle
+pub(crate) fn wireframe_cpu_mesh(cpu_mesh: &CpuMesh) -> CpuMesh {
+    let positions = cpu_mesh.positions.to_f32();
+    let indices = cpu_mesh
+        .indices
+        .to_u32()
+        .unwrap_or_else(|| (0..positions.len() as u32).collect());
+    let triangles = indices.chunks_exact(3);
+
+    let corner = |i: usize| match i % 3 {
+        0 => Srgba::new(255, 0, 0, 255),
+        1 => Srgba::new(0, 255, 0, 255),
+        _ => Srgba::new(0, 0, 255, 255),
+    };
+    let flat = triangles.flatten().map(|&i| i as usize);
+    CpuMesh {
+        positions: Positions::F32(flat.clone().map(|i| positions[i]).collect()),
+        indices: Indices::None,
+        normals: cpu_mesh
+            .normals
+            .as_ref()
+            .map(|normals| flat.clone().map(|i| normals[i]).collect()),
+        tangents: cpu_mesh
+            .tangents
+            .as_ref()
+            .map(|tangents| flat.clone().map(|i| tangents[i]).collect()),
+        uvs: cpu_mesh
+            .uvs
+            .as_ref()
+            .map(|uvs| flat.clone().map(|i| uvs[i]).collect()),
+        colors: Some(flat.enumerate().map(|(i, _)| corner(i)).collect()),
+    }
+}
+
+/// draws only the triangle edges of a mesh made by `wireframe_cpu_mesh`, the rest is discarded
+pub(crate) struct WireframeMaterial {
+    pub color: Srgba,
+}
+
+impl Default for WireframeMaterial {
+    fn default() -> Self {
+        Self {
+            color: Srgba::new(230, 230, 230, 255),
+        }
+    }
+}
+
+impl Material for WireframeMaterial {
+    fn fragment_shader_source(&self, _lights: &[&dyn Light]) -> String {
+        WIREFRAME_SHADER.to_string()
+    }
+
+    fn id(&self) -> EffectMaterialId {
+        EffectMaterialId(WIREFRAME_MATERIAL_ID)
+    }
+
+    fn fragment_attributes(&self) -> FragmentAttributes {
+        FragmentAttributes {
+            color: true,
+            ..FragmentAttributes::NONE
+        }
+    }
+
+    fn use_uniforms(&self, program: &Program, _viewer: &dyn Viewer, _lights: &[&dyn Light]) {
+        program.use_uniform("wireColor", self.color.to_linear_srgb());
+    }
+
+    fn render_states(&self) -> RenderStates {
+        // the back faces' edges show through like they did with polygon outlines
+        RenderStates {
+            cull: Cull::None,
+            ..Default::default()
+        }
+    }
+
+    fn material_type(&self) -> MaterialType {
+        MaterialType::Opaque
+    }
+}
+
+/// outside the range three_d uses for its own effects and materials, next to `FogEffect`'s
+const WIREFRAME_MATERIAL_ID: u16 = 0x0F07;
+
+/// `col` is the position inside the triangle, an edge is where one of its parts goes to 0.
+/// `fwidth` keeps the lines about a pixel and a half wide at any distance
+const WIREFRAME_SHADER: &str = r#"
+uniform vec4 wireColor;
+
+in vec4 col;
+
+layout (location = 0) out vec4 outColor;
+
+void main()
+{
+    vec3 width = fwidth(col.rgb) * 1.5;
+    vec3 inside = smoothstep(vec3(0.0), width, col.rgb);
+    float edge = 1.0 - min(min(inside.x, inside.y), inside.z);
+    if (edge < 0.5) {
+        discard;
+    }
+    outColor = wireColor;
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use three_d::{Vec2, Vec3};
+
+    use super::*;
+
+    #[test]
+    fn wireframe_mesh_unshares_the_corners() {
+        // a quad, the two triangles share the diagonal
+        let quad = CpuMesh {
+            positions: Positions::F32(vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            ]),
+            indices: Indices::U32(vec![0, 1, 2, 0, 2, 3]),
+            uvs: Some(vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(1.0, 0.0),
+                Vec2::new(1.0, 1.0),
+                Vec2::new(0.0, 1.0),
+            ]),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            view_mode_mesh(ViewMode::Shaded, &quad),
+            Cow::Borrowed(_)
+        ));
+        let wireframe = view_mode_mesh(ViewMode::Wireframe, &quad).into_owned();
+
+        let positions = wireframe.positions.to_f32();
+        assert_eq!(positions.len(), 6);
+        assert_eq!(positions[3], Vec3::new(0.0, 0.0, 0.0));
+        assert_eq!(positions[5], Vec3::new(0.0, 1.0, 0.0));
+        assert!(wireframe.indices.to_u32().is_none());
+        assert_eq!(wireframe.uvs.unwrap()[4], Vec2::new(1.0, 1.0));
+        assert!(wireframe.normals.is_none());
+
+        // every triangle has one corner of each color
+        let colors = wireframe.colors.unwrap();
+        for triangle in colors.chunks_exact(3) {
+            assert_eq!(
+                triangle,
+                [
+                    Srgba::new(255, 0, 0, 255),
+                    Srgba::new(0, 255, 0, 255),
+                    Srgba::new(0, 0, 255, 255)
+                ]
+            );
+        }
+    }
+}
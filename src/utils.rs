@@ -1,6 +1,6 @@
 use std::{
     f64::consts::PI,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, RwLock},
 };
 
 /// degrees to radians
@@ -13,6 +13,124 @@ pub const fn rad_to_deg(rad: f64) -> f64 {
     rad * (180_f64 / PI)
 }
 
+/// degrees to radians for f32
+pub const fn deg_to_rad_f32(deg: f32) -> f32 {
+    deg * (std::f32::consts::PI / 180_f32)
+}
+
+/// radians to degrees for f32
+pub const fn rad_to_deg_f32(rad: f32) -> f32 {
+    rad * (180_f32 / std::f32::consts::PI)
+}
+
+/// wraps an angle in radians into (-π, π]
+pub fn wrap_angle(rad: f32) -> f32 {
+    use std::f32::consts::{PI, TAU};
+    let wrapped = (rad + PI).rem_euclid(TAU) - PI;
+    if wrapped <= -PI { PI } else { wrapped }
+}
+
+/// clamps a pitch angle in radians to just short of straight up/down, so yaw stays meaningful
+pub fn clamp_pitch(pitch: f32) -> f32 {
+    let limit = std::f32::consts::FRAC_PI_2 - 0.001;
+    pitch.clamp(-limit, limit)
+}
+
+/// rotation from yaw (around y) and pitch (around x), in radians
+pub fn yaw_pitch_rotation(yaw: f32, pitch: f32) -> glam::Quat {
+    glam::Quat::from_rotation_y(yaw) * glam::Quat::from_rotation_x(pitch)
+}
+
+/// rotation that turns the default forward (-z) to `forward` with `up` as the up direction
+///
+/// a zero `forward` gives the identity, and if `forward` and `up` are parallel another up axis is
+/// picked instead of producing nan
+pub fn look_rotation(forward: glam::Vec3, up: glam::Vec3) -> glam::Quat {
+    let Some(forward) = forward.try_normalize() else {
+        return glam::Quat::IDENTITY;
+    };
+    let back = -forward;
+    let right = match up.cross(back).try_normalize() {
+        Some(r) => r,
+        None => {
+            let fallback = if back.y.abs() < 0.99 {
+                glam::Vec3::Y
+            } else {
+                glam::Vec3::Z
+            };
+            fallback.cross(back).normalize()
+        }
+    };
+    let up = back.cross(right);
+    glam::Quat::from_mat3(&glam::Mat3::from_cols(right, up, back)).normalize()
+}
+
+/// moves `current` toward `target` by at most `max_delta` without overshooting
+pub fn move_toward(current: glam::Vec3, target: glam::Vec3, max_delta: f32) -> glam::Vec3 {
+    let to_target = target - current;
+    let distance = to_target.length();
+    if distance <= max_delta || distance == 0.0 {
+        target
+    } else {
+        current + to_target / distance * max_delta
+    }
+}
+
+#[cfg(test)]
+mod math_tests {
+    use std::f32::consts::PI;
+
+    use glam::{Quat, Vec3};
+
+    use super::*;
+
+    #[test]
+    fn wrap_angle_edges() {
+        assert_eq!(wrap_angle(PI), PI);
+        assert_eq!(wrap_angle(-PI), PI);
+        assert_eq!(wrap_angle(0.0), 0.0);
+        assert!((wrap_angle(3.0 * PI) - PI).abs() < 1e-5);
+        assert!((wrap_angle(-PI / 2.0 - 2.0 * PI) + PI / 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn f32_conversions() {
+        assert!((deg_to_rad_f32(180.0) - PI).abs() < 1e-6);
+        assert!((rad_to_deg_f32(PI / 2.0) - 90.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn clamp_pitch_stays_below_vertical() {
+        assert!(clamp_pitch(10.0) < PI / 2.0);
+        assert!(clamp_pitch(-10.0) > -PI / 2.0);
+        assert_eq!(clamp_pitch(0.5), 0.5);
+    }
+
+    #[test]
+    fn look_rotation_points_forward() {
+        let forward = Vec3::new(1.0, 0.0, 0.0);
+        let rotation = look_rotation(forward, Vec3::Y);
+        assert!((rotation * Vec3::NEG_Z).abs_diff_eq(forward, 1e-5));
+        assert!((rotation * Vec3::Y).abs_diff_eq(Vec3::Y, 1e-5));
+    }
+
+    #[test]
+    fn look_rotation_degenerate() {
+        assert_eq!(look_rotation(Vec3::ZERO, Vec3::Y), Quat::IDENTITY);
+
+        let rotation = look_rotation(Vec3::Y, Vec3::Y);
+        assert!(rotation.is_finite());
+        assert!((rotation * Vec3::NEG_Z).abs_diff_eq(Vec3::Y, 1e-5));
+    }
+
+    #[test]
+    fn move_toward_does_not_overshoot() {
+        let target = Vec3::new(1.0, 0.0, 0.0);
+        assert_eq!(move_toward(Vec3::ZERO, target, 5.0), target);
+        assert!(move_toward(Vec3::ZERO, target, 0.25).abs_diff_eq(Vec3::new(0.25, 0.0, 0.0), 1e-6));
+    }
+}
+
 /// type alias for Arc<Mutex<Box<T>>> bc i really can't be bothered to write that every time
 pub type SharedBox<T> = Arc<Mutex<Box<T>>>;
 
@@ -29,6 +147,9 @@ pub fn new_shared<T>(t: T) -> Shared<T> {
 
 pub type WeakShared<T> = std::sync::Weak<Mutex<T>>;
 
+/// type alias for Arc<RwLock<Box<T>>>, for things that are read way more often than they're written
+pub type SharedRwBox<T> = Arc<RwLock<Box<T>>>;
+
 /// helper trait for turning glam types into cgmath types
 pub trait IntoCgmath {
     type Output;
@@ -81,3 +202,141 @@ impl IntoCgmath for glam::Quat {
         cgmath::Quaternion::new(self.w, self.x, self.y, self.z)
     }
 }
+
+impl IntoCgmath for glam::Vec4 {
+    type Output = cgmath::Vector4<f32>;
+
+    fn into_cgmath(self) -> Self::Output {
+        cgmath::Vector4::new(self.x, self.y, self.z, self.w)
+    }
+}
+
+/// angle in radians as a cgmath angle, for the three_d apis that want one
+pub fn cgmath_radians(rad: f32) -> cgmath::Rad<f32> {
+    cgmath::Rad(rad)
+}
+
+/// angle in degrees as a cgmath angle
+pub fn cgmath_degrees(deg: f32) -> cgmath::Deg<f32> {
+    cgmath::Deg(deg)
+}
+
+/// helper trait for turning cgmath types (what three_d hands back) into glam types
+pub trait IntoGlam {
+    type Output;
+    fn into_glam(self) -> Self::Output;
+}
+
+impl IntoGlam for cgmath::Vector2<f32> {
+    type Output = glam::Vec2;
+
+    fn into_glam(self) -> Self::Output {
+        glam::Vec2::new(self.x, self.y)
+    }
+}
+
+impl IntoGlam for cgmath::Vector3<f32> {
+    type Output = glam::Vec3;
+
+    fn into_glam(self) -> Self::Output {
+        glam::Vec3::new(self.x, self.y, self.z)
+    }
+}
+
+impl IntoGlam for cgmath::Point3<f32> {
+    type Output = glam::Vec3;
+
+    fn into_glam(self) -> Self::Output {
+        glam::Vec3::new(self.x, self.y, self.z)
+    }
+}
+
+impl IntoGlam for cgmath::Vector4<f32> {
+    type Output = glam::Vec4;
+
+    fn into_glam(self) -> Self::Output {
+        glam::Vec4::new(self.x, self.y, self.z, self.w)
+    }
+}
+
+impl IntoGlam for cgmath::Matrix4<f32> {
+    type Output = glam::Mat4;
+
+    fn into_glam(self) -> Self::Output {
+        glam::Mat4::from_cols(
+            self.x.into_glam(),
+            self.y.into_glam(),
+            self.z.into_glam(),
+            self.w.into_glam(),
+        )
+    }
+}
+
+impl IntoGlam for cgmath::Quaternion<f32> {
+    type Output = glam::Quat;
+
+    /// cgmath stores the scalar part separately (`s`) while glam keeps it last (`w`)
+    fn into_glam(self) -> Self::Output {
+        glam::Quat::from_xyzw(self.v.x, self.v.y, self.v.z, self.s)
+    }
+}
+
+impl IntoGlam for cgmath::Rad<f32> {
+    type Output = f32;
+
+    fn into_glam(self) -> Self::Output {
+        self.0
+    }
+}
+
+/// reverse of `IntoGlam`, for when it reads better on the glam side
+pub trait FromCgmath<T> {
+    fn from_cgmath(value: T) -> Self;
+}
+
+impl<T, G> FromCgmath<T> for G
+where
+    T: IntoGlam<Output = G>,
+{
+    fn from_cgmath(value: T) -> Self {
+        value.into_glam()
+    }
+}
+
+#[cfg(test)]
+mod conversion_tests {
+    use super::{FromCgmath, IntoCgmath, IntoGlam};
+
+    const EPSILON: f32 = 1e-6;
+
+    #[test]
+    fn vec_round_trip() {
+        let v2 = glam::Vec2::new(1.5, -2.0);
+        let v3 = glam::Vec3::new(1.0, -2.5, 3.25);
+        let v4 = glam::Vec4::new(0.1, 0.2, -0.3, 0.4);
+
+        assert!(v2.into_cgmath().into_glam().abs_diff_eq(v2, EPSILON));
+        assert!(v3.into_cgmath().into_glam().abs_diff_eq(v3, EPSILON));
+        assert!(v4.into_cgmath().into_glam().abs_diff_eq(v4, EPSILON));
+        assert_eq!(glam::Vec3::from_cgmath(v3.into_cgmath()), v3);
+    }
+
+    #[test]
+    fn mat4_round_trip() {
+        let m = glam::Mat4::from_scale_rotation_translation(
+            glam::Vec3::new(1.0, 2.0, 3.0),
+            glam::Quat::from_euler(glam::EulerRot::XYZ, 0.3, -1.2, 2.0),
+            glam::Vec3::new(-4.0, 5.0, 6.0),
+        );
+        assert!(m.into_cgmath().into_glam().abs_diff_eq(m, EPSILON));
+    }
+
+    #[test]
+    fn quat_round_trip_keeps_component_order() {
+        let q = glam::Quat::from_xyzw(0.1, 0.2, 0.3, 0.9).normalize();
+        let cg = q.into_cgmath();
+        assert_eq!(cg.s, q.w);
+        assert_eq!(cg.v.x, q.x);
+        assert!(cg.into_glam().abs_diff_eq(q, EPSILON));
+    }
+}
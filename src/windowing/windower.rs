@@ -4,11 +4,13 @@ use std::{
         Arc, RwLock, Weak,
         mpsc::{Receiver, SyncSender},
     },
+    time::{Duration, Instant},
 };
 
+use serde::{Deserialize, Serialize};
 use winit::{
     application::ApplicationHandler,
-    event_loop::EventLoopBuilder,
+    event_loop::{ControlFlow, EventLoopBuilder},
     window::{Window, WindowAttributes, WindowId},
 };
 
@@ -24,7 +26,10 @@ use crate::{
 
 use tracy_client::*;
 
-#[derive(Debug, Clone)]
+/// how often an idle `FramePacing::OnDemand` window wakes up to look for moved entities
+const ON_DEMAND_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum WindowerCommand {}
 
 pub struct Windower {
@@ -88,19 +93,29 @@ impl ApplicationHandler for Windower {
             .expect("no window");
 
         self.engine
-            .renderer
             .renderer
             .init(window, &self.engine.default_camera_id)
             .unwrap();
 
         if !self.engine_running {
             self.engine.init(&self.windows.clone()).unwrap();
+            self.engine_running = true;
         }
 
         window.request_redraw();
         log::info!("resumed");
     }
 
+    /// some platforms take the window's surface away while suspended, so the window goes with
+    /// it and `resumed` opens a new one
+    fn suspended(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        self.engine.renderer.suspend();
+        if let Some(id) = self.parent_window_id.take() {
+            self.windows.write().unwrap().remove(&id);
+        }
+        log::info!("suspended");
+    }
+
     fn window_event(
         &mut self,
         event_loop: &winit::event_loop::ActiveEventLoop,
@@ -176,6 +191,10 @@ impl ApplicationHandler for Windower {
 
                 self.engine.handle_message(msg).unwrap();
 
+                if let Err(e) = self.engine.shutdown() {
+                    log::error!("engine shutdown failed: {e}");
+                }
+
                 self.windows.write().unwrap().clear();
                 event_loop.exit();
             }
@@ -190,7 +209,38 @@ impl ApplicationHandler for Windower {
                     },
                 };
 
+                self.engine.renderer.handle_window_event(&e);
                 self.engine.event_handler.send_event(window_id, e);
+                self.engine.request_frame();
+            }
+        }
+    }
+
+    fn device_event(
+        &mut self,
+        event_loop: &winit::event_loop::ActiveEventLoop,
+        device_id: winit::event::DeviceId,
+        event: winit::event::DeviceEvent,
+    ) {
+        self.engine
+            .event_handler
+            .send_device_event(device_id, &event);
+        self.engine.request_frame();
+    }
+
+    /// asks for the next frame once it's due under the engine's `FramePacing`
+    fn about_to_wait(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        let now = Instant::now();
+        match self.engine.next_frame() {
+            Some(at) if at <= now => {
+                if let Some((window, _)) = self.get_parent_window() {
+                    window.request_redraw();
+                }
+                event_loop.set_control_flow(ControlFlow::Wait);
+            }
+            Some(at) => event_loop.set_control_flow(ControlFlow::WaitUntil(at)),
+            None => {
+                event_loop.set_control_flow(ControlFlow::WaitUntil(now + ON_DEMAND_CHECK_INTERVAL))
             }
         }
     }